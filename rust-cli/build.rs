@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // A vendored protoc, so building does not need protobuf installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    println!("cargo:rerun-if-changed=../proto/decoder.proto");
+    println!("cargo:rerun-if-changed=../proto/ethereum_log.proto");
+    println!("cargo:rerun-if-changed=../proto/bstream.proto");
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["../proto/decoder.proto", "../proto/ethereum_log.proto", "../proto/bstream.proto"], &["../proto"])?;
+    Ok(())
+}
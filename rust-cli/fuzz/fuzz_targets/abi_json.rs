@@ -0,0 +1,18 @@
+#![no_main]
+
+//! Arbitrary ABI JSON through event extraction and type parsing, then a decode of an all-zero log against each
+//! event found, which exercises the parsed types (nested and fixed-size arrays included).
+
+use ethabi::{Hash, RawLog};
+use evm_rust_decoder::abi::events_from_json;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(abi) = serde_json::from_slice::<serde_json::Value>(data) else { return };
+    let Ok(events) = events_from_json(&abi) else { return };
+    for event in &events {
+        let mut topics = vec![event.signature()];
+        topics.extend(event.inputs.iter().filter(|p| p.indexed).map(|_| Hash::zero()));
+        let _ = event.parse_log(RawLog { topics, data: vec![0; 256] });
+    }
+});
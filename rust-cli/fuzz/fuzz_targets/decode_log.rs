@@ -0,0 +1,49 @@
+#![no_main]
+
+//! Arbitrary topics and data decoded against every event of the mixed ABI, where the `core_decode` layout must agree
+//! with `Event::parse_log`, then through topic0 routing.
+//! Input framing (also written by `evm_rust_decoder fuzz-corpus --target decode-log`): one byte with the topic
+//! count, that many 32-byte topics, then the data bytes.
+
+use std::sync::OnceLock;
+
+use ethabi::ethereum_types::H256;
+use ethabi::RawLog;
+use evm_rust_decoder::abi::events_from_json;
+use evm_rust_decoder::core_decode::EventLayout;
+use evm_rust_decoder::decode::{LogDecoder, LogMeta, LogRecord};
+use libfuzzer_sys::fuzz_target;
+
+fn decoder() -> &'static (Vec<ethabi::Event>, LogDecoder) {
+    static DECODER: OnceLock<(Vec<ethabi::Event>, LogDecoder)> = OnceLock::new();
+    DECODER.get_or_init(|| {
+        let abi: serde_json::Value = serde_json::from_str(include_str!("../../../abi/mixed.json")).unwrap();
+        let events = events_from_json(&abi).unwrap();
+        let decoder = LogDecoder::new(events[0].clone(), &events, true);
+        (events, decoder)
+    })
+}
+
+fn raw_log(data: &[u8]) -> Option<RawLog> {
+    let (&count, rest) = data.split_first()?;
+    let count = count as usize % 5;
+    if rest.len() < count * 32 { return None; }
+    let (topics, data) = rest.split_at(count * 32);
+    let topics = topics.chunks_exact(32).map(H256::from_slice).collect();
+    Some(RawLog { topics, data: data.to_vec() })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some(raw) = raw_log(data) else { return };
+    let (events, decoder) = decoder();
+    for event in events {
+        let layout = EventLayout::new(event.clone());
+        match (event.parse_log(raw.clone()), layout.decode(&raw.topics, &raw.data)) {
+            (Ok(expected), Ok(log)) => assert_eq!(expected, log),
+            (Err(_), Err(_)) => {}
+            (expected, log) => panic!("parse_log gave {:?}, the layout {:?}", expected, log),
+        }
+    }
+    if raw.topics.is_empty() { return; }
+    let _ = decoder.decode(LogRecord { raw, meta: LogMeta::default() });
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+//! Arbitrary input lines through line parsing (every --input-schema shape), hex decoding and ABI decoding,
+//! plus the whole-document streaming parser. Errors are fine; panics are not.
+
+use std::sync::OnceLock;
+
+use evm_rust_decoder::abi::events_from_json;
+use evm_rust_decoder::decode::{InputSchema, LogDecoder};
+use evm_rust_decoder::json_stream;
+use libfuzzer_sys::fuzz_target;
+
+fn decoder() -> &'static LogDecoder {
+    static DECODER: OnceLock<LogDecoder> = OnceLock::new();
+    DECODER.get_or_init(|| {
+        let abi: serde_json::Value = serde_json::from_str(include_str!("../../../abi/mixed.json")).unwrap();
+        let events = events_from_json(&abi).unwrap();
+        LogDecoder::new(events[0].clone(), &events, true).with_input_schema(InputSchema::Auto)
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let decoder = decoder();
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = decoder.parse_line(line, |record| decoder.decode(record).map(|_| ()));
+    }
+    let _ = json_stream::for_each_log(data, |_| Ok(()));
+});
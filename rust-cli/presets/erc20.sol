@@ -0,0 +1,5 @@
+// ERC-20 (EIP-20) token events
+interface IERC20 {
+    event Transfer(address indexed from, address indexed to, uint256 value);
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+}
@@ -0,0 +1,20 @@
+// ERC-4337 EntryPoint events (v0.6 at 0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789 and v0.7 at
+// 0x0000000071727De22E5E9d8BAf0edAc6f37da032 emit the same ones; PostOpRevertReason and UserOperationPrefundTooLow
+// are v0.7 only), with the parameter names of the reference implementation
+interface IEntryPoint {
+    event UserOperationEvent(bytes32 indexed userOpHash, address indexed sender, address indexed paymaster, uint256 nonce, bool success, uint256 actualGasCost, uint256 actualGasUsed);
+    event AccountDeployed(bytes32 indexed userOpHash, address indexed sender, address factory, address paymaster);
+    event UserOperationRevertReason(bytes32 indexed userOpHash, address indexed sender, uint256 nonce, bytes revertReason);
+    event PostOpRevertReason(bytes32 indexed userOpHash, address indexed sender, uint256 nonce, bytes revertReason);
+    event UserOperationPrefundTooLow(bytes32 indexed userOpHash, address indexed sender, uint256 nonce);
+    event BeforeExecution();
+    event SignatureAggregatorChanged(address indexed aggregator);
+}
+
+interface IStakeManager {
+    event Deposited(address indexed account, uint256 totalDeposit);
+    event Withdrawn(address indexed account, address withdrawAddress, uint256 amount);
+    event StakeLocked(address indexed account, uint256 totalStaked, uint256 unstakeDelaySec);
+    event StakeUnlocked(address indexed account, uint256 withdrawTime);
+    event StakeWithdrawn(address indexed account, address withdrawAddress, uint256 amount);
+}
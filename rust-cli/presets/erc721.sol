@@ -0,0 +1,7 @@
+// ERC-721 (EIP-721) non-fungible token events; Transfer and Approval share their topic0s with ERC-20 but index the
+// token id
+interface IERC721 {
+    event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
+    event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId);
+    event ApprovalForAll(address indexed owner, address indexed operator, bool approved);
+}
@@ -0,0 +1,12 @@
+// Uniswap V2 pair and factory events; pairs are also ERC-20 tokens, see the erc20 preset for their Transfer and
+// Approval
+interface IUniswapV2Pair {
+    event Mint(address indexed sender, uint amount0, uint amount1);
+    event Burn(address indexed sender, uint amount0, uint amount1, address indexed to);
+    event Swap(address indexed sender, uint amount0In, uint amount1In, uint amount0Out, uint amount1Out, address indexed to);
+    event Sync(uint112 reserve0, uint112 reserve1);
+}
+
+interface IUniswapV2Factory {
+    event PairCreated(address indexed token0, address indexed token1, address pair, uint);
+}
@@ -0,0 +1,7 @@
+// WETH9 (0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2 on mainnet), with the parameter names of its verified source
+interface IWETH9 {
+    event Approval(address indexed src, address indexed guy, uint wad);
+    event Transfer(address indexed src, address indexed dst, uint wad);
+    event Deposit(address indexed dst, uint wad);
+    event Withdrawal(address indexed src, uint wad);
+}
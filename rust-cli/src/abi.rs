@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use ethabi::{Event, EventParam, Function, Param, ParamType, StateMutability};
+use ethereum_types::H256;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::presets::Preset;
+use crate::sol::events_from_solidity;
+
+/// Bumped whenever the layout of [`CachedAbi`] changes, so stale cache files are rebuilt instead of misread, or the
+/// events a file loads to do (2: `tuple` parameters, skipped before).
+const CACHE_VERSION: u32 = 2;
+
+pub fn load_event(path: &PathBuf, event_name: &str) -> Result<(Event, Vec<Event>)> {
+    let bytes = fs::read(path).with_context(|| format!("Cannot open ABI file: {:?}", path))?;
+    let events = parse_events(path, &bytes, None)?;
+    let event = select_event(&events, event_name)?;
+    Ok((event, events))
+}
+
+/// The event called `event_name`, or the first event when the name is empty.
+pub fn select_event(events: &[Event], event_name: &str) -> Result<Event> {
+    if event_name.is_empty() {
+        events
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("No events found in ABI"))
+    } else {
+        events
+            .iter()
+            .find(|e| e.name == event_name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Event '{}' not found in ABI", event_name))
+    }
+}
+
+/// How topic0 routing treats events that share a topic0 but index different parameters, such as the ERC-20 and
+/// ERC-721 `Transfer(address,address,uint256)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CollisionPolicy {
+    /// Decode with the event that comes first in ABI order
+    #[default]
+    First,
+    /// Refuse to start
+    Error,
+    /// Pick, per log, the event whose indexed parameter count matches the log's number of topics
+    ByTopicCount,
+}
+
+/// Events sharing one topic0 but with different indexed layouts, one per layout in ABI order.
+pub struct Collision {
+    pub topic0: H256,
+    pub events: Vec<Event>,
+}
+
+impl Collision {
+    /// Whether every layout has its own topic count, so [`CollisionPolicy::ByTopicCount`] can tell them apart.
+    pub fn by_topic_count_resolvable(&self) -> bool {
+        let mut counts: Vec<usize> = self.events.iter().map(topic_count).collect();
+        counts.sort_unstable();
+        counts.windows(2).all(|w| w[0] != w[1])
+    }
+}
+
+/// Topics a log of `event` carries: topic0 plus one per indexed parameter.
+pub fn topic_count(event: &Event) -> usize {
+    1 + event.inputs.iter().filter(|p| p.indexed).count()
+}
+
+/// The type a parameter decodes to: indexed strings, bytes, arrays and tuples are only their 32-byte topic hash.
+pub fn decoded_kind(param: &EventParam) -> ParamType {
+    match &param.kind {
+        ParamType::String | ParamType::Bytes | ParamType::Array(_) | ParamType::FixedArray(..) | ParamType::Tuple(_) if param.indexed => {
+            ParamType::FixedBytes(32)
+        }
+        kind => kind.clone(),
+    }
+}
+
+/// `Transfer(address indexed,address indexed,uint256)`
+pub fn describe(event: &Event) -> String {
+    let params: Vec<String> = event
+        .inputs
+        .iter()
+        .map(|p| format!("{}{}", ethabi::param_type::Writer::write(&p.kind), if p.indexed { " indexed" } else { "" }))
+        .collect();
+    format!("{}({})", event.name, params.join(","))
+}
+
+/// The chain and path of an `--abi CHAIN:PATH` (`42161:abi/gmx.json`), whose events route only that chain's logs;
+/// `None` for a plain path. Only a decimal chain ID counts, so `C:\abi.json` stays a path.
+pub fn chain_scoped(path: &Path) -> Option<(u64, PathBuf)> {
+    let (chain, rest) = path.to_str()?.split_once(':')?;
+    if rest.is_empty() || !chain.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    Some((chain.parse().ok()?, PathBuf::from(rest)))
+}
+
+/// Events of one or more ABI files, each with its topic0, and what loading them cost.
+pub struct AbiSet {
+    pub events: Vec<(H256, Event)>,
+    pub files: usize,
+    /// Files read back from the cache instead of parsed
+    pub cache_hits: usize,
+    pub load_time: Duration,
+}
+
+impl AbiSet {
+    /// Loads the events of every file in `paths`, in order: JSON ABIs, or Solidity source for `.sol` files. With
+    /// `contract`, solc standard-json files contribute only that contract's events. Files are read and parsed
+    /// across the available cores, and their topic0s hashed in one batch split the same way. With `cache`, each
+    /// file's parsed events and topic0s are stored in that directory under the SHA-256 of the file (and contract),
+    /// and later loads of the same bytes skip JSON parsing and keccak hashing; the merged set is stored too, under
+    /// the paths, sizes and modification times of all files, so an unchanged set loads from one file without
+    /// reading any ABI. The events of `presets` follow those of the files.
+    pub fn load(paths: &[PathBuf], presets: &[Preset], cache: Option<&Path>, contract: Option<&str>) -> Result<AbiSet> {
+        let started = Instant::now();
+        let mut set = AbiSet { events: Vec::new(), files: paths.len(), cache_hits: 0, load_time: Duration::ZERO };
+        if let Some(dir) = cache { fs::create_dir_all(dir).with_context(|| format!("Cannot create ABI cache {:?}", dir))?; }
+        let index = cache.map(|dir| set_index_file(dir, paths, contract)).transpose()?;
+        if let Some(events) = index.as_deref().and_then(read_cache) {
+            set.cache_hits = paths.len();
+            set.events = events;
+        } else {
+            let files = parallel_map(paths, FILES_PER_THREAD, |path| load_file(path, cache, contract))
+                .into_iter()
+                .collect::<Result<Vec<LoadedFile>>>()?;
+            let unhashed: Vec<&Event> = files.iter().filter(|f| f.topic0s.is_none()).flat_map(|f| &f.events).collect();
+            let mut topic0s = parallel_map(&unhashed, SIGNATURES_PER_THREAD, |e| e.signature()).into_iter();
+            for file in files {
+                let hit = file.topic0s.is_some();
+                set.cache_hits += hit as usize;
+                let file_topic0s = file.topic0s.unwrap_or_else(|| topic0s.by_ref().take(file.events.len()).collect());
+                let events: Vec<(H256, Event)> = file_topic0s.into_iter().zip(file.events).collect();
+                if let (false, Some(cache_file)) = (hit, &file.cache_file) { write_cache(cache_file, &events)?; }
+                set.events.extend(events);
+            }
+            if let Some(index) = &index { write_cache(index, &set.events)?; }
+        }
+        for preset in presets {
+            set.events.extend(preset.events().into_iter().map(|e| (e.signature(), e)));
+        }
+        if set.events.is_empty() { return Err(anyhow!("No events found in ABI")); }
+        set.load_time = started.elapsed();
+        Ok(set)
+    }
+
+    /// Topic0s whose events disagree on which parameters are indexed, in ABI order.
+    pub fn collisions(&self) -> Vec<Collision> {
+        let mut order: Vec<H256> = Vec::new();
+        let mut layouts: HashMap<H256, Vec<&Event>> = HashMap::new();
+        for (topic0, event) in &self.events {
+            let seen = layouts.entry(*topic0).or_insert_with(|| { order.push(*topic0); Vec::new() });
+            let indexed = |e: &Event| e.inputs.iter().map(|p| p.indexed).collect::<Vec<_>>();
+            if !seen.iter().any(|e| indexed(e) == indexed(event)) { seen.push(event); }
+        }
+        order
+            .into_iter()
+            .filter(|t| layouts[t].len() > 1)
+            .map(|topic0| Collision { topic0, events: layouts[&topic0].iter().map(|&e| e.clone()).collect() })
+            .collect()
+    }
+
+    /// The event called `event_name` in any of the files, or the first event when the name is empty.
+    pub fn select(&self, event_name: &str) -> Result<Event> {
+        let mut events = self.events.iter().map(|(_, e)| e);
+        let found = if event_name.is_empty() { events.next() } else { events.find(|e| e.name == event_name) };
+        match found {
+            Some(event) => Ok(event.clone()),
+            None if event_name.is_empty() => Err(anyhow!("No events found in ABI")),
+            None => Err(anyhow!("Event '{}' not found in ABI", event_name)),
+        }
+    }
+}
+
+/// ABI files given to one worker thread at least; fewer are read on the calling thread.
+const FILES_PER_THREAD: usize = 8;
+/// Events whose topic0 one worker thread hashes at least.
+const SIGNATURES_PER_THREAD: usize = 4096;
+
+/// The parsed events of one ABI file, with their topic0s when they came from the cache.
+struct LoadedFile {
+    events: Vec<Event>,
+    topic0s: Option<Vec<H256>>,
+    /// Where the file's events are cached once hashed
+    cache_file: Option<PathBuf>,
+}
+
+fn load_file(path: &Path, cache: Option<&Path>, contract: Option<&str>) -> Result<LoadedFile> {
+    let bytes = fs::read(path).with_context(|| format!("Cannot open ABI file: {:?}", path))?;
+    let key = Sha256::new().chain_update(&bytes).chain_update(contract.unwrap_or("")).finalize();
+    let cache_file = cache.map(|dir| dir.join(format!("{}.bin", hex::encode(key))));
+    if let Some(events) = cache_file.as_deref().and_then(read_cache) {
+        let (topic0s, events) = events.into_iter().unzip();
+        return Ok(LoadedFile { events, topic0s: Some(topic0s), cache_file });
+    }
+    Ok(LoadedFile { events: parse_events(path, &bytes, contract)?, topic0s: None, cache_file })
+}
+
+/// The cache file of a whole ABI set: keyed by the path, size and modification time of each file, in order, so
+/// checking it needs no file contents.
+fn set_index_file(dir: &Path, paths: &[PathBuf], contract: Option<&str>) -> Result<PathBuf> {
+    let mut key = Sha256::new().chain_update(b"set").chain_update(contract.unwrap_or(""));
+    for path in paths {
+        let meta = fs::metadata(path).with_context(|| format!("Cannot open ABI file: {:?}", path))?;
+        let modified = meta.modified().ok().and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok()).unwrap_or_default();
+        let path = crate::io::canonicalize(path).unwrap_or_else(|_| path.clone());
+        key.update(path.as_os_str().as_encoded_bytes());
+        key.update([0]);
+        key.update(meta.len().to_le_bytes());
+        key.update(modified.as_nanos().to_le_bytes());
+    }
+    Ok(dir.join(format!("set-{}.bin", hex::encode(key.finalize()))))
+}
+
+/// `f` over `items`, in order, split into contiguous runs of at least `min_run` items, one thread per run and at
+/// most one run per core.
+fn parallel_map<T: Sync, U: Send>(items: &[T], min_run: usize, f: impl Fn(&T) -> U + Sync) -> Vec<U> {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let run = items.len().div_ceil(cores).max(min_run);
+    if run >= items.len() { return items.iter().map(f).collect(); }
+    let f = &f;
+    thread::scope(|s| {
+        let workers: Vec<_> = items.chunks(run).map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<U>>())).collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))).collect()
+    })
+}
+
+/// Cache file layout: the events of one ABI file with their topic0s and canonical parameter types.
+#[derive(Serialize, Deserialize)]
+struct CachedAbi {
+    version: u32,
+    events: Vec<CachedEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEvent {
+    topic0: [u8; 32],
+    name: String,
+    anonymous: bool,
+    /// (name, canonical type such as `uint256[]`, indexed)
+    inputs: Vec<(String, String, bool)>,
+}
+
+/// Events of a cache file; `None` when it is missing, unreadable or from another layout version, so it is rebuilt.
+fn read_cache(file: &Path) -> Option<Vec<(H256, Event)>> {
+    let cached: CachedAbi = bincode::deserialize(&fs::read(file).ok()?).ok()?;
+    if cached.version != CACHE_VERSION { return None; }
+    cached
+        .events
+        .into_iter()
+        .map(|e| {
+            let inputs = e.inputs.into_iter().map(|(name, kind, indexed)| Some(EventParam { name, kind: parse_param_type(&kind)?, indexed }));
+            Some((H256(e.topic0), Event { name: e.name, inputs: inputs.collect::<Option<_>>()?, anonymous: e.anonymous }))
+        })
+        .collect()
+}
+
+/// Writes through a temporary file and a rename, so concurrent runs never read a half-written cache.
+fn write_cache(file: &Path, events: &[(H256, Event)]) -> Result<()> {
+    let cached = CachedAbi {
+        version: CACHE_VERSION,
+        events: events
+            .iter()
+            .map(|(topic0, e)| CachedEvent {
+                topic0: topic0.0,
+                name: e.name.clone(),
+                anonymous: e.anonymous,
+                inputs: e.inputs.iter().map(|p| (p.name.clone(), ethabi::param_type::Writer::write(&p.kind), p.indexed)).collect(),
+            })
+            .collect(),
+    };
+    let tmp = file.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&tmp, bincode::serialize(&cached)?).with_context(|| format!("Cannot write ABI cache {:?}", tmp))?;
+    fs::rename(&tmp, file).with_context(|| format!("Cannot write ABI cache {:?}", file))?;
+    Ok(())
+}
+
+/// The events of an ABI file's contents: a JSON ABI, or Solidity source when the file ends in `.sol`.
+fn parse_events(path: &Path, bytes: &[u8], contract: Option<&str>) -> Result<Vec<Event>> {
+    if path.extension().is_some_and(|ext| ext == "sol") {
+        let source = std::str::from_utf8(bytes).with_context(|| format!("{:?} is not UTF-8 Solidity source", path))?;
+        return Ok(events_from_solidity(source));
+    }
+    let json_value: Value = serde_json::from_slice(bytes).with_context(|| format!("Invalid ABI JSON in {:?}", path))?;
+    contract_events_from_json(&json_value, contract).with_context(|| format!("Failed to load event(s) from {:?}", path))
+}
+
+/// Extracts the events of an ABI document; entries that are not events or use unsupported types are skipped.
+pub fn events_from_json(json_value: &Value) -> Result<Vec<Event>> {
+    contract_events_from_json(json_value, None)
+}
+
+/// Like [`events_from_json`], also accepting solc standard-json output (`contracts.<source>.<name>.abi`, possibly
+/// under `output` as in build-info files). The events of all its contracts are merged unless `contract` picks one,
+/// as `Name` or `path/File.sol:Name`; other documents hold a single ABI and ignore `contract`.
+pub fn contract_events_from_json(json_value: &Value, contract: Option<&str>) -> Result<Vec<Event>> {
+    // Hardhat and Foundry build-info files wrap the standard-json output in `output`
+    let standard_output = json_value.get("output").unwrap_or(json_value);
+    if let Some(sources) = standard_output.get("contracts").and_then(Value::as_object) {
+        return standard_json_events(sources, contract);
+    }
+    // ABI can be an array or an object with `abi` or `events`
+    let arr = if let Some(arr) = json_value.as_array() {
+        arr
+    } else if let Some(arr) = json_value.get("abi").and_then(|v| v.as_array()) {
+        arr
+    } else if let Some(arr) = json_value.get("events").and_then(|v| v.as_array()) {
+        arr
+    } else {
+        return Err(anyhow!("Unsupported ABI JSON structure"));
+    };
+    Ok(arr.iter().filter_map(parse_event_from_value).collect())
+}
+
+fn standard_json_events(sources: &Map<String, Value>, contract: Option<&str>) -> Result<Vec<Event>> {
+    let mut events: Vec<Event> = Vec::new();
+    // Indices into `events` by signature, so deduplicating thousands of contracts stays linear
+    let mut by_signature: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut names = Vec::new();
+    for (source, contracts) in sources {
+        let Some(contracts) = contracts.as_object() else { continue };
+        for (name, output) in contracts {
+            let qualified = format!("{}:{}", source, name);
+            if contract.is_some_and(|wanted| wanted != name && wanted != qualified) {
+                names.push(qualified);
+                continue;
+            }
+            let abi = output.get("abi").and_then(Value::as_array).with_context(|| format!("{} has no abi", qualified))?;
+            // Inherited events appear in every contract that inherits them
+            for event in abi.iter().filter_map(parse_event_from_value) {
+                let same = by_signature.entry(describe(&event)).or_default();
+                if !same.iter().any(|&i| events[i] == event) {
+                    same.push(events.len());
+                    events.push(event);
+                }
+            }
+            names.clear();
+            if contract.is_some() { return Ok(events); }
+        }
+    }
+    match contract {
+        Some(wanted) => Err(anyhow!("No contract {} in the standard-json output (it has {})", wanted, names.join(", "))),
+        None => Ok(events),
+    }
+}
+
+/// The functions of the ABI files in `paths`, in order; overloads and duplicates are kept, for callers to index by
+/// selector. Solidity sources are refused, as only their events are parsed.
+pub fn load_functions(paths: &[PathBuf]) -> Result<Vec<Function>> {
+    let mut functions = Vec::new();
+    for path in paths {
+        if path.extension().is_some_and(|ext| ext == "sol") { return Err(anyhow!("{:?}: functions are read from JSON ABIs, not Solidity source", path)); }
+        let bytes = fs::read(path).with_context(|| format!("Cannot open ABI file: {:?}", path))?;
+        let json_value: Value = serde_json::from_slice(&bytes).with_context(|| format!("Invalid ABI JSON in {:?}", path))?;
+        functions.extend(functions_from_json(&json_value).with_context(|| format!("Failed to load function(s) from {:?}", path))?);
+    }
+    Ok(functions)
+}
+
+/// Extracts the functions of an ABI document, an array or an object with `abi`, or of every contract of solc
+/// standard-json output; entries that are not functions or use unsupported types are skipped.
+pub fn functions_from_json(json_value: &Value) -> Result<Vec<Function>> {
+    let standard_output = json_value.get("output").unwrap_or(json_value);
+    let abis: Vec<&Vec<Value>> = if let Some(sources) = standard_output.get("contracts").and_then(Value::as_object) {
+        sources.values().filter_map(Value::as_object).flat_map(|c| c.values()).filter_map(|c| c.get("abi")?.as_array()).collect()
+    } else if let Some(arr) = json_value.as_array().or_else(|| json_value.get("abi").and_then(Value::as_array)) {
+        vec![arr]
+    } else {
+        return Err(anyhow!("Unsupported ABI JSON structure"));
+    };
+    Ok(abis.into_iter().flatten().filter_map(parse_function_from_value).collect())
+}
+
+fn parse_function_from_value(v: &Value) -> Option<Function> {
+    if v.get("type").and_then(Value::as_str) != Some("function") { return None; }
+    let params = |key: &str| -> Option<Vec<Param>> {
+        let Some(params) = v.get(key) else { return Some(Vec::new()) };
+        params.as_array()?.iter().map(|p| {
+            let name = p.get("name").and_then(Value::as_str).unwrap_or("").to_string();
+            Some(Param { name, kind: parse_param_type(&canonical_type(p)?)?, internal_type: None })
+        }).collect()
+    };
+    let state_mutability = v.get("stateMutability").and_then(|m| serde_json::from_value(m.clone()).ok()).unwrap_or(StateMutability::NonPayable);
+    #[allow(deprecated)]
+    Some(Function { name: v.get("name")?.as_str()?.to_string(), inputs: params("inputs")?, outputs: params("outputs")?, constant: None, state_mutability })
+}
+
+fn parse_event_from_value(v: &Value) -> Option<Event> {
+    if v.get("type").and_then(|t| t.as_str()) != Some("event") { return None; }
+    let name = v.get("name")?.as_str()?.to_string();
+    let inputs_v = v.get("inputs")?.as_array()?;
+
+    let mut inputs: Vec<EventParam> = Vec::with_capacity(inputs_v.len());
+    for i in inputs_v {
+        let name_i = i.get("name").and_then(|s| s.as_str()).unwrap_or("").to_string();
+        let indexed = i.get("indexed").and_then(|b| b.as_bool()).unwrap_or(false);
+        let param_type = parse_param_type(&canonical_type(i)?)?;
+        inputs.push(EventParam { name: name_i, kind: param_type, indexed });
+    }
+
+    Some(Event { name, inputs, anonymous: false })
+}
+
+/// The type of an ABI parameter as written in signatures, spelling a `tuple` out from its `components`, e.g.
+/// `(address,uint256)[]` for a `tuple[]`.
+fn canonical_type(param: &Value) -> Option<String> {
+    let kind = param.get("type").and_then(Value::as_str).unwrap_or("");
+    let Some(suffix) = kind.strip_prefix("tuple") else { return Some(kind.to_string()) };
+    let components = param.get("components")?.as_array()?.iter().map(canonical_type).collect::<Option<Vec<_>>>()?;
+    Some(format!("({}){}", components.join(","), suffix))
+}
+
+pub(crate) fn parse_param_type(s: &str) -> Option<ParamType> {
+    // Array suffixes bind last: `uint256[]`, `bytes32[2][]`. Must be checked before the `uint`/`bytes` prefixes.
+    if let Some(open) = s.strip_suffix(']').and_then(|t| t.rfind('[')) {
+        let inner_t = parse_param_type(&s[..open])?;
+        let size = &s[open + 1..s.len() - 1];
+        return if size.is_empty() { Some(ParamType::Array(Box::new(inner_t))) } else { Some(ParamType::FixedArray(Box::new(inner_t), size.parse().ok()?)) };
+    }
+    // Tuples: `(address,(uint256,bytes)[])`, split at the commas outside nested parentheses
+    if let Some(members) = s.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        if members.is_empty() { return Some(ParamType::Tuple(Vec::new())); }
+        let mut types = Vec::new();
+        let (mut depth, mut start) = (0usize, 0);
+        for (i, c) in members.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.checked_sub(1)?,
+                ',' if depth == 0 => {
+                    types.push(parse_param_type(&members[start..i])?);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        types.push(parse_param_type(&members[start..])?);
+        return Some(ParamType::Tuple(types));
+    }
+    match s {
+        "address" => Some(ParamType::Address),
+        "bool" => Some(ParamType::Bool),
+        "string" => Some(ParamType::String),
+        "bytes" => Some(ParamType::Bytes),
+        _ if s.starts_with("bytes") => { let n: usize = s[5..].parse().ok()?; Some(ParamType::FixedBytes(n)) }
+        _ if s.starts_with("uint") => { let n: usize = s[4..].parse().unwrap_or(256); Some(ParamType::Uint(n)) }
+        _ if s.starts_with("int") => { let n: usize = s[3..].parse().unwrap_or(256); Some(ParamType::Int(n)) }
+        _ => None,
+    }
+}
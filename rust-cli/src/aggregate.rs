@@ -0,0 +1,287 @@
+//! `--aggregate`: counts, sums, minima and maxima of decoded parameters, optionally grouped, kept up to date as logs
+//! decode and printed when the input ends, e.g. `sum(value) by to` for the transfer volume per recipient.
+//!
+//! A spec is a comma-separated list of `count`, `count(f)`, `sum(f)`, `min(f)` and `max(f)`, optionally followed by
+//! `by` and comma-separated fields to group on. Fields are named as in `--where`: parameters, then `event`,
+//! `address` and `blockNumber`. `count` counts logs, `count(f)` those that have `f`, and the others the logs whose
+//! `f` is an integer; sums are exact (kept in 512 bits). Each group prints as one JSON line, in key order, with
+//! integers as decimal strings as in the decoded output.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, Result};
+use ethereum_types::{U256, U512};
+use serde_json::{Map, Value};
+
+use crate::decode::DecodedLog;
+use crate::predicate::{field, signed_cmp, Val};
+use crate::timing::{self, Phase};
+use crate::value;
+
+/// Group maps kept apart, one per decoding thread up to this many, and merged for printing.
+const SHARDS: usize = 64;
+
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The shard this thread adds to, so decoding threads do not contend for one lock
+    static SHARD: usize = NEXT_SHARD.fetch_add(1, AtomicOrdering::Relaxed) % SHARDS;
+}
+
+type Groups = HashMap<Vec<Key>, Vec<Acc>>;
+
+/// A parsed `--aggregate` spec and the groups accumulated so far, shared by all decoding threads.
+#[derive(Debug)]
+pub struct Aggregator {
+    aggregates: Vec<Aggregate>,
+    by: Vec<String>,
+    shards: Vec<Mutex<Groups>>,
+}
+
+#[derive(Debug)]
+struct Aggregate {
+    func: Func,
+    field: Option<String>,
+    /// As printed, e.g. `sum(value)`
+    name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Func {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+/// A field's value as a group key (or a `--topk` value).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Key {
+    Null,
+    Bool(bool),
+    Number { negative: bool, magnitude: U256 },
+    Bytes(Vec<u8>),
+    Str(String),
+}
+
+#[derive(Clone, Debug)]
+enum Acc {
+    Count(u64),
+    /// Positive and negative parts apart, so neither can overflow for any realistic input
+    Sum { positive: U512, negative: U512 },
+    Min(Option<(bool, U256)>),
+    Max(Option<(bool, U256)>),
+}
+
+impl Aggregator {
+    pub fn parse(spec: &str) -> Result<Aggregator> {
+        let (list, by) = match spec.split_once(" by ") {
+            Some((list, by)) => (list, by.split(',').map(|f| f.trim().to_string()).collect()),
+            None => (spec, Vec::new()),
+        };
+        let aggregates = list.split(',').map(|a| Aggregate::parse(a.trim())).collect::<Result<Vec<_>>>()?;
+        if let Some(empty) = by.iter().find(|f: &&String| !is_name(f)) {
+            bail!("--aggregate: invalid group field {:?}", empty);
+        }
+        Ok(Aggregator { aggregates, by, shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect() })
+    }
+
+    /// The fields the spec refers to, for checking against the ABI.
+    pub fn fields(&self) -> Vec<&str> {
+        self.aggregates.iter().filter_map(|a| a.field.as_deref()).chain(self.by.iter().map(String::as_str)).collect()
+    }
+
+    /// Adds `log` to its group; timed as `Phase::Aggregate`.
+    pub fn add(&self, log: &DecodedLog) {
+        let t = timing::start();
+        let key: Vec<Key> = self.by.iter().map(|name| Key::of(field(log, name))).collect();
+        let values: Vec<Option<Val>> = self.aggregates.iter().map(|a| a.field.as_deref().and_then(|name| field(log, name))).collect();
+        let mut groups = SHARD.with(|&shard| self.shards[shard].lock().expect("aggregate groups poisoned"));
+        let accs = groups.entry(key).or_insert_with(|| self.aggregates.iter().map(|a| Acc::new(a.func)).collect());
+        for ((acc, aggregate), value) in accs.iter_mut().zip(&self.aggregates).zip(&values) {
+            acc.add(aggregate.field.is_none(), value.as_ref());
+        }
+        drop(groups);
+        timing::record(Phase::Aggregate, t);
+    }
+
+    /// The shards merged into one map of groups.
+    fn merged(&self) -> Groups {
+        let mut merged = Groups::new();
+        for shard in &self.shards {
+            for (key, accs) in shard.lock().expect("aggregate groups poisoned").iter() {
+                match merged.get_mut(key) {
+                    Some(total) => total.iter_mut().zip(accs).for_each(|(total, acc)| total.merge(acc)),
+                    None => { merged.insert(key.clone(), accs.clone()); }
+                }
+            }
+        }
+        merged
+    }
+
+    pub fn groups(&self) -> usize {
+        self.merged().len()
+    }
+
+    /// Writes one JSON line per group, groups in key order.
+    pub fn write(&self, out: &mut impl Write) -> Result<()> {
+        let groups = self.merged();
+        let mut sorted: Vec<_> = groups.iter().collect();
+        sorted.sort_by(|(a, _), (b, _)| {
+            a.iter().zip(b.iter()).map(|(a, b)| a.cmp(b)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal)
+        });
+        for (key, accs) in sorted {
+            let mut record = Map::new();
+            for (name, key) in self.by.iter().zip(key) { record.insert(name.clone(), key.to_json()); }
+            for (aggregate, acc) in self.aggregates.iter().zip(accs) { record.insert(aggregate.name.clone(), acc.to_json()); }
+            serde_json::to_writer(&mut *out, &Value::Object(record))?;
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl Aggregate {
+    fn parse(text: &str) -> Result<Aggregate> {
+        let invalid = || anyhow!("--aggregate: expected count, count(f), sum(f), min(f) or max(f), found {:?}", text);
+        let (func, field) = match text.split_once('(') {
+            None if text == "count" => (Func::Count, None),
+            None => return Err(invalid()),
+            Some((func, rest)) => {
+                let field = rest.strip_suffix(')').map(str::trim).filter(|f| is_name(f)).ok_or_else(invalid)?;
+                let func = match func.trim() {
+                    "count" => Func::Count,
+                    "sum" => Func::Sum,
+                    "min" => Func::Min,
+                    "max" => Func::Max,
+                    _ => return Err(invalid()),
+                };
+                (func, Some(field.to_string()))
+            }
+        };
+        let func_name = match func {
+            Func::Count => "count",
+            Func::Sum => "sum",
+            Func::Min => "min",
+            Func::Max => "max",
+        };
+        let name = match &field {
+            Some(field) => format!("{}({})", func_name, field),
+            None => func_name.to_string(),
+        };
+        Ok(Aggregate { func, field, name })
+    }
+}
+
+fn is_name(field: &str) -> bool {
+    !field.is_empty() && field.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+impl Key {
+    pub(crate) fn of(value: Option<Val>) -> Key {
+        match value {
+            None => Key::Null,
+            Some(Val::Bool(b)) => Key::Bool(b),
+            Some(Val::Number { negative, magnitude }) => Key::Number { negative, magnitude },
+            Some(Val::Bytes(bytes)) => Key::Bytes(bytes.into_owned()),
+            Some(Val::Hex(bytes)) => Key::Bytes(bytes.to_vec()),
+            Some(Val::Str(s)) => Key::Str(s.to_string()),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Key::Null => 0,
+            Key::Bool(_) => 1,
+            Key::Number { .. } => 2,
+            Key::Bytes(_) => 3,
+            Key::Str(_) => 4,
+        }
+    }
+
+    fn cmp(&self, other: &Key) -> Ordering {
+        match (self, other) {
+            (Key::Bool(a), Key::Bool(b)) => a.cmp(b),
+            (Key::Number { negative: an, magnitude: am }, Key::Number { negative: bn, magnitude: bm }) => signed_cmp(*an, am, *bn, bm),
+            (Key::Bytes(a), Key::Bytes(b)) => a.cmp(b),
+            (Key::Str(a), Key::Str(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        match self {
+            Key::Null => Value::Null,
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Number { negative, magnitude } => Value::String(decimal(*negative, magnitude)),
+            Key::Bytes(bytes) => Value::String(format!("0x{}", hex::encode(bytes))),
+            Key::Str(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl Acc {
+    fn new(func: Func) -> Acc {
+        match func {
+            Func::Count => Acc::Count(0),
+            Func::Sum => Acc::Sum { positive: U512::zero(), negative: U512::zero() },
+            Func::Min => Acc::Min(None),
+            Func::Max => Acc::Max(None),
+        }
+    }
+
+    /// Adds one log's `value` of the aggregate's field; `all` for a bare `count`, which counts every log.
+    fn add(&mut self, all: bool, value: Option<&Val>) {
+        if let Acc::Count(n) = self {
+            if all || value.is_some() { *n += 1; }
+            return;
+        }
+        let Some(&Val::Number { negative, magnitude }) = value else { return };
+        match self {
+            Acc::Count(_) => {}
+            Acc::Sum { positive, negative: negatives } => {
+                let part = if negative { negatives } else { positive };
+                *part = part.saturating_add(U512::from(magnitude));
+            }
+            Acc::Min(min) => {
+                if min.as_ref().is_none_or(|(n, m)| signed_cmp(negative, &magnitude, *n, m).is_lt()) { *min = Some((negative, magnitude)); }
+            }
+            Acc::Max(max) => {
+                if max.as_ref().is_none_or(|(n, m)| signed_cmp(negative, &magnitude, *n, m).is_gt()) { *max = Some((negative, magnitude)); }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Acc) {
+        match (self, other) {
+            (Acc::Count(a), Acc::Count(b)) => *a += b,
+            (Acc::Sum { positive, negative }, Acc::Sum { positive: p, negative: n }) => {
+                *positive = positive.saturating_add(*p);
+                *negative = negative.saturating_add(*n);
+            }
+            (acc @ (Acc::Min(_) | Acc::Max(_)), Acc::Min(Some((negative, magnitude))) | Acc::Max(Some((negative, magnitude)))) => {
+                acc.add(false, Some(&Val::Number { negative: *negative, magnitude: *magnitude }));
+            }
+            _ => {}
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Acc::Count(n) => Value::from(*n),
+            Acc::Sum { positive, negative } if positive >= negative => Value::String((positive - negative).to_string()),
+            Acc::Sum { positive, negative } => Value::String(format!("-{}", negative - positive)),
+            Acc::Min(value) | Acc::Max(value) => value.map(|(n, m)| Value::String(decimal(n, &m))).unwrap_or(Value::Null),
+        }
+    }
+}
+
+fn decimal(negative: bool, magnitude: &U256) -> String {
+    let mut buf = [0; value::U256_DECIMAL_LEN];
+    let digits = value::u256_decimal(magnitude, &mut buf);
+    if negative { format!("-{}", digits) } else { digits.to_string() }
+}
@@ -0,0 +1,20 @@
+//! The binary's global allocator, picked at build time by the `alloc-jemalloc` and `alloc-mimalloc` features.
+
+#[cfg(all(feature = "alloc-jemalloc", feature = "alloc-mimalloc"))]
+compile_error!("the `alloc-jemalloc` and `alloc-mimalloc` features are mutually exclusive");
+
+#[cfg(all(feature = "alloc-jemalloc", not(target_env = "msvc")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "alloc-mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+/// The allocator this binary was built with, for the summary and `bench-all`.
+#[cfg(all(feature = "alloc-jemalloc", not(target_env = "msvc")))]
+pub const NAME: &str = "jemalloc";
+#[cfg(feature = "alloc-mimalloc")]
+pub const NAME: &str = "mimalloc";
+#[cfg(not(any(all(feature = "alloc-jemalloc", not(target_env = "msvc")), feature = "alloc-mimalloc")))]
+pub const NAME: &str = "system";
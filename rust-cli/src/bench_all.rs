@@ -0,0 +1,383 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use serde_json::{json, Value};
+
+use evm_rust_decoder::core_decode::HEX_DECODER;
+use evm_rust_decoder::{io, stop};
+
+use crate::config::BenchConfig;
+use crate::report::{Report, ReportFormat, Section};
+
+const DEFAULT_RUNS: usize = 3;
+const DEFAULT_NAPI_CMD: &str = "npx ts-node src/bench_napi.ts";
+
+#[derive(Args, Debug)]
+pub struct BenchAllArgs {
+    /// TOML file describing the benchmark matrix (datasets, ABIs, thread counts, runs, outputs); flags given on the
+    /// command line override it
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// ABI JSON passed to every implementation
+    #[arg(long, required_unless_present = "config")]
+    abi: Option<PathBuf>,
+
+    /// Event name to decode; omit to route by topic0 over all ABI events
+    #[arg(long)]
+    event: Option<String>,
+
+    /// Dataset (JSONL) passed to every implementation. Repeat to compare dataset sizes; each gets its own table.
+    #[arg(long, required_unless_present = "config")]
+    input: Vec<PathBuf>,
+
+    /// Runs per implementation; the table reports the median and best [default: 3]
+    #[arg(long)]
+    runs: Option<usize>,
+
+    /// Ask implementations to exclude file I/O from their timer (--preload for the CLI, PRELOAD=1 for scripts)
+    #[arg(long, default_value_t = false)]
+    preload: bool,
+
+    /// Rust CLI thread counts, e.g. `1,4,8`; each is benchmarked as its own row (`rust_cli_t4`) [default: 1]
+    #[arg(long, value_delimiter = ',')]
+    threads: Vec<usize>,
+
+    /// Command running the N-API harness (executed with `sh -c`, `cmd /C` on Windows) [default: npx ts-node src/bench_napi.ts]
+    #[arg(long)]
+    napi_cmd: Option<String>,
+
+    /// Leave out the N-API harness (e.g. when the addon is not built)
+    #[arg(long, default_value_t = false)]
+    skip_napi: bool,
+
+    /// Additional implementation as NAME=COMMAND, e.g. `go=./go-decoder --abi {abi} --input {input}`. The command
+    /// runs with `sh -c` (`cmd /C` on Windows); `{abi}`, `{input}` and `{event}` are substituted and ABI, IN, EVENT
+    /// and PRELOAD are set in its environment. It must print `decoded=N elapsed_ms=X` on stdout or stderr. Repeatable.
+    #[arg(long = "impl", value_name = "NAME=COMMAND")]
+    impls: Vec<String>,
+
+    /// Also write the combined results as JSON to this file
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// Also render the results as a Markdown or HTML report with throughput bar charts
+    #[arg(long, value_enum)]
+    report: Option<ReportFormat>,
+
+    /// Write the --report to this file instead of stdout
+    #[arg(long)]
+    report_file: Option<PathBuf>,
+
+    /// Also append the results to this `results` history store
+    #[arg(long, value_name = "STORE")]
+    record: Option<PathBuf>,
+}
+
+/// `BenchAllArgs` merged with the config file: everything a run needs, with paths resolved.
+struct Plan {
+    datasets: Vec<DatasetPlan>,
+    runs: usize,
+    preload: bool,
+    threads: Vec<usize>,
+    napi_cmd: Option<String>,
+    impls: Vec<(String, String)>,
+    /// Where implementation commands run: the config file's directory, or the current one
+    workdir: Option<PathBuf>,
+    json: Option<PathBuf>,
+    report: Option<ReportFormat>,
+    report_file: Option<PathBuf>,
+    store: Option<PathBuf>,
+}
+
+struct DatasetPlan {
+    input: PathBuf,
+    abi: PathBuf,
+    event: Option<String>,
+}
+
+/// One implementation under test and how to start it.
+struct Implementation {
+    name: String,
+    command: Command,
+    display: String,
+}
+
+/// Metrics an implementation reported for one run.
+#[derive(Clone)]
+pub(crate) struct RunMetrics {
+    pub(crate) decoded: u64,
+    pub(crate) elapsed_ms: f64,
+    /// The JS runtime of a harness that prints `runtime=bun/1.2.4` (`node/...`, `deno/...`) with its summary
+    runtime: Option<String>,
+}
+
+/// Outcome of all runs of one implementation on one dataset.
+struct Outcome {
+    name: String,
+    display: String,
+    runs: Vec<RunMetrics>,
+    error: Option<String>,
+}
+
+/// Runs every implementation `runs` times on each dataset and prints one comparison row per implementation.
+pub fn run(args: &BenchAllArgs) -> Result<()> {
+    let plan = plan(args)?;
+    if plan.runs == 0 { bail!("runs must be at least 1"); }
+    stop::install()?;
+
+    let mut report = Vec::new();
+    let mut sections = Vec::new();
+    let mut by_size = Section {
+        title: "Throughput per dataset size".to_string(),
+        columns: ["dataset", "MiB", "impl", "decoded", "throughput_lps"].map(String::from).to_vec(),
+        rows: Vec::new(),
+        bars: Vec::new(),
+    };
+    let mut failed = 0;
+    for ds in &plan.datasets {
+        let input = io::canonicalize(&ds.input).with_context(|| format!("Cannot open input file: {:?}", ds.input))?;
+        let abi = io::canonicalize(&ds.abi).with_context(|| format!("Cannot open ABI file: {:?}", ds.abi))?;
+        let size_mib = std::fs::metadata(&input)?.len() as f64 / (1024.0 * 1024.0);
+        if plan.datasets.len() > 1 { println!("== {} ({:.1} MiB)", input.display(), size_mib); }
+
+        let mut outcomes = Vec::new();
+        for mut imp in implementations(&plan, &abi, &input, ds.event.as_deref())? {
+            let mut runs = Vec::with_capacity(plan.runs);
+            let mut error = None;
+            for _ in 0..plan.runs {
+                let run = run_once(&mut imp.command);
+                // Ctrl-C reaches the child too, so the run in progress measured only part of the input
+                if stop::requested() { break; }
+                match run {
+                    Ok(m) => runs.push(m),
+                    Err(e) => { error = Some(format!("{:#}", e)); break; }
+                }
+            }
+            if stop::requested() && runs.is_empty() { break; }
+            outcomes.push(Outcome { name: imp.name, display: imp.display, runs, error });
+            if stop::requested() { break; }
+        }
+        failed += outcomes.iter().filter(|o| o.error.is_some()).count();
+
+        let fastest = outcomes.iter().filter(|o| o.error.is_none()).map(|o| median(&o.runs)).fold(f64::INFINITY, f64::min);
+        println!("{:<12} {:>4} {:>10} {:>12} {:>12} {:>14} {:>10}", "impl", "runs", "decoded", "median_ms", "best_ms", "throughput_lps", "vs_fastest");
+        let dataset = input.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let mut section = Section {
+            title: format!("{} ({:.1} MiB)", dataset, size_mib),
+            columns: ["impl", "decoded", "median_ms", "best_ms", "throughput_lps", "vs_fastest"].map(String::from).to_vec(),
+            rows: Vec::new(),
+            bars: Vec::new(),
+        };
+        for o in &outcomes {
+            if let Some(e) = &o.error {
+                println!("{:<12} failed: {}", o.name, e);
+                report.push(json!({ "name": o.name, "input": input, "abi": abi, "event": ds.event, "command": o.display, "error": e }));
+                continue;
+            }
+            let (med, best, decoded) = (median(&o.runs), o.runs.iter().map(|r| r.elapsed_ms).fold(f64::INFINITY, f64::min), o.runs[0].decoded);
+            let throughput = if med > 0.0 { (decoded as f64 / (med / 1000.0)).round() } else { 0.0 };
+            println!(
+                "{:<12} {:>4} {:>10} {:>12.3} {:>12.3} {:>14.0} {:>9.2}x",
+                o.name, o.runs.len(), decoded, med, best, throughput, med / fastest
+            );
+            section.rows.push(vec![
+                o.name.clone(), decoded.to_string(), format!("{:.3}", med), format!("{:.3}", best),
+                format!("{:.0}", throughput), format!("{:.2}x", med / fastest),
+            ]);
+            section.bars.push(throughput);
+            by_size.rows.push(vec![
+                dataset.clone(), format!("{:.1}", size_mib),
+                o.name.clone(), decoded.to_string(), format!("{:.0}", throughput),
+            ]);
+            by_size.bars.push(throughput);
+            report.push(json!({
+                "name": o.name,
+                "input": input,
+                "abi": abi,
+                "event": ds.event,
+                "command": o.display,
+                "decoded": decoded,
+                "median_ms": med,
+                "best_ms": best,
+                "throughput_lps": throughput,
+                "runs_ms": o.runs.iter().map(|r| r.elapsed_ms).collect::<Vec<_>>(),
+                "runtime": o.runs[0].runtime,
+            }));
+        }
+        sections.push(section);
+
+        let mut counts: Vec<u64> = outcomes.iter().filter(|o| o.error.is_none()).map(|o| o.runs[0].decoded).collect();
+        counts.sort_unstable();
+        counts.dedup();
+        if counts.len() > 1 { tracing::warn!("implementations decoded different numbers of logs: {:?}", counts); }
+        if stop::requested() { break; }
+    }
+
+    let doc: Value = json!({
+        "config": args.config,
+        "preload": plan.preload,
+        "runs": plan.runs,
+        "threads": plan.threads,
+        "allocator": crate::allocator::NAME,
+        "interrupted": stop::interrupted(),
+        "results": report,
+    });
+    if let Some(path) = &plan.json {
+        std::fs::write(path, serde_json::to_string_pretty(&doc)?).with_context(|| format!("Cannot write {:?}", path))?;
+    }
+    match &plan.store {
+        // A partial matrix would show up as a regression in the trends
+        Some(store) if stop::interrupted() => tracing::warn!("not recording interrupted results in {}", store.display()),
+        Some(store) => crate::results::record_results(&doc, store)?,
+        None => {}
+    }
+    if plan.datasets.len() > 1 { sections.push(by_size); }
+    if let Some(format) = plan.report {
+        let ds = &plan.datasets[0];
+        let mut notes = vec![
+            format!("{} runs per implementation, median reported; preload: {}", plan.runs, plan.preload),
+            format!(
+                "target: {}-{}, hex decoder: {}, allocator: {}",
+                std::env::consts::ARCH, std::env::consts::OS, HEX_DECODER, crate::allocator::NAME
+            ),
+        ];
+        if plan.datasets.iter().all(|d| d.abi == ds.abi && d.event == ds.event) {
+            notes.insert(0, format!("ABI: `{}`, event: {}", ds.abi.display(), ds.event.as_deref().unwrap_or("all (topic0)")));
+        }
+        if let Some(config) = &args.config { notes.push(format!("config: `{}`", config.display())); }
+        if stop::interrupted() { notes.push("interrupted: implementations and datasets after the signal were not run".to_string()); }
+        Report { title: "Decoder benchmark".to_string(), notes, sections }.emit(format, plan.report_file.as_ref())?;
+    }
+
+    if stop::interrupted() {
+        tracing::warn!("interrupted: results cover the runs completed before the signal");
+        std::process::exit(stop::INTERRUPTED_EXIT_CODE);
+    }
+    if failed > 0 { bail!("{} implementation runs failed", failed); }
+    Ok(())
+}
+
+/// Merges the command line over the config file (if any). Lists given on the command line replace the config's,
+/// except `--impl`, which adds to the configured implementations.
+fn plan(args: &BenchAllArgs) -> Result<Plan> {
+    let (config, base) = match &args.config {
+        Some(path) => { let (c, base) = BenchConfig::load(path)?; (c, Some(base)) }
+        None => (BenchConfig::default(), None),
+    };
+    let resolve = |p: &Path| match &base { Some(b) if p.is_relative() => b.join(p), _ => p.to_path_buf() };
+    // An empty event in the config means "all events by topic0"
+    let event = |e: Option<&String>| e.filter(|e| !e.is_empty()).cloned();
+
+    let default_abi = args.abi.clone().or_else(|| config.abi.as_deref().map(resolve));
+    let default_event = if args.event.is_some() { event(args.event.as_ref()) } else { event(config.event.as_ref()) };
+    let datasets = if !args.input.is_empty() {
+        let abi = default_abi.clone().context("--abi is required")?;
+        args.input.iter().map(|input| DatasetPlan { input: input.clone(), abi: abi.clone(), event: default_event.clone() }).collect()
+    } else {
+        config.datasets.iter().map(|d| {
+            let abi = d.abi.as_deref().map(resolve).or_else(|| default_abi.clone())
+                .with_context(|| format!("dataset {:?} has no abi and no top-level abi is set", d.path))?;
+            let event = if args.event.is_some() || d.event.is_none() { default_event.clone() } else { event(d.event.as_ref()) };
+            Ok(DatasetPlan { input: resolve(&d.path), abi, event })
+        }).collect::<Result<Vec<_>>>()?
+    };
+    if datasets.is_empty() { bail!("No datasets: pass --input or add [[dataset]] entries to the config"); }
+
+    let mut impls: Vec<(String, String)> = config.impls.into_iter().map(|i| (i.name, i.cmd)).collect();
+    for spec in &args.impls {
+        let Some((name, cmd)) = spec.split_once('=') else { bail!("--impl expects NAME=COMMAND, got {:?}", spec) };
+        impls.push((name.to_string(), cmd.to_string()));
+    }
+    let napi = !args.skip_napi && config.napi.unwrap_or(true);
+    let threads = if !args.threads.is_empty() { args.threads.clone() } else { config.threads.unwrap_or_else(|| vec![1]) };
+    if threads.contains(&0) { bail!("thread counts must be at least 1"); }
+
+    Ok(Plan {
+        datasets,
+        runs: args.runs.or(config.runs).unwrap_or(DEFAULT_RUNS),
+        preload: args.preload || config.preload,
+        threads,
+        napi_cmd: napi.then(|| args.napi_cmd.clone().or(config.napi_cmd).unwrap_or_else(|| DEFAULT_NAPI_CMD.to_string())),
+        impls,
+        json: args.json.clone().or_else(|| config.output.json.as_deref().map(resolve)),
+        report: args.report.or(config.output.report),
+        report_file: args.report_file.clone().or_else(|| config.output.report_file.as_deref().map(resolve)),
+        store: args.record.clone().or_else(|| config.output.store.as_deref().map(resolve)),
+        workdir: base,
+    })
+}
+
+/// The Rust CLI (once per thread count), the N-API harness and every configured implementation, set up to decode `input`.
+fn implementations(plan: &Plan, abi: &Path, input: &Path, event: Option<&str>) -> Result<Vec<Implementation>> {
+    let env = [
+        ("ABI", abi.display().to_string()),
+        ("IN", input.display().to_string()),
+        ("EVENT", event.unwrap_or_default().to_string()),
+        ("PRELOAD", if plan.preload { "1" } else { "0" }.to_string()),
+    ];
+
+    let mut impls = Vec::new();
+    for &threads in &plan.threads {
+        let mut rust = Command::new(std::env::current_exe()?);
+        rust.arg("--abi").arg(abi).arg("--input").arg(input);
+        if let Some(event) = event { rust.arg("--event").arg(event); }
+        if plan.preload { rust.arg("--preload"); }
+        if threads > 1 { rust.arg("--threads").arg(threads.to_string()); }
+        let name = if plan.threads.len() == 1 && threads == 1 { "rust_cli".to_string() } else { format!("rust_cli_t{}", threads) };
+        impls.push(Implementation { name, display: format!("{:?}", rust), command: rust });
+    }
+    if let Some(cmd) = &plan.napi_cmd { impls.push(shell_impl("napi", cmd)); }
+    for (name, cmd) in &plan.impls {
+        let cmd = cmd.replace("{abi}", &env[0].1).replace("{input}", &env[1].1).replace("{event}", &env[2].1);
+        impls.push(shell_impl(name, &cmd));
+    }
+    for imp in &mut impls {
+        imp.command.envs(env.iter().map(|(k, v)| (k, v)));
+        if let Some(dir) = &plan.workdir { imp.command.current_dir(dir); }
+    }
+    Ok(impls)
+}
+
+/// Runs `cmd` with `sh -c`, or `cmd /C` on Windows, where there is no `sh` but `npx` and the like are `.cmd` scripts.
+fn shell_impl(name: &str, cmd: &str) -> Implementation {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut command = Command::new(shell);
+    command.arg(flag).arg(cmd);
+    Implementation { name: name.to_string(), command, display: cmd.to_string() }
+}
+
+pub(crate) fn run_once(command: &mut Command) -> Result<RunMetrics> {
+    let out = command.output().context("Failed to start")?;
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    if !out.status.success() {
+        bail!("exited with {}: {}", out.status, stderr.lines().last().unwrap_or(""));
+    }
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .filter_map(parse_metrics)
+        .next_back()
+        .context("no `decoded=N elapsed_ms=X` line in output")
+}
+
+/// Reads the `decoded=N elapsed_ms=X` summary every harness in this repo prints, and its `runtime=` if any (other
+/// `key=value` pairs are ignored).
+fn parse_metrics(line: &str) -> Option<RunMetrics> {
+    let field = |key: &str| line.split_whitespace().find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='));
+    Some(RunMetrics {
+        decoded: field("decoded")?.parse().ok()?,
+        elapsed_ms: field("elapsed_ms")?.parse().ok()?,
+        runtime: field("runtime").map(String::from),
+    })
+}
+
+pub(crate) fn median(runs: &[RunMetrics]) -> f64 {
+    let mut ms: Vec<f64> = runs.iter().map(|r| r.elapsed_ms).collect();
+    ms.sort_by(f64::total_cmp);
+    let mid = ms.len() / 2;
+    if ms.len().is_multiple_of(2) { (ms[mid - 1] + ms[mid]) / 2.0 } else { ms[mid] }
+}
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use serde_json::Value;
+
+use crate::bench_all::{median, run_once};
+use crate::logging;
+
+#[derive(Args, Debug)]
+pub struct BenchPgoArgs {
+    /// ABI JSON to decode with
+    #[arg(long)]
+    abi: PathBuf,
+
+    /// Event name to decode; omit to route by topic0 over all ABI events
+    #[arg(long)]
+    event: Option<String>,
+
+    /// Dataset (JSONL) both binaries are timed on
+    #[arg(long)]
+    input: PathBuf,
+
+    /// Dataset the instrumented binary decodes to collect the profile [default: --input]
+    #[arg(long)]
+    training_input: Option<PathBuf>,
+
+    /// Timed runs per binary, alternating between them; the table reports the median and best
+    #[arg(long, default_value_t = 3)]
+    runs: usize,
+
+    /// Pass --preload to the timed runs and the training run
+    #[arg(long, default_value_t = false)]
+    preload: bool,
+
+    /// Cargo features of all three builds, e.g. `alloc-mimalloc`
+    #[arg(long)]
+    features: Option<String>,
+
+    /// Manifest of the crate to build [default: the one this binary was built from]
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+
+    /// Directory for the raw profiles and the merged `.profdata`; emptied before training [default: target/pgo-profiles
+    /// beside the manifest]
+    #[arg(long)]
+    profile_dir: Option<PathBuf>,
+}
+
+/// Builds the CLI as `release`, then instrumented (`pgo-generate`), trains it on a decode run, rebuilds it with the
+/// merged profile (`pgo-use`) and times the `release` and `pgo-use` binaries against each other.
+pub fn run(args: &BenchPgoArgs) -> Result<()> {
+    if args.runs == 0 { bail!("runs must be at least 1"); }
+    let manifest = args.manifest_path.clone().unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"));
+    let profile_dir = args.profile_dir.clone()
+        .unwrap_or_else(|| manifest.parent().unwrap_or(Path::new(".")).join("target").join("pgo-profiles"));
+    let host = host_target()?;
+    let profdata_tool = llvm_profdata(&host)?;
+
+    let baseline = build(&manifest, "release", args.features.as_deref(), None, &host)?;
+    if profile_dir.exists() {
+        std::fs::remove_dir_all(&profile_dir).with_context(|| format!("Cannot empty {:?}", profile_dir))?;
+    }
+    std::fs::create_dir_all(&profile_dir)?;
+    let profile_dir = std::path::absolute(&profile_dir)?;
+    let instrumented = build(&manifest, "pgo-generate", args.features.as_deref(), Some(format!("-Cprofile-generate={}", profile_dir.display())), &host)?;
+
+    let training = args.training_input.as_ref().unwrap_or(&args.input);
+    if logging::json() {
+        tracing::info!(input = %training.display(), "pgo training");
+    } else {
+        eprintln!("pgo training input={}", training.display());
+    }
+    let mut train = decode_command(&instrumented, args, training);
+    run_once(&mut train).context("Training run failed")?;
+
+    let profdata = profile_dir.join("merged.profdata");
+    let status = Command::new(&profdata_tool).arg("merge").arg("-o").arg(&profdata).arg(&profile_dir)
+        .stdout(Stdio::null()).status().with_context(|| format!("Cannot run {:?}", profdata_tool))?;
+    if !status.success() { bail!("llvm-profdata merge exited with {}; its LLVM version must match rustc's (`rustc -vV`)", status); }
+    let optimized = build(&manifest, "pgo-use", args.features.as_deref(), Some(format!("-Cprofile-use={}", profdata.display())), &host)?;
+
+    let mut binaries = [("release", &baseline, Vec::new()), ("pgo", &optimized, Vec::new())];
+    for _ in 0..args.runs {
+        for (name, bin, runs) in &mut binaries {
+            let m = run_once(&mut decode_command(bin, args, &args.input)).with_context(|| format!("{} run failed", name))?;
+            runs.push(m);
+        }
+    }
+
+    println!("{:<12} {:>4} {:>10} {:>12} {:>12} {:>14}", "build", "runs", "decoded", "median_ms", "best_ms", "throughput_lps");
+    for (name, _, runs) in &binaries {
+        let (med, best) = (median(runs), runs.iter().map(|r| r.elapsed_ms).fold(f64::INFINITY, f64::min));
+        let throughput = if med > 0.0 { runs[0].decoded as f64 / (med / 1000.0) } else { 0.0 };
+        println!("{:<12} {:>4} {:>10} {:>12.3} {:>12.3} {:>14.0}", name, runs.len(), runs[0].decoded, med, best, throughput);
+    }
+    let [(_, _, before), (_, _, after)] = &binaries;
+    if before[0].decoded != after[0].decoded {
+        tracing::warn!("the builds decoded different numbers of logs: {} and {}", before[0].decoded, after[0].decoded);
+    }
+    let (before_ms, after_ms) = (median(before), median(after));
+    println!(
+        "pgo speedup={:.3}x change={:+.1}% profile={}",
+        before_ms / after_ms, (after_ms / before_ms - 1.0) * 100.0, profdata.display()
+    );
+    Ok(())
+}
+
+/// The CLI's decode invocation for a timed or training run over `input`.
+fn decode_command(bin: &Path, args: &BenchPgoArgs, input: &Path) -> Command {
+    let mut command = Command::new(bin);
+    command.arg("--abi").arg(&args.abi).arg("--input").arg(input);
+    if let Some(event) = &args.event { command.arg("--event").arg(event); }
+    if args.preload { command.arg("--preload"); }
+    command
+}
+
+/// Builds the binary with `profile` and returns its path, as cargo reports it. `rustflags` is added to any
+/// `RUSTFLAGS` already set, for an explicit `--target` so that build scripts and proc macros are not instrumented;
+/// each profile has its own directory, so the builds do not invalidate each other.
+fn build(manifest: &Path, profile: &str, features: Option<&str>, rustflags: Option<String>, host: &str) -> Result<PathBuf> {
+    if logging::json() {
+        tracing::info!(profile, "pgo build");
+    } else {
+        eprintln!("pgo build profile={}", profile);
+    }
+    let mut cargo = Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    cargo.args(["build", "--bin", "evm_rust_decoder", "--message-format", "json-render-diagnostics", "--profile", profile]);
+    cargo.arg("--manifest-path").arg(manifest);
+    if let Some(features) = features { cargo.arg("--features").arg(features); }
+    if let Some(flags) = rustflags {
+        let flags = match std::env::var("RUSTFLAGS") { Ok(base) if !base.is_empty() => format!("{} {}", base, flags), _ => flags };
+        cargo.env("RUSTFLAGS", flags).arg("--target").arg(host);
+    }
+    let out = cargo.stderr(Stdio::inherit()).output().context("Cannot run cargo")?;
+    if !out.status.success() { bail!("cargo build --profile {} exited with {}", profile, out.status); }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-artifact" && msg["target"]["name"] == "evm_rust_decoder")
+        .find_map(|msg| msg["executable"].as_str().map(PathBuf::from))
+        .with_context(|| format!("cargo build --profile {} reported no executable", profile))
+}
+
+fn rustc(arg: &str) -> Option<String> {
+    Command::new("rustc").arg(arg).output().ok().filter(|o| o.status.success()).map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+}
+
+fn host_target() -> Result<String> {
+    rustc("-vV").and_then(|v| v.lines().find_map(|l| l.strip_prefix("host: ").map(String::from))).context("Cannot get the host target from `rustc -vV`")
+}
+
+/// `llvm-profdata` from the toolchain's `llvm-tools` component, or from `PATH`.
+fn llvm_profdata(host: &str) -> Result<PathBuf> {
+    if let Some(sysroot) = rustc("--print=sysroot") {
+        let tool = Path::new(sysroot.trim()).join("lib/rustlib").join(host).join("bin")
+            .join(if cfg!(windows) { "llvm-profdata.exe" } else { "llvm-profdata" });
+        if tool.exists() { return Ok(tool); }
+    }
+    // Older versions have no `--version`; being able to start it is enough
+    if Command::new("llvm-profdata").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok() {
+        return Ok(PathBuf::from("llvm-profdata"));
+    }
+    bail!("llvm-profdata not found: install it with `rustup component add llvm-tools-preview`")
+}
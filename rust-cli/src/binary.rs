@@ -0,0 +1,77 @@
+//! `--input-format bin`: logs as binary records, so topics and data reach the ABI decoder without JSON parsing or
+//! hex decoding.
+//!
+//! Each record is the number of topics (u8, at most 4), that many 32-byte topics, the data length (u32,
+//! little-endian) and the data bytes. Records follow each other with no header or padding, and carry no metadata
+//! (address, block number), so the envelope fields and the `--dedupe`/`--drop-removed` filters do not apply.
+
+use std::io::{ErrorKind, Read, Write};
+
+use anyhow::{anyhow, bail, Result};
+use ethabi::RawLog;
+use ethereum_types::H256;
+
+use crate::decode::{LogMeta, LogRecord};
+use crate::stop;
+
+/// Topics a log can have (`LOG0` to `LOG4`).
+pub const MAX_TOPICS: usize = 4;
+
+/// Streams the records of `reader` in order, calling `f` for each log; records without topics are skipped like
+/// blank JSONL lines. Reading ends early, without error, once [`stop::requested`] is set.
+pub fn for_each_log<R: Read>(mut reader: R, mut f: impl FnMut(LogRecord) -> Result<()>) -> Result<()> {
+    let mut record = 0usize;
+    while !stop::requested() {
+        let mut count = [0u8; 1];
+        if !read_first(&mut reader, &mut count)? { return Ok(()); }
+        record += 1;
+        let count = count[0] as usize;
+        if count > MAX_TOPICS { bail!("Invalid binary record {}: {} topics (at most {})", record, count, MAX_TOPICS); }
+
+        let mut topics = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut topic = [0u8; 32];
+            read_exact(&mut reader, &mut topic, record)?;
+            topics.push(H256(topic));
+        }
+        let mut len = [0u8; 4];
+        read_exact(&mut reader, &mut len, record)?;
+        let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+        read_exact(&mut reader, &mut data, record)?;
+
+        if topics.is_empty() { continue; }
+        f(LogRecord { raw: RawLog { topics, data }, meta: LogMeta::default() })?;
+    }
+    Ok(())
+}
+
+/// Fills `buf` with the first byte of a record; false at a clean end of input.
+fn read_first(reader: &mut impl Read, buf: &mut [u8; 1]) -> Result<bool> {
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return Ok(false),
+            Ok(_) => return Ok(true),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Like [`Read::read_exact`], reporting an input that ends mid-record as invalid data rather than an I/O error.
+fn read_exact(reader: &mut impl Read, buf: &mut [u8], record: usize) -> Result<()> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => anyhow!("Invalid binary record {}: input ends mid-record", record),
+        _ => e.into(),
+    })
+}
+
+/// Writes `log` as one record.
+pub fn write_record(out: &mut impl Write, log: &RawLog) -> Result<()> {
+    if log.topics.len() > MAX_TOPICS { bail!("A log has {} topics (at most {})", log.topics.len(), MAX_TOPICS); }
+    let len = u32::try_from(log.data.len()).map_err(|_| anyhow!("Log data of {} bytes does not fit a binary record", log.data.len()))?;
+    out.write_all(&[log.topics.len() as u8])?;
+    for topic in &log.topics { out.write_all(topic.as_bytes())?; }
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(&log.data)?;
+    Ok(())
+}
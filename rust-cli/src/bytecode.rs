@@ -0,0 +1,125 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use evm_rust_decoder::core_decode::parse_data_into;
+use evm_rust_decoder::decode::snippet;
+use evm_rust_decoder::disasm::{analyze, instructions};
+
+use crate::logging;
+use crate::modes::{run_lines, LineDecoder, LineOptions};
+
+/// One disassembled code blob, as printed. Fields are in sorted order.
+#[derive(Serialize)]
+struct Disassembled {
+    bad_jumps: usize,
+    bytes: usize,
+    jumpdests: Vec<usize>,
+    /// `0x0000 PUSH1 0x80`, one per instruction
+    ops: Vec<String>,
+    undefined: usize,
+}
+
+#[derive(Default)]
+struct Counts {
+    contracts: usize,
+    /// Lines without code (`0x`, as `eth_getCode` returns for accounts without any)
+    empty: usize,
+    bytes: usize,
+    instructions: usize,
+    jumpdests: usize,
+    jumps: usize,
+    static_jumps: usize,
+    bad_jumps: usize,
+    undefined: usize,
+    truncated: usize,
+}
+
+#[derive(Default)]
+struct Disassembler {
+    print: bool,
+    counts: Counts,
+    /// Reused buffer of the decoded code
+    code: Vec<u8>,
+}
+
+/// Disassembles contract bytecode, one blob per line, instead of decoding logs: the opcode stream and the jump
+/// destinations of each, reported as instructions per second and code MB/s.
+pub fn run(options: &LineOptions) -> Result<()> {
+    let mut disassembler = Disassembler { print: options.print, ..Disassembler::default() };
+    let run = run_lines(options, &mut disassembler)?;
+    let c = &disassembler.counts;
+    if logging::json() {
+        tracing::info!(
+            contracts = c.contracts, empty = c.empty, bytes = c.bytes, instructions = c.instructions, jumpdests = c.jumpdests,
+            jumps = c.jumps, static_jumps = c.static_jumps, bad_jumps = c.bad_jumps, undefined = c.undefined, truncated = c.truncated,
+            errors = run.errors, elapsed_ms = logging::round(run.elapsed_ms(), 3), throughput_ips = run.per_sec(c.instructions).round() as u64,
+            code_mb_per_s = logging::round(run.per_sec(c.bytes) / 1e6, 1), mb_per_s = logging::round(run.mb_per_s(), 1), "disasm"
+        );
+        return Ok(());
+    }
+    eprintln!(
+        "disasm contracts={} empty={} bytes={} instructions={} jumpdests={} jumps={} static_jumps={} bad_jumps={} undefined={} truncated={} errors={} elapsed_ms={:.3} throughput_ips={:.0} code_mb_per_s={:.1} mb_per_s={:.1}",
+        c.contracts, c.empty, c.bytes, c.instructions, c.jumpdests, c.jumps, c.static_jumps, c.bad_jumps, c.undefined, c.truncated, run.errors,
+        run.elapsed_ms(), run.per_sec(c.instructions), run.per_sec(c.bytes) / 1e6, run.mb_per_s()
+    );
+    Ok(())
+}
+
+impl LineDecoder for Disassembler {
+    fn decode_line(&mut self, line: &str, printed: &mut Vec<u8>) -> Result<()> {
+        let line = line.trim();
+        let value: Value;
+        let hex = match line.as_bytes()[0] {
+            b'{' | b'"' => {
+                value = serde_json::from_str(line).context("invalid JSON")?;
+                code_of(&value)?
+            }
+            _ => line,
+        };
+        parse_data_into(hex, &mut self.code).with_context(|| format!("Invalid bytecode hex: {}", snippet(hex)))?;
+        if self.code.is_empty() {
+            self.counts.empty += 1;
+            return Ok(());
+        }
+        let analysis = analyze(&self.code);
+        let c = &mut self.counts;
+        c.contracts += 1;
+        c.bytes += self.code.len();
+        c.instructions += analysis.instructions;
+        c.jumpdests += analysis.jumpdests.len();
+        c.jumps += analysis.jumps;
+        c.static_jumps += analysis.static_jumps;
+        c.bad_jumps += analysis.bad_jumps;
+        c.undefined += analysis.undefined;
+        c.truncated += usize::from(analysis.truncated);
+        if self.print {
+            let record = Disassembled {
+                bad_jumps: analysis.bad_jumps,
+                bytes: self.code.len(),
+                ops: instructions(&self.code).map(|i| format!("{:#06x} {}", i.pc, i.text())).collect(),
+                jumpdests: analysis.jumpdests,
+                undefined: analysis.undefined,
+            };
+            serde_json::to_writer(&mut *printed, &record)?;
+            printed.push(b'\n');
+        }
+        Ok(())
+    }
+}
+
+/// The code hex of a JSON line: a string, an `eth_getCode` response, or an object with `code`, `deployedBytecode`
+/// or `bytecode`, as a string or as solc and forge artifacts nest it (`{"object": "0x..."}`).
+fn code_of(value: &Value) -> Result<&str> {
+    if let Some(error) = value.get("error").filter(|_| value.get("jsonrpc").is_some()) { bail!("JSON-RPC error: {}", error); }
+    let code = match value {
+        Value::String(_) => value,
+        _ => ["result", "code", "deployedBytecode", "bytecode"]
+            .iter()
+            .find_map(|key| value.get(*key))
+            .ok_or_else(|| anyhow!("expected bytecode under `result`, `code`, `deployedBytecode` or `bytecode`"))?,
+    };
+    code.as_str()
+        .or_else(|| code.get("object").and_then(Value::as_str))
+        .ok_or_else(|| anyhow!("expected a bytecode hex string, got {}", snippet(&code.to_string())))
+}
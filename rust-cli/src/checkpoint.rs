@@ -0,0 +1,117 @@
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use evm_rust_decoder::io::{self, BytesRead};
+
+use crate::logging;
+
+/// Input lines between two checkpoint saves.
+const CHECKPOINT_LINES: u64 = 100_000;
+
+/// Progress through one input file, as saved in the checkpoint file. Counts cover every session so far.
+#[derive(Serialize, Deserialize, Debug)]
+struct Progress {
+    input: PathBuf,
+    /// Byte offset just past the last line that was fully decoded
+    offset: u64,
+    lines: u64,
+    decoded: u64,
+    elapsed_ms: f64,
+}
+
+/// Periodically persists how far a sequential run got, so `--resume` can continue from there after a crash or Ctrl-C.
+pub struct Checkpoint {
+    path: PathBuf,
+    progress: Progress,
+    bytes: BytesRead,
+    unsaved: u64,
+    /// `elapsed_ms` of earlier sessions and when this one started
+    earlier_ms: f64,
+    started: Instant,
+}
+
+impl Checkpoint {
+    /// Starts tracking `input`. With `resume`, continues from the progress saved at `path`, if there is any.
+    pub fn open(path: PathBuf, input: &Path, resume: bool) -> Result<Checkpoint> {
+        let input = io::canonicalize(input).with_context(|| format!("Cannot open input file: {:?}", input))?;
+        let saved = if resume && path.exists() {
+            let text = fs::read_to_string(&path).with_context(|| format!("Cannot read checkpoint {:?}", path))?;
+            let saved: Progress = serde_json::from_str(&text).with_context(|| format!("Invalid checkpoint {:?}", path))?;
+            if saved.input != input { bail!("Checkpoint {:?} is for {:?}, not {:?}", path, saved.input, input); }
+            let len = fs::metadata(&input)?.len();
+            if saved.offset > len { bail!("Checkpoint {:?} is at byte {}, past the end of {:?} ({} bytes)", path, saved.offset, input, len); }
+            tracing::info!(lines = saved.lines, offset = saved.offset, "resuming {}", input.display());
+            Some(saved)
+        } else {
+            None
+        };
+        let progress = saved.unwrap_or(Progress { input, offset: 0, lines: 0, decoded: 0, elapsed_ms: 0.0 });
+        Ok(Checkpoint {
+            path,
+            bytes: BytesRead::new(progress.offset),
+            earlier_ms: progress.elapsed_ms,
+            progress,
+            unsaved: 0,
+            started: Instant::now(),
+        })
+    }
+
+    /// Byte offset decoding starts from.
+    pub fn offset(&self) -> u64 {
+        self.progress.offset
+    }
+
+    /// Input lines handled before decoding starts from [`Checkpoint::offset`].
+    pub fn lines(&self) -> u64 {
+        self.progress.lines
+    }
+
+    /// Wraps the input reader so saved offsets follow the bytes it consumes.
+    pub fn track(&self, reader: Box<dyn BufRead + Send>) -> Box<dyn BufRead + Send> {
+        io::count_bytes(reader, self.bytes.clone())
+    }
+
+    /// Records one fully handled input line that yielded `decoded` logs, saving every [`CHECKPOINT_LINES`] lines.
+    /// `out` is flushed first so printed output always covers at least what the checkpoint claims.
+    pub fn line(&mut self, decoded: usize, out: &mut impl Write) -> Result<()> {
+        self.progress.lines += 1;
+        self.progress.decoded += decoded as u64;
+        self.unsaved += 1;
+        if self.unsaved >= CHECKPOINT_LINES {
+            out.flush()?;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the progress to a temporary file and renames it over the checkpoint, so a crash never leaves it torn.
+    pub fn save(&mut self) -> Result<()> {
+        self.progress.offset = self.bytes.get();
+        self.progress.elapsed_ms = self.earlier_ms + self.started.elapsed().as_secs_f64() * 1000.0;
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.progress)?).with_context(|| format!("Cannot write checkpoint {:?}", tmp))?;
+        fs::rename(&tmp, &self.path).with_context(|| format!("Cannot write checkpoint {:?}", self.path))?;
+        self.unsaved = 0;
+        Ok(())
+    }
+
+    /// Prints the totals over all sessions.
+    pub fn print_summary(&self) {
+        let p = &self.progress;
+        if logging::json() {
+            return tracing::info!(
+                lines = p.lines, decoded = p.decoded, elapsed_ms = logging::round(p.elapsed_ms, 3), offset = p.offset,
+                path = %self.path.display(), "checkpoint"
+            );
+        }
+        eprintln!(
+            "checkpoint lines={} decoded={} elapsed_ms={:.3} offset={} path={}",
+            p.lines, p.decoded, p.elapsed_ms, p.offset, self.path.display()
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::report::ReportFormat;
+
+/// A benchmark matrix read from `bench-all --config bench.toml`. Relative paths are resolved against the
+/// directory of the config file, which is also where implementation commands run.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BenchConfig {
+    /// Default ABI for datasets that do not name one
+    pub abi: Option<PathBuf>,
+    /// Default event; `""` decodes all ABI events by topic0
+    pub event: Option<String>,
+    pub runs: Option<usize>,
+    #[serde(default)]
+    pub preload: bool,
+    /// Rust CLI thread counts; each count is benchmarked as its own implementation
+    pub threads: Option<Vec<usize>>,
+    /// Set to false to leave out the N-API harness
+    pub napi: Option<bool>,
+    pub napi_cmd: Option<String>,
+    #[serde(default, rename = "dataset")]
+    pub datasets: Vec<DatasetConfig>,
+    #[serde(default, rename = "impl")]
+    pub impls: Vec<ImplConfig>,
+    #[serde(default)]
+    pub output: OutputConfig,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DatasetConfig {
+    pub path: PathBuf,
+    pub abi: Option<PathBuf>,
+    pub event: Option<String>,
+}
+
+/// An external implementation, same as `--impl NAME=COMMAND`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImplConfig {
+    pub name: String,
+    pub cmd: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    pub json: Option<PathBuf>,
+    pub report: Option<ReportFormat>,
+    pub report_file: Option<PathBuf>,
+    /// Results store to append the run to, as `results record` does
+    pub store: Option<PathBuf>,
+}
+
+impl BenchConfig {
+    /// Reads the config and returns it with the directory its relative paths are relative to.
+    pub fn load(path: &Path) -> Result<(BenchConfig, PathBuf)> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Cannot read config {:?}", path))?;
+        let config: BenchConfig = toml::from_str(&text).with_context(|| format!("Invalid config {:?}", path))?;
+        let base = evm_rust_decoder::io::canonicalize(path)?.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok((config, base))
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use evm_rust_decoder::decode::LogMeta;
+
+use crate::logging;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// The emitting contract (`address` of each log)
+    Address,
+    /// The chain of each log (`chainId` of its record, or --chain-id), for multi-chain datasets
+    Chain,
+}
+
+impl GroupBy {
+    /// The plural name of the groups, the header of their key column and the count of logs without a key.
+    fn labels(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            GroupBy::Address => ("contracts", "address", "no_address"),
+            GroupBy::Chain => ("chains", "chain", "no_chain"),
+        }
+    }
+
+    fn key(self, meta: &LogMeta) -> Option<String> {
+        match self {
+            GroupBy::Address => meta.address.as_ref().map(|a| a.to_ascii_lowercase()),
+            GroupBy::Chain => meta.chain_id.map(|c| c.to_string()),
+        }
+    }
+}
+
+/// Log counts and decode time per emitting contract or per chain, for `--group-by`.
+pub struct GroupStats {
+    group_by: GroupBy,
+    groups: HashMap<String, Group>,
+    logs: u64,
+    time: Duration,
+    /// Logs without the key (no `address`, or no chain)
+    ungrouped: u64,
+}
+
+#[derive(Default)]
+struct Group {
+    logs: u64,
+    time: Duration,
+    events: HashMap<String, u64>,
+}
+
+impl GroupStats {
+    pub fn new(group_by: GroupBy) -> Self {
+        GroupStats { group_by, groups: HashMap::new(), logs: 0, time: Duration::ZERO, ungrouped: 0 }
+    }
+
+    /// Records the logs decoded from one input line, as (metadata, event name), splitting the line's time evenly.
+    pub fn record_line(&mut self, logs: &[(LogMeta, String)], time: Duration) {
+        if logs.is_empty() { return; }
+        let share = time / logs.len() as u32;
+        for (meta, event) in logs {
+            self.logs += 1;
+            self.time += share;
+            let Some(key) = self.group_by.key(meta) else { self.ungrouped += 1; continue };
+            let group = self.groups.entry(key).or_default();
+            group.logs += 1;
+            group.time += share;
+            *group.events.entry(event.clone()).or_default() += 1;
+        }
+    }
+
+    /// Prints the `top` groups by log count, then by decode time.
+    pub fn print(&self, top: usize) {
+        let (groups, key, ungrouped) = self.group_by.labels();
+        if logging::json() {
+            tracing::info!(group_by = key, distinct = self.groups.len(), logs = self.logs, ungrouped = self.ungrouped, "{}", groups);
+        } else {
+            eprintln!("{} distinct={} logs={} {}={}", groups, self.groups.len(), self.logs, ungrouped, self.ungrouped);
+        }
+        if self.groups.is_empty() { return; }
+        let mut by_logs: Vec<(&String, &Group)> = self.groups.iter().collect();
+        by_logs.sort_by(|a, b| b.1.logs.cmp(&a.1.logs).then(a.0.cmp(b.0)));
+        self.table(groups, "logs", key, &by_logs[..top.min(by_logs.len())]);
+        let mut by_time = by_logs;
+        by_time.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+        self.table(groups, "decode time", key, &by_time[..top.min(by_time.len())]);
+    }
+
+    /// One row per group of `rows`, ranked by `ranking`; with `--log-format json`, one event per row.
+    fn table(&self, groups: &str, ranking: &str, key: &str, rows: &[(&String, &Group)]) {
+        let json = logging::json();
+        if !json {
+            eprintln!("top {} by {}\n  {:>10} {:>7} {:>11} {:>10}  {:<42} events", groups, ranking, "logs", "share", "decode_ms", "us_per_log", key);
+        }
+        for (rank, (value, g)) in rows.iter().enumerate() {
+            let mut events: Vec<(&String, &u64)> = g.events.iter().collect();
+            events.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let events: Vec<String> = events.iter().map(|(name, n)| format!("{}:{}", name, n)).collect();
+            let (decode_ms, us_per_log) = (g.time.as_secs_f64() * 1000.0, g.time.as_secs_f64() * 1e6 / g.logs as f64);
+            if json {
+                tracing::info!(
+                    by = ranking, rank = rank + 1, key = %value, logs = g.logs, share_pct = logging::round(share(g.logs, self.logs), 2),
+                    decode_ms = logging::round(decode_ms, 3), us_per_log = logging::round(us_per_log, 2), events = %events.join(","), "{}", groups
+                );
+                continue;
+            }
+            eprintln!(
+                "  {:>10} {:>6.2}% {:>11.3} {:>10.2}  {:<42} {}",
+                g.logs, share(g.logs, self.logs), decode_ms, us_per_log, value, events.join(",")
+            );
+        }
+    }
+}
+
+fn share(count: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { count as f64 / total as f64 * 100.0 }
+}
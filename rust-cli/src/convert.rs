@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use evm_rust_decoder::binary;
+use evm_rust_decoder::decode::{parse_log_lines, to_raw_log, InputFormat, InputSchema, LogRecord};
+use evm_rust_decoder::io::open_file;
+use evm_rust_decoder::{era1, firehose, json_stream, protobuf};
+
+use crate::logging;
+
+/// Format written by `convert`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ConvertTo {
+    /// Binary records, as read by `--input-format bin`
+    Bin,
+    /// Length-delimited `sf.ethereum.type.v2.Log` messages, as read by `--input-format protobuf`
+    Protobuf,
+    /// One `{"topics":[...],"data":"0x..."}` object per line, with `address` and `blockNumber` when known
+    Jsonl,
+}
+
+#[derive(Serialize)]
+pub(crate) struct JsonlLog<'a> {
+    pub(crate) topics: Vec<String>,
+    pub(crate) data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) address: Option<&'a str>,
+    #[serde(rename = "blockNumber", skip_serializing_if = "Option::is_none")]
+    pub(crate) block_number: Option<u64>,
+    #[serde(rename = "chainId", skip_serializing_if = "Option::is_none")]
+    pub(crate) chain_id: Option<u64>,
+    #[serde(rename = "l1BlockNumber", skip_serializing_if = "Option::is_none")]
+    pub(crate) l1_block_number: Option<u64>,
+    #[serde(rename = "l1TxOrigin", skip_serializing_if = "Option::is_none")]
+    pub(crate) l1_tx_origin: Option<&'a str>,
+}
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// Input framing, as for decoding
+    #[arg(long, value_enum, default_value_t = InputFormat::Jsonl)]
+    from: InputFormat,
+
+    #[arg(long, value_enum, default_value_t = ConvertTo::Bin)]
+    to: ConvertTo,
+
+    /// Shape of each JSONL line
+    #[arg(long, value_enum, default_value_t = InputSchema::Auto)]
+    input_schema: InputSchema,
+
+    /// File to convert (`.zst` is decompressed), default stdin
+    #[arg(long)]
+    input: Option<PathBuf>,
+
+    /// File to write, default stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+/// Rewrites the logs of one input in another format, e.g. JSONL as binary records so a benchmark can time ABI
+/// decoding without any text parsing. Logs without topics are dropped, as decoding skips them.
+pub fn run(args: &ConvertArgs) -> Result<()> {
+    if matches!((args.from, args.to), (InputFormat::Bin, ConvertTo::Bin) | (InputFormat::Protobuf, ConvertTo::Protobuf)) {
+        bail!("--from {0} --to {0} would copy the input", name(args.to));
+    }
+    let reader: Box<dyn BufRead + Send> = match &args.input {
+        Some(path) => open_file(path)?,
+        None => Box::new(io::BufReader::new(io::stdin())),
+    };
+    let mut out: BufWriter<Box<dyn Write>> = BufWriter::new(match &args.output {
+        Some(path) => Box::new(File::create(path).with_context(|| format!("Cannot create output file {:?}", path))?),
+        None => Box::new(io::stdout().lock()),
+    });
+
+    let (mut logs, mut without_topics) = (0usize, 0usize);
+    let mut write = |record: Option<LogRecord>| -> Result<()> {
+        let Some(record) = record else { without_topics += 1; return Ok(()); };
+        logs += 1;
+        match args.to {
+            ConvertTo::Bin => binary::write_record(&mut out, &record.raw),
+            ConvertTo::Protobuf => protobuf::write_record(&mut out, &record),
+            ConvertTo::Jsonl => {
+                let line = JsonlLog {
+                    topics: record.raw.topics.iter().map(|t| format!("{:?}", t)).collect(),
+                    data: format!("0x{}", hex::encode(&record.raw.data)),
+                    address: record.meta.address.as_deref(),
+                    block_number: record.meta.block_number,
+                    chain_id: record.meta.chain_id,
+                    l1_block_number: record.meta.l1_block_number,
+                    l1_tx_origin: record.meta.l1_tx_origin.as_deref(),
+                };
+                serde_json::to_writer(&mut out, &line)?;
+                Ok(out.write_all(b"\n")?)
+            }
+        }
+    };
+    match args.from {
+        InputFormat::Jsonl => {
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() { continue; }
+                for log in parse_log_lines(&line, args.input_schema)? { write(to_raw_log(&log)?)?; }
+            }
+        }
+        InputFormat::Json => json_stream::for_each_log(reader, |log| write(to_raw_log(&log)?))?,
+        InputFormat::Bin => binary::for_each_log(reader, |record| write(Some(record)))?,
+        InputFormat::Protobuf => protobuf::for_each_log(reader, |record| write(Some(record?)))?,
+        InputFormat::Firehose => firehose::for_each_log(reader, |record| write(Some(record?)))?,
+        InputFormat::Era1 => era1::for_each_log(reader, |record| write(Some(record?)))?,
+    }
+    out.flush()?;
+
+    if logging::json() {
+        tracing::info!(from = %name(args.from), to = %name(args.to), logs, without_topics, "convert");
+    } else {
+        eprintln!("convert from={} to={} logs={} without_topics={}", name(args.from), name(args.to), logs, without_topics);
+    }
+    Ok(())
+}
+
+fn name(value: impl ValueEnum) -> String {
+    value.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+}
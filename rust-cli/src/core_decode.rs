@@ -0,0 +1,520 @@
+//! The decoding core: hex topics and data to bytes, and bytes to ABI tokens against an event. It uses neither
+//! `std` I/O nor `anyhow`, and builds with `--no-default-features` (then the only module, with the crate `no_std`
+//! on `alloc`) for embedding in constrained environments and other runtimes; [`crate::decode`] runs every log
+//! through it.
+//!
+//! The per-log path allocates only what it returns: [`parse_topic`] fills a fixed buffer, [`parse_data_into`]
+//! reuses the caller's, and an [`EventLayout`] works out an event's parameter types and signature once instead of
+//! on every call as `Event::parse_log` does, decoding topics and data in place without copying the log. For a batch
+//! of logs of one event, [`EventLayout::decode_batch`] also reads each parameter that fits one word from an offset
+//! resolved up front, and [`EventLayout::keep_fields`] cuts the decoding down to the parameters a caller needs.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use ethabi::{Event, Log, LogParam, ParamType, Token};
+use ethereum_types::{Address, H256, U256};
+use hex::FromHexError;
+
+/// Why a log could not be decoded.
+#[derive(Debug)]
+pub enum Error {
+    /// A topic or the data is not valid hex, or a topic is not 32 bytes
+    Hex(FromHexError),
+    /// The log's topic0 matches no event of an [`EventTable`]
+    UnknownTopic0,
+    /// The topics or data do not fit the event's parameters
+    Abi(ethabi::Error),
+    /// The data is longer than [`Limits::max_data_bytes`]
+    DataTooLarge { len: usize, max: usize },
+    /// An array in the data is longer than [`Limits::max_array_len`]
+    ArrayTooLong { len: usize, max: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Hex(e) => e.fmt(f),
+            Error::UnknownTopic0 => f.write_str("Unknown topic0 for provided ABI"),
+            Error::DataTooLarge { len, max } => write!(f, "data of {} bytes is over the limit of {}", len, max),
+            Error::ArrayTooLong { len, max } => write!(f, "array of {} elements is over the limit of {}", len, max),
+            #[cfg(feature = "std")]
+            Error::Abi(e) => e.fmt(f),
+            // ethabi only implements Display with its `std` feature
+            #[cfg(not(feature = "std"))]
+            Error::Abi(ethabi::Error::InvalidData) => f.write_str("Invalid data"),
+            #[cfg(not(feature = "std"))]
+            Error::Abi(e) => write!(f, "{:?}", e),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<ethabi::Error> for Error {
+    fn from(e: ethabi::Error) -> Self {
+        Error::Abi(e)
+    }
+}
+
+fn strip_0x(hex: &str) -> &str {
+    hex.strip_prefix("0x").unwrap_or(hex)
+}
+
+/// How [`parse_topic`] and [`parse_data_into`] turn hex into bytes on this target, for the summary: `neon` on
+/// aarch64, where NEON is part of the base instruction set, `scalar` (the `hex` crate) elsewhere.
+#[cfg(target_arch = "aarch64")]
+pub const HEX_DECODER: &str = "neon";
+#[cfg(not(target_arch = "aarch64"))]
+pub const HEX_DECODER: &str = "scalar";
+
+/// `hex::decode_to_slice`, 32 characters at a time with NEON on aarch64. Any error is reported by
+/// `hex::decode_to_slice` itself, so it is the same on every target.
+fn decode_hex(hex: &str, out: &mut [u8]) -> Result<(), FromHexError> {
+    #[cfg(target_arch = "aarch64")]
+    if hex.len() == out.len() * 2 && neon::decode(hex.as_bytes(), out) { return Ok(()); }
+    hex::decode_to_slice(hex, out)
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use core::arch::aarch64::*;
+
+    /// Decodes `hex` (twice as long as `out`) into `out`, or returns false at the first invalid character, leaving
+    /// `out` partly written.
+    pub fn decode(hex: &[u8], out: &mut [u8]) -> bool {
+        let blocks = out.len() / 16;
+        for i in 0..blocks {
+            // SAFETY: NEON is always available on aarch64, and block `i` lies within both slices.
+            if !unsafe { decode_block(hex.as_ptr().add(i * 32), out.as_mut_ptr().add(i * 16)) } { return false; }
+        }
+        let tail = blocks * 16;
+        hex::decode_to_slice(&hex[tail * 2..], &mut out[tail..]).is_ok()
+    }
+
+    /// 32 hex characters to 16 bytes: `vld2q_u8` splits the characters into high and low nibbles, each lane is
+    /// mapped to its value, and any lane that is not a hex digit fails the block.
+    #[inline(always)]
+    unsafe fn decode_block(hex: *const u8, out: *mut u8) -> bool {
+        let chars = vld2q_u8(hex);
+        let (high, high_ok) = nibbles(chars.0);
+        let (low, low_ok) = nibbles(chars.1);
+        if vminvq_u8(vandq_u8(high_ok, low_ok)) != 0xff { return false; }
+        vst1q_u8(out, vorrq_u8(vshlq_n_u8::<4>(high), low));
+        true
+    }
+
+    /// Each lane's value, and 0xff in the lanes that were `0-9`, `a-f` or `A-F`.
+    #[inline(always)]
+    unsafe fn nibbles(chars: uint8x16_t) -> (uint8x16_t, uint8x16_t) {
+        let digit = vsubq_u8(chars, vdupq_n_u8(b'0'));
+        // Setting 0x20 lowercases letters and leaves digits as they are
+        let letter = vsubq_u8(vorrq_u8(chars, vdupq_n_u8(0x20)), vdupq_n_u8(b'a'));
+        let is_digit = vcltq_u8(digit, vdupq_n_u8(10));
+        let is_letter = vcltq_u8(letter, vdupq_n_u8(6));
+        (vbslq_u8(is_digit, digit, vaddq_u8(letter, vdupq_n_u8(10))), vorrq_u8(is_digit, is_letter))
+    }
+}
+
+/// Parses a 32-byte topic, with or without `0x`, without allocating.
+pub fn parse_topic(hex: &str) -> Result<H256, Error> {
+    let mut topic = [0u8; 32];
+    decode_hex(strip_0x(hex), &mut topic).map_err(Error::Hex)?;
+    Ok(H256(topic))
+}
+
+/// Parses hex data, with or without `0x`, into `buf`, replacing its contents and keeping its capacity.
+pub fn parse_data_into(hex: &str, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let hex = strip_0x(hex);
+    if !hex.len().is_multiple_of(2) { return Err(Error::Hex(FromHexError::OddLength)); }
+    buf.clear();
+    buf.resize(hex.len() / 2, 0);
+    decode_hex(hex, buf).map_err(Error::Hex)
+}
+
+/// Parses hex data into a new buffer of exactly its length.
+pub fn parse_data(hex: &str) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    parse_data_into(hex, &mut buf)?;
+    Ok(buf)
+}
+
+/// Bounds on what a log may hold, checked by [`EventLayout::check_limits`] before it is decoded. Arrays nest, so
+/// with `max_array_len` a parameter of `uint256[][]` still decodes to at most `max_array_len` squared elements,
+/// however often its offsets point at the same inner array.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub max_data_bytes: Option<usize>,
+    pub max_array_len: Option<usize>,
+}
+
+impl Limits {
+    pub fn is_set(&self) -> bool {
+        self.max_data_bytes.is_some() || self.max_array_len.is_some()
+    }
+}
+
+/// An event ready to decode logs: its signature and the types of its topics and data, computed once.
+#[derive(Clone, Debug)]
+pub struct EventLayout {
+    pub event: Event,
+    /// `None` for an anonymous event, whose topics are all parameters
+    topic0: Option<H256>,
+    /// Indexed parameter types as they are stored in topics: dynamic types and tuples as their 32-byte hash
+    indexed: Vec<ParamType>,
+    data: Vec<ParamType>,
+    /// One per parameter when every parameter is read from a single 32-byte word
+    words: Option<Vec<Word>>,
+    /// Set by [`EventLayout::keep_fields`]
+    fields: Option<Fields>,
+}
+
+/// The parameters [`EventLayout::keep_fields`] keeps, and where to read them.
+#[derive(Clone, Debug)]
+struct Fields {
+    /// Indexes into the event's inputs, in declaration order
+    keep: Vec<usize>,
+    /// One per kept parameter when each is read from a single word at a fixed topic or data offset
+    words: Option<Vec<Word>>,
+    /// Bytes of data the event's head takes, which every log of it has
+    head_len: usize,
+}
+
+impl EventLayout {
+    pub fn new(event: Event) -> Self {
+        let signature = event.signature();
+        EventLayout::with_signature(event, signature)
+    }
+
+    /// Like [`EventLayout::new`] with the event's signature hash already known, e.g. read from an ABI cache.
+    pub fn with_signature(event: Event, signature: H256) -> Self {
+        let topic0 = (!event.anonymous).then_some(signature);
+        let indexed: Vec<ParamType> = event.inputs.iter().filter(|p| p.indexed).map(|p| topic_type(&p.kind)).collect();
+        let data: Vec<ParamType> = event.inputs.iter().filter(|p| !p.indexed).map(|p| p.kind.clone()).collect();
+        let all: Vec<usize> = (0..event.inputs.len()).collect();
+        let words = Word::plan(&event, &indexed, &all);
+        EventLayout { event, topic0, indexed, data, words, fields: None }
+    }
+
+    /// The signature topic logs of this event start with; `None` for an anonymous event.
+    pub fn topic0(&self) -> Option<H256> {
+        self.topic0
+    }
+
+    /// Keeps only the parameters named in `fields`, so the logs this layout decodes carry just those, in declaration
+    /// order. When each kept parameter fits one word (as any indexed one does) it is read straight from its topic
+    /// or data offset and nothing else is decoded; otherwise the data is decoded whole and cut down. Parameters that
+    /// are not read are not checked either, so a log malformed only in those decodes rather than failing.
+    pub fn keep_fields<S: AsRef<str>>(&mut self, fields: &[S]) {
+        let inputs = &self.event.inputs;
+        let keep: Vec<usize> = (0..inputs.len()).filter(|&i| fields.iter().any(|f| f.as_ref() == inputs[i].name)).collect();
+        let words = Word::plan(&self.event, &self.indexed, &keep);
+        let head_len = heads(&self.event).1;
+        self.fields = Some(Fields { keep, words, head_len });
+    }
+
+    /// Decodes a log of this event, with the same results and errors as `Event::parse_log` but for parameters that
+    /// share a name (or have none), which keep their own values rather than all taking the last one's, and for
+    /// [`EventLayout::keep_fields`].
+    pub fn decode(&self, topics: &[H256], data: &[u8]) -> Result<Log, Error> {
+        match &self.fields {
+            Some(fields) => self.decode_fields(fields, topics, data),
+            None => self.decode_all(topics, data),
+        }
+    }
+
+    fn decode_all(&self, topics: &[H256], data: &[u8]) -> Result<Log, Error> {
+        let topics = self.indexed_topics(topics)?;
+        let mut indexed = Vec::with_capacity(topics.len());
+        for (kind, topic) in self.indexed.iter().zip(topics) {
+            indexed.extend(ethabi::decode(core::slice::from_ref(kind), topic.as_bytes())?);
+        }
+        let mut indexed = indexed.into_iter();
+        let mut data = ethabi::decode(&self.data, data)?.into_iter();
+
+        let params = self.event.inputs.iter().map(|input| {
+            let value = if input.indexed { indexed.next() } else { data.next() };
+            LogParam { name: input.name.clone(), value: value.expect("one token per parameter") }
+        }).collect();
+        Ok(Log { params })
+    }
+
+    fn decode_fields(&self, fields: &Fields, topics: &[H256], data: &[u8]) -> Result<Log, Error> {
+        let Some(words) = fields.words.as_deref().filter(|_| data.len() >= fields.head_len) else {
+            // short data fails here as it would without `keep_fields`
+            let log = self.decode_all(topics, data)?;
+            let params = log.params.into_iter().enumerate().filter(|(i, _)| fields.keep.binary_search(i).is_ok());
+            return Ok(Log { params: params.map(|(_, param)| param).collect() });
+        };
+        let indexed = self.indexed_topics(topics)?;
+        let params = fields.keep.iter().zip(words).map(|(&i, word)| {
+            Ok(LogParam { name: self.event.inputs[i].name.clone(), value: word.token(indexed, data)? })
+        }).collect::<Result<_, Error>>()?;
+        Ok(Log { params })
+    }
+
+    /// Decodes logs of this event in order, with the results [`EventLayout::decode`] gives each. When every
+    /// parameter fits one word (addresses, integers, bools, `bytesN` and indexed parameters, as in an ERC-20
+    /// `Transfer`), each is read straight from its topic or data offset; logs whose data is too short take
+    /// [`EventLayout::decode`], so errors stay the same.
+    pub fn decode_batch<'a>(&self, logs: impl ExactSizeIterator<Item = (&'a [H256], &'a [u8])>) -> Vec<Result<Log, Error>> {
+        let mut decoded = Vec::with_capacity(logs.len());
+        match self.words.as_deref().filter(|_| self.fields.is_none()) {
+            Some(words) => decoded.extend(logs.map(|(topics, data)| self.decode_words(words, topics, data))),
+            None => decoded.extend(logs.map(|(topics, data)| self.decode(topics, data))),
+        }
+        decoded
+    }
+
+    fn decode_words(&self, words: &[Word], topics: &[H256], data: &[u8]) -> Result<Log, Error> {
+        let indexed = self.indexed_topics(topics)?;
+        if data.len() < self.data.len() * 32 { return self.decode_all(topics, data); }
+        let params = self.event.inputs.iter().zip(words).map(|(input, word)| {
+            Ok(LogParam { name: input.name.clone(), value: word.token(indexed, data)? })
+        }).collect::<Result<_, Error>>()?;
+        Ok(Log { params })
+    }
+
+    /// Whether every parameter fits one word, so [`EventLayout::gather_words`] applies; never after
+    /// [`EventLayout::keep_fields`].
+    pub fn is_fixed(&self) -> bool {
+        self.words.is_some() && self.fields.is_none()
+    }
+
+    /// Appends the word each parameter is read from, in declaration order, for converting elsewhere (e.g. on a
+    /// GPU) and [`EventLayout::log_from_words`]. Returns `false`, appending nothing, when the event is not
+    /// fixed-layout or the log does not fit it; [`EventLayout::decode`] then reports why.
+    pub fn gather_words(&self, topics: &[H256], data: &[u8], out: &mut Vec<[u8; 32]>) -> bool {
+        let Some(words) = self.words.as_ref().filter(|_| self.fields.is_none()) else { return false };
+        let Ok(indexed) = self.indexed_topics(topics) else { return false };
+        if data.len() < self.data.len() * 32 { return false; }
+        out.extend(words.iter().map(|word| <[u8; 32]>::try_from(word.read(indexed, data)).expect("32-byte word")));
+        true
+    }
+
+    /// Builds a log of this fixed-layout event from its parameters' words read as big-endian integers, one per
+    /// parameter as [`EventLayout::gather_words`] returns them.
+    pub fn log_from_words(&self, values: &[U256]) -> Result<Log, Error> {
+        let words = self.words.as_deref().filter(|_| self.fields.is_none()).expect("fixed-layout event");
+        let mut word = [0u8; 32];
+        let params = self.event.inputs.iter().zip(words).zip(values).map(|((input, w), value)| {
+            value.to_big_endian(&mut word);
+            Ok(LogParam { name: input.name.clone(), value: w.kind.token(&word)? })
+        }).collect::<Result<_, Error>>()?;
+        Ok(Log { params })
+    }
+
+    /// Checks `data` against `limits` without decoding it: its length, and the length word of every array in it,
+    /// following offsets as decoding would. Data that is malformed in other ways passes, for decoding to report.
+    pub fn check_limits(&self, data: &[u8], limits: &Limits) -> Result<(), Error> {
+        if let Some(max) = limits.max_data_bytes {
+            if data.len() > max { return Err(Error::DataTooLarge { len: data.len(), max }); }
+        }
+        match limits.max_array_len {
+            Some(max) => check_arrays(self.data.iter(), data, 0, max),
+            None => Ok(()),
+        }
+    }
+
+    /// The topics after topic0, checked against the event's signature and number of indexed parameters.
+    fn indexed_topics<'t>(&self, topics: &'t [H256]) -> Result<&'t [H256], Error> {
+        let topics = match self.topic0 {
+            Some(topic0) => match topics.split_first() {
+                Some((first, rest)) if *first == topic0 => rest,
+                _ => return Err(ethabi::Error::InvalidData.into()),
+            },
+            None => topics,
+        };
+        if topics.len() != self.indexed.len() { return Err(ethabi::Error::InvalidData.into()); }
+        Ok(topics)
+    }
+}
+
+/// Where [`EventLayout::decode_batch`] reads one parameter, and as what.
+#[derive(Clone, Copy, Debug)]
+struct Word {
+    source: Source,
+    kind: WordKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Source {
+    /// Index into the topics after topic0
+    Topic(usize),
+    /// Byte offset into the data
+    Data(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum WordKind {
+    Address,
+    Uint,
+    Int,
+    Bool,
+    FixedBytes(usize),
+}
+
+impl Word {
+    /// The words of the parameters of `event` at `params` (indexes into its inputs), or `None` if any needs more
+    /// than one word.
+    fn plan(event: &Event, indexed: &[ParamType], params: &[usize]) -> Option<Vec<Word>> {
+        let sources = heads(event).0;
+        params.iter().map(|&i| {
+            let source = sources[i];
+            let kind = match source {
+                Source::Topic(topic) => &indexed[topic],
+                Source::Data(_) => &event.inputs[i].kind,
+            };
+            let kind = match kind {
+                ParamType::Address => WordKind::Address,
+                ParamType::Uint(_) => WordKind::Uint,
+                ParamType::Int(_) => WordKind::Int,
+                ParamType::Bool => WordKind::Bool,
+                ParamType::FixedBytes(len) if *len <= 32 => WordKind::FixedBytes(*len),
+                _ => return None,
+            };
+            Some(Word { source, kind })
+        }).collect()
+    }
+
+    /// Reads the token as `ethabi::decode` would; the caller has checked that `data` holds every word.
+    #[inline]
+    fn token(&self, topics: &[H256], data: &[u8]) -> Result<Token, Error> {
+        self.kind.token(self.read(topics, data))
+    }
+
+    #[inline]
+    fn read<'w>(&self, topics: &'w [H256], data: &'w [u8]) -> &'w [u8] {
+        match self.source {
+            Source::Topic(i) => topics[i].as_bytes(),
+            Source::Data(offset) => &data[offset..offset + 32],
+        }
+    }
+}
+
+impl WordKind {
+    #[inline]
+    fn token(self, word: &[u8]) -> Result<Token, Error> {
+        Ok(match self {
+            WordKind::Address => Token::Address(Address::from_slice(&word[12..])),
+            WordKind::Uint => Token::Uint(U256::from_big_endian(word)),
+            WordKind::Int => Token::Int(U256::from_big_endian(word)),
+            WordKind::Bool => {
+                if word[..31].iter().any(|&b| b != 0) { return Err(ethabi::Error::InvalidData.into()); }
+                Token::Bool(word[31] == 1)
+            }
+            WordKind::FixedBytes(len) => Token::FixedBytes(word[..len].to_vec()),
+        })
+    }
+}
+
+/// Where each of `event`'s parameters starts, in declaration order (its topic, or the offset of its head in the
+/// data), and the length of the data's head.
+fn heads(event: &Event) -> (Vec<Source>, usize) {
+    let (mut topic, mut offset) = (0, 0);
+    let sources = event.inputs.iter().map(|input| {
+        if input.indexed {
+            topic += 1;
+            Source::Topic(topic - 1)
+        } else {
+            offset += head_len(&input.kind);
+            Source::Data(offset - head_len(&input.kind))
+        }
+    }).collect();
+    (sources, offset)
+}
+
+/// Bytes a parameter of `kind` takes in the head of ABI-encoded data: a static type is encoded in place, a dynamic
+/// one as a 32-byte offset.
+fn head_len(kind: &ParamType) -> usize {
+    match kind {
+        ParamType::FixedArray(inner, len) if !kind.is_dynamic() => len * head_len(inner),
+        ParamType::Tuple(types) if !kind.is_dynamic() => types.iter().map(head_len).sum(),
+        _ => 32,
+    }
+}
+
+/// Checks the array lengths in the values of `types` encoded from `base` in `data`, as [`EventLayout::check_limits`].
+fn check_arrays<'t>(types: impl Iterator<Item = &'t ParamType>, data: &[u8], base: usize, max: usize) -> Result<(), Error> {
+    let mut head = base;
+    for kind in types {
+        if kind.is_dynamic() {
+            let Some(offset) = read_usize(data, head) else { return Ok(()) };
+            let Some(start) = base.checked_add(offset) else { return Ok(()) };
+            check_dynamic(kind, data, start, max)?;
+        }
+        // static values hold no arrays of their own length
+        head += head_len(kind);
+    }
+    Ok(())
+}
+
+fn check_dynamic(kind: &ParamType, data: &[u8], start: usize, max: usize) -> Result<(), Error> {
+    match kind {
+        ParamType::Array(inner) => {
+            let len = read_word(data, start).map(|w| if w > U256::from(usize::MAX) { usize::MAX } else { w.as_usize() });
+            let Some(len) = len else { return Ok(()) };
+            if len > max { return Err(Error::ArrayTooLong { len, max }); }
+            if !inner.is_dynamic() { return Ok(()); }
+            check_arrays(core::iter::repeat_n(&**inner, len), data, start + 32, max)
+        }
+        ParamType::FixedArray(inner, len) => check_arrays(core::iter::repeat_n(&**inner, *len), data, start, max),
+        ParamType::Tuple(types) => check_arrays(types.iter(), data, start, max),
+        _ => Ok(()),
+    }
+}
+
+fn read_word(data: &[u8], at: usize) -> Option<U256> {
+    data.get(at..at.checked_add(32)?).map(U256::from_big_endian)
+}
+
+/// A word as an offset into the data; `None` past the data or beyond what any data could hold.
+fn read_usize(data: &[u8], at: usize) -> Option<usize> {
+    read_word(data, at).filter(|w| *w <= U256::from(data.len())).map(|w| w.as_usize())
+}
+
+/// How an indexed parameter of `kind` is stored in its topic.
+fn topic_type(kind: &ParamType) -> ParamType {
+    match kind {
+        ParamType::String | ParamType::Bytes | ParamType::Array(_) | ParamType::FixedArray(..) | ParamType::Tuple(_) => {
+            ParamType::FixedBytes(32)
+        }
+        kind => kind.clone(),
+    }
+}
+
+/// Events looked up by topic0 without hashing, for decoding logs of several events: a list sorted by topic0, where
+/// the first event of each topic0 wins. Anonymous events have no topic0 and are left out.
+#[derive(Clone, Debug, Default)]
+pub struct EventTable {
+    layouts: Vec<EventLayout>,
+}
+
+impl EventTable {
+    pub fn new(events: impl IntoIterator<Item = Event>) -> Self {
+        let mut layouts: Vec<EventLayout> = events.into_iter().map(EventLayout::new).filter(|l| l.topic0.is_some()).collect();
+        // stable, so the first of each topic0 stays first
+        layouts.sort_by_key(|l| l.topic0);
+        layouts.dedup_by_key(|l| l.topic0);
+        EventTable { layouts }
+    }
+
+    pub fn get(&self, topic0: &H256) -> Option<&EventLayout> {
+        let i = self.layouts.binary_search_by_key(&Some(*topic0), |l| l.topic0).ok()?;
+        Some(&self.layouts[i])
+    }
+
+    /// Decodes a log against the event of its topic0, returning that event with the decoded parameters.
+    pub fn decode(&self, topics: &[H256], data: &[u8]) -> Result<(&Event, Log), Error> {
+        let layout = topics.first().and_then(|t| self.get(t)).ok_or(Error::UnknownTopic0)?;
+        Ok((&layout.event, layout.decode(topics, data)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+}
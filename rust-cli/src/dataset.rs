@@ -0,0 +1,245 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+const DEFAULT_MANIFEST: &str = "datasets/manifest.toml";
+
+#[derive(Args, Debug)]
+pub struct DatasetArgs {
+    /// Manifest listing the shared datasets
+    #[arg(long, global = true, default_value = DEFAULT_MANIFEST)]
+    manifest: PathBuf,
+
+    /// Download cache [default: $EVM_BENCH_CACHE, else $XDG_CACHE_HOME/evm-bench/datasets, else ~/.cache/evm-bench/datasets]
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: DatasetCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum DatasetCommand {
+    /// List the datasets in the manifest and whether they are cached
+    List,
+    /// Download datasets (or reuse the cache), verify their SHA-256 and copy them into --out
+    Fetch(FetchArgs),
+    /// Download a published file, hash it and append it to the manifest
+    Add(AddArgs),
+}
+
+#[derive(Args, Debug)]
+struct FetchArgs {
+    /// Dataset names from the manifest
+    #[arg(required = true)]
+    names: Vec<String>,
+
+    /// Directory the datasets are copied into
+    #[arg(long, default_value = "data")]
+    out: PathBuf,
+
+    /// Re-hash cached files instead of trusting the cache
+    #[arg(long, default_value_t = false)]
+    verify: bool,
+}
+
+#[derive(Args, Debug)]
+struct AddArgs {
+    name: String,
+
+    /// Where the file is published (http(s)://, file:// or a local path)
+    #[arg(long)]
+    url: String,
+
+    #[arg(long, default_value = "")]
+    description: String,
+
+    /// ABI to decode the dataset with (path relative to the repo root)
+    #[arg(long)]
+    abi: Option<String>,
+
+    /// Event to decode; omit for topic0 routing
+    #[arg(long)]
+    event: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Manifest {
+    #[serde(default, rename = "dataset")]
+    datasets: Vec<DatasetEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct DatasetEntry {
+    name: String,
+    #[serde(default)]
+    description: String,
+    url: String,
+    sha256: String,
+    size: Option<u64>,
+    /// File name under --out (default: last URL segment)
+    file: Option<String>,
+    abi: Option<String>,
+    event: Option<String>,
+}
+
+impl DatasetEntry {
+    fn file_name(&self) -> String {
+        self.file.clone().unwrap_or_else(|| url_file_name(&self.url))
+    }
+}
+
+pub fn run(args: &DatasetArgs) -> Result<()> {
+    let cache = match &args.cache_dir { Some(dir) => dir.clone(), None => default_cache_dir()? };
+    match &args.command {
+        DatasetCommand::List => list(&load_manifest(&args.manifest)?, &cache),
+        DatasetCommand::Fetch(a) => fetch(&load_manifest(&args.manifest)?, &cache, a),
+        DatasetCommand::Add(a) => add(&args.manifest, &cache, a),
+    }
+}
+
+fn list(manifest: &Manifest, cache: &Path) -> Result<()> {
+    if manifest.datasets.is_empty() { println!("No datasets in the manifest; register one with `dataset add`."); }
+    for d in &manifest.datasets {
+        let cached = cached_path(cache, d).exists();
+        let size = d.size.map_or("?".to_string(), |s| format!("{:.1} MiB", s as f64 / (1024.0 * 1024.0)));
+        println!("{:<28} {:>10} {:<8} {}", d.name, size, if cached { "cached" } else { "" }, d.description);
+    }
+    Ok(())
+}
+
+fn fetch(manifest: &Manifest, cache: &Path, args: &FetchArgs) -> Result<()> {
+    fs::create_dir_all(&args.out).with_context(|| format!("Cannot create {:?}", args.out))?;
+    for name in &args.names {
+        let Some(entry) = manifest.datasets.iter().find(|d| d.name == *name) else {
+            bail!("Unknown dataset {:?}; `dataset list` shows the manifest", name);
+        };
+        let cached = cached_path(cache, entry);
+        if cached.exists() && args.verify {
+            let actual = sha256_file(&cached)?;
+            if actual != entry.sha256 {
+                fs::remove_file(&cached)?;
+                bail!("{}: cached file is corrupt (sha256 {}), removed it; fetch again", name, actual);
+            }
+        }
+        if !cached.exists() {
+            tracing::info!(url = %entry.url, "downloading {}", name);
+            let (tmp, actual, size) = download(&entry.url, cached.parent().expect("cache paths have a parent"))?;
+            if actual != entry.sha256 {
+                fs::remove_file(&tmp)?;
+                bail!("{}: checksum mismatch, expected sha256 {} but downloaded {}", name, entry.sha256, actual);
+            }
+            if entry.size.is_some_and(|s| s != size) {
+                fs::remove_file(&tmp)?;
+                bail!("{}: size mismatch, expected {} bytes but downloaded {}", name, entry.size.unwrap_or_default(), size);
+            }
+            fs::rename(&tmp, &cached)?;
+        }
+
+        let dest = args.out.join(entry.file_name());
+        // Hard-link when cache and data dir share a filesystem, so large datasets are not stored twice
+        let _ = fs::remove_file(&dest);
+        if fs::hard_link(&cached, &dest).is_err() { fs::copy(&cached, &dest)?; }
+        let decode_hint = match (&entry.abi, &entry.event) {
+            (Some(abi), Some(event)) => format!(" (--abi {} --event {})", abi, event),
+            (Some(abi), None) => format!(" (--abi {})", abi),
+            _ => String::new(),
+        };
+        println!("{} -> {}{}", name, dest.display(), decode_hint);
+    }
+    Ok(())
+}
+
+fn add(manifest_path: &Path, cache: &Path, args: &AddArgs) -> Result<()> {
+    let manifest = if manifest_path.exists() { load_manifest(manifest_path)? } else { Manifest::default() };
+    if manifest.datasets.iter().any(|d| d.name == args.name) { bail!("Dataset {:?} is already in {:?}", args.name, manifest_path); }
+
+    let staging = cache.join("staging");
+    let (tmp, sha256, size) = download(&args.url, &staging)?;
+    let entry_dir = cache.join(&sha256);
+    fs::create_dir_all(&entry_dir)?;
+    fs::rename(&tmp, entry_dir.join(url_file_name(&args.url)))?;
+
+    let mut entry = format!(
+        "\n[[dataset]]\nname = {}\ndescription = {}\nurl = {}\nsha256 = {:?}\nsize = {}\n",
+        toml_str(&args.name), toml_str(&args.description), toml_str(&args.url), sha256, size
+    );
+    if let Some(abi) = &args.abi { entry.push_str(&format!("abi = {}\n", toml_str(abi))); }
+    if let Some(event) = &args.event { entry.push_str(&format!("event = {}\n", toml_str(event))); }
+    if let Some(dir) = manifest_path.parent().filter(|d| !d.as_os_str().is_empty()) { fs::create_dir_all(dir)?; }
+    OpenOptions::new().create(true).append(true).open(manifest_path)?.write_all(entry.as_bytes())?;
+    println!("added {} sha256={} size={} to {}", args.name, sha256, size, manifest_path.display());
+    Ok(())
+}
+
+fn load_manifest(path: &Path) -> Result<Manifest> {
+    let text = fs::read_to_string(path).with_context(|| format!("Cannot read manifest {:?}", path))?;
+    toml::from_str(&text).with_context(|| format!("Invalid manifest {:?}", path))
+}
+
+fn default_cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("EVM_BENCH_CACHE") { return Ok(PathBuf::from(dir)); }
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").context("Set --cache-dir or $HOME")?).join(".cache"),
+    };
+    Ok(base.join("evm-bench").join("datasets"))
+}
+
+/// Cache entries are keyed by content hash, so renamed or re-hosted copies of a dataset share one download.
+fn cached_path(cache: &Path, entry: &DatasetEntry) -> PathBuf {
+    cache.join(&entry.sha256).join(url_file_name(&entry.url))
+}
+
+/// Streams `url` into a temporary file in `dir`, hashing as it goes. Returns the file, its SHA-256 and size.
+fn download(url: &str, dir: &Path) -> Result<(PathBuf, String, u64)> {
+    fs::create_dir_all(dir).with_context(|| format!("Cannot create cache directory {:?}", dir))?;
+    let mut reader: Box<dyn Read> = if url.starts_with("http://") || url.starts_with("https://") {
+        Box::new(ureq::get(url).call().with_context(|| format!("GET {} failed", url))?.into_reader())
+    } else {
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        Box::new(File::open(path).with_context(|| format!("Cannot open {}", path))?)
+    };
+
+    let tmp = dir.join(format!(".download-{}", std::process::id()));
+    let mut file = File::create(&tmp)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    let mut size = 0u64;
+    loop {
+        let n = reader.read(&mut buf).with_context(|| format!("Reading {} failed", url))?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    file.sync_all()?;
+    Ok((tmp, hex::encode(hasher.finalize()), size))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Cannot open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn url_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().filter(|s| !s.is_empty()).unwrap_or("dataset").to_string()
+}
+
+fn toml_str(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
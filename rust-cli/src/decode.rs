@@ -0,0 +1,686 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use ethereum_types::H256;
+use ethabi::{Event, Log, RawLog};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::abi::{topic_count, AbiSet, CollisionPolicy};
+use crate::core_decode::{self, EventLayout, Limits};
+use crate::error::DecodeError;
+use crate::io::is_io_error;
+use crate::timing::{self, Count, Phase};
+use crate::topic0_map::{MapImpl, Topic0Map};
+
+/// A single log. `address`, `blockNumber` and `chainId` are kept when present, as are the L1 origin fields of L2
+/// exports, `transactionHash`, `logIndex` and `removed` feed the --dedupe and --drop-removed filters; other
+/// `eth_getLogs` fields are ignored. `null` `topics`, `data` and `removed`, as some Arbitrum Classic exports write them, read as
+/// empty, `0x` and `false`.
+#[derive(Deserialize)]
+pub struct LogLine {
+    #[serde(deserialize_with = "de_null_default")]
+    pub topics: Vec<String>,
+    #[serde(deserialize_with = "de_data")]
+    pub data: String,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default, rename = "blockNumber", deserialize_with = "de_quantity")]
+    pub block_number: Option<u64>,
+    #[serde(default, rename = "transactionHash")]
+    pub transaction_hash: Option<String>,
+    #[serde(default, rename = "logIndex", deserialize_with = "de_quantity")]
+    pub log_index: Option<u64>,
+    #[serde(default, deserialize_with = "de_null_default")]
+    pub removed: bool,
+    /// The chain of a log in a multi-chain dataset, as a number or a `0x` quantity
+    #[serde(default, rename = "chainId", deserialize_with = "de_quantity")]
+    pub chain_id: Option<u64>,
+    /// The L1 block an L2 log's transaction was sequenced at (Arbitrum and some Optimism indexers)
+    #[serde(default, rename = "l1BlockNumber", deserialize_with = "de_quantity")]
+    pub l1_block_number: Option<u64>,
+    /// The L1 sender of an L2 transaction submitted from L1 (Arbitrum retryables, OP Stack deposits)
+    #[serde(default, rename = "l1TxOrigin")]
+    pub l1_tx_origin: Option<String>,
+}
+
+/// Log metadata carried from the input to the output envelope.
+#[derive(Clone, Debug, Default)]
+pub struct LogMeta {
+    pub address: Option<String>,
+    pub block_number: Option<u64>,
+    pub log_index: Option<u64>,
+    pub chain_id: Option<u64>,
+    pub l1_block_number: Option<u64>,
+    pub l1_tx_origin: Option<String>,
+}
+
+/// A log ready for ABI decoding.
+pub struct LogRecord {
+    pub raw: RawLog,
+    pub meta: LogMeta,
+}
+
+/// A decoded log with the event it was decoded against.
+pub struct DecodedLog<'a> {
+    pub event: &'a Event,
+    pub log: Log,
+    pub meta: LogMeta,
+}
+
+/// Accepts JSON-RPC quantities as hex strings (`"0x1b4"`), decimal strings or plain numbers.
+fn de_quantity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    use serde::de::Error;
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => n.as_u64().map(Some).ok_or_else(|| D::Error::custom("quantity out of range")),
+        Some(Value::String(s)) => {
+            let parsed = match s.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => s.parse(),
+            };
+            parsed.map(Some).map_err(|_| D::Error::custom(format!("invalid quantity: {}", s)))
+        }
+        Some(other) => Err(D::Error::custom(format!("invalid quantity: {}", other))),
+    }
+}
+
+fn de_null_default<'de, D: Deserializer<'de>, T: Deserialize<'de> + Default>(deserializer: D) -> Result<T, D::Error> {
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn de_data<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_else(|| "0x".to_string()))
+}
+
+/// One JSON-RPC response whose `result` is a list of logs, as returned by `eth_getLogs`.
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<Vec<LogLine>>,
+    error: Option<Value>,
+}
+
+/// Framing of the input stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// One JSON value per line (see --input-schema)
+    Jsonl,
+    /// A single JSON document, e.g. a full `eth_getLogs` response dump, streamed element by element
+    Json,
+    /// Binary records of topics and data, decoded without JSON parsing or hex decoding (see `binary`)
+    Bin,
+    /// Length-delimited `sf.ethereum.type.v2.Log` protobuf messages, as in Firehose and Substreams (see `protobuf`)
+    Protobuf,
+    /// Firehose merged-blocks (dbin) files of Ethereum blocks, with the logs of each block extracted (see `firehose`)
+    Firehose,
+    /// era1 archives of pre-merge history, with the logs of each block's receipts extracted (see `era1`)
+    Era1,
+}
+
+/// Shape of each input line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InputSchema {
+    /// Detect per line: a log object, a JSON-RPC response, or a batch (array) of responses
+    Auto,
+    /// One log object per line: `{"topics":[...],"data":"0x..."}`, optionally with the other `eth_getLogs` fields
+    Log,
+    /// One JSON-RPC response per line with the logs under `result`
+    Response,
+    /// One JSON-RPC batch per line (as sent back by Alchemy/Infura), each response carrying logs under `result`
+    Batch,
+}
+
+impl InputSchema {
+    fn detect(line: &str) -> InputSchema {
+        if line.trim_start().starts_with('[') { return InputSchema::Batch; }
+        match (line.find("\"result\""), line.find("\"topics\"")) {
+            (Some(r), Some(t)) if r < t => InputSchema::Response,
+            (Some(_), None) => InputSchema::Response,
+            _ => InputSchema::Log,
+        }
+    }
+}
+
+/// What happens to an input line that cannot be parsed or a log that cannot be decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnError {
+    /// Stop the run with the error
+    #[default]
+    Abort,
+    /// Count the line (with JSON-RPC input in pipeline mode, the log) and continue with the next
+    Skip,
+}
+
+/// Which parsed logs are dropped before decoding.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogFilter {
+    /// Keep only the first log of each (transactionHash, logIndex); logs without both are always kept
+    pub dedupe: bool,
+    /// Skip logs marked `"removed": true` by the node after a reorg
+    pub drop_removed: bool,
+}
+
+/// Logs dropped by the [`LogFilter`] since the decoder was created or last reset.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FilterCounts {
+    pub removed: usize,
+    pub duplicates: usize,
+}
+
+/// `--prefilter`: a Bloom filter over the first 8 bytes of each known topic0, checked ahead of the topic0 map so
+/// logs of events outside the ABI are turned away without hashing. Topic0s are keccak hashes, so three slices of
+/// those bytes serve as the filter's hash functions; at 16 bits per topic0 about 1 in 500 unknown topic0s gets
+/// through to the map.
+pub struct Prefilter {
+    words: Vec<u64>,
+    mask: u64,
+}
+
+impl Prefilter {
+    const BITS_PER_TOPIC0: usize = 16;
+    /// Each position takes 21 of the 64 bits, so the filter holds at most 2^21 bits (256 KiB)
+    const MAX_BITS: usize = 1 << 21;
+
+    pub fn new<'a>(topic0s: impl ExactSizeIterator<Item = &'a H256>) -> Self {
+        let bits = (topic0s.len() * Self::BITS_PER_TOPIC0).next_power_of_two().clamp(64, Self::MAX_BITS);
+        let mut filter = Prefilter { words: vec![0; bits / 64], mask: bits as u64 - 1 };
+        for topic0 in topic0s {
+            for bit in filter.positions(topic0) { filter.words[(bit / 64) as usize] |= 1 << (bit % 64); }
+        }
+        filter
+    }
+
+    fn positions(&self, topic0: &H256) -> [u64; 3] {
+        let prefix = u64::from_be_bytes(topic0[..8].try_into().expect("8 bytes"));
+        [prefix & self.mask, (prefix >> 21) & self.mask, (prefix >> 42) & self.mask]
+    }
+
+    /// False only for topic0s that are certainly not in the filter.
+    #[inline]
+    pub fn may_contain(&self, topic0: &H256) -> bool {
+        self.positions(topic0).iter().all(|&bit| self.words[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    pub fn bits(&self) -> usize {
+        self.words.len() * 64
+    }
+}
+
+/// Resolves the event for each log: either a single selected event or a topic0 lookup over all ABI events.
+pub struct LogDecoder {
+    selected_event: EventLayout,
+    topic0_to_event: Option<Topic0Map>,
+    /// With [`CollisionPolicy::ByTopicCount`], the layouts of each colliding topic0, chosen between per log
+    by_topic_count: HashMap<H256, Vec<EventLayout>>,
+    input_schema: InputSchema,
+    filter: LogFilter,
+    /// (transactionHash, logIndex) pairs seen so far, shared by all threads decoding with this decoder
+    seen: Mutex<HashSet<(H256, u64)>>,
+    removed: AtomicUsize,
+    duplicates: AtomicUsize,
+    on_error: OnError,
+    skipped: AtomicUsize,
+    /// Skipped errors per [`DecodeError::ALL`] kind, then those of no kind
+    skipped_kinds: [AtomicUsize; DecodeError::ALL.len() + 1],
+    prefilter: Option<Prefilter>,
+    homogeneous_fast_path: bool,
+    skip_decode: bool,
+    limits: Limits,
+    /// The chain of logs whose record has no `chainId`
+    chain_id: Option<u64>,
+    /// Topic0 routing of the ABIs scoped to one chain, tried before the rest for that chain's logs
+    by_chain: HashMap<u64, ChainRoutes>,
+}
+
+/// The topic0 routing of `--abi CHAIN:PATH` files for one chain.
+struct ChainRoutes {
+    topic0_to_event: Topic0Map,
+    by_topic_count: HashMap<H256, Vec<EventLayout>>,
+}
+
+impl LogDecoder {
+    pub fn new(selected_event: Event, all_events: &[Event], by_topic0: bool) -> Self {
+        let mut topic0_to_event: Option<HashMap<H256, EventLayout>> = None;
+        if by_topic0 {
+            let mut map = HashMap::new();
+            for ev in all_events {
+                let sig: H256 = ev.signature();
+                map.insert(sig, EventLayout::with_signature(ev.clone(), sig));
+            }
+            topic0_to_event = Some(map);
+        }
+        Self::with_topic0_map(selected_event, topic0_to_event)
+    }
+
+    /// Like [`LogDecoder::new`] with topic0s already computed by an [`AbiSet`]. The first event of each topic0
+    /// wins, unless `policy` is [`CollisionPolicy::ByTopicCount`] and the topic0's events index different parameters.
+    pub fn from_abi_set(selected_event: Event, abis: &AbiSet, by_topic0: bool, policy: CollisionPolicy) -> Self {
+        if !by_topic0 { return Self::with_topic0_map(selected_event, None); }
+        let (topic0_to_event, by_topic_count) = routes(abis, policy);
+        let mut decoder = Self::with_topic0_map(selected_event, Some(topic0_to_event));
+        decoder.by_topic_count = by_topic_count;
+        decoder
+    }
+
+    /// Routes the logs of each chain through the topic0s of its own ABIs first (`--abi CHAIN:PATH`), then through
+    /// those the decoder was built with, so a topic0 can decode with a different event on each chain. A log's chain
+    /// is its record's `chainId`, or [`LogDecoder::with_chain_id`]. Without topic0 routing this does nothing.
+    pub fn with_chain_abis(mut self, chains: &[(u64, AbiSet)], policy: CollisionPolicy) -> Self {
+        if self.topic0_to_event.is_none() { return self; }
+        self.by_chain = chains
+            .iter()
+            .map(|(chain, abis)| {
+                let (topic0_to_event, by_topic_count) = routes(abis, policy);
+                (*chain, ChainRoutes { topic0_to_event: Topic0Map::Std(topic0_to_event), by_topic_count })
+            })
+            .collect();
+        self
+    }
+
+    fn with_topic0_map(selected_event: Event, topic0_to_event: Option<HashMap<H256, EventLayout>>) -> Self {
+        LogDecoder {
+            selected_event: EventLayout::new(selected_event),
+            topic0_to_event: topic0_to_event.map(Topic0Map::Std),
+            by_topic_count: HashMap::new(),
+            input_schema: InputSchema::Log,
+            filter: LogFilter::default(),
+            seen: Mutex::new(HashSet::new()),
+            removed: AtomicUsize::new(0),
+            duplicates: AtomicUsize::new(0),
+            on_error: OnError::Abort,
+            skipped: AtomicUsize::new(0),
+            skipped_kinds: Default::default(),
+            prefilter: None,
+            homogeneous_fast_path: false,
+            skip_decode: false,
+            limits: Limits::default(),
+            chain_id: None,
+            by_chain: HashMap::new(),
+        }
+    }
+
+    pub fn with_input_schema(mut self, input_schema: InputSchema) -> Self {
+        self.input_schema = input_schema;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: LogFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
+    /// Checks each log's topic0 against a [`Prefilter`] of the known topic0s first. Without topic0 routing there is
+    /// no lookup to save and this does nothing.
+    pub fn with_prefilter(mut self) -> Self {
+        self.prefilter = self.topic0_to_event.as_ref().map(|map| Prefilter::new(map.topic0s().iter()));
+        self
+    }
+
+    /// Rebuilds the topic0 index as `map_impl`; without topic0 routing there is none.
+    pub fn with_map_impl(mut self, map_impl: MapImpl) -> Self {
+        self.topic0_to_event = self.topic0_to_event.map(|map| map.into_impl(map_impl));
+        self.by_chain = self.by_chain
+            .into_iter()
+            .map(|(chain, routes)| (chain, ChainRoutes { topic0_to_event: routes.topic0_to_event.into_impl(map_impl), by_topic_count: routes.by_topic_count }))
+            .collect();
+        self
+    }
+
+    /// Decodes only the parameters named in `fields` of every event ([`EventLayout::keep_fields`]); events with
+    /// none of them decode to logs without parameters. Fails when a field is no parameter of any event, e.g. a typo.
+    pub fn with_fields(mut self, fields: &[String]) -> Result<Self> {
+        let mut found = HashSet::new();
+        let mut keep = |layout: &mut EventLayout| {
+            found.extend(layout.event.inputs.iter().map(|p| p.name.clone()).filter(|name| fields.contains(name)));
+            layout.keep_fields(fields);
+        };
+        keep(&mut self.selected_event);
+        if let Some(map) = &mut self.topic0_to_event { map.for_each_layout(&mut keep); }
+        self.by_topic_count.values_mut().flatten().for_each(&mut keep);
+        for routes in self.by_chain.values_mut() {
+            routes.topic0_to_event.for_each_layout(&mut keep);
+            routes.by_topic_count.values_mut().flatten().for_each(&mut keep);
+        }
+        if let Some(missing) = fields.iter().find(|f| !found.contains(*f)) {
+            return Err(anyhow!("--fields: no event in the ABI has a parameter named {:?}", missing));
+        }
+        Ok(self)
+    }
+
+    /// Has batched callers ([`LogDecoder::homogeneous_fast_path`]) hand logs to [`LogDecoder::decode_batch`].
+    pub fn with_homogeneous_fast_path(mut self) -> Self {
+        self.homogeneous_fast_path = true;
+        self
+    }
+
+    /// Stops short of ABI decoding: logs are parsed, hex-decoded and matched to their event as usual, then come out
+    /// without parameters, so a run measures everything but [`EventLayout::decode`].
+    pub fn with_skip_decode(mut self) -> Self {
+        self.skip_decode = true;
+        self
+    }
+
+    /// Rejects logs over `limits` before decoding them ([`EventLayout::check_limits`]), so crafted input cannot make
+    /// a long run or a server decode it into more memory than the limits allow.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Tags the logs whose record has no `chainId` with `chain_id`, so datasets of one chain can be told apart
+    /// from others in the output.
+    pub fn with_chain_id(mut self, chain_id: Option<u64>) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// `meta` with the decoder's default chain when the record has none.
+    pub(crate) fn tag(&self, mut meta: LogMeta) -> LogMeta {
+        meta.chain_id = meta.chain_id.or(self.chain_id);
+        meta
+    }
+
+    /// Whether every log of `records` is within the limits for `layout`, so a batch path may decode them all.
+    pub fn within_limits(&self, layout: &EventLayout, records: &[LogRecord]) -> bool {
+        !self.limits.is_set() || records.iter().all(|r| layout.check_limits(&r.raw.data, &self.limits).is_ok())
+    }
+
+    pub fn homogeneous_fast_path(&self) -> bool {
+        self.homogeneous_fast_path
+    }
+
+    pub fn prefilter(&self) -> Option<&Prefilter> {
+        self.prefilter.as_ref()
+    }
+
+    /// Under [`OnError::Skip`], counts and drops the parse or decode error of one line or log, so the caller moves
+    /// on to the next one. I/O errors (e.g. a closed stdout) are always returned.
+    pub fn skip_error(&self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(e) if self.on_error == OnError::Skip && !is_io_error(&e) => {
+                self.skipped.fetch_add(1, Ordering::Relaxed);
+                let kind = DecodeError::of(&e).and_then(|kind| DecodeError::ALL.iter().position(|&k| k == kind));
+                self.skipped_kinds[kind.unwrap_or(DecodeError::ALL.len())].fetch_add(1, Ordering::Relaxed);
+                tracing::debug!("skipped: {:#}", e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Lines or logs dropped by [`LogDecoder::skip_error`] since the decoder was created or last reset.
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// [`LogDecoder::skipped`] by kind, `None` for errors of no kind, leaving out kinds with none skipped.
+    pub fn skipped_by_kind(&self) -> Vec<(Option<DecodeError>, usize)> {
+        let kinds = DecodeError::ALL.iter().map(|&kind| Some(kind)).chain([None]);
+        kinds.zip(&self.skipped_kinds).map(|(kind, n)| (kind, n.load(Ordering::Relaxed))).filter(|&(_, n)| n > 0).collect()
+    }
+
+    /// Whether `log` passes the filter. Counts the logs it drops.
+    pub fn admit(&self, log: &LogLine) -> bool {
+        if self.filter.drop_removed && log.removed {
+            self.removed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        if self.filter.dedupe {
+            if let (Some(tx), Some(index)) = (&log.transaction_hash, log.log_index) {
+                let Ok(tx) = parse_h256(tx) else { return true };
+                if !self.seen.lock().expect("dedupe set poisoned").insert((tx, index)) {
+                    self.duplicates.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    pub fn filtered(&self) -> FilterCounts {
+        FilterCounts { removed: self.removed.load(Ordering::Relaxed), duplicates: self.duplicates.load(Ordering::Relaxed) }
+    }
+
+    /// Forgets the logs seen so far and zeroes the counts (including skipped errors), so the same input can be
+    /// decoded again.
+    pub fn reset_filter(&self) {
+        self.seen.lock().expect("dedupe set poisoned").clear();
+        self.removed.store(0, Ordering::Relaxed);
+        self.duplicates.store(0, Ordering::Relaxed);
+        self.skipped.store(0, Ordering::Relaxed);
+        for n in &self.skipped_kinds { n.store(0, Ordering::Relaxed); }
+    }
+
+    pub fn event_for(&self, topic0: &H256) -> Result<&Event> {
+        Ok(&self.layout_for(topic0)?.event)
+    }
+
+    fn layout_for(&self, topic0: &H256) -> Result<&EventLayout> {
+        match &self.topic0_to_event {
+            Some(map) => match &self.prefilter {
+                Some(filter) if !filter.may_contain(topic0) => {
+                    timing::count(Count::PrefilterRejected);
+                    Err(core_decode::Error::UnknownTopic0.into())
+                }
+                Some(_) => {
+                    timing::count(Count::PrefilterPassed);
+                    let layout = map.get(topic0);
+                    if layout.is_none() { timing::count(Count::PrefilterFalsePositive); }
+                    layout.ok_or_else(|| core_decode::Error::UnknownTopic0.into())
+                }
+                None => map.get(topic0).ok_or_else(|| core_decode::Error::UnknownTopic0.into()),
+            },
+            None => Ok(&self.selected_event),
+        }
+    }
+
+    /// The event for a log of `chain`, from that chain's ABIs first, telling apart events that share its topic0 by
+    /// its number of topics when configured to.
+    fn layout_for_log(&self, raw: &RawLog, chain: Option<u64>) -> Result<&EventLayout> {
+        if let Some(routes) = chain.and_then(|chain| self.by_chain.get(&chain)) {
+            if let Some(events) = routes.by_topic_count.get(&raw.topics[0]) { return by_topic_count(events, raw); }
+            if let Some(layout) = routes.topic0_to_event.get(&raw.topics[0]) { return Ok(layout); }
+        }
+        if self.by_topic_count.is_empty() { return self.layout_for(&raw.topics[0]); }
+        match self.by_topic_count.get(&raw.topics[0]) {
+            Some(events) => by_topic_count(events, raw),
+            None => self.layout_for(&raw.topics[0]),
+        }
+    }
+
+    /// The chain a record is routed and tagged with.
+    fn chain_of(&self, record: &LogRecord) -> Option<u64> {
+        record.meta.chain_id.or(self.chain_id)
+    }
+
+    pub fn decode(&self, record: LogRecord) -> Result<DecodedLog<'_>> {
+        let lookup = timing::start();
+        let layout = self.layout_for_log(&record.raw, self.chain_of(&record));
+        timing::record(Phase::Lookup, lookup);
+        let layout = layout?;
+        layout.check_limits(&record.raw.data, &self.limits).context(DecodeError::OverLimit)?;
+        if self.skip_decode { return Ok(DecodedLog { event: &layout.event, log: Log { params: Vec::new() }, meta: self.tag(record.meta) }); }
+        let t = timing::start();
+        let log = layout
+            .decode(&record.raw.topics, &record.raw.data)
+            .context(DecodeError::AbiMismatch);
+        timing::record(Phase::Abi, t);
+        Ok(DecodedLog { event: &layout.event, log: log?, meta: self.tag(record.meta) })
+    }
+
+    /// The event of every log in `records`, looked up once, when they all share a topic0, topic count and chain and
+    /// it is known; `None` otherwise, including for an empty batch.
+    pub fn batch_layout(&self, records: &[LogRecord]) -> Option<&EventLayout> {
+        let first = records.first()?;
+        let shape = |r: &LogRecord| (r.raw.topics[0], r.raw.topics.len(), self.chain_of(r));
+        if !records.iter().all(|r| shape(r) == shape(first)) { return None; }
+        let lookup = timing::start();
+        let layout = self.layout_for_log(&first.raw, self.chain_of(first));
+        timing::record(Phase::Lookup, lookup);
+        layout.ok()
+    }
+
+    /// Decodes `records` in order, calling `f` with what [`LogDecoder::decode`] would return for each. When they all
+    /// share a topic0 and topic count, as in a Transfer-only dataset, the event is looked up once for the batch and
+    /// its logs go through [`EventLayout::decode_batch`]; otherwise each is decoded on its own.
+    pub fn decode_batch(&self, records: Vec<LogRecord>, mut f: impl FnMut(Result<DecodedLog<'_>>) -> Result<()>) -> Result<()> {
+        // mixed topic0s, or one unknown: the per-log path reports each error
+        // a log over the limits is rejected on the per-log path too
+        let layout = if self.skip_decode { None } else { self.batch_layout(&records) };
+        let layout = layout.filter(|l| self.within_limits(l, &records));
+        let Some(layout) = layout else {
+            for record in records { f(self.decode(record))?; }
+            return Ok(());
+        };
+
+        let t = timing::start();
+        let logs = layout.decode_batch(records.iter().map(|r| (&r.raw.topics[..], &r.raw.data[..])));
+        timing::record(Phase::Abi, t);
+        for (record, log) in records.into_iter().zip(logs) {
+            let log = log.context(DecodeError::AbiMismatch);
+            f(log.map(|log| DecodedLog { event: &layout.event, log, meta: self.tag(record.meta) }))?;
+        }
+        Ok(())
+    }
+
+    /// Parses one input line and calls `f` for each log it contains. Blank lines, logs without topics and logs
+    /// rejected by the filter are skipped.
+    pub fn parse_line(&self, line: &str, mut f: impl FnMut(LogRecord) -> Result<()>) -> Result<()> {
+        // `lines()` strips each `\r\n`, but keeps the `\r` of a CRLF file's last line when no newline follows it
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() { return Ok(()); }
+        let schema = match self.input_schema {
+            InputSchema::Auto => InputSchema::detect(line),
+            schema => schema,
+        };
+        let t = timing::start();
+        let logs: Vec<LogLine> = match schema {
+            InputSchema::Log => {
+                let parsed: LogLine = serde_json::from_str(line)
+                    .with_context(|| format!("Invalid JSON line: {}", snippet(line)))?;
+                timing::record(Phase::Json, t);
+                if !self.admit(&parsed) { return Ok(()); }
+                return match to_raw_log(&parsed)? { Some(raw) => f(raw), None => Ok(()) };
+            }
+            schema => parse_log_lines(line, schema)?,
+        };
+        timing::record(Phase::Json, t);
+        for parsed in logs.iter().filter(|log| self.admit(log)) {
+            if let Some(raw) = to_raw_log(parsed)? { f(raw)?; }
+        }
+        Ok(())
+    }
+}
+
+/// The first layout of each topic0 of `abis`, and under [`CollisionPolicy::ByTopicCount`] every layout of the
+/// topic0s whose events index different parameters.
+fn routes(abis: &AbiSet, policy: CollisionPolicy) -> (HashMap<H256, EventLayout>, HashMap<H256, Vec<EventLayout>>) {
+    let mut map = HashMap::with_capacity(abis.events.len());
+    for (topic0, event) in &abis.events {
+        map.entry(*topic0).or_insert_with(|| EventLayout::with_signature(event.clone(), *topic0));
+    }
+    let by_topic_count = match policy {
+        CollisionPolicy::ByTopicCount => abis.collisions().into_iter()
+            .map(|c| (c.topic0, c.events.into_iter().map(|e| EventLayout::with_signature(e, c.topic0)).collect()))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    (map, by_topic_count)
+}
+
+/// The layout among those sharing a log's topic0 that has its number of topics.
+fn by_topic_count<'a>(events: &'a [EventLayout], raw: &RawLog) -> Result<&'a EventLayout> {
+    events
+        .iter()
+        .find(|l| topic_count(&l.event) == raw.topics.len())
+        .ok_or_else(|| anyhow!("No event with this topic0 has {} topics", raw.topics.len()).context(DecodeError::AbiMismatch))
+}
+
+/// Parses one input line into the logs it contains, leaving topics and data as hex strings.
+pub fn parse_log_lines(line: &str, schema: InputSchema) -> Result<Vec<LogLine>> {
+    match schema {
+        InputSchema::Auto => parse_log_lines(line, InputSchema::detect(line)),
+        InputSchema::Log => {
+            let parsed: LogLine = serde_json::from_str(line)
+                .with_context(|| format!("Invalid JSON line: {}", snippet(line)))?;
+            Ok(vec![parsed])
+        }
+        InputSchema::Response => {
+            let resp: RpcResponse = serde_json::from_str(line)
+                .with_context(|| format!("Invalid JSON-RPC response line: {}", snippet(line)))?;
+            response_logs(resp)
+        }
+        InputSchema::Batch => {
+            let batch: Vec<RpcResponse> = serde_json::from_str(line)
+                .with_context(|| format!("Invalid JSON-RPC batch line: {}", snippet(line)))?;
+            let mut logs = Vec::new();
+            for resp in batch { logs.extend(response_logs(resp)?); }
+            Ok(logs)
+        }
+    }
+}
+
+fn response_logs(resp: RpcResponse) -> Result<Vec<LogLine>> {
+    match (resp.result, resp.error) {
+        (_, Some(err)) => Err(anyhow!("JSON-RPC error response: {}", err)),
+        (Some(logs), None) => Ok(logs),
+        (None, None) => Ok(Vec::new()),
+    }
+}
+
+pub fn to_raw_log(parsed: &LogLine) -> Result<Option<LogRecord>> {
+    let t = timing::start();
+    let raw = hex_to_raw_log(parsed);
+    timing::record(Phase::Hex, t);
+    let meta = LogMeta {
+        address: parsed.address.clone(),
+        block_number: parsed.block_number,
+        log_index: parsed.log_index,
+        chain_id: parsed.chain_id,
+        l1_block_number: parsed.l1_block_number,
+        l1_tx_origin: parsed.l1_tx_origin.clone(),
+    };
+    Ok(raw?.map(|raw| LogRecord { raw, meta }))
+}
+
+fn hex_to_raw_log(parsed: &LogLine) -> Result<Option<RawLog>> {
+    let parsed_topics: Vec<H256> = parsed
+        .topics
+        .iter()
+        .map(|t| parse_h256(t))
+        .collect::<Result<Vec<H256>>>()?;
+    if parsed_topics.is_empty() { return Ok(None); }
+
+    Ok(Some(RawLog {
+        topics: parsed_topics,
+        data: parse_hex_bytes(&parsed.data)?,
+    }))
+}
+
+pub fn parse_h256(s: &str) -> Result<H256> {
+    core_decode::parse_topic(s).with_context(|| format!("Invalid H256 hex: {}", snippet(s.strip_prefix("0x").unwrap_or(s))))
+}
+
+pub fn parse_hex_bytes(s: &str) -> Result<Vec<u8>> {
+    core_decode::parse_data(s).with_context(|| format!("Invalid hex bytes: {}", snippet(s.strip_prefix("0x").unwrap_or(s))))
+}
+
+/// Longest [`snippet`] of a line, before the `… (N bytes)` marker.
+const SNIPPET_BYTES: usize = 120;
+
+/// `text` for an error message: as is up to 120 bytes, else its start cut at a char boundary and its full length,
+/// so a failing multi-MB line or data field does not flood the log.
+pub fn snippet(text: &str) -> String {
+    if text.len() <= SNIPPET_BYTES { return text.to_string(); }
+    let mut end = SNIPPET_BYTES;
+    while !text.is_char_boundary(end) { end -= 1; }
+    format!("{}… ({} bytes)", &text[..end], text.len())
+}
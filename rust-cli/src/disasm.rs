@@ -0,0 +1,165 @@
+//! EVM bytecode disassembly: the opcode stream of a contract's code, with the immediates of `PUSHn` skipped as
+//! the EVM skips them, and jump-destination analysis, the pass a node runs before executing code. A `JUMPDEST`
+//! byte inside push data is not a destination, and a `PUSHn` immediately followed by `JUMP` or `JUMPI` is a static
+//! jump whose target can be checked against the destinations.
+
+/// Opcodes up to Prague; anything else is undefined and executes as `INVALID`.
+const MNEMONICS: [Option<&str>; 256] = {
+    let mut table = [None; 256];
+    let named: &[(u8, &str)] = &[
+        (0x00, "STOP"), (0x01, "ADD"), (0x02, "MUL"), (0x03, "SUB"), (0x04, "DIV"), (0x05, "SDIV"), (0x06, "MOD"),
+        (0x07, "SMOD"), (0x08, "ADDMOD"), (0x09, "MULMOD"), (0x0a, "EXP"), (0x0b, "SIGNEXTEND"),
+        (0x10, "LT"), (0x11, "GT"), (0x12, "SLT"), (0x13, "SGT"), (0x14, "EQ"), (0x15, "ISZERO"), (0x16, "AND"),
+        (0x17, "OR"), (0x18, "XOR"), (0x19, "NOT"), (0x1a, "BYTE"), (0x1b, "SHL"), (0x1c, "SHR"), (0x1d, "SAR"),
+        (0x20, "KECCAK256"),
+        (0x30, "ADDRESS"), (0x31, "BALANCE"), (0x32, "ORIGIN"), (0x33, "CALLER"), (0x34, "CALLVALUE"),
+        (0x35, "CALLDATALOAD"), (0x36, "CALLDATASIZE"), (0x37, "CALLDATACOPY"), (0x38, "CODESIZE"),
+        (0x39, "CODECOPY"), (0x3a, "GASPRICE"), (0x3b, "EXTCODESIZE"), (0x3c, "EXTCODECOPY"),
+        (0x3d, "RETURNDATASIZE"), (0x3e, "RETURNDATACOPY"), (0x3f, "EXTCODEHASH"),
+        (0x40, "BLOCKHASH"), (0x41, "COINBASE"), (0x42, "TIMESTAMP"), (0x43, "NUMBER"), (0x44, "PREVRANDAO"),
+        (0x45, "GASLIMIT"), (0x46, "CHAINID"), (0x47, "SELFBALANCE"), (0x48, "BASEFEE"), (0x49, "BLOBHASH"),
+        (0x4a, "BLOBBASEFEE"),
+        (0x50, "POP"), (0x51, "MLOAD"), (0x52, "MSTORE"), (0x53, "MSTORE8"), (0x54, "SLOAD"), (0x55, "SSTORE"),
+        (0x56, "JUMP"), (0x57, "JUMPI"), (0x58, "PC"), (0x59, "MSIZE"), (0x5a, "GAS"), (0x5b, "JUMPDEST"),
+        (0x5c, "TLOAD"), (0x5d, "TSTORE"), (0x5e, "MCOPY"), (0x5f, "PUSH0"),
+        (0xf0, "CREATE"), (0xf1, "CALL"), (0xf2, "CALLCODE"), (0xf3, "RETURN"), (0xf4, "DELEGATECALL"),
+        (0xf5, "CREATE2"), (0xfa, "STATICCALL"), (0xfd, "REVERT"), (0xfe, "INVALID"), (0xff, "SELFDESTRUCT"),
+    ];
+    let mut i = 0;
+    while i < named.len() {
+        table[named[i].0 as usize] = Some(named[i].1);
+        i += 1;
+    }
+    const PUSH: [&str; 32] = [
+        "PUSH1", "PUSH2", "PUSH3", "PUSH4", "PUSH5", "PUSH6", "PUSH7", "PUSH8", "PUSH9", "PUSH10", "PUSH11", "PUSH12",
+        "PUSH13", "PUSH14", "PUSH15", "PUSH16", "PUSH17", "PUSH18", "PUSH19", "PUSH20", "PUSH21", "PUSH22", "PUSH23",
+        "PUSH24", "PUSH25", "PUSH26", "PUSH27", "PUSH28", "PUSH29", "PUSH30", "PUSH31", "PUSH32",
+    ];
+    const DUP: [&str; 16] = ["DUP1", "DUP2", "DUP3", "DUP4", "DUP5", "DUP6", "DUP7", "DUP8", "DUP9", "DUP10", "DUP11", "DUP12", "DUP13", "DUP14", "DUP15", "DUP16"];
+    const SWAP: [&str; 16] = ["SWAP1", "SWAP2", "SWAP3", "SWAP4", "SWAP5", "SWAP6", "SWAP7", "SWAP8", "SWAP9", "SWAP10", "SWAP11", "SWAP12", "SWAP13", "SWAP14", "SWAP15", "SWAP16"];
+    const LOG: [&str; 5] = ["LOG0", "LOG1", "LOG2", "LOG3", "LOG4"];
+    let mut n = 0;
+    while n < 32 {
+        table[0x60 + n] = Some(PUSH[n]);
+        if n < 16 {
+            table[0x80 + n] = Some(DUP[n]);
+            table[0x90 + n] = Some(SWAP[n]);
+        }
+        if n < 5 { table[0xa0 + n] = Some(LOG[n]); }
+        n += 1;
+    }
+    table
+};
+
+const JUMP: u8 = 0x56;
+const JUMPI: u8 = 0x57;
+const JUMPDEST: u8 = 0x5b;
+
+/// The mnemonic of `opcode`, `None` for an undefined one.
+pub fn mnemonic(opcode: u8) -> Option<&'static str> {
+    MNEMONICS[opcode as usize]
+}
+
+/// One instruction of a code blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction<'a> {
+    pub pc: usize,
+    pub opcode: u8,
+    /// The immediate of a `PUSHn`, shorter than `n` bytes when the code ends inside it
+    pub immediate: &'a [u8],
+}
+
+impl Instruction<'_> {
+    /// `PUSH2 0x0040`, `JUMPDEST` or `UNKNOWN(0x0c)`.
+    pub fn text(&self) -> String {
+        match (mnemonic(self.opcode), self.immediate) {
+            (Some(name), []) => name.to_string(),
+            (Some(name), immediate) => format!("{} 0x{}", name, hex::encode(immediate)),
+            (None, _) => format!("UNKNOWN(0x{:02x})", self.opcode),
+        }
+    }
+}
+
+/// The instructions of `code` in order.
+pub fn instructions(code: &[u8]) -> Instructions<'_> {
+    Instructions { code, pc: 0 }
+}
+
+pub struct Instructions<'a> {
+    code: &'a [u8],
+    pc: usize,
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Instruction<'a>;
+
+    fn next(&mut self) -> Option<Instruction<'a>> {
+        let pc = self.pc;
+        let opcode = *self.code.get(pc)?;
+        let size = push_size(opcode);
+        let end = (pc + 1 + size).min(self.code.len());
+        self.pc = pc + 1 + size;
+        Some(Instruction { pc, opcode, immediate: &self.code[pc + 1..end] })
+    }
+}
+
+fn push_size(opcode: u8) -> usize {
+    match opcode {
+        0x60..=0x7f => (opcode - 0x5f) as usize,
+        _ => 0,
+    }
+}
+
+/// What the analysis of one code blob found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Analysis {
+    pub instructions: usize,
+    /// Valid jump destinations: `JUMPDEST`s outside push data, by `pc`
+    pub jumpdests: Vec<usize>,
+    /// `JUMP` and `JUMPI` instructions, and those of them whose target is pushed right before
+    pub jumps: usize,
+    pub static_jumps: usize,
+    /// Static jumps to a `pc` that is not a valid destination, which revert when taken
+    pub bad_jumps: usize,
+    /// Undefined opcodes, which execute as `INVALID` (Solidity metadata and data sections are full of them)
+    pub undefined: usize,
+    /// Whether the code ends inside a `PUSHn` immediate, which the EVM reads as zero-padded
+    pub truncated: bool,
+}
+
+/// Runs the instruction pass and the jump-destination analysis over `code`.
+pub fn analyze(code: &[u8]) -> Analysis {
+    let mut analysis = Analysis::default();
+    let mut is_dest = vec![false; code.len()];
+    let mut static_targets = Vec::new();
+    let mut previous: Option<Instruction> = None;
+    for instruction in instructions(code) {
+        analysis.instructions += 1;
+        match instruction.opcode {
+            JUMPDEST => {
+                is_dest[instruction.pc] = true;
+                analysis.jumpdests.push(instruction.pc);
+            }
+            JUMP | JUMPI => {
+                analysis.jumps += 1;
+                if let Some(push) = previous.filter(|p| push_size(p.opcode) > 0) {
+                    static_targets.push(push.immediate);
+                }
+            }
+            opcode if mnemonic(opcode).is_none() => analysis.undefined += 1,
+            opcode => analysis.truncated |= instruction.immediate.len() < push_size(opcode),
+        }
+        previous = Some(instruction);
+    }
+    analysis.static_jumps = static_targets.len();
+    analysis.bad_jumps = static_targets
+        .into_iter()
+        .filter(|target| {
+            // Targets wider than a `usize` cannot be in the code
+            let skip = target.iter().take_while(|&&b| b == 0).count();
+            let target = &target[skip..];
+            target.len() > size_of::<usize>() || !is_dest.get(target.iter().fold(0usize, |pc, &b| pc << 8 | b as usize)).copied().unwrap_or(false)
+        })
+        .count();
+    analysis
+}
@@ -0,0 +1,193 @@
+//! EIP-712 hashing of typed-data JSON, as `eth_signTypedData_v4` takes it: `{"types","primaryType","domain",
+//! "message"}`. Structs are hashed as `keccak256(typeHash ‖ encodeData)`, with `string` and `bytes` members hashed,
+//! nested structs replaced by their hash and arrays by the hash of their encoded elements. Integers may be JSON
+//! numbers, decimal strings or `0x` hex strings, as wallets send them.
+
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethereum_types::{H256, U256};
+use serde_json::{Map, Value};
+use sha3::{Digest, Keccak256};
+
+use crate::value::I256;
+
+/// The hashes a signer computes for one typed-data document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypedDataHash {
+    pub domain_separator: H256,
+    /// `None` when the primary type is `EIP712Domain` itself, which signs the domain alone
+    pub struct_hash: Option<H256>,
+    /// `keccak256(0x1901 ‖ domainSeparator ‖ structHash)`, the digest that is signed
+    pub digest: H256,
+    /// Structs hashed, the domain and nested ones included
+    pub structs: usize,
+}
+
+/// The members of every struct type of a document, in declaration order, and the hashes of their `encodeType`.
+struct Types<'a> {
+    members: HashMap<&'a str, Vec<(&'a str, &'a str)>>,
+    type_hashes: HashMap<&'a str, [u8; 32]>,
+    structs: Cell<usize>,
+}
+
+/// Hashes a typed-data document.
+pub fn hash_typed_data(doc: &Value) -> Result<TypedDataHash> {
+    let types = doc.get("types").and_then(Value::as_object).ok_or_else(|| anyhow!("typed data without `types`"))?;
+    let primary = doc.get("primaryType").and_then(Value::as_str).ok_or_else(|| anyhow!("typed data without `primaryType`"))?;
+    let types = Types::parse(types)?;
+    let domain = doc.get("domain").ok_or_else(|| anyhow!("typed data without `domain`"))?;
+    let domain_separator = types.hash_struct("EIP712Domain", domain).context("domain")?;
+    let struct_hash = match primary {
+        "EIP712Domain" => None,
+        _ => Some(types.hash_struct(primary, doc.get("message").ok_or_else(|| anyhow!("typed data without `message`"))?).context("message")?),
+    };
+    let mut hasher = Keccak256::new().chain_update([0x19, 0x01]).chain_update(domain_separator);
+    if let Some(hash) = &struct_hash { hasher.update(hash); }
+    Ok(TypedDataHash { domain_separator, struct_hash, digest: H256(hasher.finalize().into()), structs: types.structs.get() })
+}
+
+impl<'a> Types<'a> {
+    fn parse(types: &'a Map<String, Value>) -> Result<Types<'a>> {
+        let mut members = HashMap::with_capacity(types.len());
+        for (name, fields) in types {
+            let fields = fields.as_array().ok_or_else(|| anyhow!("type {} is not an array of members", name))?;
+            let fields = fields
+                .iter()
+                .map(|f| {
+                    let member = f.get("name").and_then(Value::as_str).ok_or_else(|| anyhow!("a member of {} has no name", name))?;
+                    let kind = f.get("type").and_then(Value::as_str).ok_or_else(|| anyhow!("member {}.{} has no type", name, member))?;
+                    Ok((member, kind))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            members.insert(name.as_str(), fields);
+        }
+        let mut types = Types { members, type_hashes: HashMap::new(), structs: Cell::new(0) };
+        for name in types.members.keys() {
+            types.type_hashes.insert(name, Keccak256::digest(types.encode_type(name)?.as_bytes()).into());
+        }
+        Ok(types)
+    }
+
+    /// `encodeType`: the struct's signature followed by those of the structs it references, sorted by name.
+    fn encode_type(&self, name: &str) -> Result<String> {
+        let mut deps = BTreeSet::new();
+        self.dependencies(name, &mut deps)?;
+        deps.remove(name);
+        let mut out = String::new();
+        for dep in std::iter::once(name).chain(deps) {
+            let fields: Vec<String> = self.members[dep].iter().map(|(member, kind)| format!("{} {}", kind, member)).collect();
+            out.push_str(&format!("{}({})", dep, fields.join(",")));
+        }
+        Ok(out)
+    }
+
+    fn dependencies(&self, name: &'a str, deps: &mut BTreeSet<&'a str>) -> Result<()> {
+        let fields = self.members.get(name).ok_or_else(|| anyhow!("type {} is not in `types`", name))?;
+        if !deps.insert(name) { return Ok(()); }
+        for (_, kind) in fields {
+            let base = kind.split('[').next().unwrap_or(kind);
+            if self.members.contains_key(base) { self.dependencies(base, deps)?; }
+        }
+        Ok(())
+    }
+
+    fn hash_struct(&self, name: &str, value: &Value) -> Result<H256> {
+        let object = value.as_object().ok_or_else(|| anyhow!("{} value is not an object", name))?;
+        let type_hash = self.type_hashes.get(name).ok_or_else(|| anyhow!("type {} is not in `types`", name))?;
+        let mut hasher = Keccak256::new().chain_update(type_hash);
+        for &(member, kind) in &self.members[name] {
+            // A missing member encodes as zero, as most signers treat it
+            let word = self.encode_value(kind, object.get(member).unwrap_or(&Value::Null)).with_context(|| format!("{}.{}", name, member))?;
+            hasher.update(word);
+        }
+        self.structs.set(self.structs.get() + 1);
+        Ok(H256(hasher.finalize().into()))
+    }
+
+    /// The 32-byte word a member contributes to `encodeData`.
+    fn encode_value(&self, kind: &str, value: &Value) -> Result<H256> {
+        if let Some(inner) = kind.strip_suffix(']').and_then(|k| k.rsplit_once('[')) {
+            let (element, len) = inner;
+            let items = match value {
+                Value::Null => &Vec::new(),
+                Value::Array(items) => items,
+                _ => bail!("expected an array, got {}", value),
+            };
+            if !len.is_empty() && len.parse::<usize>().ok() != Some(items.len()) { bail!("expected {} elements, got {}", len, items.len()); }
+            let mut hasher = Keccak256::new();
+            for item in items { hasher.update(self.encode_value(element, item)?); }
+            return Ok(H256(hasher.finalize().into()));
+        }
+        if self.members.contains_key(kind) {
+            return match value {
+                Value::Null => Ok(H256::zero()),
+                _ => self.hash_struct(kind, value),
+            };
+        }
+        let word = |v: U256| {
+            let mut word = H256::zero();
+            v.to_big_endian(word.as_bytes_mut());
+            word
+        };
+        Ok(match kind {
+            "string" => H256(Keccak256::digest(value.as_str().unwrap_or("").as_bytes()).into()),
+            "bytes" => H256(Keccak256::digest(hex_bytes(value)?).into()),
+            "bool" => word(U256::from(u8::from(match value {
+                Value::Bool(b) => *b,
+                Value::String(s) => s == "true",
+                Value::Null => false,
+                _ => bail!("expected a bool, got {}", value),
+            }))),
+            "address" => {
+                let bytes = hex_bytes(value)?;
+                if bytes.len() != 20 && !bytes.is_empty() { bail!("expected a 20-byte address, got {} bytes", bytes.len()); }
+                let mut word = H256::zero();
+                word.as_bytes_mut()[32 - bytes.len()..].copy_from_slice(&bytes);
+                word
+            }
+            _ if kind.starts_with("bytes") => {
+                let bytes = hex_bytes(value)?;
+                let size: usize = kind[5..].parse().map_err(|_| anyhow!("unsupported type {}", kind))?;
+                if bytes.len() > size || size > 32 { bail!("expected at most {} bytes for {}, got {}", size, kind, bytes.len()); }
+                let mut word = H256::zero();
+                word.as_bytes_mut()[..bytes.len()].copy_from_slice(&bytes);
+                word
+            }
+            _ if kind.starts_with("uint") => word(integer(value)?.0),
+            _ if kind.starts_with("int") => word(integer(value)?.0),
+            _ => bail!("unsupported type {}", kind),
+        })
+    }
+}
+
+/// `0x` hex, or empty for a missing value.
+fn hex_bytes(value: &Value) -> Result<Vec<u8>> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::String(s) => hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|e| anyhow!("invalid hex {:?}: {}", s, e)),
+        _ => bail!("expected a 0x hex string, got {}", value),
+    }
+}
+
+/// An integer as a JSON number, a decimal string (negative for `intN`) or a `0x` hex string, in two's complement.
+fn integer(value: &Value) -> Result<I256> {
+    match value {
+        Value::Null => Ok(I256(U256::zero())),
+        Value::Number(n) => decimal(&n.to_string()).ok_or_else(|| anyhow!("expected an integer, got {}", n)),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(digits) => Ok(I256(U256::from_str_radix(digits, 16).map_err(|_| anyhow!("invalid hex integer {:?}", s))?)),
+            None => decimal(s).ok_or_else(|| anyhow!("invalid integer {:?}", s)),
+        },
+        _ => bail!("expected an integer, got {}", value),
+    }
+}
+
+/// A decimal integer; only negative ones are read as `int256`, so `uint256` values of 2^255 and over parse too.
+fn decimal(s: &str) -> Option<I256> {
+    match s.starts_with('-') {
+        true => s.parse().ok(),
+        false => U256::from_dec_str(s).ok().map(I256),
+    }
+}
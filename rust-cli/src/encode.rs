@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use ethabi::{Event, ParamType, RawLog, Token};
+use ethereum_types::H256;
+use serde_json::{Map, Value};
+use sha3::{Digest, Keccak256};
+
+use evm_rust_decoder::abi::{decoded_kind, AbiSet};
+use evm_rust_decoder::decode::{LogDecoder, LogMeta, LogRecord};
+use evm_rust_decoder::io::open_file;
+use evm_rust_decoder::value::{params, token_from_json};
+
+use crate::convert::JsonlLog;
+use crate::logging;
+
+#[derive(Args, Debug)]
+pub struct EncodeArgs {
+    /// ABI JSON (or Solidity) holding the events; repeatable
+    #[arg(long, required = true)]
+    abi: Vec<PathBuf>,
+
+    /// Event of lines that are bare args (`--print` without `--output-envelope`); envelope records name their own
+    #[arg(long)]
+    event: Option<String>,
+
+    /// Decoded JSONL to encode (`.zst` is decompressed), default stdin
+    #[arg(long)]
+    input: Option<PathBuf>,
+
+    /// File to write the logs to, default stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Decode every encoded log again and fail unless it prints the args it was encoded from
+    #[arg(long, default_value_t = false)]
+    check: bool,
+}
+
+/// ABI-encodes decoded JSON, the CLI's `--print` output with or without `--output-envelope`, back into
+/// `{"topics","data"}` logs that decode to the same args, for round-trip checks and hand-written fixtures.
+pub fn run(args: &EncodeArgs) -> Result<()> {
+    let abis = AbiSet::load(&args.abi, &[], None, None)?;
+    let events: Vec<Event> = abis.events.iter().map(|(_, e)| e.clone()).collect();
+    let default_event = args.event.as_deref().map(|name| abis.select(name)).transpose()?;
+    let reader: Box<dyn BufRead + Send> = match &args.input {
+        Some(path) => open_file(path)?,
+        None => Box::new(io::BufReader::new(io::stdin())),
+    };
+    let mut out: BufWriter<Box<dyn Write>> = BufWriter::new(match &args.output {
+        Some(path) => Box::new(File::create(path).with_context(|| format!("Cannot create output file {:?}", path))?),
+        None => Box::new(io::stdout().lock()),
+    });
+
+    let mut logs = 0usize;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        let record: Value = serde_json::from_str(&line).with_context(|| format!("line {}: invalid JSON", i + 1))?;
+        let (event, log, meta) = encode_line(&record, &events, default_event.as_ref()).with_context(|| format!("line {}", i + 1))?;
+        if args.check { check(&event, &events, &log, &record).with_context(|| format!("line {}: round trip", i + 1))?; }
+        let line = JsonlLog {
+            topics: log.topics.iter().map(|t| format!("{:?}", t)).collect(),
+            data: format!("0x{}", hex::encode(&log.data)),
+            address: meta.address.as_deref(),
+            block_number: meta.block_number,
+            chain_id: meta.chain_id,
+            l1_block_number: meta.l1_block_number,
+            l1_tx_origin: meta.l1_tx_origin.as_deref(),
+        };
+        serde_json::to_writer(&mut out, &line)?;
+        out.write_all(b"\n")?;
+        logs += 1;
+    }
+    out.flush()?;
+    if logging::json() {
+        tracing::info!(logs, checked = args.check, "encode");
+    } else {
+        eprintln!("encode logs={}{}", logs, if args.check { " checked=true" } else { "" });
+    }
+    Ok(())
+}
+
+/// The event a record is for, its log, and the envelope's `address` and `blockNumber`.
+fn encode_line(record: &Value, events: &[Event], default_event: Option<&Event>) -> Result<(Event, RawLog, LogMeta)> {
+    let object = record.as_object().ok_or_else(|| anyhow!("expected a JSON object"))?;
+    let (event, args, meta) = match (object.get("event"), object.get("args")) {
+        (Some(Value::String(name)), Some(Value::Object(args))) => {
+            let meta = LogMeta {
+                address: object.get("address").and_then(Value::as_str).map(String::from),
+                block_number: object.get("blockNumber").and_then(Value::as_u64),
+                chain_id: object.get("chainId").and_then(Value::as_u64),
+                l1_block_number: object.get("l1BlockNumber").and_then(Value::as_u64),
+                l1_tx_origin: object.get("l1TxOrigin").and_then(Value::as_str).map(String::from),
+                ..LogMeta::default()
+            };
+            (find_event(events, name, args)?, args, meta)
+        }
+        _ => (default_event.cloned().context("the line is not an envelope record, and no --event is given")?, object, LogMeta::default()),
+    };
+    let log = encode_log(&event, args).with_context(|| format!("event {}", event.name))?;
+    Ok((event, log, meta))
+}
+
+/// The event called `name` whose parameters are exactly the keys of `args`; overloaded events share a name.
+fn find_event(events: &[Event], name: &str, args: &Map<String, Value>) -> Result<Event> {
+    let mut named = events.iter().filter(|e| e.name == name).peekable();
+    let first = named.peek().copied().ok_or_else(|| anyhow!("Event '{}' not found in ABI", name))?;
+    let fits = |e: &Event| e.inputs.len() == args.len() && e.inputs.iter().enumerate().all(|(i, p)| args.contains_key(&key(i, &p.name)));
+    Ok(named.find(|e| fits(e)).unwrap_or(first).clone())
+}
+
+/// The key a parameter is printed under: its name, or `argN` when unnamed.
+fn key(index: usize, name: &str) -> String {
+    if name.is_empty() { format!("arg{}", index) } else { name.to_string() }
+}
+
+/// Topic0 (unless anonymous), a topic per indexed parameter and the ABI-encoded others as data. An indexed string
+/// or bytes value is hashed unless it is already printed as its 32-byte topic, as decoding does; indexed arrays
+/// and tuples only decode to that hash, so that is what they take.
+fn encode_log(event: &Event, args: &Map<String, Value>) -> Result<RawLog> {
+    if let Some(unknown) = args.keys().find(|k| !event.inputs.iter().enumerate().any(|(i, p)| key(i, &p.name) == **k)) {
+        bail!("no parameter {:?}", unknown);
+    }
+    let mut topics = Vec::new();
+    if !event.anonymous { topics.push(event.signature()); }
+    let mut data = Vec::new();
+    for (i, param) in event.inputs.iter().enumerate() {
+        let name = key(i, &param.name);
+        let value = args.get(&name).ok_or_else(|| anyhow!("missing parameter {:?}", name))?;
+        if !param.indexed {
+            data.push(token_from_json(value, &param.kind).with_context(|| format!("parameter {:?}", name))?);
+            continue;
+        }
+        let topic = match (&param.kind, value.as_str()) {
+            (ParamType::String, Some(s)) if !is_topic(s) => H256(Keccak256::digest(s.as_bytes()).into()),
+            (ParamType::Bytes, Some(s)) if !is_topic(s) => {
+                let Token::Bytes(bytes) = token_from_json(value, &ParamType::Bytes).with_context(|| format!("parameter {:?}", name))? else { unreachable!() };
+                H256(Keccak256::digest(bytes).into())
+            }
+            _ => H256::from_slice(&ethabi::encode(&[token_from_json(value, &decoded_kind(param)).with_context(|| format!("parameter {:?}", name))?])),
+        };
+        topics.push(topic);
+    }
+    Ok(RawLog { topics, data: ethabi::encode(&data) })
+}
+
+/// Whether `s` is a printed topic hash: `0x` and 64 hex digits.
+fn is_topic(s: &str) -> bool {
+    s.strip_prefix("0x").is_some_and(|h| h.len() == 64 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Decodes `log` with `event` and compares its args with the record's.
+fn check(event: &Event, events: &[Event], log: &RawLog, record: &Value) -> Result<()> {
+    let decoder = LogDecoder::new(event.clone(), events, false);
+    let decoded = decoder.decode(LogRecord { raw: log.clone(), meta: LogMeta::default() })?;
+    let printed = serde_json::to_value(params(&decoded.log))?;
+    let expected = record.get("args").filter(|_| record.get("event").is_some()).unwrap_or(record);
+    if let Some((name, value)) = printed.as_object().into_iter().flatten().find(|(k, v)| expected.get(k.as_str()) != Some(*v)) {
+        bail!("{:?} decodes to {}, not {}", name, value, expected.get(name.as_str()).unwrap_or(&Value::Null));
+    }
+    Ok(())
+}
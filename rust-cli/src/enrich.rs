@@ -0,0 +1,108 @@
+//! `--enrich`: token metadata (symbol, decimals) looked up by contract address and added to `--output-envelope`
+//! records, the post-decode step most indexers run. Lookups are timed as their own `--phase-timing` phase so the
+//! decode numbers stay comparable with plain runs.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use ethabi::{ParamType, Token};
+use ethereum_types::U256;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::decode::DecodedLog;
+
+/// Events whose one non-indexed `uint256` is an amount of the emitting token (ERC-20 and WETH).
+const AMOUNT_EVENTS: [&str; 4] = ["Transfer", "Approval", "Deposit", "Withdrawal"];
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenInfo {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+#[derive(Deserialize)]
+struct ListedToken {
+    address: String,
+    #[serde(flatten)]
+    info: TokenInfo,
+}
+
+/// Token metadata by lowercase `0x` address.
+#[derive(Debug, Default)]
+pub struct Tokens {
+    by_address: HashMap<String, TokenInfo>,
+}
+
+impl Tokens {
+    /// Reads `{"0xa0b8...": {"symbol": "USDC", "decimals": 6}, ...}`, or a token list (`{"tokens": [{"address",
+    /// "symbol", "decimals", ...}]}`, as published by Uniswap and CoinGecko, or the bare array). Addresses may use any
+    /// case; other fields are ignored.
+    pub fn load(path: &Path) -> Result<Tokens> {
+        let bytes = fs::read(path).with_context(|| format!("Cannot open token file {:?}", path))?;
+        let json: Value = serde_json::from_slice(&bytes).with_context(|| format!("Invalid JSON in token file {:?}", path))?;
+        Self::from_json(json).with_context(|| format!("Invalid token file {:?}", path))
+    }
+
+    fn from_json(json: Value) -> Result<Tokens> {
+        let list = match json {
+            Value::Object(mut obj) if obj.contains_key("tokens") => obj.remove("tokens").unwrap_or_default(),
+            Value::Object(obj) => {
+                let by_address = obj.into_iter()
+                    .map(|(address, info)| Ok((address.to_ascii_lowercase(), serde_json::from_value(info).with_context(|| format!("token {}", address))?)))
+                    .collect::<Result<_>>()?;
+                return Ok(Tokens { by_address });
+            }
+            list => list,
+        };
+        let list: Vec<ListedToken> = serde_json::from_value(list)?;
+        Ok(Tokens { by_address: list.into_iter().map(|t| (t.address.to_ascii_lowercase(), t.info)).collect() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_address.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_address.is_empty()
+    }
+
+    pub fn get(&self, address: &str) -> Option<&TokenInfo> {
+        self.by_address.get(&address.to_ascii_lowercase())
+    }
+
+    /// Adds `"token": {"symbol", "decimals"}` to the envelope `record` of a log emitted by a known token, with the
+    /// event's amount scaled by the decimals (`"amount": "1.5"`) for ERC-20 style transfers and approvals. Logs of
+    /// unknown or missing addresses are left untouched.
+    pub fn annotate(&self, decoded: &DecodedLog, record: &mut Value) -> Result<()> {
+        let Some(info) = decoded.meta.address.as_deref().and_then(|a| self.get(a)) else { return Ok(()) };
+        let mut token = json!({ "symbol": info.symbol, "decimals": info.decimals });
+        if let Some(amount) = amount(decoded) { token["amount"] = json!(scaled(amount, info.decimals)); }
+        record.as_object_mut().ok_or_else(|| anyhow!("--enrich needs envelope records"))?.insert("token".into(), token);
+        Ok(())
+    }
+}
+
+/// The single non-indexed `uint256` of an [`AMOUNT_EVENTS`] event.
+fn amount(decoded: &DecodedLog) -> Option<U256> {
+    if !AMOUNT_EVENTS.contains(&decoded.event.name.as_str()) { return None; }
+    let mut amounts = decoded.event.inputs.iter().zip(&decoded.log.params)
+        .filter(|(input, _)| !input.indexed && input.kind == ParamType::Uint(256));
+    match (amounts.next(), amounts.next()) {
+        (Some((_, param)), None) => match param.value { Token::Uint(value) => Some(value), _ => None },
+        _ => None,
+    }
+}
+
+/// `value / 10^decimals` in decimal, without trailing zeros (`1500000`, 6 -> `1.5`).
+fn scaled(value: U256, decimals: u8) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 { return digits; }
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() { whole.to_string() } else { format!("{}.{}", whole, fraction) }
+}
@@ -0,0 +1,214 @@
+//! `--input-format era1`: era1 archives, the e2store files of pre-merge history (8192 blocks each) that execution
+//! clients export and serve for history expiry, with the logs of each block's receipts extracted in block order.
+//!
+//! An e2store file is a sequence of entries, each a little-endian u16 type, a u32 length, two zero bytes and the
+//! data. An era1 file opens with a version entry, then holds a header, a body, a receipt list (each RLP, snappy
+//! framed) and a total difficulty per block, and ends with an accumulator and a block index. Only headers (for the
+//! block number) and receipts are decoded; the other entries are skipped unread.
+
+use std::io::{self, ErrorKind, Read};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethabi::RawLog;
+use ethereum_types::H256;
+use rlp::Rlp;
+
+use crate::decode::{LogMeta, LogRecord};
+use crate::stop;
+
+const VERSION: u16 = 0x3265;
+const COMPRESSED_HEADER: u16 = 0x03;
+const COMPRESSED_RECEIPTS: u16 = 0x05;
+
+/// Largest decompressed block of a snappy frame, per the framing format.
+const MAX_SNAPPY_BLOCK: usize = 65536;
+
+/// Streams the logs of every block in `reader`, calling `f` with each log, or with the error of a block or log that
+/// does not decode, so `--on-error skip` can step over it. Logs carry the block number, address and log index;
+/// logs without topics are skipped. A file that is not e2store, or an entry cut short, fails the whole read.
+/// Reading ends early, without error, once [`stop::requested`] is set.
+pub fn for_each_log<R: Read>(mut reader: R, mut f: impl FnMut(Result<LogRecord>) -> Result<()>) -> Result<()> {
+    let mut entry = 0usize;
+    let (mut data, mut rlp) = (Vec::new(), Vec::new());
+    let mut block = None;
+    while !stop::requested() {
+        let mut header = [0u8; 8];
+        if !read_first(&mut reader, &mut header)? { return Ok(()); }
+        entry += 1;
+        let kind = u16::from_le_bytes([header[0], header[1]]);
+        let len = u32::from_le_bytes([header[2], header[3], header[4], header[5]]) as usize;
+        if entry == 1 && kind != VERSION { bail!("Invalid era1 file: missing the e2store version entry"); }
+        if header[6..] != [0, 0] { bail!("Invalid e2store entry {}: reserved bytes are not zero", entry); }
+        let cut_short = |e: io::Error| match e.kind() {
+            ErrorKind::UnexpectedEof => anyhow!("Invalid e2store entry {}: input ends mid-entry", entry),
+            _ => e.into(),
+        };
+        if kind != COMPRESSED_HEADER && kind != COMPRESSED_RECEIPTS {
+            let skipped = io::copy(&mut (&mut reader).take(len as u64), &mut io::sink())?;
+            if skipped < len as u64 { return Err(cut_short(ErrorKind::UnexpectedEof.into())); }
+            continue;
+        }
+        data.resize(len, 0);
+        reader.read_exact(&mut data).map_err(cut_short)?;
+
+        if kind == COMPRESSED_HEADER {
+            block = None;
+            match unsnappy(&data, &mut rlp).and_then(|_| block_number(&rlp)).with_context(|| format!("Invalid block header in e2store entry {}", entry)) {
+                Ok(number) => block = Some(number),
+                Err(e) => f(Err(e))?,
+            }
+            continue;
+        }
+        let label = || match block {
+            Some(number) => format!("block {}", number),
+            None => format!("e2store entry {}", entry),
+        };
+        if let Err(e) = unsnappy(&data, &mut rlp) {
+            f(Err(e.context(format!("Invalid receipts in {}", label()))))?;
+            continue;
+        }
+        let receipts = Rlp::new(&rlp);
+        if !receipts.is_list() {
+            f(Err(anyhow!("Invalid receipts in {}: not an RLP list", label())))?;
+            continue;
+        }
+        let mut log_index = 0u64;
+        for (i, receipt) in receipts.iter().enumerate() {
+            let logs = match receipt_logs(&receipt) {
+                Ok(logs) => logs,
+                Err(e) => {
+                    f(Err(e.context(format!("Invalid receipt {} in {}", i, label()))))?;
+                    continue;
+                }
+            };
+            for log in logs.iter() {
+                let meta = LogMeta { block_number: block, log_index: Some(log_index), ..LogMeta::default() };
+                log_index += 1;
+                match to_record(&log, meta).with_context(|| format!("Invalid log in {}", label())) {
+                    Ok(record) if record.raw.topics.is_empty() => {}
+                    record => f(record)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `number` of an RLP block header, its ninth field.
+fn block_number(header: &[u8]) -> Result<u64> {
+    Ok(Rlp::new(header).val_at(8)?)
+}
+
+/// The log list of one receipt, legacy (`[status, cumulativeGas, bloom, logs]`) or typed, which the list holds as
+/// a byte string of the type followed by that list.
+fn receipt_logs<'a>(receipt: &Rlp<'a>) -> Result<Rlp<'a>> {
+    let receipt = match receipt.is_list() {
+        true => receipt.clone(),
+        false => match receipt.data()? {
+            [_, list @ ..] => Rlp::new(list),
+            [] => bail!("empty typed receipt"),
+        },
+    };
+    let logs = receipt.at(3)?;
+    if !logs.is_list() { bail!("logs are not an RLP list"); }
+    Ok(logs)
+}
+
+/// A log as RLP `[address, [topics], data]`.
+fn to_record(log: &Rlp, meta: LogMeta) -> Result<LogRecord> {
+    let address = log.at(0)?.data()?;
+    if address.len() != 20 { bail!("address of {} bytes, expected 20", address.len()); }
+    let topics = log
+        .at(1)?
+        .iter()
+        .map(|topic| match topic.data()? {
+            topic if topic.len() == 32 => Ok(H256::from_slice(topic)),
+            topic => bail!("topic of {} bytes, expected 32", topic.len()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let data = log.at(2)?.data()?.to_vec();
+    let meta = LogMeta { address: Some(format!("0x{}", hex::encode(address))), ..meta };
+    Ok(LogRecord { raw: RawLog { topics, data }, meta })
+}
+
+/// Unframes and decompresses a snappy framed stream into `out`. Chunk checksums are not verified: a corrupt chunk
+/// fails to decompress or to parse as RLP instead.
+fn unsnappy(framed: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    out.clear();
+    let mut rest = framed;
+    while !rest.is_empty() {
+        let [kind, a, b, c, ..] = *rest else { bail!("snappy chunk header cut short") };
+        let len = u32::from_le_bytes([a, b, c, 0]) as usize;
+        let chunk = rest.get(4..4 + len).ok_or_else(|| anyhow!("snappy chunk of {} bytes cut short", len))?;
+        rest = &rest[4 + len..];
+        let checksummed = || chunk.get(4..).ok_or_else(|| anyhow!("snappy chunk without a checksum"));
+        match kind {
+            0xff if chunk != b"sNaPpY" => bail!("invalid snappy stream identifier"),
+            0xff => {}
+            0x00 => decompress_block(checksummed()?, out)?,
+            0x01 => out.extend_from_slice(checksummed()?),
+            0x02..=0x7f => bail!("unsupported snappy chunk type {:#04x}", kind),
+            // skippable chunks and padding
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Appends one snappy-compressed block to `out`.
+fn decompress_block(mut input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    let mut len = 0usize;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or_else(|| anyhow!("snappy block length cut short"))?;
+        input = rest;
+        len |= ((byte & 0x7f) as usize) << shift;
+        if byte < 0x80 { break; }
+    }
+    if len > MAX_SNAPPY_BLOCK { bail!("snappy block of {} bytes, over the {} of a frame", len, MAX_SNAPPY_BLOCK); }
+    let start = out.len();
+    out.reserve(len);
+    while let Some((&tag, rest)) = input.split_first() {
+        input = rest;
+        let mut take = |n: usize| -> Result<&[u8]> {
+            let (bytes, rest) = input.split_at_checked(n).ok_or_else(|| anyhow!("snappy element cut short"))?;
+            input = rest;
+            Ok(bytes)
+        };
+        let le = |bytes: &[u8]| bytes.iter().rev().fold(0usize, |n, &b| n << 8 | b as usize);
+        let (length, offset) = match tag & 3 {
+            0 => {
+                let length = match (tag >> 2) as usize {
+                    n @ 0..=59 => n + 1,
+                    n => le(take(n - 59)?) + 1,
+                };
+                out.extend_from_slice(take(length)?);
+                continue;
+            }
+            1 => (((tag >> 2) & 7) as usize + 4, ((tag >> 5) as usize) << 8 | take(1)?[0] as usize),
+            2 => ((tag >> 2) as usize + 1, le(take(2)?)),
+            _ => ((tag >> 2) as usize + 1, le(take(4)?)),
+        };
+        if offset == 0 || offset > out.len() - start { bail!("snappy copy offset {} out of range", offset); }
+        // copies may overlap what they write, so go byte by byte
+        let from = out.len() - offset;
+        for i in from..from + length { out.push(out[i]); }
+        if out.len() - start > len { break; }
+    }
+    if out.len() - start != len { bail!("snappy block decompressed to {} bytes, expected {}", out.len() - start, len); }
+    Ok(())
+}
+
+/// Fills `buf` with an entry header; false at a clean end of input.
+fn read_first(reader: &mut impl Read, buf: &mut [u8; 8]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => bail!("Invalid e2store file: input ends mid-header"),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,90 @@
+//! [`DecodeError`]: the kind of a failure, so the CLI's exit codes and the summary's error counts are worked out
+//! from typed errors rather than from messages. Most kinds are read off the errors already in an `anyhow` chain (a
+//! [`core_decode::Error`], a `serde_json::Error`, an `io::Error`); the others are attached as context where they
+//! arise, as `.context(DecodeError::AbiLoad)`.
+
+use std::fmt;
+use std::io;
+
+use crate::core_decode;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DecodeError {
+    /// An ABI file that cannot be read or parsed, or an `--event` that is not in it
+    AbiLoad,
+    /// A log whose topic0 matches no event of the ABI
+    UnknownTopic0,
+    /// A topic or the data is not valid hex, or a topic is not 32 bytes
+    HexParse,
+    /// An input line that is not the JSON its `--input-schema` expects
+    JsonParse,
+    /// A log whose topics or data do not fit its event
+    AbiMismatch,
+    /// A log over `--max-data-bytes` or `--max-array-len`
+    OverLimit,
+    /// Reading the input or writing the output failed
+    Io,
+}
+
+impl DecodeError {
+    pub const ALL: [DecodeError; 7] = [
+        DecodeError::AbiLoad,
+        DecodeError::UnknownTopic0,
+        DecodeError::HexParse,
+        DecodeError::JsonParse,
+        DecodeError::AbiMismatch,
+        DecodeError::OverLimit,
+        DecodeError::Io,
+    ];
+
+    /// The kind of `error`: the outermost one attached as context, else the first typed error in its chain that has
+    /// one. `None` for failures of no kind here, such as a JSON-RPC error response or invalid arguments.
+    pub fn of(error: &anyhow::Error) -> Option<DecodeError> {
+        if let Some(&kind) = error.downcast_ref::<DecodeError>() { return Some(kind); }
+        error.chain().find_map(|cause| {
+            if cause.is::<io::Error>() { return Some(DecodeError::Io); }
+            if let Some(e) = cause.downcast_ref::<core_decode::Error>() { return Some(DecodeError::from(e)); }
+            cause.downcast_ref::<serde_json::Error>().map(|e| if e.is_io() { DecodeError::Io } else { DecodeError::JsonParse })
+        })
+    }
+
+    /// As counted in the summary, e.g. `unknown_topic0`.
+    pub fn name(self) -> &'static str {
+        match self {
+            DecodeError::AbiLoad => "abi_load",
+            DecodeError::UnknownTopic0 => "unknown_topic0",
+            DecodeError::HexParse => "hex_parse",
+            DecodeError::JsonParse => "json_parse",
+            DecodeError::AbiMismatch => "abi_mismatch",
+            DecodeError::OverLimit => "over_limit",
+            DecodeError::Io => "io",
+        }
+    }
+}
+
+impl From<&core_decode::Error> for DecodeError {
+    fn from(e: &core_decode::Error) -> Self {
+        match e {
+            core_decode::Error::Hex(_) => DecodeError::HexParse,
+            core_decode::Error::UnknownTopic0 => DecodeError::UnknownTopic0,
+            core_decode::Error::Abi(_) => DecodeError::AbiMismatch,
+            core_decode::Error::DataTooLarge { .. } | core_decode::Error::ArrayTooLong { .. } => DecodeError::OverLimit,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DecodeError::AbiLoad => "Cannot load the ABI",
+            DecodeError::UnknownTopic0 => "Unknown topic0 for provided ABI",
+            DecodeError::HexParse => "Invalid hex",
+            DecodeError::JsonParse => "Invalid JSON",
+            DecodeError::AbiMismatch => "Log does not match its ABI event",
+            DecodeError::OverLimit => "Log over the decode limits",
+            DecodeError::Io => "I/O error",
+        })
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,63 @@
+//! `--error-log PATH`: one JSON line per input line that failed to parse or decode, with where it starts in the input
+//! and why it failed, so the bad records of a multi-GB dataset can be found again without re-running with
+//! `RUST_LOG=debug`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use evm_rust_decoder::decode::snippet;
+use evm_rust_decoder::error::DecodeError;
+
+use crate::logging;
+
+/// Where an input line starts.
+#[derive(Clone, Copy, Debug)]
+pub struct LinePosition {
+    /// 1-based
+    pub line: u64,
+    /// Of the line's first byte, from the start of the input
+    pub offset: u64,
+}
+
+pub struct ErrorLog {
+    out: BufWriter<File>,
+    path: PathBuf,
+    failures: u64,
+}
+
+impl ErrorLog {
+    pub fn create(path: &Path) -> Result<ErrorLog> {
+        let file = File::create(path).with_context(|| format!("Cannot create --error-log {:?}", path))?;
+        Ok(ErrorLog { out: BufWriter::new(file), path: path.to_path_buf(), failures: 0 })
+    }
+
+    /// Writes `{"line","offset","kind","error","snippet"}` for the line at `position` that failed with `error`.
+    /// `kind` is the [`DecodeError::name`], or null for errors of no kind.
+    pub fn record(&mut self, position: LinePosition, line: &str, error: &anyhow::Error) -> Result<()> {
+        let record = json!({
+            "line": position.line,
+            "offset": position.offset,
+            "kind": DecodeError::of(error).map(DecodeError::name),
+            "error": format!("{:#}", error),
+            "snippet": snippet(line),
+        });
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n").with_context(|| format!("Cannot write --error-log {:?}", self.path))?;
+        self.failures += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.out.flush().with_context(|| format!("Cannot write --error-log {:?}", self.path))?;
+        if logging::json() {
+            tracing::info!(failures = self.failures, path = %self.path.display(), "error_log");
+        } else {
+            eprintln!("error_log failures={} path={}", self.failures, self.path.display());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use std::process::ExitCode;
+
+use evm_rust_decoder::error::DecodeError;
+
+/// Any failure without a more specific status, as well as invalid arguments (clap's own status).
+pub const FAILURE: u8 = 1;
+/// The run completed but skipped lines under `--on-error skip`, and `--fail-on-skip` was given.
+pub const PARTIAL: u8 = 2;
+/// An `--abi` file could not be read or parsed, or `--event` is not in it.
+pub const ABI: u8 = 3;
+/// Reading the input, writing the output or another file operation failed.
+pub const IO: u8 = 4;
+/// A log's topic0 is not in the ABI.
+pub const UNKNOWN_TOPIC0: u8 = 5;
+/// A topic or the data of a log is not valid hex.
+pub const HEX: u8 = 6;
+/// An input line is not valid JSON of the expected shape.
+pub const JSON: u8 = 7;
+/// A log's topics or data do not fit its event.
+pub const ABI_MISMATCH: u8 = 8;
+/// A log is over `--max-data-bytes` or `--max-array-len`.
+pub const OVER_LIMIT: u8 = 9;
+
+/// The exit status for a run that failed with `error`.
+pub fn of(error: &anyhow::Error) -> ExitCode {
+    ExitCode::from(match DecodeError::of(error) {
+        Some(DecodeError::AbiLoad) => ABI,
+        Some(DecodeError::Io) => IO,
+        Some(DecodeError::UnknownTopic0) => UNKNOWN_TOPIC0,
+        Some(DecodeError::HexParse) => HEX,
+        Some(DecodeError::JsonParse) => JSON,
+        Some(DecodeError::AbiMismatch) => ABI_MISMATCH,
+        Some(DecodeError::OverLimit) => OVER_LIMIT,
+        None => FAILURE,
+    })
+}
@@ -0,0 +1,125 @@
+//! `--serializer itoa`: the JSON of [`crate::value`] written straight from the decoded tokens, without building the
+//! `BTreeMap` of [`crate::value::params`] or cloning any value, with integers formatted by [`value::u256_decimal`]
+//! and hex by table. The output is byte-identical to the serde path.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use ethabi::{Log, Token};
+use ethereum_types::U256;
+
+use crate::decode::DecodedLog;
+use crate::output::ENVELOPE_SCHEMA_VERSION;
+use crate::value;
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+thread_local! {
+    /// Parameter order of the log being written, reused from one log to the next
+    static ORDER: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Appends the bare args object of `log`.
+pub fn write_params(buf: &mut Vec<u8>, log: &Log) {
+    ORDER.with(|order| {
+        let mut order = order.borrow_mut();
+        order.clear();
+        order.extend(0..log.params.len());
+        // keys in `BTreeMap` order; stable, so of parameters sharing a name the last comes last and wins
+        order.sort_by(|&a, &b| key(log, a).cmp(&key(log, b)));
+        buf.push(b'{');
+        let mut first = true;
+        for (k, &i) in order.iter().enumerate() {
+            if order.get(k + 1).is_some_and(|&next| key(log, next) == key(log, i)) { continue; }
+            if !first { buf.push(b','); }
+            first = false;
+            write_str(buf, &key(log, i));
+            buf.push(b':');
+            write_token(buf, &log.params[i].value);
+        }
+        buf.push(b'}');
+    })
+}
+
+/// Appends the `--output-envelope` record of `decoded`, fields in the order [`crate::value::DecodedLog`] has them.
+pub fn write_envelope(buf: &mut Vec<u8>, decoded: &DecodedLog) {
+    buf.extend_from_slice(b"{\"address\":");
+    match &decoded.meta.address {
+        Some(address) => write_str(buf, address),
+        None => buf.extend_from_slice(b"null"),
+    }
+    buf.extend_from_slice(b",\"args\":");
+    write_params(buf, &decoded.log);
+    buf.extend_from_slice(b",\"blockNumber\":");
+    match decoded.meta.block_number {
+        Some(block) => buf.extend_from_slice(itoa::Buffer::new().format(block).as_bytes()),
+        None => buf.extend_from_slice(b"null"),
+    }
+    if let Some(chain) = decoded.meta.chain_id {
+        buf.extend_from_slice(b",\"chainId\":");
+        buf.extend_from_slice(itoa::Buffer::new().format(chain).as_bytes());
+    }
+    buf.extend_from_slice(b",\"event\":");
+    write_str(buf, &decoded.event.name);
+    if let Some(block) = decoded.meta.l1_block_number {
+        buf.extend_from_slice(b",\"l1BlockNumber\":");
+        buf.extend_from_slice(itoa::Buffer::new().format(block).as_bytes());
+    }
+    if let Some(origin) = &decoded.meta.l1_tx_origin {
+        buf.extend_from_slice(b",\"l1TxOrigin\":");
+        write_str(buf, origin);
+    }
+    buf.extend_from_slice(b",\"schemaVersion\":");
+    buf.extend_from_slice(itoa::Buffer::new().format(ENVELOPE_SCHEMA_VERSION).as_bytes());
+    buf.push(b'}');
+}
+
+fn key(log: &Log, i: usize) -> Cow<'_, str> {
+    let name = &log.params[i].name;
+    if name.is_empty() { Cow::Owned(format!("arg{}", i)) } else { Cow::Borrowed(name) }
+}
+
+fn write_token(buf: &mut Vec<u8>, token: &Token) {
+    match token {
+        Token::Address(addr) => write_hex(buf, addr.as_bytes()),
+        Token::Uint(uint) => {
+            buf.push(b'"');
+            write_uint(buf, uint);
+            buf.push(b'"');
+        }
+        Token::Int(int) => {
+            let int = value::I256(*int);
+            buf.push(b'"');
+            if int.is_negative() { buf.push(b'-'); }
+            write_uint(buf, &int.magnitude());
+            buf.push(b'"');
+        }
+        Token::Bool(b) => buf.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => write_hex(buf, bytes),
+        Token::String(s) => write_str(buf, s),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            buf.push(b'[');
+            for (i, token) in tokens.iter().enumerate() {
+                if i > 0 { buf.push(b','); }
+                write_token(buf, token);
+            }
+            buf.push(b']');
+        }
+    }
+}
+
+fn write_uint(buf: &mut Vec<u8>, uint: &U256) {
+    buf.extend_from_slice(value::u256_decimal(uint, &mut [0; value::U256_DECIMAL_LEN]).as_bytes());
+}
+
+fn write_hex(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.reserve(bytes.len() * 2 + 4);
+    buf.extend_from_slice(b"\"0x");
+    for &b in bytes { buf.extend_from_slice(&[HEX[usize::from(b >> 4)], HEX[usize::from(b & 0xf)]]); }
+    buf.push(b'"');
+}
+
+/// A JSON string, escaped as serde_json escapes it.
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    serde_json::to_writer(&mut *buf, s).expect("writing to a Vec");
+}
@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, ValueEnum};
+use serde_json::{json, Value};
+
+/// EIP-1967 storage slot holding the implementation address: `keccak256("eip1967.proxy.implementation") - 1`.
+const IMPLEMENTATION_SLOT: &str = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+/// EIP-1967 storage slot holding a beacon, whose `implementation()` returns the implementation address.
+const BEACON_SLOT: &str = "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+/// Selector of `implementation()`.
+const IMPLEMENTATION_SELECTOR: &str = "0x5c60da1b";
+/// Proxies of proxies are followed this many levels deep.
+const MAX_PROXY_DEPTH: usize = 4;
+
+#[derive(Args, Debug)]
+pub struct FetchAbiArgs {
+    /// Contract addresses
+    #[arg(required = true)]
+    addresses: Vec<String>,
+
+    /// Etherscan-compatible API endpoint
+    #[arg(long, default_value = "https://api.etherscan.io/api")]
+    explorer: String,
+
+    /// Explorer API key [default: $ETHERSCAN_API_KEY]
+    #[arg(long)]
+    api_key: Option<String>,
+
+    /// JSON-RPC endpoint used to read the EIP-1967 proxy slots [default: $ETH_RPC_URL]. Without it, the
+    /// implementation the explorer reports for a proxy is used.
+    #[arg(long)]
+    rpc: Option<String>,
+
+    /// ABI written for a proxy
+    #[arg(long, value_enum, default_value_t = ProxyAbi::Merge)]
+    proxy: ProxyAbi,
+
+    /// Directory the ABIs are written to, as <address>.json
+    #[arg(long, default_value = "abi/fetched")]
+    out: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ProxyAbi {
+    /// The proxy's own entries plus the implementation's, since logs at the proxy address come from both
+    Merge,
+    /// Only the implementation's ABI
+    Implementation,
+    /// Only the proxy's own ABI, as if it were not a proxy
+    Proxy,
+}
+
+/// Downloads verified ABIs by address. EIP-1967 proxies (plain and beacon) are resolved to their implementation,
+/// because a proxy's own ABI lacks the events its implementation emits at the proxy address, which then fail as
+/// unknown topic0s.
+pub fn run(args: &FetchAbiArgs) -> Result<()> {
+    let client = Client {
+        explorer: args.explorer.clone(),
+        api_key: args.api_key.clone().or_else(|| std::env::var("ETHERSCAN_API_KEY").ok()),
+        rpc: args.rpc.clone().or_else(|| std::env::var("ETH_RPC_URL").ok()),
+    };
+    fs::create_dir_all(&args.out).with_context(|| format!("Cannot create {:?}", args.out))?;
+
+    let mut failed = 0;
+    for address in &args.addresses {
+        match fetch(&client, &address.to_ascii_lowercase(), args) {
+            Ok(line) => println!("{}", line),
+            Err(e) => { failed += 1; println!("{} failed: {:#}", address, e); }
+        }
+    }
+    if failed > 0 { bail!("{} of {} ABIs could not be fetched", failed, args.addresses.len()); }
+    Ok(())
+}
+
+fn fetch(client: &Client, address: &str, args: &FetchAbiArgs) -> Result<String> {
+    // The chain of implementations behind `address`, nearest first
+    let mut chain = Vec::new();
+    if args.proxy != ProxyAbi::Proxy {
+        let mut current = address.to_string();
+        while let Some((kind, next)) = client.implementation(&current)? {
+            if chain.len() == MAX_PROXY_DEPTH { bail!("more than {} nested proxies", MAX_PROXY_DEPTH); }
+            chain.push((kind, next.clone()));
+            current = next;
+        }
+    }
+
+    let own = client.abi(address);
+    let abi = match chain.last() {
+        None => own?,
+        Some((_, implementation)) => {
+            let implementation = client.abi(implementation).with_context(|| format!("implementation {}", implementation))?;
+            match (args.proxy, own) {
+                (ProxyAbi::Merge, Ok(own)) => merge(own, implementation),
+                (ProxyAbi::Merge, Err(e)) => {
+                    tracing::warn!("{}: proxy ABI unavailable ({:#}), using the implementation's", address, e);
+                    implementation
+                }
+                _ => implementation,
+            }
+        }
+    };
+
+    let events = abi.iter().filter(|e| e.get("type").and_then(Value::as_str) == Some("event")).count();
+    let path = args.out.join(format!("{}.json", address));
+    fs::write(&path, serde_json::to_string_pretty(&abi)?).with_context(|| format!("Cannot write {:?}", path))?;
+    let resolved: Vec<String> = chain.iter().map(|(kind, a)| format!(" {}={}", kind, a)).collect();
+    Ok(format!("{}{} events={} -> {}", address, resolved.concat(), events, path.display()))
+}
+
+/// Entries of both ABIs, without repeating entries present in both.
+fn merge(mut own: Vec<Value>, implementation: Vec<Value>) -> Vec<Value> {
+    for entry in implementation {
+        if !own.contains(&entry) { own.push(entry); }
+    }
+    own
+}
+
+struct Client {
+    explorer: String,
+    api_key: Option<String>,
+    rpc: Option<String>,
+}
+
+impl Client {
+    fn explorer(&self, action: &str, address: &str) -> Result<Value> {
+        let mut request = ureq::get(&self.explorer).query("module", "contract").query("action", action).query("address", address);
+        if let Some(key) = &self.api_key { request = request.query("apikey", key); }
+        let body: Value = serde_json::from_str(&request.call().with_context(|| format!("{} {} failed", action, address))?.into_string()?)?;
+        if body.get("status").and_then(Value::as_str) != Some("1") {
+            bail!("explorer: {}", body.get("result").and_then(Value::as_str).or(body.get("message").and_then(Value::as_str)).unwrap_or("error"));
+        }
+        body.get("result").cloned().context("explorer response without result")
+    }
+
+    /// The verified ABI of `address`; explorers return it as a JSON string.
+    fn abi(&self, address: &str) -> Result<Vec<Value>> {
+        let result = self.explorer("getabi", address)?;
+        let text = result.as_str().context("getabi result is not a string")?;
+        serde_json::from_str(text).context("getabi result is not an ABI array")
+    }
+
+    /// The implementation `address` delegates to, if it is a proxy, with how it was found.
+    fn implementation(&self, address: &str) -> Result<Option<(&'static str, String)>> {
+        let Some(rpc) = &self.rpc else {
+            let result = self.explorer("getsourcecode", address)?;
+            let implementation = result.get(0).and_then(|r| r.get("Implementation")).and_then(Value::as_str).unwrap_or("");
+            return Ok(non_zero_address(implementation).map(|a| ("implementation", a)));
+        };
+        if let Some(implementation) = non_zero_address(&rpc_call(rpc, "eth_getStorageAt", json!([address, IMPLEMENTATION_SLOT, "latest"]))?) {
+            return Ok(Some(("eip1967", implementation)));
+        }
+        if let Some(beacon) = non_zero_address(&rpc_call(rpc, "eth_getStorageAt", json!([address, BEACON_SLOT, "latest"]))?) {
+            let returned = rpc_call(rpc, "eth_call", json!([{ "to": beacon, "data": IMPLEMENTATION_SELECTOR }, "latest"]))?;
+            let implementation = non_zero_address(&returned).with_context(|| format!("beacon {} returned no implementation", beacon))?;
+            return Ok(Some(("eip1967_beacon", implementation)));
+        }
+        Ok(None)
+    }
+}
+
+fn rpc_call(rpc: &str, method: &str, params: Value) -> Result<String> {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response = ureq::post(rpc)
+        .set("content-type", "application/json")
+        .send_string(&request.to_string())
+        .with_context(|| format!("{} failed", method))?;
+    let body: Value = serde_json::from_str(&response.into_string()?)?;
+    if let Some(error) = body.get("error") { return Err(anyhow!("{}: {}", method, error)); }
+    body.get("result").and_then(Value::as_str).map(str::to_string).with_context(|| format!("{} returned no result", method))
+}
+
+/// The address in the low 20 bytes of a 32-byte word (or in a plain address), unless it is zero.
+fn non_zero_address(word: &str) -> Option<String> {
+    let hex = word.strip_prefix("0x").unwrap_or(word);
+    if hex.len() < 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) { return None; }
+    let address = &hex[hex.len() - 40..];
+    if address.bytes().all(|b| b == b'0') { return None; }
+    Some(format!("0x{}", address.to_ascii_lowercase()))
+}
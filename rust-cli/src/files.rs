@@ -0,0 +1,65 @@
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use crate::decode::LogDecoder;
+use crate::io::open_file;
+use crate::output::Output;
+use crate::parallel::decode_lines;
+use crate::stop;
+use crate::timing;
+
+/// Lines read from a file before they are decoded as one batch.
+const BATCH_LINES: usize = 1024;
+
+/// Decoding totals of one input file.
+pub struct FileStats {
+    pub path: PathBuf,
+    pub decoded: usize,
+    pub elapsed: Duration,
+}
+
+/// Decodes each of `files` on a single thread, `parallelism` files at a time, and returns their totals in the order
+/// of `files`. Output lines of one file stay in order; with `parallelism` above 1, files interleave in 64 KiB runs.
+pub fn run(files: &[PathBuf], decoder: &LogDecoder, output: &Output, parallelism: usize) -> Result<Vec<FileStats>> {
+    let next = AtomicUsize::new(0);
+    let stats = Mutex::new(Vec::with_capacity(files.len()));
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..parallelism.clamp(1, files.len().max(1)))
+            .map(|_| {
+                s.spawn(|| -> Result<()> {
+                    while !stop::requested() {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(i) else { break };
+                        let file = decode_file(path, decoder, output).with_context(|| format!("in {}", path.display()))?;
+                        stats.lock().expect("file stats poisoned").push((i, file));
+                    }
+                    timing::flush();
+                    Ok(())
+                })
+            })
+            .collect();
+        workers.into_iter().try_for_each(|w| w.join().map_err(|_| anyhow!("file worker panicked"))?)
+    })?;
+    let mut stats = stats.into_inner().expect("file stats poisoned");
+    stats.sort_unstable_by_key(|(i, _)| *i);
+    Ok(stats.into_iter().map(|(_, file)| file).collect())
+}
+
+fn decode_file(path: &Path, decoder: &LogDecoder, output: &Output) -> Result<FileStats> {
+    let start = Instant::now();
+    let mut lines = open_file(path)?.lines();
+    let mut batch = Vec::with_capacity(BATCH_LINES);
+    let mut decoded = 0;
+    while !stop::requested() {
+        batch.clear();
+        for line in lines.by_ref().take(BATCH_LINES) { batch.push(line?); }
+        if batch.is_empty() { break; }
+        decoded += decode_lines(batch.iter().map(String::as_str), decoder, output, None)?;
+    }
+    Ok(FileStats { path: path.to_path_buf(), decoded, elapsed: start.elapsed() })
+}
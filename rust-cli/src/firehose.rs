@@ -0,0 +1,108 @@
+//! `--input-format firehose`: Firehose merged-blocks files, the dbin containers of `sf.bstream.v1.Block` messages
+//! that StreamingFast's Firehose stores (usually 100 blocks per `.dbin.zst` file), with the logs of each
+//! `sf.ethereum.type.v2.Block` payload extracted in block order.
+//!
+//! A dbin file starts with the magic `dbin` and a version byte. Version 0 follows it with a 3-byte content type
+//! (`ETH`) and a 2-byte content version; version 1 with the content type as a big-endian u16 length and that many
+//! bytes. Messages follow, each a big-endian u32 length and that many bytes.
+
+use std::io::{ErrorKind, Read};
+
+use anyhow::{bail, Context, Result};
+use prost::Message;
+
+use crate::decode::LogRecord;
+use crate::protobuf::{pb, to_record};
+use crate::stop;
+
+pub mod bstream {
+    tonic::include_proto!("sf.bstream.v1");
+}
+
+/// Type URL of the Ethereum payload in `bstream::Block::payload`.
+pub const ETHEREUM_BLOCK: &str = "type.googleapis.com/sf.ethereum.type.v2.Block";
+
+/// Streams the logs of every block in `reader`, calling `f` with each log, or with the error of a block or log that
+/// does not decode, so `--on-error skip` can step over it. Logs carry the block number, address and log index;
+/// logs without topics are skipped. A header that is not dbin, or a message cut short, fails the whole read.
+/// Reading ends early, without error, once [`stop::requested`] is set.
+pub fn for_each_log<R: Read>(mut reader: R, mut f: impl FnMut(Result<LogRecord>) -> Result<()>) -> Result<()> {
+    read_header(&mut reader)?;
+    let mut message = 0usize;
+    let mut buf = Vec::new();
+    while !stop::requested() {
+        let mut len = [0u8; 4];
+        if !read_first(&mut reader, &mut len)? { return Ok(()); }
+        message += 1;
+        // Read through `take` so a corrupt length cannot allocate up to 4 GiB before the input runs out
+        let len = u64::from(u32::from_be_bytes(len));
+        buf.clear();
+        (&mut reader).take(len).read_to_end(&mut buf)?;
+        if (buf.len() as u64) < len { bail!("Invalid dbin message {}: input ends mid-message", message); }
+
+        let block = match ethereum_block(&buf).with_context(|| format!("Invalid block in dbin message {}", message)) {
+            Ok(block) => block,
+            Err(e) => { f(Err(e))?; continue; }
+        };
+        let logs = block.transaction_traces.into_iter().filter_map(|trace| trace.receipt).flat_map(|receipt| receipt.logs);
+        for log in logs {
+            match to_record(log, Some(block.number)).with_context(|| format!("Invalid log in block {}", block.number)) {
+                Ok(record) if record.raw.topics.is_empty() => {}
+                record => f(record)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Unwraps the Ethereum block of one `sf.bstream.v1.Block` message.
+fn ethereum_block(bytes: &[u8]) -> Result<pb::Block> {
+    let wrapper = bstream::Block::decode(bytes)?;
+    let payload = match wrapper.payload {
+        Some(any) if any.type_url != ETHEREUM_BLOCK => bail!("payload is {}, expected {}", any.type_url, ETHEREUM_BLOCK),
+        Some(any) => any.value,
+        None => wrapper.payload_buffer,
+    };
+    Ok(pb::Block::decode(&payload[..])?)
+}
+
+fn read_header(reader: &mut impl Read) -> Result<()> {
+    let mut head = [0u8; 5];
+    reader.read_exact(&mut head).context("Invalid dbin file: shorter than its header")?;
+    if &head[..4] != b"dbin" { bail!("Invalid dbin file: missing the dbin magic"); }
+    let content_type = match head[4] {
+        0 => {
+            let mut rest = [0u8; 5];
+            reader.read_exact(&mut rest).context("Invalid dbin file: shorter than its header")?;
+            rest[..3].to_vec()
+        }
+        1 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len).context("Invalid dbin file: shorter than its header")?;
+            let mut content_type = vec![0u8; u16::from_be_bytes(len) as usize];
+            reader.read_exact(&mut content_type).context("Invalid dbin file: shorter than its header")?;
+            content_type
+        }
+        version => bail!("Invalid dbin file: unsupported version {}", version),
+    };
+    let content_type = String::from_utf8_lossy(&content_type);
+    if content_type != "ETH" && content_type != ETHEREUM_BLOCK {
+        bail!("dbin file holds {} blocks, expected Ethereum (ETH)", content_type);
+    }
+    Ok(())
+}
+
+/// Fills `buf` with a message length; false at a clean end of input.
+fn read_first(reader: &mut impl Read, buf: &mut [u8; 4]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => bail!("Invalid dbin file: input ends mid-length"),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
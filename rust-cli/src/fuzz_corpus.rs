@@ -0,0 +1,86 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use evm_rust_decoder::decode::{parse_h256, parse_hex_bytes, LogLine};
+
+/// Fuzz target the seeds are written for (see `fuzz/fuzz_targets/`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FuzzTarget {
+    /// Raw input lines, as read by the JSONL decoder
+    ParseLine,
+    /// Binary-framed logs: topic count byte, 32-byte topics, then the data bytes
+    DecodeLog,
+}
+
+#[derive(Args, Debug)]
+pub struct FuzzCorpusArgs {
+    /// JSONL input to take seeds from (any --input-schema shape)
+    #[arg(long)]
+    input: PathBuf,
+
+    /// Corpus directory to write into (default: fuzz/corpus/<target>)
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = FuzzTarget::ParseLine)]
+    target: FuzzTarget,
+
+    /// Maximum number of seeds to write
+    #[arg(long, default_value_t = 256)]
+    max: usize,
+}
+
+/// Writes one seed per distinct log shape (topic0, topic count, data length) found in the input, so the fuzzer
+/// starts from every event layout the benchmark data contains instead of thousands of near-identical Transfers.
+pub fn run(args: &FuzzCorpusArgs) -> Result<()> {
+    let out_dir = args.out.clone().unwrap_or_else(|| {
+        let name = match args.target { FuzzTarget::ParseLine => "parse_line", FuzzTarget::DecodeLog => "decode_log" };
+        PathBuf::from("fuzz/corpus").join(name)
+    });
+    fs::create_dir_all(&out_dir).with_context(|| format!("Cannot create corpus directory {:?}", out_dir))?;
+    let file = File::open(&args.input).with_context(|| format!("Cannot open input file: {:?}", args.input))?;
+
+    let mut shapes = HashSet::new();
+    let (mut scanned, mut written) = (0usize, 0usize);
+    for line in BufReader::new(file).lines() {
+        if written >= args.max { break; }
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        scanned += 1;
+        let log: Option<LogLine> = serde_json::from_str(&line).ok();
+        let shape = match &log {
+            Some(log) => format!("log:{}:{}:{}", log.topics.first().map_or("", |t| t.as_str()), log.topics.len(), log.data.len()),
+            // Responses and batches are kept once per leading character and rough size
+            None => format!("doc:{}:{}", line.trim_start().chars().next().unwrap_or(' '), line.len().next_power_of_two()),
+        };
+        if !shapes.insert(shape) { continue; }
+
+        let seed = match args.target {
+            FuzzTarget::ParseLine => line.into_bytes(),
+            FuzzTarget::DecodeLog => match log.as_ref().map(frame_log) {
+                Some(Ok(seed)) => seed,
+                _ => continue,
+            },
+        };
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        fs::write(out_dir.join(format!("{:016x}", hasher.finish())), &seed)?;
+        written += 1;
+    }
+    println!("fuzz-corpus scanned={} seeds={} out={}", scanned, written, out_dir.display());
+    Ok(())
+}
+
+/// Binary framing read by the `decode_log` fuzz target.
+fn frame_log(log: &LogLine) -> Result<Vec<u8>> {
+    let mut seed = vec![log.topics.len() as u8];
+    for t in &log.topics { seed.extend_from_slice(parse_h256(t)?.as_bytes()); }
+    seed.extend(parse_hex_bytes(&log.data)?);
+    Ok(seed)
+}
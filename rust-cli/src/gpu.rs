@@ -0,0 +1,234 @@
+//! `--engine gpu` (feature `gpu`): an experiment in decoding fixed-layout events (every parameter one word, e.g.
+//! ERC-20 `Transfer`) on a GPU through wgpu. The host gathers each log's parameter words, a compute shader turns
+//! each big-endian word into the little-endian limbs of a `U256`, and the host builds the tokens back from those.
+//! Every batch is also decoded on the CPU, so the report sets upload, compute and download time against it.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use ethereum_types::U256;
+use wgpu::util::DeviceExt;
+
+use crate::decode::{DecodedLog, LogDecoder, LogRecord};
+use crate::error::DecodeError;
+
+/// Logs sent to the GPU at once.
+pub const GPU_BATCH_LOGS: usize = 1 << 16;
+/// Words per workgroup, as declared in the shader.
+const WORKGROUP_WORDS: u32 = 64;
+/// Workgroups per dispatch dimension allowed by every backend.
+const MAX_WORKGROUPS: u32 = 65535;
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var<storage, read> words: array<u32>;
+@group(0) @binding(1) var<storage, read_write> limbs: array<u32>;
+@group(0) @binding(2) var<uniform> count: vec4<u32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let w = id.x + id.y * groups.x * 64u;
+    if (w >= count.x) { return; }
+    // u32 i of the result is the byte-swapped u32 7 - i of the big-endian word
+    for (var i = 0u; i < 8u; i++) {
+        let x = words[w * 8u + 7u - i];
+        limbs[w * 8u + i] = (x << 24u) | ((x << 8u) & 0xff0000u) | ((x >> 8u) & 0xff00u) | (x >> 24u);
+    }
+}
+"#;
+
+/// Time spent per stage across all batches, and how the logs were split between GPU and CPU.
+#[derive(Clone, Debug, Default)]
+pub struct GpuTimes {
+    pub batches: usize,
+    pub gpu_logs: usize,
+    /// Logs decoded on the CPU only: batches of mixed topic0s or other events, and logs that do not fit the layout
+    pub cpu_logs: usize,
+    /// GPU results that differ from the CPU's; always 0 unless the shader is wrong
+    pub mismatches: usize,
+    /// Copying parameter words out of the logs
+    pub gather: Duration,
+    /// Buffer creation and the copy to the device
+    pub upload: Duration,
+    pub compute: Duration,
+    /// The copy back and mapping it
+    pub download: Duration,
+    /// Building tokens from the returned limbs
+    pub assemble: Duration,
+    /// `EventLayout::decode_batch` over the same logs
+    pub cpu: Duration,
+}
+
+pub struct GpuEngine {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    /// The adapter's name and backend, for the report
+    pub adapter: String,
+}
+
+impl GpuEngine {
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .context("No GPU adapter found (wgpu found no Vulkan, Metal, DX12 or GL device)")?;
+        let info = adapter.get_info();
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("evm-decoder"),
+            ..Default::default()
+        }))
+        .context("Cannot open the GPU device")?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("words_to_limbs"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("words_to_limbs"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Ok(GpuEngine { device, queue, pipeline, adapter: format!("{} ({:?})", info.name, info.backend) })
+    }
+
+    /// Decodes `records` (up to [`GPU_BATCH_LOGS`] of them) in order, calling `f` with what
+    /// [`LogDecoder::decode`] would return for each. A batch whose logs share a fixed-layout event goes to the GPU;
+    /// anything else, and any log that does not fit the layout, is decoded on the CPU.
+    pub fn decode_batch(
+        &self,
+        decoder: &LogDecoder,
+        records: Vec<LogRecord>,
+        times: &mut GpuTimes,
+        mut f: impl FnMut(Result<DecodedLog<'_>>) -> Result<()>,
+    ) -> Result<()> {
+        if records.is_empty() { return Ok(()); }
+        let layout = decoder.batch_layout(&records).filter(|l| l.is_fixed() && decoder.within_limits(l, &records));
+        let Some(layout) = layout else {
+            times.cpu_logs += records.len();
+            return decoder.decode_batch(records, f);
+        };
+        let per_log = layout.event.inputs.len();
+        let max_words = self.device.limits().max_storage_buffer_binding_size as usize / 32;
+
+        let t = Instant::now();
+        let mut words = Vec::with_capacity(records.len() * per_log);
+        let fits: Vec<bool> = records.iter().map(|r| layout.gather_words(&r.raw.topics, &r.raw.data, &mut words)).collect();
+        times.gather += t.elapsed();
+        if words.is_empty() || words.len() > max_words {
+            times.cpu_logs += records.len();
+            return decoder.decode_batch(records, f);
+        }
+
+        let limbs = self.words_to_limbs(&words, times)?;
+
+        let t = Instant::now();
+        let mut values = limbs.chunks_exact(8).map(|l| {
+            U256([0, 2, 4, 6].map(|i| u64::from(l[i]) | (u64::from(l[i + 1]) << 32)))
+        });
+        let logs: Vec<_> = fits.iter().map(|&fits| {
+            fits.then(|| {
+                let log: Vec<U256> = values.by_ref().take(per_log).collect();
+                layout.log_from_words(&log)
+            })
+        }).collect();
+        times.assemble += t.elapsed();
+
+        let t = Instant::now();
+        let cpu = layout.decode_batch(records.iter().map(|r| (&r.raw.topics[..], &r.raw.data[..])));
+        times.cpu += t.elapsed();
+
+        times.batches += 1;
+        for ((record, gpu), cpu) in records.into_iter().zip(logs).zip(cpu) {
+            let log = match gpu {
+                Some(gpu) => {
+                    times.gpu_logs += 1;
+                    if gpu.as_ref().ok() != cpu.as_ref().ok() { times.mismatches += 1; }
+                    gpu
+                }
+                None => {
+                    times.cpu_logs += 1;
+                    cpu
+                }
+            };
+            let log = log.context(DecodeError::AbiMismatch);
+            f(log.map(|log| DecodedLog { event: &layout.event, log, meta: decoder.tag(record.meta) }))?;
+        }
+        Ok(())
+    }
+
+    /// Runs the shader over `words`, returning eight little-endian u32 limbs per word.
+    fn words_to_limbs(&self, words: &[[u8; 32]], times: &mut GpuTimes) -> Result<Vec<u32>> {
+        let size = (words.len() * 32) as u64;
+        let t = Instant::now();
+        let input = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("words"),
+            contents: words.as_flattened(),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let count = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("count"),
+            contents: &[words.len() as u32, 0, 0, 0].map(u32::to_le_bytes).concat(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("limbs"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: input.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: output.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: count.as_entire_binding() },
+            ],
+        });
+        self.queue.submit([]);
+        self.wait()?;
+        times.upload += t.elapsed();
+
+        let t = Instant::now();
+        let groups = (words.len() as u32).div_ceil(WORKGROUP_WORDS);
+        let (x, y) = (groups.min(MAX_WORKGROUPS), groups.div_ceil(MAX_WORKGROUPS));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(x, y, 1);
+        }
+        self.queue.submit([encoder.finish()]);
+        self.wait()?;
+        times.compute += t.elapsed();
+
+        let t = Instant::now();
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, size);
+        self.queue.submit([encoder.finish()]);
+        let slice = staging.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |mapped| { let _ = tx.send(mapped); });
+        self.wait()?;
+        rx.recv().context("GPU buffer mapping was dropped")?.context("Cannot map the GPU result")?;
+        let limbs = slice.get_mapped_range().chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().expect("4 bytes"))).collect();
+        staging.unmap();
+        times.download += t.elapsed();
+        Ok(limbs)
+    }
+
+    fn wait(&self) -> Result<()> {
+        self.device.poll(wgpu::PollType::Wait).map(|_| ()).map_err(|e| anyhow!("GPU device lost: {}", e))
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::io::{BufRead, Read};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use ethereum_types::{H160, H256};
+
+use evm_rust_decoder::decode::{parse_h256, parse_log_lines, snippet, InputSchema};
+use evm_rust_decoder::io::open_file;
+use evm_rust_decoder::topic0_map::MapImpl;
+
+#[derive(Args, Debug)]
+pub struct IndexArgs {
+    /// JSONL input to index (`.zst` is decompressed)
+    #[arg(long)]
+    input: PathBuf,
+
+    #[arg(long, value_enum, default_value_t = InputSchema::Auto)]
+    input_schema: InputSchema,
+
+    /// Hasher of both maps; `perfect` needs the keys in advance, so it is not offered here
+    #[arg(long, value_enum, default_value_t = MapImpl::Std)]
+    map_impl: MapImpl,
+
+    /// Read the whole input before starting the timer
+    #[arg(long, default_value_t = false)]
+    preload: bool,
+
+    /// Number of topic0s and addresses listed after the timings
+    #[arg(long, default_value_t = 5)]
+    top: usize,
+}
+
+/// topic0 → count and address → count, the first thing an indexer builds over a log stream.
+struct Index<S> {
+    topic0: HashMap<H256, u64, S>,
+    address: HashMap<H160, u64, S>,
+    logs: usize,
+    no_address: usize,
+    /// Every log's keys in input order, replayed by the lookup pass
+    keys: Vec<(H256, Option<H160>)>,
+}
+
+/// Instead of decoding, builds a topic0 and an address count index over the input, then looks every log's keys
+/// up again, and reports the throughput of both.
+pub fn run(args: &IndexArgs) -> Result<()> {
+    match args.map_impl {
+        MapImpl::Std => index::<std::hash::RandomState>(args),
+        MapImpl::Fx => index::<rustc_hash::FxBuildHasher>(args),
+        MapImpl::Ahash => index::<ahash::RandomState>(args),
+        MapImpl::Perfect => bail!("--map-impl perfect needs the keys in advance; use std, fx or ahash"),
+    }
+}
+
+fn index<S: BuildHasher + Default>(args: &IndexArgs) -> Result<()> {
+    let mut reader = open_file(&args.input)?;
+    let preloaded = if args.preload {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).with_context(|| format!("Cannot read input {:?}", args.input))?;
+        Some(text)
+    } else {
+        None
+    };
+
+    let mut index = Index::<S> {
+        topic0: HashMap::default(),
+        address: HashMap::default(),
+        logs: 0,
+        no_address: 0,
+        keys: Vec::new(),
+    };
+    let start = Instant::now();
+    match &preloaded {
+        Some(text) => for (i, line) in text.lines().enumerate() { index.add_line(i + 1, line, args.input_schema)?; },
+        None => for (i, line) in reader.lines().enumerate() { index.add_line(i + 1, &line?, args.input_schema)?; },
+    }
+    let build = start.elapsed().as_secs_f64();
+
+    let start = Instant::now();
+    let mut hits = 0usize;
+    for (topic0, address) in &index.keys {
+        hits += usize::from(index.topic0.contains_key(topic0));
+        if let Some(address) = address { hits += usize::from(index.address.contains_key(address)); }
+    }
+    let lookup = start.elapsed().as_secs_f64();
+    let lookups = index.keys.len() + index.keys.len() - index.no_address;
+
+    println!(
+        "index logs={} topic0s={} addresses={} no_address={} build_ms={:.3} build_lps={:.0}",
+        index.logs, index.topic0.len(), index.address.len(), index.no_address, build * 1000.0, per_sec(index.logs, build)
+    );
+    println!("index lookups={} hits={} lookup_ms={:.3} lookups_per_s={:.0}", lookups, hits, lookup * 1000.0, per_sec(lookups, lookup));
+    for (topic0, count) in top(&index.topic0, args.top) { println!("  topic0 {:>10}  {:?}", count, topic0); }
+    for (address, count) in top(&index.address, args.top) { println!("  address {:>10}  {:?}", count, address); }
+    Ok(())
+}
+
+impl<S: BuildHasher> Index<S> {
+    /// Indexes the logs of one input line; logs without topics are skipped, as decoding skips them.
+    fn add_line(&mut self, number: usize, line: &str, schema: InputSchema) -> Result<()> {
+        if line.trim().is_empty() { return Ok(()); }
+        let logs = parse_log_lines(line, schema).with_context(|| format!("line {}", number))?;
+        for log in &logs {
+            let Some(topic0) = log.topics.first() else { continue };
+            let topic0 = parse_h256(topic0).with_context(|| format!("line {}", number))?;
+            let address = log.address.as_deref().map(parse_address).transpose().with_context(|| format!("line {}", number))?;
+            *self.topic0.entry(topic0).or_default() += 1;
+            match address {
+                Some(address) => *self.address.entry(address).or_default() += 1,
+                None => self.no_address += 1,
+            }
+            self.keys.push((topic0, address));
+            self.logs += 1;
+        }
+        Ok(())
+    }
+}
+
+fn parse_address(s: &str) -> Result<H160> {
+    let mut address = H160::zero();
+    hex::decode_to_slice(s.strip_prefix("0x").unwrap_or(s), address.as_bytes_mut())
+        .with_context(|| format!("Invalid address: {}", snippet(s)))?;
+    Ok(address)
+}
+
+/// The `n` largest counts, ties by key so the output is stable.
+fn top<K: Ord + Hash, S>(counts: &HashMap<K, u64, S>, n: usize) -> Vec<(&K, u64)> {
+    let mut entries: Vec<(&K, u64)> = counts.iter().map(|(k, &v)| (k, v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    entries.truncate(n);
+    entries
+}
+
+fn per_sec(count: usize, secs: f64) -> f64 {
+    if secs > 0.0 { count as f64 / secs } else { 0.0 }
+}
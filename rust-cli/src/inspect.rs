@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Args;
+use ethereum_types::H256;
+use evm_rust_decoder::abi::load_event;
+use evm_rust_decoder::decode::{parse_h256, parse_hex_bytes, parse_log_lines, InputSchema, LogLine};
+
+const HISTOGRAM_BAR_WIDTH: f64 = 40.0;
+const MALFORMED_EXAMPLES: usize = 5;
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// JSONL input to describe
+    #[arg(long)]
+    input: PathBuf,
+
+    /// ABI used to name topic0s and count logs no ABI event matches
+    #[arg(long)]
+    abi: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = InputSchema::Auto)]
+    input_schema: InputSchema,
+
+    /// Number of topic0s and addresses listed
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Default)]
+struct Stats {
+    lines: usize,
+    logs: usize,
+    no_topics: usize,
+    malformed: Vec<(usize, String)>,
+    topic0: HashMap<String, usize>,
+    addresses: HashMap<String, usize>,
+    no_address: usize,
+    data_sizes: Vec<usize>,
+    blocks: Option<(u64, u64)>,
+}
+
+/// Describes the workload of a dataset: how many logs of which events, how large their data is and how many
+/// contracts emitted them. A file mostly made of one event benchmarks a different code path than a mixed one.
+pub fn run(args: &InspectArgs) -> Result<()> {
+    let names: HashMap<H256, String> = match &args.abi {
+        Some(abi) => load_event(abi, "")?.1.into_iter().map(|e| (e.signature(), e.name)).collect(),
+        None => HashMap::new(),
+    };
+    let file = File::open(&args.input).with_context(|| format!("Cannot open input file: {:?}", args.input))?;
+
+    let mut stats = Stats::default();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        stats.lines += 1;
+        match parse_log_lines(&line, args.input_schema).and_then(|logs| check(&logs).map(|()| logs)) {
+            Ok(logs) => logs.iter().for_each(|log| stats.add(log)),
+            Err(e) => stats.malformed.push((i + 1, format!("{:#}", e))),
+        }
+    }
+    stats.print(args, &names);
+    Ok(())
+}
+
+/// Rejects lines the decoder would fail on, so they are counted as malformed rather than as logs.
+fn check(logs: &[LogLine]) -> Result<()> {
+    for log in logs {
+        for topic in &log.topics { parse_h256(topic)?; }
+        parse_hex_bytes(&log.data)?;
+    }
+    Ok(())
+}
+
+impl Stats {
+    fn add(&mut self, log: &LogLine) {
+        self.logs += 1;
+        match log.topics.first() {
+            Some(t) => *self.topic0.entry(t.to_ascii_lowercase()).or_default() += 1,
+            None => self.no_topics += 1,
+        }
+        match &log.address {
+            Some(a) => *self.addresses.entry(a.to_ascii_lowercase()).or_default() += 1,
+            None => self.no_address += 1,
+        }
+        self.data_sizes.push(log.data.strip_prefix("0x").unwrap_or(&log.data).len() / 2);
+        if let Some(b) = log.block_number {
+            self.blocks = Some(self.blocks.map_or((b, b), |(lo, hi)| (lo.min(b), hi.max(b))));
+        }
+    }
+
+    fn print(mut self, args: &InspectArgs, names: &HashMap<H256, String>) {
+        println!(
+            "inspect input={} lines={} logs={} malformed_lines={} no_topics={}",
+            args.input.display(), self.lines, self.logs, self.malformed.len(), self.no_topics
+        );
+        for (line, error) in self.malformed.iter().take(MALFORMED_EXAMPLES) { println!("  malformed line {}: {}", line, error); }
+        if let Some((lo, hi)) = self.blocks { println!("blocks {}..{}", lo, hi); }
+
+        println!("\ntopic0s distinct={}", self.topic0.len());
+        let mut unknown = (0, 0);
+        for (topic0, &count) in &self.topic0 {
+            if !names.is_empty() && !parse_h256(topic0).is_ok_and(|t| names.contains_key(&t)) { unknown.0 += 1; unknown.1 += count; }
+        }
+        for (topic0, count) in sorted(&self.topic0).into_iter().take(args.top) {
+            let name = parse_h256(topic0).ok().and_then(|t| names.get(&t)).map_or("", String::as_str);
+            println!("  {:>10} {:>6.2}%  {}  {}", count, share(count, self.logs), topic0, name);
+        }
+        if self.topic0.len() > args.top { println!("  ... {} more", self.topic0.len() - args.top); }
+        if !names.is_empty() {
+            println!("  not in ABI: {} topic0s, {} logs ({:.2}%)", unknown.0, unknown.1, share(unknown.1, self.logs));
+        }
+
+        self.data_sizes.sort_unstable();
+        if let (Some(&min), Some(&max)) = (self.data_sizes.first(), self.data_sizes.last()) {
+            let mean = self.data_sizes.iter().sum::<usize>() as f64 / self.data_sizes.len() as f64;
+            println!(
+                "\ndata bytes min={} p50={} p90={} p99={} max={} mean={:.1}",
+                min, self.percentile(50.0), self.percentile(90.0), self.percentile(99.0), max, mean
+            );
+            // Buckets up to the next power of two, starting at one 32-byte ABI word
+            let mut buckets: BTreeMap<usize, usize> = BTreeMap::new();
+            for &size in &self.data_sizes {
+                *buckets.entry(if size == 0 { 0 } else { size.next_power_of_two().max(32) }).or_default() += 1;
+            }
+            let most = buckets.values().copied().max().unwrap_or(1);
+            let mut lower = 0;
+            for (&upper, &count) in &buckets {
+                let label = if upper == 0 { "0".to_string() } else { format!("{}-{}", lower + 1, upper) };
+                let width = (count as f64 / most as f64 * HISTOGRAM_BAR_WIDTH).round() as usize;
+                println!("  {:>11} {:>10} {}", label, count, "#".repeat(width.max(1)));
+                lower = upper;
+            }
+        }
+
+        println!("\naddresses distinct={} missing={}", self.addresses.len(), self.no_address);
+        for (address, count) in sorted(&self.addresses).into_iter().take(args.top) {
+            println!("  {:>10} {:>6.2}%  {}", count, share(count, self.logs), address);
+        }
+        if self.addresses.len() > args.top { println!("  ... {} more", self.addresses.len() - args.top); }
+    }
+
+    fn percentile(&self, p: f64) -> usize {
+        let rank = (p / 100.0 * (self.data_sizes.len() - 1) as f64).round() as usize;
+        self.data_sizes[rank]
+    }
+}
+
+/// Entries by descending count, ties by key so the output is stable.
+fn sorted(counts: &HashMap<String, usize>) -> Vec<(&str, usize)> {
+    let mut entries: Vec<(&str, usize)> = counts.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    entries
+}
+
+fn share(count: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { count as f64 / total as f64 * 100.0 }
+}
@@ -0,0 +1,298 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+
+/// Size of each read submitted to the uring backend.
+#[cfg(target_os = "linux")]
+const URING_CHUNK_BYTES: usize = 1 << 20;
+/// Number of chunks the reader thread may run ahead of the decoder.
+#[cfg(target_os = "linux")]
+const URING_QUEUE_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IoBackend {
+    /// Blocking reads through `BufReader`
+    Std,
+    /// io_uring reads on a dedicated thread, overlapping with decoding (Linux only)
+    Uring,
+}
+
+/// Opened input plus a handle to query how long the consumer waited on I/O.
+pub struct Input {
+    pub reader: Box<dyn BufRead + Send>,
+    pub io_wait: Option<IoWait>,
+}
+
+/// Shared counter of time the decoding thread spent blocked on the reader thread.
+#[derive(Clone, Default)]
+pub struct IoWait(Arc<AtomicU64>);
+
+impl IoWait {
+    pub fn get(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn add(&self, d: Duration) {
+        self.0.fetch_add(d.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Whether `error` was caused by reading or writing rather than by the data, anywhere in its chain.
+pub fn is_io_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| cause.is::<io::Error>())
+}
+
+/// `--input -`: standard input, as when `--input` is left out.
+pub const STDIN: &str = "-";
+
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN
+}
+
+/// Whether `path` names a pipe rather than a file: a FIFO on Unix, `\\.\pipe\NAME` on Windows. A pipe is read
+/// front to back once, so it cannot be seeked to a `--checkpoint` offset or read at offsets by `--io-backend uring`.
+pub fn is_pipe(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        std::fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+    }
+    #[cfg(not(unix))]
+    {
+        let path = path.to_string_lossy().to_ascii_lowercase();
+        path.starts_with(r"\\.\pipe\") || path.starts_with(r"\\?\pipe\")
+    }
+}
+
+/// `fs::canonicalize`, without the `\\?\` prefix Windows puts on it where the path is just as valid without
+/// (`\\?\C:\data` → `C:\data`, `\\?\UNC\host\share` → `\\host\share`): such paths print as users typed them, and
+/// passed back as `--input` their `?` is not taken for a glob.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let path = std::fs::canonicalize(path)?;
+    if cfg!(windows) {
+        let text = path.to_string_lossy();
+        if let Some(unc) = text.strip_prefix(r"\\?\UNC\") { return Ok(PathBuf::from(format!(r"\\{}", unc))); }
+        if let Some(drive) = text.strip_prefix(r"\\?\").filter(|rest| rest.as_bytes().get(1) == Some(&b':')) {
+            return Ok(PathBuf::from(drive));
+        }
+    }
+    Ok(path)
+}
+
+/// Expands the glob patterns among `inputs` (e.g. `logs/*.jsonl.zst`) into the matching files in sorted order, so
+/// datasets sharded by block range are read in block order. Other paths, including `-` and Windows `\\?\` and
+/// `\\.\` device paths, are kept as given.
+pub fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let pattern = input.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) || pattern.starts_with(r"\\?\") || pattern.starts_with(r"\\.\") {
+            files.push(input.clone());
+            continue;
+        }
+        let mut matched = glob::glob(&pattern)
+            .with_context(|| format!("Invalid --input pattern {}", pattern))?
+            .collect::<Result<Vec<PathBuf>, _>>()?;
+        if matched.is_empty() { return Err(anyhow!("--input {} matches no files", pattern)); }
+        matched.sort();
+        files.extend(matched);
+    }
+    Ok(files)
+}
+
+/// Whether `path` is zstd-compressed, judged by its `.zst` extension.
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zst")
+}
+
+/// Opens `path` for reading, decompressing `.zst` files on the fly.
+pub fn open_file(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path).with_context(|| format!("Cannot open input {:?}", path))?;
+    if is_compressed(path) {
+        return Ok(Box::new(BufReader::new(zstd::Decoder::new(file)?)));
+    }
+    Ok(Box::new(BufReader::new(file)))
+}
+
+pub fn open_input(path: Option<&Path>, backend: IoBackend) -> Result<Input> {
+    match (backend, path) {
+        (IoBackend::Std, Some(path)) => Ok(Input { reader: open_file(path)?, io_wait: None }),
+        (IoBackend::Std, None) => Ok(Input { reader: Box::new(BufReader::new(io::stdin())), io_wait: None }),
+        (IoBackend::Uring, None) => Err(anyhow!("--io-backend uring requires --input")),
+        (IoBackend::Uring, Some(path)) if is_compressed(path) => Err(anyhow!("--io-backend uring reads uncompressed input only")),
+        (IoBackend::Uring, Some(path)) if is_pipe(path) => Err(anyhow!("--io-backend uring reads files at offsets, which a pipe cannot be")),
+        (IoBackend::Uring, Some(path)) => open_uring(path),
+    }
+}
+
+/// Opens `path` with the `std` backend, positioned at byte `offset` (a line boundary saved by a checkpoint).
+pub fn open_input_at(path: &Path, offset: u64) -> Result<Input> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(Input { reader: Box::new(BufReader::new(file)), io_wait: None })
+}
+
+#[cfg(target_os = "linux")]
+fn open_uring(path: &Path) -> Result<Input> {
+    use std::sync::mpsc::sync_channel;
+    use std::thread;
+
+    let (tx, rx) = sync_channel::<io::Result<Vec<u8>>>(URING_QUEUE_DEPTH);
+    let path = path.to_path_buf();
+    thread::Builder::new().name("uring-reader".into()).spawn(move || {
+        tokio_uring::start(async move {
+            let file = match tokio_uring::fs::File::open(&path).await {
+                Ok(f) => f,
+                Err(e) => { let _ = tx.send(Err(e)); return; }
+            };
+            let mut offset: u64 = 0;
+            loop {
+                let (res, mut buf) = file.read_at(vec![0u8; URING_CHUNK_BYTES], offset).await;
+                match res {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        buf.truncate(n);
+                        offset += n as u64;
+                        if tx.send(Ok(buf)).is_err() { break; }
+                    }
+                    Err(e) => { let _ = tx.send(Err(e)); break; }
+                }
+            }
+            let _ = file.close().await;
+        });
+    })?;
+
+    let io_wait = IoWait::default();
+    let reader = ChunkReader { rx, chunk: Vec::new(), pos: 0, io_wait: io_wait.clone() };
+    Ok(Input { reader: Box::new(BufReader::with_capacity(URING_CHUNK_BYTES, reader)), io_wait: Some(io_wait) })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_uring(_path: &Path) -> Result<Input> {
+    Err(anyhow!("--io-backend uring is only available on Linux"))
+}
+
+/// `Read` adapter over chunks produced by a background reader thread.
+#[cfg(target_os = "linux")]
+struct ChunkReader {
+    rx: std::sync::mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    io_wait: IoWait,
+}
+
+#[cfg(target_os = "linux")]
+impl io::Read for ChunkReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.chunk.len() {
+            let waited = std::time::Instant::now();
+            let next = self.rx.recv();
+            self.io_wait.add(waited.elapsed());
+            match next {
+                Ok(chunk) => { self.chunk = chunk?; self.pos = 0; }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = out.len().min(self.chunk.len() - self.pos);
+        out[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Ends the input after `lines` lines, for `--exit-after`.
+pub fn limit_lines(reader: Box<dyn BufRead + Send>, lines: usize) -> Box<dyn BufRead + Send> {
+    Box::new(LineLimit { inner: reader, remaining: lines, window: 0 })
+}
+
+struct LineLimit<R> {
+    inner: R,
+    remaining: usize,
+    /// Bytes at the start of the inner buffer already found to hold no more than the remaining lines, so each byte
+    /// is scanned once however many times it is filled
+    window: usize,
+}
+
+impl<R: BufRead> io::Read for LineLimit<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let buf = self.fill_buf()?;
+        let n = buf.len().min(out.len());
+        out[..n].copy_from_slice(&buf[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for LineLimit<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.remaining == 0 { return Ok(&[]); }
+        let buf = self.inner.fill_buf()?;
+        if self.window == 0 {
+            // Expose bytes up to and including the newline that ends the last allowed line
+            self.window = buf.iter().enumerate().filter(|&(_, &b)| b == b'\n').nth(self.remaining - 1).map_or(buf.len(), |(i, _)| i + 1);
+        }
+        Ok(&buf[..self.window])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // After the last line the inner buffer may be empty, and filling it would block on an endless input
+        if amt == 0 { return; }
+        // The buffer is already filled, so this returns it without reading
+        if let Ok(buf) = self.inner.fill_buf() {
+            let newlines = buf[..amt].iter().filter(|&&b| b == b'\n').count();
+            self.remaining = self.remaining.saturating_sub(newlines);
+        }
+        self.window = self.window.saturating_sub(amt);
+        self.inner.consume(amt);
+    }
+}
+
+/// Byte offset in the input just past what a reader wrapped by [`count_bytes`] has consumed.
+#[derive(Clone)]
+pub struct BytesRead(Arc<AtomicU64>);
+
+impl BytesRead {
+    pub fn new(start: u64) -> BytesRead {
+        BytesRead(Arc::new(AtomicU64::new(start)))
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts the bytes consumed from `reader` into `bytes`. Line readers consume exactly up to each newline, so
+/// between lines the count is the offset of the next line.
+pub fn count_bytes(reader: Box<dyn BufRead + Send>, bytes: BytesRead) -> Box<dyn BufRead + Send> {
+    Box::new(CountingReader { inner: reader, bytes })
+}
+
+struct CountingReader<R> {
+    inner: R,
+    bytes: BytesRead,
+}
+
+impl<R: BufRead> io::Read for CountingReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(out)?;
+        self.bytes.0.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.bytes.0.fetch_add(amt as u64, Ordering::Relaxed);
+        self.inner.consume(amt);
+    }
+}
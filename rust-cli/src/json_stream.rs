@@ -0,0 +1,102 @@
+use std::fmt;
+use std::io::Read;
+
+use anyhow::{anyhow, Result};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+
+use crate::decode::LogLine;
+use crate::stop;
+use crate::timing::{self, Phase};
+
+/// Streams the logs of a single JSON document — a JSON-RPC response `{"jsonrpc":"2.0","result":[...]}` or a bare
+/// array of logs — calling `f` for each log as it is parsed, so the document never has to fit in memory. Parsing
+/// ends early, without error, once [`stop::requested`] is set.
+pub fn for_each_log<R: Read>(reader: R, mut f: impl FnMut(LogLine) -> Result<()>) -> Result<()> {
+    let mut failed: Option<anyhow::Error> = None;
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let parsed = Document { f: &mut f, failed: &mut failed }.deserialize(&mut de).and_then(|_| de.end());
+    match (failed, parsed) {
+        (Some(e), _) => Err(e),
+        (None, Err(_)) if stop::requested() => Ok(()),
+        (None, Err(e)) => Err(anyhow!("Invalid JSON document: {}", e)),
+        (None, Ok(())) => Ok(()),
+    }
+}
+
+/// Top level: an object whose `result` holds the logs, or the array of logs itself.
+struct Document<'a, F> {
+    f: &'a mut F,
+    failed: &'a mut Option<anyhow::Error>,
+}
+
+impl<'de, F: FnMut(LogLine) -> Result<()>> DeserializeSeed<'de> for Document<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, F: FnMut(LogLine) -> Result<()>> Visitor<'de> for Document<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON-RPC response object or an array of logs")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "result" => map.next_value_seed(Logs { f: &mut *self.f, failed: &mut *self.failed })?,
+                "error" => {
+                    let err: Value = map.next_value()?;
+                    if !err.is_null() { return Err(de::Error::custom(format!("JSON-RPC error response: {}", err))); }
+                }
+                _ => { map.next_value::<IgnoredAny>()?; }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<(), A::Error> {
+        Logs { f: self.f, failed: self.failed }.visit_seq(seq)
+    }
+}
+
+/// The array of logs, handed to the callback one element at a time.
+struct Logs<'a, F> {
+    f: &'a mut F,
+    failed: &'a mut Option<anyhow::Error>,
+}
+
+impl<'de, F: FnMut(LogLine) -> Result<()>> DeserializeSeed<'de> for Logs<'_, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(LogLine) -> Result<()>> Visitor<'de> for Logs<'_, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of logs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        loop {
+            if stop::requested() { return Err(de::Error::custom("stopped")); }
+            let t = timing::start();
+            let next = seq.next_element::<LogLine>()?;
+            timing::record(Phase::Json, t);
+            let Some(log) = next else { break };
+            if let Err(e) = (self.f)(log) {
+                *self.failed = Some(e);
+                return Err(de::Error::custom("aborted"));
+            }
+        }
+        Ok(())
+    }
+}
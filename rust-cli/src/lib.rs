@@ -0,0 +1,72 @@
+//! Decoding core of `evm_rust_decoder`, shared by the CLI binary and the fuzz targets in `fuzz/`. Everything but
+//! [`core_decode`] needs the default `std` feature; without it the crate is `no_std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod abi;
+#[cfg(feature = "std")]
+pub mod aggregate;
+#[cfg(feature = "std")]
+pub mod binary;
+pub mod core_decode;
+#[cfg(feature = "std")]
+pub mod decode;
+#[cfg(feature = "std")]
+pub mod disasm;
+#[cfg(feature = "std")]
+pub mod eip712;
+#[cfg(feature = "std")]
+pub mod enrich;
+#[cfg(feature = "std")]
+pub mod era1;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod fast_json;
+#[cfg(feature = "std")]
+pub mod files;
+#[cfg(feature = "std")]
+pub mod firehose;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "std")]
+pub mod json_stream;
+#[cfg(feature = "std")]
+pub mod output;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "std")]
+pub mod predicate;
+#[cfg(feature = "std")]
+pub mod presets;
+#[cfg(feature = "std")]
+pub mod projection;
+#[cfg(feature = "std")]
+pub mod protobuf;
+#[cfg(feature = "std")]
+pub mod sol;
+#[cfg(feature = "std")]
+pub mod source;
+#[cfg(feature = "std")]
+pub mod stop;
+#[cfg(feature = "std")]
+pub mod storage;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod timing;
+#[cfg(feature = "std")]
+pub mod topic0_map;
+#[cfg(feature = "std")]
+pub mod topk;
+#[cfg(feature = "std")]
+pub mod value;
+#[cfg(feature = "std")]
+pub mod writer;
@@ -0,0 +1,48 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ValueEnum;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Level of the diagnostics printed when `RUST_LOG` is unset.
+const DEFAULT_FILTER: &str = "info";
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// `LEVEL message key=value ...` lines
+    #[default]
+    Text,
+    /// One JSON object per event, including the close of each phase span with its busy and idle time
+    Json,
+}
+
+/// Sends diagnostics to stderr, filtered by `RUST_LOG` (e.g. `RUST_LOG=debug` or `RUST_LOG=evm_rust_decoder=trace`).
+/// Result lines such as `decoded=N elapsed_ms=X` keep their plain format in text mode, since the harnesses parse them.
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let builder = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.without_time().with_target(false).init(),
+        LogFormat::Json => {
+            JSON.store(true, Ordering::Relaxed);
+            builder.json().flatten_event(true).with_span_events(FmtSpan::CLOSE).init()
+        }
+    }
+}
+
+/// Whether `--log-format json` is in effect, so result lines are emitted as events rather than printed.
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// `value` to `decimals` places, for event fields, which have no format precision.
+pub fn round(value: f64, decimals: i32) -> f64 {
+    let scale = 10f64.powi(decimals);
+    (value * scale).round() / scale
+}
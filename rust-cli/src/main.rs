@@ -1,222 +1,1361 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+mod allocator;
+mod bench_all;
+mod bench_pgo;
+mod bytecode;
+mod checkpoint;
+mod config;
+mod contracts;
+mod convert;
+mod dataset;
+mod encode;
+mod error_log;
+mod exit_code;
+mod fetch_abi;
+mod fuzz_corpus;
+mod index;
+mod inspect;
+mod logging;
+mod metrics;
+mod modes;
+mod parallel_mode;
+mod partitions;
+mod rate;
+mod replay;
+mod report;
+mod results;
+mod sample;
+mod schema;
+mod selectors;
+mod selftest;
+mod selftest_anvil;
+mod serve;
+mod serve_grpc;
+mod serve_http;
+mod sink;
+mod state_diff;
+mod traces;
+mod typed_data;
+mod verify;
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, Read, Write};
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::time::Instant;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use parallel_mode::{ParallelMode, Shape};
+use report::{Report, ReportFormat, Section};
+use tracing::{error, info, info_span, warn};
+
+use evm_rust_decoder::abi::{self, AbiSet, CollisionPolicy};
+use evm_rust_decoder::aggregate::Aggregator;
+use evm_rust_decoder::core_decode::{Limits, HEX_DECODER};
+use evm_rust_decoder::{binary, era1, firehose, protobuf};
+use evm_rust_decoder::decode::{snippet, to_raw_log, InputFormat, InputSchema, LogDecoder, LogFilter, LogRecord, OnError};
+use evm_rust_decoder::enrich::Tokens;
+use evm_rust_decoder::error::DecodeError;
+use evm_rust_decoder::io::{self as input_io, open_input, IoBackend};
+use evm_rust_decoder::source::{Source, SourceCounts};
+use evm_rust_decoder::output::{Output, OutputOrder, Serializer};
+use evm_rust_decoder::predicate::{Predicate, METADATA_FIELDS};
+use evm_rust_decoder::parallel::{self, CoreList, Placement, SplitStrategy, Workload, SWEEP_THREADS};
+use evm_rust_decoder::presets::Preset;
+use evm_rust_decoder::projection::Projection;
+use evm_rust_decoder::timing::{self, Phase};
+use evm_rust_decoder::topic0_map::MapImpl;
+use evm_rust_decoder::topk::TopK;
+use evm_rust_decoder::writer::{self, OutputWriter};
+use evm_rust_decoder::{files, json_stream, pipeline, stop};
+
+/// What decodes the logs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum Engine {
+    #[default]
+    Cpu,
+    /// Fixed-layout events on a GPU through wgpu, next to the CPU for comparison (needs `--features gpu`)
+    Gpu,
+}
 
-use anyhow::{anyhow, Context, Result};
-use clap::Parser;
-use ethereum_types::H256;
-use ethabi::{Event, EventParam, ParamType, RawLog, Token};
-use hex::FromHex;
-use serde::Deserialize;
-use serde_json::{json, Value};
+/// What the input holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Event logs, decoded against the ABI's events
+    #[default]
+    Logs,
+    /// `debug_traceTransaction` callTracer output, whose call inputs and outputs are decoded against the ABI's
+    /// functions (see `traces.rs`)
+    Traces,
+    /// `prestateTracer` output, with or without `diffMode`, whose storage slots are decoded into the state
+    /// variables of --storage-layout (see `storage.rs`)
+    StateDiff,
+    /// EIP-712 typed-data documents (or `eth_signTypedData_v4` requests), hashed into their domain separator,
+    /// struct hash and signed digest (see `eip712.rs`)
+    Eip712,
+    /// Contract bytecode (hex, `eth_getCode` responses or compiler artifacts), disassembled into opcodes with
+    /// jump-destination analysis (see `disasm.rs`)
+    Disasm,
+    /// Transactions (objects, full blocks or `eth_getBlockByNumber` responses), counted by the 4-byte selector of
+    /// their `input` and resolved against the ABI's functions and --signatures (see `selectors.rs`)
+    Selectors,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "EVM log decoder using ethabi", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct CliArgs {
-    /// Path to ABI JSON file (array or object containing events)
-    #[arg(long)]
-    abi: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Format of the diagnostics on stderr; `RUST_LOG` sets their verbosity (default `info`)
+    #[arg(long, value_enum, global = true, default_value_t = logging::LogFormat::Text)]
+    log_format: logging::LogFormat,
+
+    /// Path to ABI JSON file (array or object containing events). Repeat to decode with the events of several ABIs;
+    /// `CHAIN:PATH` (`42161:abi/gmx.json`) decodes only the logs of that chain with the file's events
+    #[arg(long, required_unless_present_any = ["self_test", "sol", "preset", "mode"])]
+    abi: Vec<PathBuf>,
+
+    /// Decode with a built-in event set instead of, or on top of, ABI files. Repeatable; preset events come after
+    /// those of --abi and --sol in ABI order
+    #[arg(long, value_enum, value_name = "NAME", conflicts_with = "serve")]
+    preset: Vec<Preset>,
+
+    /// Solidity source whose `event` declarations are decoded, for contracts without a compiled ABI (no solc
+    /// needed). Repeatable, and combined with any --abi files.
+    #[arg(long, value_name = "PATH")]
+    sol: Vec<PathBuf>,
+
+    /// With solc standard-json output as --abi, decode only this contract's events (`Name` or `path/File.sol:Name`)
+    /// instead of those of every contract in the file
+    #[arg(long, value_name = "NAME")]
+    contract: Option<String>,
+
+    /// With topic0 routing, how to treat events that share a topic0 but index different parameters (e.g. ERC-20 and
+    /// ERC-721 Transfer): keep the first in ABI order, refuse to start, or pick per log by its number of topics
+    #[arg(long, value_enum, default_value_t = CollisionPolicy::First)]
+    collision_policy: CollisionPolicy,
+
+    /// Keep parsed events and topic0s of each ABI file in DIR, keyed by the file's SHA-256, and of the whole --abi set,
+    /// keyed by file metadata, so later runs skip ABI parsing
+    #[arg(long, value_name = "DIR")]
+    abi_cache: Option<PathBuf>,
+
+    /// Decode every golden fixture under DIR (default `fixtures`) and fail on any difference from its expected output
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "fixtures")]
+    self_test: Option<PathBuf>,
 
     /// Event name to decode (e.g. Transfer). If omitted, all events in ABI are supported via topic0.
     #[arg(long)]
     event: Option<String>,
 
-    /// Read input from file (JSONL with {"topics":[...],"data":"0x..."}), default stdin
+    /// Read input from file (JSONL with {"topics":[...],"data":"0x..."}), default stdin (also `--input -`). `.zst`
+    /// files are decompressed; a FIFO or Windows named pipe (`\\.\pipe\NAME`) is read like a file. Repeat it or
+    /// pass a quoted glob (`'logs/*.jsonl.zst'`) to decode several files and report each.
     #[arg(long)]
-    input: Option<PathBuf>,
+    input: Vec<PathBuf>,
+
+    /// Read input from a socket instead: `unix:PATH` listens on a Unix domain socket and decodes what the first
+    /// connection sends, in the --input-format framing, until it closes; `zmq://HOST:PORT[?subscribe=PREFIX]`
+    /// subscribes to a ZeroMQ PUB socket over tcp, reconnecting when it drops, and decodes the JSONL of each message
+    /// until --exit-after or Ctrl-C
+    #[arg(long, value_name = "SOURCE", conflicts_with_all = ["input", "serve", "parallel_mode", "checkpoint"])]
+    source: Option<Source>,
+
+    /// With several --input files, decode this many files at a time, one thread per file
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    file_parallelism: usize,
+
+    /// Input framing: JSONL, one large JSON document (`{"jsonrpc":"2.0","result":[...]}` or an array of logs) parsed
+    /// as a stream, binary records (topic count, 32-byte topics, u32 LE data length, data) that skip JSON and hex, or
+    /// length-delimited `sf.ethereum.type.v2.Log` protobuf messages (proto/ethereum_log.proto), Firehose merged-blocks
+    /// (dbin) files, or era1 archives of pre-merge blocks
+    #[arg(long, value_enum, default_value_t = InputFormat::Jsonl)]
+    input_format: InputFormat,
+
+    /// Shape of each input line: plain log objects, JSON-RPC `eth_getLogs` responses, or batches of responses
+    #[arg(long, value_enum, default_value_t = InputSchema::Auto)]
+    input_schema: InputSchema,
+
+    /// Decode event logs, or one JSON document per line: call traces (callTracer frames, JSON-RPC responses
+    /// holding one, or `debug_traceBlockByNumber` results, reported as `traces= calls= ... throughput_cps=`), state
+    /// diffs (prestateTracer results in the same shapes, reported as `state_diff ... throughput_sps=`), EIP-712
+    /// typed data (reported as `eip712 documents= ... throughput_dps=`), contract bytecode (reported as `disasm
+    /// contracts= ... throughput_ips= code_mb_per_s=`) or transactions (reported as `selectors txs= ...
+    /// throughput_tps=`). These take --input, --print, --preload and --on-error; traces and selectors also take
+    /// --abi and --preset (for its functions), selectors --signatures and --top, and state diffs --storage-layout
+    #[arg(long, value_enum, default_value_t = Mode::Logs, conflicts_with_all = ["serve", "sol"])]
+    mode: Mode,
+
+    /// With --mode state-diff, solc `storageLayout` output (standard-json, with --contract, or `forge inspect
+    /// CONTRACT storageLayout --json`) to decode storage with, as `0xADDRESS=PATH` for one contract or `PATH` for
+    /// every account without its own. Repeatable
+    #[arg(long, value_name = "[0xADDRESS=]PATH")]
+    storage_layout: Vec<state_diff::LayoutArg>,
+
+    /// With --mode selectors, a signature database resolving selectors the ABI's functions do not: a JSON object
+    /// of `0x` selectors to a signature (or a list, whose first entry is used), or lines of `0xSELECTOR signature`
+    /// or bare signatures. Repeatable; earlier files and the ABI win
+    #[arg(long, value_name = "PATH")]
+    signatures: Vec<PathBuf>,
+
+    /// Drop repeated (transactionHash, logIndex) pairs, as found in raw node exports that overlap across reorgs
+    #[arg(long, default_value_t = false)]
+    dedupe: bool,
+
+    /// Skip logs marked `"removed": true` (logs of blocks that were reorged out)
+    #[arg(long, default_value_t = false)]
+    drop_removed: bool,
+
+    /// Reject logs whose data is longer than this many bytes, before decoding them
+    #[arg(long, value_name = "BYTES")]
+    max_data_bytes: Option<usize>,
+
+    /// Reject logs with an array of more than N elements anywhere in their data, before decoding them
+    #[arg(long, value_name = "N")]
+    max_array_len: Option<usize>,
+
+    /// What to do with an input line that cannot be parsed or decoded (e.g. an unknown topic0): stop with the
+    /// error, or count it and continue; skipped lines are reported as `skipped errors=N`
+    #[arg(long, value_enum, default_value_t = OnError::Abort)]
+    on_error: OnError,
+
+    /// Write a JSON line per failing input line to PATH, with its line number, byte offset, error kind, message and
+    /// a truncated snippet, to triage the bad records of a large input (single-threaded JSONL decoding)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate", "replay"])]
+    error_log: Option<PathBuf>,
+
+    /// Check each topic0 against a Bloom filter of the ABI's topic0s before the topic0 map, so logs of other events
+    /// (with `--on-error skip`) are turned away cheaply; reports `prefilter passed= rejected= false_positives=`
+    #[arg(long, default_value_t = false, conflicts_with_all = ["event", "serve"])]
+    prefilter: bool,
+
+    /// Topic0 index used by topic0 routing (lookup time is reported as lookup_ms by --phase-timing)
+    #[arg(long, value_enum, default_value_t = MapImpl::Std, conflicts_with = "event")]
+    map_impl: MapImpl,
+
+    /// Decode in batches of 4096 logs, looking the event up once for a batch whose logs share a topic0 and reading
+    /// one-word parameters from offsets resolved up front; applies to --preload, --threads, --ordered,
+    /// --scaling-sweep and several --input files
+    #[arg(long, default_value_t = false, conflicts_with_all = ["serve", "pipeline", "rate"])]
+    homogeneous_fast_path: bool,
+
+    /// Parse JSON and hex and look up each log's event, but skip ABI decoding: logs come out without parameters, so
+    /// the run is a baseline of everything the ABI library does not do. Refused with --sink, whose rows need every
+    /// parameter
+    #[arg(long, default_value_t = false, conflicts_with_all = ["serve", "fields", "sink"])]
+    skip_decode: bool,
+
+    /// Decode only these parameters (comma-separated names, e.g. `from,to,value`) of every event; one-word
+    /// parameters are read from their offsets without decoding the rest of the log, and others kept from a full
+    /// decode. Events with none of them print empty args. Refused with --sink, whose rows need every
+    /// parameter
+    #[arg(long, value_delimiter = ',', value_name = "NAMES", conflicts_with_all = ["serve", "sink"])]
+    fields: Vec<String>,
+
+    /// Emit only decoded logs matching this expression over their parameters, e.g.
+    /// `value > 1e18 && to == 0x...dead` (see `predicate.rs`); reports `where matched= decoded=`
+    #[arg(long = "where", value_name = "EXPR", conflicts_with_all = ["serve", "scaling_sweep", "parallel_mode"])]
+    where_expr: Option<String>,
+
+    /// Print a jq-style projection of each record instead of the record, e.g. `.args.value, .blockNumber` (each on
+    /// its own line) or `{value: .args.value, blockNumber}`; paths start at the envelope with --output-envelope
+    /// and at the args otherwise (see `projection.rs`)
+    #[arg(long, value_name = "FILTER", requires = "print", conflicts_with = "serve")]
+    select: Option<String>,
+
+    /// Compute aggregates over decoded parameters as logs decode and print them at the end, one JSON line per group,
+    /// e.g. `sum(value) by to` or `count, max(value) by event` (see `aggregate.rs`); combines with --where
+    #[arg(long, value_name = "SPEC", conflicts_with_all = ["print", "serve", "scaling_sweep", "parallel_mode"])]
+    aggregate: Option<String>,
+
+    /// Report the k most frequent values of decoded fields (comma-separated `field:k`, k 10 when left out, e.g.
+    /// `to:20`), estimated with a space-saving sketch of 100 counters per value reported (see `topk.rs`)
+    #[arg(long, value_delimiter = ',', value_name = "FIELD:K", conflicts_with_all = ["serve", "scaling_sweep", "parallel_mode"])]
+    topk: Vec<String>,
+
+    /// Decoding engine. `gpu` loads the JSONL input, sends batches whose logs share one fixed-layout event (every
+    /// parameter one word, e.g. Transfer) to the GPU, decodes every batch on the CPU as well and reports
+    /// `gpu ... upload_ms= compute_ms= download_ms= ... cpu_decode_ms=`; an experiment, built with `--features gpu`
+    #[arg(long, value_enum, default_value_t = Engine::Cpu, conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "ordered", "rate", "threads"])]
+    engine: Engine,
+
+    /// Exit with status 2 when any line was skipped under `--on-error skip`
+    #[arg(long, default_value_t = false)]
+    fail_on_skip: bool,
 
     /// Print decoded JSON per line to stdout. If not set, decoding is performed silently.
     #[arg(long, default_value_t = false)]
     print: bool,
+
+    /// How --print output is serialized; `simd-json` and `itoa` print the same bytes as `serde`, and `itoa` skips
+    /// the intermediate map and value copies
+    #[arg(long, value_enum, default_value_t = Serializer::Serde)]
+    serializer: Serializer,
+
+    /// Format and write --print output on a dedicated thread fed through a bounded channel, so it no longer
+    /// interleaves with decoding, and report `writer format_ms= write_ms= ... format_vs_decode=` (single-threaded JSONL)
+    #[arg(long, default_value_t = false, requires = "print", conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate", "checkpoint"])]
+    writer_thread: bool,
+
+    /// Bytes of formatted output the --writer-thread collects before each write to stdout
+    #[arg(long, value_name = "BYTES", default_value_t = writer::DEFAULT_BUFFER_SIZE, requires = "writer_thread")]
+    output_buffer_size: usize,
+
+    /// With --print, wrap each log as {"event","address","blockNumber","args","schemaVersion":1} for cross-implementation comparison
+    #[arg(long, default_value_t = false)]
+    output_envelope: bool,
+
+    /// Add the symbol and decimals of each log's contract, from a `{"0xaddress": {"symbol", "decimals"}}` map or a
+    /// token list, to the envelope as `token`, with ERC-20 amounts scaled (timed as enrich_ms by --phase-timing)
+    #[arg(long, value_name = "TOKENS.json", requires_all = ["print", "output_envelope"])]
+    enrich: Option<PathBuf>,
+
+    /// With --print and --threads, reassemble output in input order (default) or by blockNumber/logIndex
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "input", requires = "print", conflicts_with = "pipeline")]
+    ordered: Option<OutputOrder>,
+
+    /// Stay resident and answer length-prefixed JSON requests (decode, reload, stats, shutdown) on stdin/stdout
+    #[arg(long, default_value_t = false, conflicts_with_all = ["input", "pipeline", "scaling_sweep", "ordered"])]
+    serve: bool,
+
+    /// With --serve, listen on this Unix socket instead of stdin/stdout
+    #[arg(long, value_name = "PATH", requires = "serve")]
+    serve_socket: Option<PathBuf>,
+
+    /// With --serve, drop a log that takes longer than this many microseconds to decode, counting it as timed out
+    #[arg(long, value_name = "US", requires = "serve")]
+    per_log_timeout_us: Option<u64>,
+
+    /// With --serve, stop decoding a request after this many milliseconds, counting its remaining lines and logs as
+    /// timed out
+    #[arg(long, value_name = "MS", requires = "serve")]
+    batch_timeout: Option<u64>,
+
+    /// Serve Prometheus metrics (logs decoded, errors, per-event counts, decode latency) on http://ADDR/metrics
+    /// while decoding a stream or running --serve
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["pipeline", "scaling_sweep", "preload", "ordered"])]
+    metrics_listen: Option<SocketAddr>,
+
+    /// Stop after N input lines (N logs with an --input-format other than jsonl) and report as usual, for bounded runs
+    /// over large or endless inputs. Ctrl-C (SIGINT/SIGTERM) also stops at the next line and prints the summary.
+    #[arg(long, value_name = "N", conflicts_with = "serve")]
+    exit_after: Option<usize>,
+
+    /// Save the input byte offset and counts to this JSON file every 100k lines and at the end of the run
+    /// (single-threaded JSONL decoding of an --input file)
+    #[arg(long, value_name = "PATH", requires = "input", conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered"])]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue from the offset saved in --checkpoint (if the file exists), adding to its counts
+    #[arg(long, default_value_t = false, requires = "checkpoint")]
+    resume: bool,
+
+    /// Offer input at a fixed rate of this many lines (logs) per second and report decode latency percentiles
+    /// under that load, instead of decoding as fast as possible
+    #[arg(long, value_name = "LPS", conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "checkpoint", "metrics_listen"])]
+    rate: Option<f64>,
+
+    /// Report the contracts (or chains) that emit the most logs and take the most decode time (single-threaded
+    /// JSONL decoding)
+    #[arg(long, value_enum, conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate"])]
+    group_by: Option<contracts::GroupBy>,
+
+    /// The chain of logs whose record has no `chainId`, added to --output-envelope records, `--where chainId`
+    /// and `--group-by chain`, so files of several chains can be decoded in one run and told apart
+    #[arg(long, value_name = "ID", conflicts_with = "serve")]
+    chain_id: Option<u64>,
+
+    /// Number of contracts or chains listed by --group-by, or of selectors by --mode selectors
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// Report logs, decode throughput and events per range of N blocks, by each log's `blockNumber`
+    /// (single-threaded JSONL decoding)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate"])]
+    partition_blocks: Option<u64>,
+
+    /// Decode only a sample of the input lines, e.g. `1%` (picked by a hash of the line number, so reruns decode
+    /// the same lines), and estimate the whole input's logs, events and decode time with 95% intervals
+    /// (single-threaded JSONL decoding)
+    #[arg(long, value_name = "RATE", conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate", "checkpoint", "sample_every"])]
+    sample: Option<sample::SampleRate>,
+
+    /// As --sample, decoding the first of every N input lines
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate", "checkpoint"])]
+    sample_every: Option<u64>,
+
+    /// Insert decoded logs into `sqlite:PATH` or a `postgres://` database, one table per event with columns from
+    /// the ABI, and report insert throughput apart from decoding (single-threaded JSONL decoding)
+    #[arg(long, value_name = "TARGET", conflicts_with_all = ["serve", "pipeline", "scaling_sweep", "preload", "ordered", "rate"])]
+    sink: Option<sink::SinkTarget>,
+
+    /// Rows of one table per --sink insert
+    #[arg(long, value_name = "N", default_value_t = 1000, requires = "sink")]
+    sink_batch: usize,
+
+    /// File read backend. `uring` reads ahead on an io_uring thread so reads overlap with decoding (Linux only, needs --input).
+    #[arg(long, value_enum, default_value_t = IoBackend::Std)]
+    io_backend: IoBackend,
+
+    /// Run reader, JSON/hex parser, ABI decoder and output as separate threads joined by bounded channels, and report per-stage busy time.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["threads", "scaling_sweep", "preload"])]
+    pipeline: bool,
+
+    /// Read and line-split the whole input before starting the timer, so elapsed/throughput cover parsing and decoding only
+    #[arg(long, default_value_t = false)]
+    preload: bool,
+
+    /// Number of decoding threads. Values above 1 load the whole input into memory and split it per --split-strategy.
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+
+    /// How to divide the input between threads
+    #[arg(long, value_enum, default_value_t = SplitStrategy::Lines)]
+    split_strategy: SplitStrategy,
+
+    /// Load the input once, then decode it with 1, 2, 4, 8 and 16 threads and print a scaling table (load time excluded)
+    #[arg(long, default_value_t = false)]
+    scaling_sweep: bool,
+
+    /// Decode the input once, then serialize the decoded logs N times into memory with each --serializer and report
+    /// the fastest and mean pass, so output formats are compared without paying for decoding again
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..),
+          conflicts_with_all = ["serve", "print", "pipeline", "scaling_sweep", "ordered", "rate", "checkpoint", "group_by", "partition_blocks", "sink", "sample", "sample_every"])]
+    replay: Option<u64>,
+
+    /// Decode the --input files once with each of these parallelization strategies (comma-separated; `chunk` and
+    /// `file` use --threads workers), report throughput per strategy along with the dataset's shape, and name the
+    /// fastest
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "MODES", requires = "input",
+        conflicts_with_all = ["serve", "print", "pipeline", "scaling_sweep", "preload", "ordered", "rate", "checkpoint", "exit_after"])]
+    parallel_mode: Vec<ParallelMode>,
+
+    /// With --scaling-sweep or --parallel-mode, also render the results as a Markdown or HTML report with a throughput bar chart
+    #[arg(long, value_enum)]
+    report: Option<ReportFormat>,
+
+    /// Write the --report to this file instead of stdout
+    #[arg(long, requires = "report")]
+    report_file: Option<PathBuf>,
+
+    /// Pin worker threads to these cores, e.g. `0-7` or `0,2,4-6`. Worker i runs on the i-th listed core (wrapping).
+    #[arg(long)]
+    pin_cores: Option<CoreList>,
+
+    /// With --pin-cores, have each worker copy its input into memory it allocates itself so pages land on its local NUMA node
+    #[arg(long, default_value_t = false, requires = "pin_cores")]
+    numa_local: bool,
+
+    /// Report time spent in file read, JSON parse, hex decode, ABI decode and output serialization (summed over threads)
+    #[arg(long, default_value_t = false)]
+    phase_timing: bool,
 }
 
-#[derive(Deserialize)]
-struct LogLine {
-    topics: Vec<String>,
-    data: String,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two decoded-output files (e.g. Rust vs Node) and report mismatching records
+    Verify(verify::VerifyArgs),
+    /// Run the Rust CLI, the N-API harness and external implementations on the same datasets and compare their timings
+    BenchAll(bench_all::BenchAllArgs),
+    /// Build the CLI with profile-guided optimization from a training run and time it against the plain release build
+    BenchPgo(bench_pgo::BenchPgoArgs),
+    /// Record bench-all results in a local history store and print throughput trends
+    Results(results::ResultsArgs),
+    /// List, fetch and register the shared benchmark datasets of datasets/manifest.toml
+    Dataset(dataset::DatasetArgs),
+    /// Download verified ABIs by contract address from an Etherscan-compatible explorer, resolving EIP-1967 proxies
+    FetchAbi(fetch_abi::FetchAbiArgs),
+    /// Deploy a contract emitting every supported parameter type on an Anvil or Hardhat node, then fetch and decode
+    /// its logs and check the round trip
+    SelftestAnvil(selftest_anvil::SelftestAnvilArgs),
+    /// Serve POST /decode and GET /metrics over HTTP, to benchmark the decoder as a sidecar service
+    ServeHttp(serve_http::ServeHttpArgs),
+    /// Serve the batch decoding gRPC service of proto/decoder.proto, for clients in other languages
+    ServeGrpc(serve_grpc::ServeGrpcArgs),
+    /// Rewrite a dataset in another input format, e.g. JSONL as binary records for `--input-format bin`
+    Convert(convert::ConvertArgs),
+    /// ABI-encode decoded JSON (`--print` output) back into logs, for round-trip checks and hand-edited fixtures
+    Encode(encode::EncodeArgs),
+    /// Summarise a dataset: log count, topic0s (named from --abi), data sizes, address cardinality and malformed lines
+    Inspect(inspect::InspectArgs),
+    /// Build a topic0 → count and address → count index over a dataset instead of decoding, and time building and
+    /// looking it up
+    Index(index::IndexArgs),
+    /// Export distinct logs from a JSONL input as seed files for the fuzz targets in fuzz/
+    FuzzCorpus(fuzz_corpus::FuzzCorpusArgs),
+    /// Print the SQL, Arrow or JSON Schema of each event's decoded output, to provision sinks and consumers
+    Schema(schema::SchemaArgs),
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let args = CliArgs::parse();
+    logging::init(args.log_format);
+    let result = match &args.command {
+        Some(Command::Verify(verify_args)) => verify::run(verify_args),
+        Some(Command::BenchAll(bench_args)) => bench_all::run(bench_args),
+        Some(Command::BenchPgo(pgo_args)) => bench_pgo::run(pgo_args),
+        Some(Command::Results(results_args)) => results::run(results_args),
+        Some(Command::Dataset(dataset_args)) => dataset::run(dataset_args),
+        Some(Command::FetchAbi(fetch_args)) => fetch_abi::run(fetch_args),
+        Some(Command::SelftestAnvil(anvil_args)) => selftest_anvil::run(anvil_args),
+        Some(Command::ServeHttp(serve_args)) => serve_http::run(serve_args),
+        Some(Command::ServeGrpc(serve_args)) => serve_grpc::run(serve_args),
+        Some(Command::Convert(convert_args)) => convert::run(convert_args),
+        Some(Command::Encode(encode_args)) => encode::run(encode_args),
+        Some(Command::Inspect(inspect_args)) => inspect::run(inspect_args),
+        Some(Command::Index(index_args)) => index::run(index_args),
+        Some(Command::FuzzCorpus(corpus_args)) => fuzz_corpus::run(corpus_args),
+        Some(Command::Schema(schema_args)) => schema::run(schema_args),
+        None => decode(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if logging::json() => {
+            error!(error = %format!("{:#}", e), "failed");
+            exit_code::of(&e)
+        }
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit_code::of(&e)
+        }
+    }
+}
 
-    let (selected_event, all_events) = load_event(&args.abi, args.event.as_deref().unwrap_or(""))
-        .with_context(|| format!("Failed to load event(s) from {:?}", args.abi))?;
-
-    let mut topic0_to_event: Option<HashMap<H256, Event>> = None;
-    if args.event.is_none() {
-        let mut map = HashMap::new();
-        for ev in &all_events {
-            let sig: H256 = ev.signature();
-            map.insert(sig, ev.clone());
+fn decode(args: CliArgs) -> Result<()> {
+    if let Some(dir) = &args.self_test {
+        return selftest::run(dir);
+    }
+    if args.mode != Mode::Logs {
+        return decode_documents(&args);
+    }
+    if !args.storage_layout.is_empty() { bail!("--storage-layout decodes storage with --mode state-diff"); }
+    if !args.signatures.is_empty() { bail!("--signatures resolves selectors with --mode selectors"); }
+    let abi_paths: Vec<PathBuf> = args.abi.iter().chain(&args.sol).cloned().collect();
+    if abi_paths.is_empty() && args.preset.is_empty() { bail!("--abi, --sol or --preset is required"); }
+    // `--abi CHAIN:PATH` files route only the logs of that chain; the others route every log
+    let mut chain_paths: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    let mut shared_paths = Vec::new();
+    for path in &abi_paths {
+        match abi::chain_scoped(path) {
+            Some((chain, path)) => chain_paths.entry(chain).or_default().push(path),
+            None => shared_paths.push(path.clone()),
         }
-        topic0_to_event = Some(map);
     }
+    if !chain_paths.is_empty() && args.event.is_some() { bail!("--abi CHAIN:PATH routes logs by topic0, which --event turns off"); }
+    if args.phase_timing { timing::enable(); }
 
-    let reader: Box<dyn BufRead> = match &args.input {
-        Some(path) => Box::new(BufReader::new(File::open(path)?)),
-        None => Box::new(BufReader::new(io::stdin())),
+    let filter = LogFilter { dedupe: args.dedupe, drop_removed: args.drop_removed };
+    let limits = Limits { max_data_bytes: args.max_data_bytes, max_array_len: args.max_array_len };
+    let enrich = args.enrich.as_deref().map(Tokens::load).transpose()?.map(Arc::new);
+    if let Some(tokens) = &enrich { info!(tokens = tokens.len(), "enrich"); }
+    let select = args.select.as_deref().map(Projection::parse).transpose()?.map(Arc::new);
+    let aggregate = args.aggregate.as_deref().map(Aggregator::parse).transpose()?.map(Arc::new);
+    let topk = args.topk.iter().map(|spec| TopK::parse(spec).map(Arc::new)).collect::<Result<Vec<_>>>()?;
+    let predicate = args.where_expr.as_deref().map(Predicate::parse).transpose()?.map(Arc::new);
+    let output = Output { print: args.print, envelope: args.output_envelope, order: args.ordered, enrich, serializer: args.serializer, filter: predicate, select, aggregate, topk };
+    if args.serve {
+        let timeouts = serve::Timeouts::new(args.per_log_timeout_us, args.batch_timeout);
+        let options = serve::ServeOptions {
+            input_schema: args.input_schema, filter, limits, collision_policy: args.collision_policy, map_impl: args.map_impl,
+            abi_cache: args.abi_cache.clone(), on_error: args.on_error, timeouts, output,
+        };
+        if !chain_paths.is_empty() { bail!("--serve loads one ABI for every log; --abi CHAIN:PATH scopes files to a chain's logs"); }
+        let [abi_path] = &abi_paths[..] else { bail!("--serve loads a single --abi or --sol (and `reload` replaces it)") };
+        let server = serve::Server::new(abi_path.clone(), args.event.clone(), options).context(DecodeError::AbiLoad)?;
+        if let Some(addr) = args.metrics_listen { metrics::spawn_endpoint(addr, server.metrics())?; }
+        return serve::run(server, args.serve_socket.clone());
+    }
+
+    let abis = if shared_paths.is_empty() && args.preset.is_empty() {
+        AbiSet { events: Vec::new(), files: 0, cache_hits: 0, load_time: Duration::ZERO }
+    } else {
+        let abis = info_span!("load_abi").in_scope(|| AbiSet::load(&shared_paths, &args.preset, args.abi_cache.as_deref(), args.contract.as_deref())).context(DecodeError::AbiLoad)?;
+        info!(files = abis.files, events = abis.events.len(), cache_hits = abis.cache_hits, load_ms = rounded_ms(abis.load_time), "abi");
+        if args.event.is_none() { check_collisions(&abis, args.collision_policy)?; }
+        abis
     };
+    let mut chain_abis = Vec::with_capacity(chain_paths.len());
+    for (chain, paths) in chain_paths {
+        let set = info_span!("load_abi", chain).in_scope(|| AbiSet::load(&paths, &[], args.abi_cache.as_deref(), args.contract.as_deref()))
+            .with_context(|| format!("--abi {}:{}", chain, paths[0].display()))
+            .context(DecodeError::AbiLoad)?;
+        info!(chain, files = set.files, events = set.events.len(), cache_hits = set.cache_hits, load_ms = rounded_ms(set.load_time), "abi");
+        check_collisions(&set, args.collision_policy)?;
+        chain_abis.push((chain, set));
+    }
+    let all_abis: Vec<&AbiSet> = std::iter::once(&abis).chain(chain_abis.iter().map(|(_, set)| set)).collect();
+    let selected_event = match chain_abis.first() {
+        Some((_, set)) if abis.events.is_empty() => set.select(""),
+        _ => abis.select(args.event.as_deref().unwrap_or("")),
+    }
+    .context(DecodeError::AbiLoad)?;
+    if let Some(predicate) = &output.filter { check_fields("--where", predicate.fields(), &all_abis)?; }
+    if let Some(aggregate) = &output.aggregate { check_fields("--aggregate", aggregate.fields(), &all_abis)?; }
+    for topk in &output.topk { check_fields("--topk", vec![topk.field.as_str()], &all_abis)?; }
+    let decoder = LogDecoder::from_abi_set(selected_event, &abis, args.event.is_none(), args.collision_policy)
+        .with_chain_abis(&chain_abis, args.collision_policy)
+        .with_input_schema(args.input_schema)
+        .with_filter(filter)
+        .with_limits(limits)
+        .with_chain_id(args.chain_id)
+        .with_on_error(args.on_error);
+    let decoder = decoder.with_map_impl(args.map_impl);
+    let decoder = if args.prefilter { decoder.with_prefilter() } else { decoder };
+    let decoder = if args.homogeneous_fast_path { decoder.with_homogeneous_fast_path() } else { decoder };
+    let decoder = if args.skip_decode { decoder.with_skip_decode() } else { decoder };
+    let decoder = if args.fields.is_empty() { decoder } else { decoder.with_fields(&args.fields)? };
 
-    let start = Instant::now();
-    let mut total: usize = 0;
+    stop::install()?;
+    let mut inputs = input_io::expand_inputs(&args.input)?;
+    if inputs.iter().any(|p| input_io::is_stdin(p)) {
+        if inputs.len() > 1 || !args.parallel_mode.is_empty() { bail!("--input - reads stdin, which cannot be combined with other inputs or --parallel-mode"); }
+        inputs.clear();
+    }
+    let batched = inputs.len() > 1 || args.preload || args.threads > 1 || args.ordered.is_some() || args.scaling_sweep;
+    if args.homogeneous_fast_path && (args.input_format != InputFormat::Jsonl || !batched) {
+        bail!("--homogeneous-fast-path batches JSONL logs across lines; combine it with --preload, --threads, --ordered, --scaling-sweep or several --input files");
+    }
+    if args.engine == Engine::Gpu && (args.input_format != InputFormat::Jsonl || inputs.len() > 1) {
+        bail!("--engine gpu decodes a single JSONL input");
+    }
+    if args.engine == Engine::Gpu && args.skip_decode { bail!("--skip-decode measures the CPU path; it cannot be combined with --engine gpu"); }
+    if args.writer_thread && (args.input_format != InputFormat::Jsonl || args.threads > 1 || inputs.len() > 1 || !args.parallel_mode.is_empty() || args.engine == Engine::Gpu) {
+        bail!("--writer-thread covers single-threaded JSONL decoding of one input");
+    }
+    if args.report.is_some() && !args.scaling_sweep && args.parallel_mode.is_empty() {
+        bail!("--report renders --scaling-sweep or --parallel-mode results");
+    }
+    let placement = Placement {
+        cores: args.pin_cores.as_ref().map(|c| c.0.clone()).unwrap_or_default(),
+        numa_local: args.numa_local,
+    };
 
-    for line in reader.lines() {
-        let line = line?;
-        if line.is_empty() { continue; }
-        let parsed: LogLine = serde_json::from_str(&line)
-            .with_context(|| format!("Invalid JSON line: {}", line))?;
-
-        let parsed_topics: Vec<H256> = parsed
-            .topics
-            .iter()
-            .map(|t| parse_h256(t))
-            .collect::<Result<Vec<H256>>>()?;
-        if parsed_topics.is_empty() { continue; }
-
-        let event = if let Some(map) = &topic0_to_event {
-            map.get(&parsed_topics[0])
-                .cloned()
-                .ok_or_else(|| anyhow!("Unknown topic0 for provided ABI"))?
-        } else {
-            selected_event.clone()
-        };
+    if !args.parallel_mode.is_empty() {
+        if args.input_format != InputFormat::Jsonl || args.io_backend != IoBackend::Std { bail!("--parallel-mode compares JSONL decoding of --input files"); }
+        compare_parallel_modes(&args, &abi_paths, &inputs, &decoder, &output, &placement)?;
+        return finish(&args, &decoder, &output, None, None);
+    }
+    if inputs.len() > 1 {
+        decode_files(&args, &inputs, &decoder, &output)?;
+        return finish(&args, &decoder, &output, None, None);
+    }
+    let input_path = inputs.first();
 
-        let raw_log = RawLog {
-            topics: parsed_topics,
-            data: parse_hex_bytes(&parsed.data)?,
-        };
+    if args.checkpoint.is_some() && (args.input_format != InputFormat::Jsonl || args.threads > 1 || args.io_backend != IoBackend::Std) {
+        bail!("--checkpoint covers single-threaded JSONL decoding with --io-backend std");
+    }
+    if args.checkpoint.is_some() && input_path.is_some_and(|path| input_io::is_compressed(path)) {
+        bail!("--checkpoint cannot resume compressed input, whose byte offsets are not seekable");
+    }
+    if args.checkpoint.is_some() && input_path.is_none_or(|path| input_io::is_pipe(path)) {
+        bail!("--checkpoint resumes a file at a byte offset, which stdin and pipes cannot seek to");
+    }
+    let mut checkpoint = match (&args.checkpoint, input_path) {
+        (Some(path), Some(input)) => Some(checkpoint::Checkpoint::open(path.clone(), input, args.resume)?),
+        _ => None,
+    };
+    if args.source.is_some() && args.io_backend != IoBackend::Std { bail!("--io-backend uring reads files at offsets, which a --source stream cannot be"); }
+    if matches!(args.source, Some(Source::Zmq { .. })) && args.input_format != InputFormat::Jsonl {
+        bail!("--source zmq:// carries JSONL messages; it cannot be read with another --input-format");
+    }
+    let (input, source_counts) = match (&checkpoint, input_path, &args.source) {
+        (_, _, Some(source)) => source.open()?,
+        (Some(c), Some(path), _) => (input_io::open_input_at(path, c.offset())?, None),
+        _ => (open_input(input_path.map(PathBuf::as_path), args.io_backend)?, None),
+    };
+    let reader = match &checkpoint {
+        Some(c) => c.track(input.reader),
+        None => input.reader,
+    };
+    let reader = match args.exit_after {
+        Some(lines) if args.input_format == InputFormat::Jsonl => input_io::limit_lines(reader, lines),
+        _ => reader,
+    };
+
+    if args.metrics_listen.is_some() && (args.input_format != InputFormat::Jsonl || args.threads > 1) {
+        bail!("--metrics-listen covers single-threaded JSONL decoding and --serve");
+    }
+    let metrics = match args.metrics_listen {
+        Some(addr) => {
+            let metrics = Arc::new(metrics::Metrics::new("input line"));
+            metrics::spawn_endpoint(addr, metrics.clone())?;
+            Some(metrics)
+        }
+        None => None,
+    };
 
-        let log = event
-            .parse_log(raw_log)
-            .with_context(|| "Failed to parse log with ethabi")?;
+    let sample_rate = args.sample.or(args.sample_every.map(sample::SampleRate::Every));
+    if (args.group_by.is_some() || args.partition_blocks.is_some() || args.sink.is_some() || sample_rate.is_some() || args.error_log.is_some())
+        && (args.input_format != InputFormat::Jsonl || args.threads > 1 || args.engine == Engine::Gpu)
+    {
+        bail!("--group-by, --partition-blocks, --sink, --sample and --error-log cover single-threaded JSONL decoding");
+    }
+    if let Some(rate) = args.rate {
+        if !(rate > 0.0 && rate.is_finite()) { bail!("--rate must be a positive number of lines per second"); }
+        if args.input_format != InputFormat::Jsonl || args.threads > 1 { bail!("--rate paces single-threaded JSONL decoding"); }
+    }
 
-        total += 1;
+    if args.replay.is_some() && (args.input_format != InputFormat::Jsonl || args.threads > 1 || args.engine == Engine::Gpu) {
+        bail!("--replay decodes JSONL once on a single CPU thread");
+    }
+    if args.input_format != InputFormat::Jsonl && (args.pipeline || args.threads > 1 || args.scaling_sweep || args.ordered.is_some()) {
+        bail!("--input-format json, bin, protobuf, firehose and era1 are decoded on a single thread; they cannot be combined with --pipeline, --threads, --scaling-sweep or --ordered");
+    }
+    if matches!(args.input_format, InputFormat::Bin | InputFormat::Protobuf | InputFormat::Firehose | InputFormat::Era1) && (args.dedupe || args.drop_removed) {
+        bail!("--input-format bin, protobuf, firehose and era1 carry no transaction hashes or removed flags for --dedupe and --drop-removed");
+    }
 
-        if args.print {
-            let values = log.params.iter().map(|p| p.value.clone()).collect();
-            let value = tokens_to_json(&event.inputs, &values);
-            println!("{}", serde_json::to_string(&value)?);
+    let decode_span = info_span!("decode", threads = args.threads).entered();
+    let mut start = Instant::now();
+    if args.input_format == InputFormat::Json {
+        let total = if args.preload {
+            let mut buf = Vec::new();
+            let mut reader = reader;
+            reader.read_to_end(&mut buf)?;
+            start = Instant::now();
+            run_json_document(&buf[..], &decoder, &output, args.exit_after)?
+        } else {
+            run_json_document(reader, &decoder, &output, args.exit_after)?
+        };
+        print_summary(total, start.elapsed());
+    } else if matches!(args.input_format, InputFormat::Bin | InputFormat::Protobuf | InputFormat::Firehose | InputFormat::Era1) {
+        let total = if args.preload {
+            let mut buf = Vec::new();
+            let mut reader = reader;
+            reader.read_to_end(&mut buf)?;
+            start = Instant::now();
+            run_records(&buf[..], args.input_format, &decoder, &output, args.exit_after)?
+        } else {
+            run_records(reader, args.input_format, &decoder, &output, args.exit_after)?
+        };
+        print_summary(total, start.elapsed());
+    } else if args.engine == Engine::Gpu {
+        run_gpu(reader, &decoder, &output)?;
+    } else if let Some(passes) = args.replay {
+        let report = replay::run(reader, &decoder, &output, passes as usize)?;
+        print_summary(report.decoded, report.decode);
+        report.print();
+    } else if args.scaling_sweep {
+        let workload = Workload::load(reader, args.split_strategy)?;
+        if !logging::json() { eprintln!("threads decoded elapsed_ms throughput_lps speedup"); }
+        let mut baseline: Option<f64> = None;
+        let mut section = Section {
+            title: "Throughput per thread count".to_string(),
+            columns: ["threads", "decoded", "elapsed_ms", "throughput_lps", "speedup"].map(String::from).to_vec(),
+            rows: Vec::new(),
+            bars: Vec::new(),
+        };
+        for threads in SWEEP_THREADS {
+            decoder.reset_filter();
+            let start = Instant::now();
+            let total = parallel::run(&workload, &decoder, threads, &placement, &output)?;
+            let secs = start.elapsed().as_secs_f64();
+            let base = *baseline.get_or_insert(secs);
+            let speedup = if secs > 0.0 { base / secs } else { 0.0 };
+            let row = [
+                threads.to_string(), total.to_string(), format!("{:.3}", secs * 1000.0),
+                format!("{:.0}", lps(total, secs)), format!("{:.2}", speedup),
+            ];
+            if logging::json() {
+                info!(
+                    threads, decoded = total, elapsed_ms = logging::round(secs * 1000.0, 3), throughput_lps = lps(total, secs) as u64,
+                    speedup = logging::round(speedup, 2), "scaling_sweep"
+                );
+            } else {
+                eprintln!("{}", row.join(" "));
+            }
+            section.rows.push(row.to_vec());
+            section.bars.push(lps(total, secs));
+            if stop::requested() { break; }
+        }
+        if let Some(format) = args.report {
+            let mut notes = vec![
+                format!("ABI: `{}`, input: `{}`", abi_paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join("`, `"), input_path.map(|p| p.display().to_string()).unwrap_or_else(|| "stdin".to_string())),
+                format!("split strategy: {:?}, load time excluded", args.split_strategy),
+            ];
+            if stop::interrupted() { notes.push("interrupted: the last row covers only part of the input".to_string()); }
+            Report { title: "Thread scaling".to_string(), notes, sections: vec![section] }.emit(format, args.report_file.as_ref())?;
+        }
+    } else if args.preload || args.threads > 1 || args.ordered.is_some() {
+        let workload = Workload::load(reader, args.split_strategy)?;
+        if args.preload { start = Instant::now(); }
+        let total = parallel::run(&workload, &decoder, args.threads, &placement, &output)?;
+        print_summary(total, start.elapsed());
+    } else if args.pipeline {
+        let (total, stages) = pipeline::run(reader, &decoder, &output)?;
+        print_summary(total, start.elapsed());
+        if logging::json() {
+            info!(
+                read_ms = rounded_ms(stages.read), parse_ms = rounded_ms(stages.parse), decode_ms = rounded_ms(stages.decode),
+                output_ms = rounded_ms(stages.output), stage_sum_ms = rounded_ms(stages.total()), "pipeline"
+            );
+        } else {
+            eprintln!(
+                "pipeline read_ms={:.3} parse_ms={:.3} decode_ms={:.3} output_ms={:.3} stage_sum_ms={:.3}",
+                ms(stages.read), ms(stages.parse), ms(stages.decode), ms(stages.output), ms(stages.total())
+            );
+        }
+    } else if let Some(rate) = args.rate {
+        let report = rate::run(reader, &decoder, &output, rate, &mut std::io::stdout().lock())?;
+        print_summary(report.decoded, report.elapsed);
+        report.print(rate);
+    } else {
+        let mut contracts = args.group_by.map(contracts::GroupStats::new);
+        let mut partitions = args.partition_blocks.map(partitions::PartitionStats::new);
+        let mut sample = sample_rate.map(sample::Sample::new);
+        let mut sink = match &args.sink {
+            Some(target) => {
+                let events: Vec<_> = match &args.event {
+                    Some(name) => vec![abis.select(name)?],
+                    None => {
+                        // An event in the ABIs of several chains gets one table
+                        let mut seen = HashSet::new();
+                        all_abis.iter().flat_map(|set| &set.events).filter(|(_, e)| seen.insert(abi::describe(e))).map(|(_, e)| e.clone()).collect()
+                    }
+                };
+                Some(sink::Sink::open(target, &events, args.sink_batch)?)
+            }
+            None => None,
+        };
+        let mut error_log = args.error_log.as_deref().map(error_log::ErrorLog::create).transpose()?;
+        let observers = Observers {
+            metrics: metrics.as_deref(),
+            checkpoint: checkpoint.as_mut(),
+            contracts: contracts.as_mut(),
+            partitions: partitions.as_mut(),
+            sample: sample.as_mut(),
+            sink: sink.as_mut(),
+            error_log: error_log.as_mut(),
+        };
+        let total = if args.writer_thread {
+            let (total, stats) = std::thread::scope(|s| -> Result<_> {
+                let mut writer = OutputWriter::spawn(s, &output, args.output_buffer_size);
+                let total = run_sequential(reader, &decoder, &output, &mut std::io::sink(), Some(&mut writer), observers);
+                let stats = writer.finish()?;
+                Ok((total?, stats))
+            })?;
+            let decode_busy = start.elapsed().saturating_sub(stats.blocked);
+            let format_vs_decode = if decode_busy.is_zero() { 0.0 } else { stats.format.as_secs_f64() / decode_busy.as_secs_f64() };
+            if logging::json() {
+                info!(
+                    logs = stats.logs, buffer_bytes = args.output_buffer_size, format_ms = rounded_ms(stats.format),
+                    write_ms = rounded_ms(stats.write), idle_ms = rounded_ms(stats.idle), decode_blocked_ms = rounded_ms(stats.blocked),
+                    decode_busy_ms = rounded_ms(decode_busy), format_vs_decode = logging::round(format_vs_decode, 2), "writer"
+                );
+            } else {
+                eprintln!(
+                    "writer logs={} buffer_bytes={} format_ms={:.3} write_ms={:.3} idle_ms={:.3} decode_blocked_ms={:.3} decode_busy_ms={:.3} format_vs_decode={:.2}",
+                    stats.logs, args.output_buffer_size, ms(stats.format), ms(stats.write), ms(stats.idle), ms(stats.blocked),
+                    ms(decode_busy), format_vs_decode
+                );
+            }
+            total
+        } else {
+            // stdin stays line-buffered, so logs streamed in are printed as they decode
+            match input_path {
+                Some(_) => {
+                    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+                    let total = run_sequential(reader, &decoder, &output, &mut out, None, observers)?;
+                    out.flush()?;
+                    total
+                }
+                None => run_sequential(reader, &decoder, &output, &mut std::io::stdout().lock(), None, observers)?,
+            }
+        };
+        print_summary(total, start.elapsed());
+        if let Some(sink) = &sink { sink.print_summary(); }
+        if let Some(contracts) = &contracts { contracts.print(args.top); }
+        if let Some(partitions) = &partitions { partitions.print(); }
+        if let Some(sample) = &sample { sample.print(); }
+        if let Some(error_log) = error_log { error_log.finish()?; }
+        if let Some(c) = &mut checkpoint {
+            std::io::stdout().flush()?;
+            c.save()?;
+            c.print_summary();
         }
     }
+    drop(decode_span);
+    finish(&args, &decoder, &output, input.io_wait.as_ref(), source_counts.as_ref())
+}
 
+/// Decodes several `--input` files (after glob expansion), reporting each file and then the whole set.
+fn decode_files(args: &CliArgs, inputs: &[PathBuf], decoder: &LogDecoder, output: &Output) -> Result<()> {
+    let single_input_only = args.input_format != InputFormat::Jsonl || args.threads > 1 || args.io_backend != IoBackend::Std
+        || args.pipeline || args.scaling_sweep || args.preload || args.ordered.is_some() || args.checkpoint.is_some()
+        || args.rate.is_some() || args.metrics_listen.is_some() || args.group_by.is_some() || args.partition_blocks.is_some()
+        || args.exit_after.is_some() || args.sample.is_some() || args.sample_every.is_some() || args.replay.is_some()
+        || args.error_log.is_some();
+    if single_input_only {
+        bail!("several --input files are decoded as JSONL, one thread per file (see --file-parallelism); the other modes take a single input");
+    }
+    let _span = info_span!("decode", files = inputs.len(), file_parallelism = args.file_parallelism).entered();
+    let start = Instant::now();
+    let files = files::run(inputs, decoder, output, args.file_parallelism)?;
     let elapsed = start.elapsed();
-    eprintln!(
-        "decoded={} elapsed_ms={:.3} throughput_lps={:.0}",
-        total,
-        elapsed.as_secs_f64() * 1000.0,
-        if elapsed.as_secs_f64() > 0.0 { (total as f64 / elapsed.as_secs_f64()).round() } else { 0.0 }
-    );
+    for file in &files {
+        let throughput = lps(file.decoded, file.elapsed.as_secs_f64());
+        if logging::json() {
+            info!(decoded = file.decoded, elapsed_ms = rounded_ms(file.elapsed), throughput_lps = throughput as u64, path = %file.path.display(), "file");
+        } else {
+            eprintln!("file decoded={} elapsed_ms={:.3} throughput_lps={:.0} path={}", file.decoded, ms(file.elapsed), throughput, file.path.display());
+        }
+    }
+    if logging::json() {
+        info!(decoded = files.len(), of = inputs.len(), file_parallelism = args.file_parallelism, "files");
+    } else {
+        eprintln!("files decoded={} of={} file_parallelism={}", files.len(), inputs.len(), args.file_parallelism);
+    }
+    print_summary(files.iter().map(|f| f.decoded).sum(), elapsed);
+    Ok(())
+}
+
+/// The `--mode`s other than `logs`, which decode one JSON document per input line without the log decoder.
+fn decode_documents(args: &CliArgs) -> Result<()> {
+    if let Some(source) = &args.source { bail!("--source {} streams logs; the other --modes read --input files or stdin", source); }
+    let inputs = input_io::expand_inputs(&args.input)?;
+    let inputs = if inputs.iter().any(|p| input_io::is_stdin(p)) { Vec::new() } else { inputs };
+    if let Some(path) = args.abi.iter().find(|p| abi::chain_scoped(p).is_some()) { bail!("--abi {} scopes events to a chain's logs, which only --mode logs decodes", path.display()); }
+    let options = modes::LineOptions { inputs: &inputs, print: args.print, preload: args.preload, on_error: args.on_error };
+    match args.mode {
+        Mode::Traces if args.abi.is_empty() && args.preset.is_empty() => bail!("--mode traces decodes calls against the functions of --abi or --preset"),
+        Mode::Traces => traces::run(&args.abi, &args.preset, &options),
+        Mode::Selectors => selectors::run(&args.abi, &args.preset, &args.signatures, args.top, &options),
+        _ if !args.preset.is_empty() => bail!("--preset decodes logs, or calls with --mode traces and selectors"),
+        Mode::StateDiff if args.storage_layout.is_empty() => bail!("--mode state-diff decodes storage with --storage-layout"),
+        Mode::StateDiff => state_diff::run(&args.storage_layout, args.contract.as_deref(), &options),
+        Mode::Eip712 => typed_data::run(&options),
+        Mode::Disasm => bytecode::run(&options),
+        Mode::Logs => unreachable!("logs are decoded by `decode`"),
+    }
+}
 
+/// `--parallel-mode`: one pass over `inputs` per strategy, then the winner and what the dataset looks like.
+fn compare_parallel_modes(args: &CliArgs, abi_paths: &[PathBuf], inputs: &[PathBuf], decoder: &LogDecoder, output: &Output, placement: &Placement) -> Result<()> {
+    let shape = Shape::of(inputs)?;
+    let threads = args.threads.max(1);
+    let _span = info_span!("decode", files = inputs.len(), threads).entered();
+    let mut runs = Vec::new();
+    for &mode in &args.parallel_mode {
+        let run = parallel_mode::run(mode, inputs, decoder, output, threads, placement)?;
+        if logging::json() {
+            info!(
+                mode = parallel_mode::name(mode), decoded = run.decoded, elapsed_ms = rounded_ms(run.elapsed),
+                throughput_lps = lps(run.decoded, run.elapsed.as_secs_f64()) as u64, load_ms = run.load.map(rounded_ms), "parallel_mode"
+            );
+        } else {
+            eprintln!(
+                "parallel_mode mode={} decoded={} elapsed_ms={:.3} throughput_lps={:.0}{}",
+                parallel_mode::name(mode), run.decoded, ms(run.elapsed), lps(run.decoded, run.elapsed.as_secs_f64()),
+                run.load.map(|load| format!(" load_ms={:.3}", ms(load))).unwrap_or_default()
+            );
+        }
+        runs.push(run);
+        if stop::requested() { break; }
+    }
+    let best = runs.iter().min_by_key(|r| r.elapsed).map(|r| r.elapsed.as_secs_f64()).unwrap_or(0.0);
+    if let Some(winner) = runs.iter().min_by_key(|r| r.elapsed) {
+        if logging::json() {
+            info!(
+                winner = parallel_mode::name(winner.mode), threads, files = shape.files, bytes = shape.bytes,
+                largest_file_share = logging::round(shape.largest_share, 2), "parallel_mode"
+            );
+        } else {
+            eprintln!(
+                "parallel_mode winner={} threads={} files={} bytes={} largest_file_share={:.2}",
+                parallel_mode::name(winner.mode), threads, shape.files, shape.bytes, shape.largest_share
+            );
+        }
+    }
+    if let Some(format) = args.report {
+        let mut section = Section {
+            title: "Throughput per parallel mode".to_string(),
+            columns: ["mode", "decoded", "elapsed_ms", "throughput_lps", "vs_fastest"].map(String::from).to_vec(),
+            rows: Vec::new(),
+            bars: Vec::new(),
+        };
+        for run in &runs {
+            let secs = run.elapsed.as_secs_f64();
+            section.rows.push(vec![
+                parallel_mode::name(run.mode), run.decoded.to_string(), format!("{:.3}", ms(run.elapsed)),
+                format!("{:.0}", lps(run.decoded, secs)), format!("{:.2}", if secs > 0.0 { best / secs } else { 0.0 }),
+            ]);
+            section.bars.push(lps(run.decoded, secs));
+        }
+        let mut notes = vec![
+            format!("ABI: `{}`, input: `{}`", abi_paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join("`, `"), inputs.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join("`, `")),
+            format!("{} file(s), {} bytes, largest file {:.0}% of the bytes; {} threads; load time included", shape.files, shape.bytes, shape.largest_share * 100.0, threads),
+        ];
+        if stop::interrupted() { notes.push("interrupted: the last row covers only part of the input".to_string()); }
+        Report { title: "Parallel modes".to_string(), notes, sections: vec![section] }.emit(format, args.report_file.as_ref())?;
+    }
     Ok(())
 }
 
-fn load_event(path: &PathBuf, event_name: &str) -> Result<(Event, Vec<Event>)> {
-    let file = File::open(path).with_context(|| format!("Cannot open ABI file: {:?}", path))?;
-    let json_value: Value = serde_json::from_reader(file)?;
-
-    // ABI can be an array or an object with `abi` or `events`
-    let events: Vec<Event> = if json_value.is_array() {
-        let arr = json_value.as_array().unwrap();
-        arr.iter()
-            .filter_map(|v| parse_event_from_value(v))
-            .collect::<Vec<Event>>()
-    } else if json_value.is_object() {
-        if let Some(arr) = json_value.get("abi").and_then(|v| v.as_array()) {
-            arr.iter()
-                .filter_map(|v| parse_event_from_value(v))
-                .collect::<Vec<Event>>()
-        } else if let Some(arr) = json_value.get("events").and_then(|v| v.as_array()) {
-            arr.iter()
-                .filter_map(|v| parse_event_from_value(v))
-                .collect::<Vec<Event>>()
+/// Reports the counters every mode shares and exits with the status of an interrupted or partial run.
+fn finish(args: &CliArgs, decoder: &LogDecoder, output: &Output, io_wait: Option<&input_io::IoWait>, source_counts: Option<&SourceCounts>) -> Result<()> {
+    if args.dedupe || args.drop_removed {
+        let counts = decoder.filtered();
+        if logging::json() {
+            info!(removed = counts.removed, duplicates = counts.duplicates, "filtered");
         } else {
-            return Err(anyhow!("Unsupported ABI JSON structure"));
+            eprintln!("filtered removed={} duplicates={}", counts.removed, counts.duplicates);
         }
-    } else {
-        return Err(anyhow!("Unsupported ABI JSON structure"));
-    };
+    }
 
-    let event = if event_name.is_empty() {
-        events
-            .first()
-            .cloned()
-            .ok_or_else(|| anyhow!("No events found in ABI"))?
-    } else {
-        events
-            .iter()
-            .find(|e| e.name == event_name)
-            .cloned()
-            .ok_or_else(|| anyhow!("Event '{}' not found in ABI", event_name))?
-    };
+    let skipped = decoder.skipped();
+    if args.on_error == OnError::Skip && logging::json() {
+        info!(errors = skipped, "skipped");
+        for (kind, n) in decoder.skipped_by_kind() {
+            info!(kind = kind.map_or("other", DecodeError::name), errors = n, "skipped");
+        }
+    } else if args.on_error == OnError::Skip {
+        let kinds: String = decoder.skipped_by_kind().iter()
+            .map(|(kind, n)| format!(" {}={}", kind.map_or("other", DecodeError::name), n))
+            .collect();
+        eprintln!("skipped errors={}{}", skipped, kinds);
+    }
 
-    Ok((event, events))
+    if let Some(filter) = &output.filter {
+        if logging::json() {
+            info!(matched = filter.matched(), decoded = filter.evaluated(), "where");
+        } else {
+            eprintln!("where matched={} decoded={}", filter.matched(), filter.evaluated());
+        }
+    }
+    if let Some(aggregate) = &output.aggregate {
+        let mut out = std::io::stdout().lock();
+        aggregate.write(&mut out)?;
+        out.flush()?;
+        if logging::json() {
+            info!(groups = aggregate.groups(), "aggregate");
+        } else {
+            eprintln!("aggregate groups={}", aggregate.groups());
+        }
+    }
+    for topk in &output.topk {
+        if logging::json() {
+            info!(field = %topk.field, k = topk.k, logs = topk.total(), "topk");
+            for (rank, hitter) in topk.top().iter().enumerate() {
+                info!(field = %topk.field, rank = rank + 1, value = %hitter.value, count = hitter.count, error = hitter.error, "topk");
+            }
+            continue;
+        }
+        eprintln!("topk field={} k={} logs={}", topk.field, topk.k, topk.total());
+        for (rank, hitter) in topk.top().iter().enumerate() {
+            eprintln!("topk field={} rank={} value={} count={} error={}", topk.field, rank + 1, hitter.value, hitter.count, hitter.error);
+        }
+    }
+
+    if let Some(filter) = decoder.prefilter() {
+        timing::flush();
+        let [passed, rejected, false_positives] = timing::counts();
+        if logging::json() {
+            info!(bits = filter.bits(), passed, rejected, false_positives, "prefilter");
+        } else {
+            eprintln!("prefilter bits={} passed={} rejected={} false_positives={}", filter.bits(), passed, rejected, false_positives);
+        }
+    }
+
+    if timing::is_enabled() {
+        timing::flush();
+        let [read, json, hex, abi, output_time, enrich, lookup, format, where_time, aggregate] = timing::totals();
+        let enrich = args.enrich.is_some().then_some(enrich);
+        let where_time = output.filter.is_some().then_some(where_time);
+        let aggregate = (output.aggregate.is_some() || !output.topk.is_empty()).then_some(aggregate);
+        if logging::json() {
+            info!(
+                read_ms = rounded_ms(read), json_ms = rounded_ms(json), hex_ms = rounded_ms(hex), lookup_ms = rounded_ms(lookup),
+                abi_ms = rounded_ms(abi), output_ms = rounded_ms(output_time), format_ms = rounded_ms(format),
+                enrich_ms = enrich.map(rounded_ms), where_ms = where_time.map(rounded_ms), aggregate_ms = aggregate.map(rounded_ms), "phases"
+            );
+        } else {
+            eprintln!(
+                "phases read_ms={:.3} json_ms={:.3} hex_ms={:.3} lookup_ms={:.3} abi_ms={:.3} output_ms={:.3} format_ms={:.3}{}{}{}",
+                ms(read), ms(json), ms(hex), ms(lookup), ms(abi), ms(output_time), ms(format),
+                enrich.map(|t| format!(" enrich_ms={:.3}", ms(t))).unwrap_or_default(),
+                where_time.map(|t| format!(" where_ms={:.3}", ms(t))).unwrap_or_default(),
+                aggregate.map(|t| format!(" aggregate_ms={:.3}", ms(t))).unwrap_or_default()
+            );
+        }
+    }
+    match (io_wait, &args.source) {
+        (Some(io_wait), Some(source)) if logging::json() => info!(
+            source = %source, io_wait_ms = rounded_ms(io_wait.get()),
+            messages = source_counts.map(|c| c.messages()), reconnects = source_counts.map(|c| c.reconnects()), "source"
+        ),
+        (Some(io_wait), Some(source)) => eprintln!(
+            "source={} io_wait_ms={:.3}{}",
+            source, ms(io_wait.get()),
+            source_counts.map_or(String::new(), |c| format!(" messages={} reconnects={}", c.messages(), c.reconnects()))
+        ),
+        (Some(io_wait), None) if logging::json() => info!(io_backend = "uring", io_wait_ms = rounded_ms(io_wait.get()), "io_backend"),
+        (Some(io_wait), None) => eprintln!("io_backend=uring io_wait_ms={:.3}", ms(io_wait.get())),
+        (None, _) => {}
+    }
+
+    if stop::interrupted() {
+        warn!("interrupted: the results above cover the input decoded before the signal");
+        std::io::stdout().flush()?;
+        std::process::exit(stop::INTERRUPTED_EXIT_CODE);
+    }
+    if args.fail_on_skip && skipped > 0 {
+        std::io::stdout().flush()?;
+        std::process::exit(exit_code::PARTIAL.into());
+    }
+    Ok(())
+}
+
+/// What a sequential run reports besides its summary; all optional.
+#[derive(Default)]
+struct Observers<'a> {
+    /// Each line's decode time and each decoded log
+    metrics: Option<&'a metrics::Metrics>,
+    /// Each line that was fully handled
+    checkpoint: Option<&'a mut checkpoint::Checkpoint>,
+    /// Each line's decode time and logs, per emitting contract
+    contracts: Option<&'a mut contracts::GroupStats>,
+    /// Each line's decode time and logs, per block range
+    partitions: Option<&'a mut partitions::PartitionStats>,
+    /// Which lines to decode, and each sampled line's decode time and logs
+    sample: Option<&'a mut sample::Sample>,
+    /// Each decoded log, inserted whenever its table fills a batch
+    sink: Option<&'a mut sink::Sink>,
+    /// Each line that failed, with its position
+    error_log: Option<&'a mut error_log::ErrorLog>,
 }
 
-fn parse_event_from_value(v: &Value) -> Option<Event> {
-    if v.get("type").and_then(|t| t.as_str()) != Some("event") { return None; }
-    let name = v.get("name")?.as_str()?.to_string();
-    let inputs_v = v.get("inputs")?.as_array()?.clone();
+/// Decodes JSONL on the calling thread. Errors are given the failing line's number, byte offset and a snippet,
+/// counted from where a resumed checkpoint starts.
+/// With `writer`, printed logs go to the writer thread instead of `out`.
+fn run_sequential<'s, 'd: 's>(
+    reader: Box<dyn BufRead + Send>,
+    decoder: &'d LogDecoder,
+    output: &Output,
+    out: &mut impl Write,
+    mut writer: Option<&mut OutputWriter<'s, 'd>>,
+    observers: Observers,
+) -> Result<usize> {
+    let Observers { metrics, mut checkpoint, mut contracts, mut partitions, mut sample, mut sink, mut error_log } = observers;
+    let per_line = contracts.is_some() || partitions.is_some() || sample.is_some();
+    let mut total: usize = 0;
+    let mut line_logs = Vec::new();
+
+    let (mut line_number, offset) = checkpoint.as_deref().map(|c| (c.lines(), c.offset())).unwrap_or_default();
+    let bytes = input_io::BytesRead::new(offset);
+    let mut lines = input_io::count_bytes(reader, bytes.clone()).lines();
+    while !stop::requested() {
+        let position = error_log::LinePosition { line: line_number + 1, offset: bytes.get() };
+        let t = timing::start();
+        let next = lines.next();
+        timing::record(Phase::Read, t);
+        let Some(line) = next else { break };
+        let line = line?;
+        line_number += 1;
+        if sample.as_deref_mut().is_some_and(|s| !s.take()) { continue; }
+        let started = (metrics.is_some() || per_line).then(Instant::now);
+        let before = total;
+        let decoded = decoder.parse_line(&line, |raw_log| {
+            let log = decoder.decode(raw_log)?;
+
+            total += 1;
+            if let Some(m) = metrics { m.decoded(&log.event.name); }
+            if per_line { line_logs.push((log.meta.clone(), log.event.name.clone())); }
+            if !output.admit(&log) { return Ok(()); }
+            if let Some(s) = sink.as_deref_mut() { s.push(&log)?; }
 
-    let mut inputs: Vec<EventParam> = Vec::with_capacity(inputs_v.len());
-    for i in inputs_v {
-        let name_i = i.get("name").and_then(|s| s.as_str()).unwrap_or("").to_string();
-        let indexed = i.get("indexed").and_then(|b| b.as_bool()).unwrap_or(false);
-        let type_str = i.get("type").and_then(|s| s.as_str()).unwrap_or("");
-        let param_type = match parse_param_type(type_str) { Some(t) => t, None => return None };
-        inputs.push(EventParam { name: name_i, kind: param_type, indexed });
+            if output.print {
+                match writer.as_deref_mut() {
+                    Some(writer) => writer.push(log)?,
+                    None => output.write(out, &log)?,
+                }
+            }
+            Ok(())
+        });
+        let elapsed = started.map(|s| s.elapsed()).unwrap_or_default();
+        if let Some(m) = metrics {
+            m.observe(elapsed);
+            if decoded.is_err() { m.error(); }
+        }
+        if let Err(e) = &decoded {
+            if let Some(log) = error_log.as_deref_mut().filter(|_| !input_io::is_io_error(e)) { log.record(position, &line, e)?; }
+        }
+        let decoded = decoded.with_context(|| format!("line {} (byte {}): {}", position.line, position.offset, snippet(&line)));
+        decoder.skip_error(decoded)?;
+        // Outside the closure, so database errors are never skipped as decode errors
+        if let Some(s) = sink.as_deref_mut() { s.flush_full()?; }
+        if per_line {
+            if let Some(c) = contracts.as_deref_mut() { c.record_line(&line_logs, elapsed); }
+            if let Some(p) = partitions.as_deref_mut() { p.record_line(&line_logs, elapsed); }
+            if let Some(s) = sample.as_deref_mut() { s.record_line(&line_logs, elapsed); }
+            line_logs.clear();
+        }
+        if let Some(c) = checkpoint.as_deref_mut() { c.line(total - before, out)?; }
     }
+    if let Some(s) = sink { s.finish()?; }
 
-    Some(Event { name, inputs, anonymous: false })
+    Ok(total)
 }
 
-fn parse_param_type(s: &str) -> Option<ParamType> {
-    match s {
-        "address" => Some(ParamType::Address),
-        "bool" => Some(ParamType::Bool),
-        "string" => Some(ParamType::String),
-        "bytes" => Some(ParamType::Bytes),
-        _ if s.starts_with("bytes") => { let n: usize = s[5..].parse().ok()?; Some(ParamType::FixedBytes(n)) }
-        _ if s.starts_with("uint") => { let n: usize = s[4..].parse().unwrap_or(256); Some(ParamType::Uint(n)) }
-        _ if s.starts_with("int") => { let n: usize = s[3..].parse().unwrap_or(256); Some(ParamType::Int(n)) }
-        _ if s.ends_with("[]") => { let inner = &s[..s.len()-2]; let inner_t = parse_param_type(inner)?; Some(ParamType::Array(Box::new(inner_t))) }
-        _ => None,
+/// Rejects a `--where` or `--aggregate` field that is neither metadata nor a parameter of any ABI event, which would
+/// otherwise match nothing without saying why.
+fn check_fields(flag: &str, fields: Vec<&str>, abis: &[&AbiSet]) -> Result<()> {
+    let known = |name: &str| METADATA_FIELDS.contains(&name) || abis.iter().flat_map(|set| &set.events).any(|(_, e)| e.inputs.iter().any(|p| p.name == name));
+    match fields.into_iter().find(|name| !known(name)) {
+        Some(name) => bail!("{}: no event in the ABI has a parameter named {:?}", flag, name),
+        None => Ok(()),
+    }
+}
+
+/// `--engine gpu`: loads the JSONL input, then decodes it in GPU batches and reports where the time went.
+#[cfg(feature = "gpu")]
+fn run_gpu(reader: Box<dyn BufRead + Send>, decoder: &LogDecoder, output: &Output) -> Result<()> {
+    use evm_rust_decoder::gpu::{GpuEngine, GpuTimes, GPU_BATCH_LOGS};
+
+    let engine = GpuEngine::new()?;
+    let Workload::Lines(lines) = Workload::load(reader, SplitStrategy::Lines)? else { unreachable!("loaded as lines") };
+    let start = Instant::now();
+    let mut times = GpuTimes::default();
+    let mut total = 0;
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let mut emit = |log: evm_rust_decoder::decode::DecodedLog<'_>| -> Result<()> {
+        total += 1;
+        if output.admit(&log) && output.print { output.write(&mut out, &log)?; }
+        Ok(())
+    };
+    let mut records = Vec::with_capacity(GPU_BATCH_LOGS);
+    for line in &lines {
+        if stop::requested() { break; }
+        decoder.skip_error(decoder.parse_line(line, |record| { records.push(record); Ok(()) }))?;
+        if records.len() >= GPU_BATCH_LOGS {
+            engine.decode_batch(decoder, std::mem::take(&mut records), &mut times, |log| decoder.skip_error(log.and_then(&mut emit)))?;
+        }
     }
+    engine.decode_batch(decoder, records, &mut times, |log| decoder.skip_error(log.and_then(&mut emit)))?;
+    out.flush()?;
+    print_summary(total, start.elapsed());
+    if logging::json() {
+        info!(
+            adapter = %engine.adapter, batches = times.batches, gpu_logs = times.gpu_logs, cpu_logs = times.cpu_logs,
+            mismatches = times.mismatches, gather_ms = rounded_ms(times.gather), upload_ms = rounded_ms(times.upload),
+            compute_ms = rounded_ms(times.compute), download_ms = rounded_ms(times.download), assemble_ms = rounded_ms(times.assemble),
+            cpu_decode_ms = rounded_ms(times.cpu), "gpu"
+        );
+        return Ok(());
+    }
+    eprintln!(
+        "gpu adapter=\"{}\" batches={} gpu_logs={} cpu_logs={} mismatches={} gather_ms={:.3} upload_ms={:.3} compute_ms={:.3} download_ms={:.3} assemble_ms={:.3} cpu_decode_ms={:.3}",
+        engine.adapter, times.batches, times.gpu_logs, times.cpu_logs, times.mismatches, ms(times.gather), ms(times.upload),
+        ms(times.compute), ms(times.download), ms(times.assemble), ms(times.cpu)
+    );
+    Ok(())
 }
 
-fn parse_h256(s: &str) -> Result<H256> {
-    let s = s.strip_prefix("0x").unwrap_or(s);
-    let bytes = <[u8; 32]>::from_hex(s).with_context(|| format!("Invalid H256 hex: {}", s))?;
-    Ok(H256::from(bytes))
+#[cfg(not(feature = "gpu"))]
+fn run_gpu(_reader: Box<dyn BufRead + Send>, _decoder: &LogDecoder, _output: &Output) -> Result<()> {
+    bail!("--engine gpu needs a build with the GPU engine: cargo build --release --features gpu")
 }
 
-fn parse_hex_bytes(s: &str) -> Result<Vec<u8>> {
-    let s = s.strip_prefix("0x").unwrap_or(s);
-    let bytes = Vec::from_hex(s).with_context(|| format!("Invalid hex bytes: {}", s))?;
-    Ok(bytes)
+/// Decodes one JSON document; with `exit_after`, parsing stops after that many logs.
+fn run_json_document(reader: impl Read, decoder: &LogDecoder, output: &Output, exit_after: Option<usize>) -> Result<usize> {
+    let mut total: usize = 0;
+    let mut seen: usize = 0;
+    let mut out = std::io::stdout().lock();
+
+    json_stream::for_each_log(reader, |parsed| {
+        seen += 1;
+        if exit_after == Some(seen) { stop::request(); }
+        if !decoder.admit(&parsed) { return Ok(()) }
+        let decoded = (|| {
+            let Some(raw_log) = to_raw_log(&parsed)? else { return Ok(()) };
+            let log = decoder.decode(raw_log)?;
+            total += 1;
+            if output.admit(&log) && output.print {
+                output.write(&mut out, &log)?;
+            }
+            Ok(())
+        })();
+        decoder.skip_error(decoded)
+    })?;
+
+    Ok(total)
+}
+
+/// Decodes `--input-format bin`, `protobuf`, `firehose` or `era1` records; `exit_after` counts records.
+fn run_records(reader: impl Read, format: InputFormat, decoder: &LogDecoder, output: &Output, exit_after: Option<usize>) -> Result<usize> {
+    let mut total: usize = 0;
+    let mut seen: usize = 0;
+    let mut out = std::io::stdout().lock();
+
+    let mut decode = |record: Result<LogRecord>| {
+        seen += 1;
+        if exit_after == Some(seen) { stop::request(); }
+        let decoded = record.and_then(|record| decoder.decode(record)).and_then(|log| {
+            total += 1;
+            if output.admit(&log) && output.print { output.write(&mut out, &log)?; }
+            Ok(())
+        });
+        decoder.skip_error(decoded)
+    };
+    match format {
+        InputFormat::Protobuf => protobuf::for_each_log(reader, decode)?,
+        InputFormat::Firehose => firehose::for_each_log(reader, decode)?,
+        InputFormat::Era1 => era1::for_each_log(reader, decode)?,
+        _ => binary::for_each_log(reader, |record| decode(Ok(record)))?,
+    }
+
+    Ok(total)
 }
 
-fn token_to_json(token: &Token) -> Value {
-    match token {
-        Token::Address(addr) => json!(format!("0x{}", hex::encode(addr.as_bytes()))),
-        Token::Uint(uint) => json!(uint.to_string()),
-        Token::Int(int) => json!(int.to_string()),
-        Token::Bool(b) => json!(*b),
-        Token::FixedBytes(b) | Token::Bytes(b) => json!(format!("0x{}", hex::encode(b))),
-        Token::String(s) => json!(s),
-        Token::Array(arr) => Value::Array(arr.iter().map(token_to_json).collect()),
-        Token::Tuple(arr) => Value::Array(arr.iter().map(token_to_json).collect()),
-        Token::FixedArray(arr) => Value::Array(arr.iter().map(token_to_json).collect()),
+/// Reports topic0s whose events index different parameters; fails under `--collision-policy error`.
+fn check_collisions(abis: &AbiSet, policy: CollisionPolicy) -> Result<()> {
+    let collisions = abis.collisions();
+    for c in &collisions {
+        let layouts: Vec<String> = c.events.iter().map(abi::describe).collect();
+        let resolution = match policy {
+            CollisionPolicy::First => "decoding with the first",
+            CollisionPolicy::ByTopicCount if c.by_topic_count_resolvable() => "choosing by topic count",
+            CollisionPolicy::ByTopicCount => "choosing by topic count, but layouts with equal counts decode as the first of them",
+            CollisionPolicy::Error => "",
+        };
+        warn!("topic0 collision {:?}: {}{}{}", c.topic0, layouts.join(" vs "), if resolution.is_empty() { "" } else { "; " }, resolution);
     }
+    if policy == CollisionPolicy::Error && !collisions.is_empty() {
+        bail!("{} topic0s are shared by events with different indexed parameters (see --collision-policy)", collisions.len());
+    }
+    Ok(())
 }
 
-fn tokens_to_json(inputs: &[EventParam], tokens: &Vec<Token>) -> Value {
-    let mut obj = serde_json::Map::new();
-    for (i, token) in tokens.iter().enumerate() {
-        let name = inputs.get(i).map(|p| p.name.as_str()).unwrap_or("");
-        let key = if name.is_empty() { format!("arg{}", i) } else { name.to_string() };
-        obj.insert(key, token_to_json(token));
+fn print_summary(total: usize, elapsed: Duration) {
+    if logging::json() {
+        return info!(
+            decoded = total, elapsed_ms = rounded_ms(elapsed), throughput_lps = lps(total, elapsed.as_secs_f64()) as u64,
+            arch = std::env::consts::ARCH, hex_decoder = HEX_DECODER, allocator = allocator::NAME, "summary"
+        );
     }
-    Value::Object(obj)
+    eprintln!(
+        "decoded={} elapsed_ms={:.3} throughput_lps={:.0} arch={} hex_decoder={} allocator={}",
+        total,
+        ms(elapsed),
+        lps(total, elapsed.as_secs_f64()),
+        std::env::consts::ARCH,
+        HEX_DECODER,
+        allocator::NAME
+    );
+}
+
+fn lps(total: usize, secs: f64) -> f64 {
+    if secs > 0.0 { (total as f64 / secs).round() } else { 0.0 }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Milliseconds to the microsecond, for log fields, which have no format precision.
+fn rounded_ms(d: Duration) -> f64 {
+    (ms(d) * 1000.0).round() / 1000.0
 }
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use axum::http::header;
+use axum::routing::get;
+use axum::Router;
+
+/// Upper bounds, in seconds, of the decode latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [1e-5, 5e-5, 1e-4, 5e-4, 1e-3, 5e-3, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// Counters of a long-running decoder, rendered in the Prometheus text format for `/metrics`.
+pub struct Metrics {
+    started: Instant,
+    requests: AtomicU64,
+    decoded: AtomicU64,
+    errors: AtomicU64,
+    /// Logs and lines dropped or left undecoded by the serve timeouts
+    timed_out: AtomicU64,
+    /// Decoded logs per event name; the write lock is only taken the first time an event is seen
+    events: RwLock<HashMap<String, AtomicU64>>,
+    latency: Histogram,
+    /// What one latency observation covers, for the metric's help text
+    latency_unit: &'static str,
+}
+
+/// A cumulative histogram with the buckets of [`LATENCY_BUCKETS`].
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_ns: AtomicU64,
+}
+
+impl Metrics {
+    /// `latency_unit` names what [`Metrics::observe`] is called for, e.g. "request" or "input line".
+    pub fn new(latency_unit: &'static str) -> Metrics {
+        Metrics {
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            decoded: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            timed_out: AtomicU64::new(0),
+            events: RwLock::new(HashMap::new()),
+            latency: Histogram { buckets: Default::default(), count: AtomicU64::new(0), sum_ns: AtomicU64::new(0) },
+            latency_unit,
+        }
+    }
+
+    pub fn request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn timed_out(&self, n: u64) {
+        self.timed_out.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Counts one decoded log of `event`.
+    pub fn decoded(&self, event: &str) {
+        self.decoded.fetch_add(1, Ordering::Relaxed);
+        if let Some(count) = self.events.read().expect("metrics lock poisoned").get(event) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.events.write().expect("metrics lock poisoned").entry(event.to_string()).or_default().fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the decode time of one request or input line.
+    pub fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|&bound| secs <= bound) {
+            self.latency.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency.count.fetch_add(1, Ordering::Relaxed);
+        self.latency.sum_ns.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self) -> u64 { self.requests.load(Ordering::Relaxed) }
+    pub fn decoded_total(&self) -> u64 { self.decoded.load(Ordering::Relaxed) }
+    pub fn errors(&self) -> u64 { self.errors.load(Ordering::Relaxed) }
+    pub fn timed_out_total(&self) -> u64 { self.timed_out.load(Ordering::Relaxed) }
+    pub fn decode_time(&self) -> Duration { Duration::from_nanos(self.latency.sum_ns.load(Ordering::Relaxed)) }
+    pub fn uptime(&self) -> Duration { self.started.elapsed() }
+
+    pub fn render(&self) -> String {
+        let mut s = String::new();
+        let head = |s: &mut String, name: &str, kind: &str, help: &str| {
+            let _ = writeln!(s, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        };
+        head(&mut s, "evm_decoder_requests_total", "counter", "Requests answered");
+        let _ = writeln!(s, "evm_decoder_requests_total {}", self.requests());
+        head(&mut s, "evm_decoder_logs_decoded_total", "counter", "Logs decoded");
+        let _ = writeln!(s, "evm_decoder_logs_decoded_total {}", self.decoded_total());
+        head(&mut s, "evm_decoder_errors_total", "counter", "Requests or lines that failed");
+        let _ = writeln!(s, "evm_decoder_errors_total {}", self.errors());
+        head(&mut s, "evm_decoder_logs_timed_out_total", "counter", "Logs dropped over the per-log timeout or left undecoded at the batch timeout");
+        let _ = writeln!(s, "evm_decoder_logs_timed_out_total {}", self.timed_out_total());
+
+        head(&mut s, "evm_decoder_event_logs_total", "counter", "Logs decoded per event");
+        let events = self.events.read().expect("metrics lock poisoned");
+        let mut names: Vec<&String> = events.keys().collect();
+        names.sort();
+        for name in names {
+            let _ = writeln!(s, "evm_decoder_event_logs_total{{event=\"{}\"}} {}", label(name), events[name].load(Ordering::Relaxed));
+        }
+
+        let help = format!("Decode time per {}", self.latency_unit);
+        head(&mut s, "evm_decoder_decode_latency_seconds", "histogram", &help);
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.latency.buckets) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(s, "evm_decoder_decode_latency_seconds_bucket{{le=\"{}\"}} {}", bound, cumulative);
+        }
+        let count = self.latency.count.load(Ordering::Relaxed);
+        let _ = writeln!(s, "evm_decoder_decode_latency_seconds_bucket{{le=\"+Inf\"}} {}", count);
+        let _ = writeln!(s, "evm_decoder_decode_latency_seconds_sum {}", self.decode_time().as_secs_f64());
+        let _ = writeln!(s, "evm_decoder_decode_latency_seconds_count {}", count);
+
+        head(&mut s, "evm_decoder_uptime_seconds", "gauge", "Seconds since the decoder started");
+        let _ = writeln!(s, "evm_decoder_uptime_seconds {}", self.uptime().as_secs_f64());
+        s
+    }
+}
+
+/// Escapes a Prometheus label value.
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Serves `GET /metrics` on `addr` from a background thread, for modes that have no HTTP server of their own.
+pub fn spawn_endpoint(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let listener = std::net::TcpListener::bind(addr).with_context(|| format!("Cannot listen on {} for metrics", addr))?;
+    listener.set_nonblocking(true)?;
+    tracing::info!("metrics on http://{}/metrics", listener.local_addr()?);
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    std::thread::spawn(move || {
+        let served = runtime.block_on(async {
+            let app = Router::new().route("/metrics", get(move || async move { endpoint(&metrics) }));
+            axum::serve(tokio::net::TcpListener::from_std(listener)?, app).await
+        });
+        if let Err(e) = served { tracing::error!("metrics endpoint failed: {}", e); }
+    });
+    Ok(())
+}
+
+/// Response of `GET /metrics`.
+pub fn endpoint(metrics: &Metrics) -> ([(header::HeaderName, &'static str); 1], String) {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics.render())
+}
@@ -0,0 +1,86 @@
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use evm_rust_decoder::decode::OnError;
+use evm_rust_decoder::io::{open_input, IoBackend};
+
+/// What the `--mode`s other than `logs` take from the command line besides their own inputs.
+pub struct LineOptions<'a> {
+    /// Files decoded in turn; stdin when empty
+    pub inputs: &'a [PathBuf],
+    pub print: bool,
+    pub preload: bool,
+    pub on_error: OnError,
+}
+
+/// A `--mode` that decodes one JSON document per input line.
+pub trait LineDecoder {
+    /// Decodes one non-empty line, appending the lines it prints (with `--print`) to `printed`.
+    fn decode_line(&mut self, line: &str, printed: &mut Vec<u8>) -> Result<()>;
+}
+
+/// What a pass over the inputs took.
+pub struct LineRun {
+    pub elapsed: Duration,
+    pub bytes: usize,
+    /// Lines skipped by `--on-error skip`
+    pub errors: usize,
+}
+
+impl LineRun {
+    pub fn per_sec(&self, count: usize) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { count as f64 / secs } else { 0.0 }
+    }
+
+    pub fn mb_per_s(&self) -> f64 {
+        self.per_sec(self.bytes) / 1e6
+    }
+
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed.as_secs_f64() * 1000.0
+    }
+}
+
+/// Runs `decoder` over every line of the inputs, timed from the first line (after reading the file, with
+/// `--preload`). With `--on-error skip` a failing line is counted and none of what it printed is written; what
+/// it had counted before failing stays counted.
+pub fn run_lines(options: &LineOptions, decoder: &mut impl LineDecoder) -> Result<LineRun> {
+    let mut out = options.print.then(|| BufWriter::new(io::stdout().lock()));
+    let mut run = LineRun { elapsed: Duration::ZERO, bytes: 0, errors: 0 };
+    let mut printed = Vec::new();
+    let mut line = |number: usize, line: &str, run: &mut LineRun| -> Result<()> {
+        run.bytes += line.len() + 1;
+        if line.trim().is_empty() { return Ok(()); }
+        printed.clear();
+        match decoder.decode_line(line, &mut printed) {
+            Err(_) if options.on_error == OnError::Skip => run.errors += 1,
+            result => result.with_context(|| format!("line {}", number))?,
+        }
+        if let Some(out) = &mut out { out.write_all(&printed)?; }
+        Ok(())
+    };
+
+    let inputs: Vec<Option<&Path>> = if options.inputs.is_empty() { vec![None] } else { options.inputs.iter().map(|p| Some(p.as_path())).collect() };
+    for path in inputs {
+        let mut reader = open_input(path, IoBackend::Std)?.reader;
+        let preloaded = if options.preload {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).with_context(|| format!("Cannot read input {:?}", path.unwrap_or(Path::new("-"))))?;
+            Some(text)
+        } else {
+            None
+        };
+        let start = Instant::now();
+        match &preloaded {
+            Some(text) => for (i, text) in text.lines().enumerate() { line(i + 1, text, &mut run)?; },
+            None => for (i, text) in reader.lines().enumerate() { line(i + 1, &text?, &mut run)?; },
+        }
+        run.elapsed += start.elapsed();
+    }
+    if let Some(out) = &mut out { out.flush()?; }
+    Ok(run)
+}
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use ethabi::{Log, Token};
+use ethereum_types::U256;
+use serde_json::Value;
+
+use crate::aggregate::Aggregator;
+use crate::decode::DecodedLog;
+use crate::enrich::Tokens;
+use crate::fast_json;
+use crate::predicate::Predicate;
+use crate::projection::Projection;
+use crate::timing::{self, Phase};
+use crate::topk::TopK;
+use crate::value::{self, DecodedValue};
+
+/// Version of the `--output-envelope` record layout; bump on any structural change.
+pub const ENVELOPE_SCHEMA_VERSION: u32 = 1;
+
+/// Order multi-threaded output is reassembled in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputOrder {
+    /// Same order as the input lines
+    Input,
+    /// By (blockNumber, logIndex); logs without them come first, and ties keep input order
+    Block,
+}
+
+/// How each output line is serialized; all three print the same bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Serializer {
+    /// serde_json over the typed values of `value.rs`
+    #[default]
+    Serde,
+    /// simd-json's serde serializer over the same values
+    SimdJson,
+    /// Written straight from the decoded tokens, with `itoa` for integers that fit 64 bits and no intermediate map
+    Itoa,
+}
+
+thread_local! {
+    /// The line being formatted, reused from one log to the next so each is handed to the writer in one piece
+    static LINE: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// What to emit for each decoded log.
+#[derive(Clone, Debug, Default)]
+pub struct Output {
+    /// Write decoded logs to stdout
+    pub print: bool,
+    /// Wrap each log as `{"event","address","blockNumber","args","schemaVersion"}` instead of printing bare args
+    pub envelope: bool,
+    /// Reassemble the output of parallel workers in this order; `None` writes it as workers finish
+    pub order: Option<OutputOrder>,
+    /// Annotate envelope records with the token metadata of their address (`--enrich`)
+    pub enrich: Option<Arc<Tokens>>,
+    pub serializer: Serializer,
+    /// Emit only the logs matching this `--where` expression
+    pub filter: Option<Arc<Predicate>>,
+    /// Print this `--select` projection of each record instead of the record
+    pub select: Option<Arc<Projection>>,
+    /// Add each emitted log to these `--aggregate` groups
+    pub aggregate: Option<Arc<Aggregator>>,
+    /// Count each emitted log's value of these `--topk` fields
+    pub topk: Vec<Arc<TopK>>,
+}
+
+impl Output {
+    /// Whether `decoded` goes on to the sink and stdout: true unless a `--where` filter rejects it. A log that does
+    /// is added to the `--aggregate` groups and `--topk` sketches. Every path calls this once per decoded log,
+    /// printing or not, so the filter's counts and the aggregates cover the whole run.
+    pub fn admit(&self, decoded: &DecodedLog) -> bool {
+        if !self.filter.as_ref().is_none_or(|filter| filter.matches(decoded)) { return false; }
+        if let Some(aggregate) = &self.aggregate { aggregate.add(decoded); }
+        for topk in &self.topk { topk.add(decoded); }
+        true
+    }
+
+    /// Appends the log as one JSON line to `out`, counted as output time apart from the enrichment lookup. The line
+    /// is formatted into a reused buffer and written with a single `write_all`.
+    pub fn write(&self, out: &mut impl Write, decoded: &DecodedLog) -> Result<()> {
+        LINE.with(|line| {
+            let mut line = line.borrow_mut();
+            line.clear();
+            self.format(&mut line, decoded)?;
+            line.push(b'\n');
+            let t = timing::start();
+            out.write_all(&line)?;
+            timing::record(Phase::Output, t);
+            Ok(())
+        })
+    }
+
+    fn format(&self, line: &mut Vec<u8>, decoded: &DecodedLog) -> Result<()> {
+        if self.enrich.is_some() || self.select.is_some() { return self.format_value(line, decoded); }
+        let t = timing::start();
+        match self.serializer {
+            Serializer::Serde if self.envelope => serde_json::to_writer(&mut *line, &value::DecodedLog::from(decoded))?,
+            Serializer::Serde => serde_json::to_writer(&mut *line, &value::params(&decoded.log))?,
+            Serializer::SimdJson if self.envelope => simd_json::to_writer(&mut *line, &value::DecodedLog::from(decoded))?,
+            Serializer::SimdJson => simd_json::to_writer(&mut *line, &value::params(&decoded.log))?,
+            Serializer::Itoa if self.envelope => fast_json::write_envelope(line, decoded),
+            Serializer::Itoa => fast_json::write_params(line, &decoded.log),
+        }
+        timing::record(Phase::Output, t);
+        Ok(())
+    }
+
+    /// `--enrich` and `--select` work on the record as a `serde_json::Value`, whatever the serializer.
+    fn format_value(&self, line: &mut Vec<u8>, decoded: &DecodedLog) -> Result<()> {
+        let t = timing::start();
+        let mut value = if self.envelope { envelope_json(decoded) } else { log_to_json(&decoded.log) };
+        timing::record(Phase::Output, t);
+        if let Some(tokens) = &self.enrich {
+            let lookup = timing::start();
+            tokens.annotate(decoded, &mut value)?;
+            timing::record(Phase::Enrich, lookup);
+        }
+        let t = timing::start();
+        match &self.select {
+            Some(projection) => projection.write(line, &value)?,
+            None => serde_json::to_writer(&mut *line, &value)?,
+        }
+        timing::record(Phase::Output, t);
+        Ok(())
+    }
+}
+
+pub fn token_to_json(token: &Token) -> Value {
+    serde_json::to_value(DecodedValue::from(token)).expect("decoded values serialize")
+}
+
+/// Formats an ABI-decoded `intN` (sign-extended two's complement in 256 bits) as a signed decimal.
+pub fn int_to_string(int: &U256) -> String {
+    value::I256(*int).to_string()
+}
+
+pub fn log_to_json(log: &Log) -> Value {
+    serde_json::to_value(value::params(log)).expect("decoded values serialize")
+}
+
+pub fn envelope_json(decoded: &DecodedLog) -> Value {
+    serde_json::to_value(value::DecodedLog::from(decoded)).expect("decoded values serialize")
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use core_affinity::CoreId;
+use tracing::{debug_span, info_span, Span};
+
+use crate::decode::{DecodedLog, LogDecoder};
+use crate::output::{Output, OutputOrder};
+use crate::stop;
+use crate::timing::{self, Phase};
+
+/// Target size of one byte chunk; each chunk is extended to the next newline.
+const BYTE_CHUNK_BYTES: usize = 1 << 20;
+/// Printed output a worker buffers before taking the stdout lock.
+const OUTPUT_FLUSH_BYTES: usize = 64 * 1024;
+/// Lines per reorder-buffer entry with `--ordered` and the `lines` split strategy.
+const ORDERED_BATCH_LINES: usize = 4096;
+/// Logs per [`LogDecoder::decode_batch`] call with `--homogeneous-fast-path`.
+const HOMOGENEOUS_BATCH_LOGS: usize = 4096;
+/// Thread counts measured by `--scaling-sweep`.
+pub const SWEEP_THREADS: [usize; 5] = [1, 2, 4, 8, 16];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SplitStrategy {
+    /// Split the input into lines up front and give each thread one contiguous range of lines
+    Lines,
+    /// Keep the input as one buffer, cut it into ~1 MiB chunks at newline boundaries and let threads pull chunks as they finish
+    ByteChunks,
+}
+
+/// Core ids parsed from a list such as `0-7,12,14-15`.
+#[derive(Clone, Debug)]
+pub struct CoreList(pub Vec<usize>);
+
+impl FromStr for CoreList {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut cores = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('-') {
+                Some((lo, hi)) => {
+                    let (lo, hi): (usize, usize) = (lo.trim().parse()?, hi.trim().parse()?);
+                    if lo > hi { return Err(anyhow!("Invalid core range: {}", part)); }
+                    cores.extend(lo..=hi);
+                }
+                None => cores.push(part.parse()?),
+            }
+        }
+        if cores.is_empty() { return Err(anyhow!("Empty core list")); }
+        Ok(CoreList(cores))
+    }
+}
+
+/// Where worker threads run. Worker `i` is pinned to `cores[i % cores.len()]`; with `numa_local` each worker
+/// copies its input into memory it allocates after pinning, so first-touch places those pages on the worker's node.
+#[derive(Clone, Debug, Default)]
+pub struct Placement {
+    pub cores: Vec<usize>,
+    pub numa_local: bool,
+}
+
+impl Placement {
+    fn enter(&self, worker: usize) -> Result<()> {
+        if self.cores.is_empty() { return Ok(()); }
+        let id = self.cores[worker % self.cores.len()];
+        if !core_affinity::set_for_current(CoreId { id }) {
+            return Err(anyhow!("Failed to pin worker {} to core {}", worker, id));
+        }
+        Ok(())
+    }
+}
+
+/// Whole input held in memory, shaped for the chosen split strategy.
+pub enum Workload {
+    Lines(Vec<String>),
+    ByteChunks { buf: Vec<u8>, chunks: Vec<(usize, usize)> },
+}
+
+impl Workload {
+    pub fn load(mut reader: Box<dyn BufRead + Send>, strategy: SplitStrategy) -> Result<Workload> {
+        let _span = info_span!("load_input", strategy = ?strategy).entered();
+        let t = timing::start();
+        let workload = match strategy {
+            SplitStrategy::Lines => Workload::Lines(reader.lines().collect::<io::Result<Vec<String>>>()?),
+            SplitStrategy::ByteChunks => {
+                let mut buf = Vec::new();
+                reader.read_to_end(&mut buf)?;
+                let chunks = split_at_newlines(&buf, BYTE_CHUNK_BYTES);
+                Workload::ByteChunks { buf, chunks }
+            }
+        };
+        timing::record(Phase::Read, t);
+        Ok(workload)
+    }
+}
+
+fn split_at_newlines(buf: &[u8], target: usize) -> Vec<(usize, usize)> {
+    let mut chunks = Vec::with_capacity(buf.len() / target + 1);
+    let mut start = 0;
+    while start < buf.len() {
+        let mut end = (start + target).min(buf.len());
+        match buf[end..].iter().position(|&b| b == b'\n') {
+            Some(nl) => end += nl + 1,
+            None => end = buf.len(),
+        }
+        chunks.push((start, end));
+        start = end;
+    }
+    chunks
+}
+
+/// Decodes the workload on `threads` worker threads and returns the number of decoded logs.
+///
+/// With `output.order` set, workers hand their printed output to the calling thread in sequence-numbered batches
+/// (line ranges or byte chunks), which writes them out in order. The `lines` strategy gives each worker one
+/// contiguous range, so output of later workers is held until earlier ones finish; `byte-chunks` hands out chunks in
+/// input order and keeps the reorder buffer to a few chunks. `Block` order keeps all output until the end.
+pub fn run(workload: &Workload, decoder: &LogDecoder, threads: usize, placement: &Placement, output: &Output) -> Result<usize> {
+    let threads = threads.max(1);
+    let next_chunk = AtomicUsize::new(0);
+    let order = output.order.filter(|_| output.print);
+    let (tx, rx) = mpsc::channel::<OrderedBatch>();
+    let span = Span::current();
+    thread::scope(|s| {
+        let handles: Vec<_> = match workload {
+            Workload::Lines(lines) => {
+                let per_thread = lines.len().div_ceil(threads).max(1);
+                lines
+                    .chunks(per_thread)
+                    .enumerate()
+                    .map(|(worker, part)| {
+                        let tx = order.map(|_| tx.clone());
+                        let span = &span;
+                        s.spawn(move || {
+                            let _span = debug_span!(parent: span, "worker", worker).entered();
+                            placement.enter(worker)?;
+                            let first = worker * per_thread;
+                            let total = if placement.numa_local {
+                                let local = part.to_vec();
+                                decode_part(&local, first, decoder, output, tx.as_ref())
+                            } else {
+                                decode_part(part, first, decoder, output, tx.as_ref())
+                            };
+                            timing::flush();
+                            total
+                        })
+                    })
+                    .collect()
+            }
+            Workload::ByteChunks { buf, chunks } => {
+                let next = &next_chunk;
+                (0..threads)
+                    .map(|worker| {
+                        let tx = order.map(|_| tx.clone());
+                        let span = &span;
+                        s.spawn(move || {
+                            let _span = debug_span!(parent: span, "worker", worker).entered();
+                            placement.enter(worker)?;
+                            let mut total = 0;
+                            let mut local = Vec::new();
+                            loop {
+                                if stop::requested() { break; }
+                                let i = next.fetch_add(1, Ordering::Relaxed);
+                                let Some(&(start, end)) = chunks.get(i) else { break };
+                                let bytes = if placement.numa_local {
+                                    local.clear();
+                                    local.extend_from_slice(&buf[start..end]);
+                                    &local[..]
+                                } else {
+                                    &buf[start..end]
+                                };
+                                let text = std::str::from_utf8(bytes).context("Input is not valid UTF-8")?;
+                                match &tx {
+                                    Some(tx) => {
+                                        let mut batch = OrderedBatch::new(start, end);
+                                        total += decode_lines(text.lines(), decoder, output, Some(&mut batch))?;
+                                        let _ = tx.send(batch);
+                                    }
+                                    None => total += decode_lines(text.lines(), decoder, output, None)?,
+                                }
+                            }
+                            timing::flush();
+                            Ok(total)
+                        })
+                    })
+                    .collect()
+            }
+        };
+        drop(tx);
+
+        let written = match order {
+            Some(order) => write_ordered(rx, order),
+            None => Ok(()),
+        };
+        let mut total = 0;
+        for h in handles {
+            total += h.join().map_err(|_| anyhow!("worker thread panicked"))??;
+        }
+        written?;
+        Ok(total)
+    })
+}
+
+/// Printed output of the input units `start..end` (line indices or byte offsets), with the block/logIndex of each log.
+pub(crate) struct OrderedBatch {
+    start: usize,
+    end: usize,
+    out: Vec<u8>,
+    keys: Vec<LogKey>,
+}
+
+/// Sort key of one log in an [`OrderedBatch`]; `end` is where its line ends in `out`.
+struct LogKey {
+    block: Option<u64>,
+    log_index: Option<u64>,
+    end: usize,
+}
+
+impl OrderedBatch {
+    fn new(start: usize, end: usize) -> Self {
+        OrderedBatch { start, end, out: Vec::new(), keys: Vec::new() }
+    }
+}
+
+fn decode_part(part: &[String], first: usize, decoder: &LogDecoder, output: &Output, tx: Option<&Sender<OrderedBatch>>) -> Result<usize> {
+    let Some(tx) = tx else { return decode_lines(part.iter().map(String::as_str), decoder, output, None) };
+    let mut total = 0;
+    for (k, lines) in part.chunks(ORDERED_BATCH_LINES).enumerate() {
+        let start = first + k * ORDERED_BATCH_LINES;
+        let mut batch = OrderedBatch::new(start, start + lines.len());
+        total += decode_lines(lines.iter().map(String::as_str), decoder, output, Some(&mut batch))?;
+        if tx.send(batch).is_err() || stop::requested() { break; }
+    }
+    Ok(total)
+}
+
+/// Writes batches to stdout in input order as soon as the next one arrives, or sorted by block/logIndex at the end.
+fn write_ordered(rx: Receiver<OrderedBatch>, order: OutputOrder) -> Result<()> {
+    let mut out = io::stdout().lock();
+    match order {
+        OutputOrder::Input => {
+            let mut pending: BTreeMap<usize, OrderedBatch> = BTreeMap::new();
+            let mut next = 0;
+            for batch in rx {
+                pending.insert(batch.start, batch);
+                while let Some(batch) = pending.remove(&next) {
+                    out.write_all(&batch.out)?;
+                    next = batch.end;
+                }
+            }
+            // Only a stopped run leaves gaps in the sequence; write what it decoded in input order
+            for batch in pending.into_values() { out.write_all(&batch.out)?; }
+        }
+        OutputOrder::Block => {
+            let mut batches: Vec<OrderedBatch> = rx.into_iter().collect();
+            batches.sort_unstable_by_key(|b| b.start);
+            // (block, logIndex, batch, line start, line end): the last three keep input order among ties
+            let mut logs = Vec::new();
+            for (i, batch) in batches.iter().enumerate() {
+                let mut start = 0;
+                for key in &batch.keys {
+                    logs.push((key.block, key.log_index, i, start, key.end));
+                    start = key.end;
+                }
+            }
+            logs.sort_unstable();
+            for (_, _, i, start, end) in logs { out.write_all(&batches[i].out[start..end])?; }
+        }
+    }
+    Ok(())
+}
+
+/// With [`LogDecoder::homogeneous_fast_path`], the logs of consecutive lines are parsed into batches and decoded
+/// with [`LogDecoder::decode_batch`], and a decode error skips only its own log rather than the rest of its line.
+pub(crate) fn decode_lines<'a>(lines: impl Iterator<Item = &'a str>, decoder: &LogDecoder, output: &Output, mut batch: Option<&mut OrderedBatch>) -> Result<usize> {
+    let mut total = 0;
+    let mut out: Vec<u8> = Vec::new();
+    let mut emit = |log: DecodedLog<'_>| -> Result<()> {
+        total += 1;
+        if !output.admit(&log) || !output.print { return Ok(()); }
+        match batch.as_deref_mut() {
+            Some(batch) => {
+                output.write(&mut batch.out, &log)?;
+                batch.keys.push(LogKey { block: log.meta.block_number, log_index: log.meta.log_index, end: batch.out.len() });
+            }
+            None => {
+                output.write(&mut out, &log)?;
+                if out.len() >= OUTPUT_FLUSH_BYTES {
+                    io::stdout().lock().write_all(&out)?;
+                    out.clear();
+                }
+            }
+        }
+        Ok(())
+    };
+    if decoder.homogeneous_fast_path() {
+        let mut records = Vec::with_capacity(HOMOGENEOUS_BATCH_LOGS);
+        for line in lines {
+            if stop::requested() { break; }
+            decoder.skip_error(decoder.parse_line(line, |record| { records.push(record); Ok(()) }))?;
+            if records.len() >= HOMOGENEOUS_BATCH_LOGS {
+                decoder.decode_batch(std::mem::take(&mut records), |log| decoder.skip_error(log.and_then(&mut emit)))?;
+            }
+        }
+        decoder.decode_batch(records, |log| decoder.skip_error(log.and_then(&mut emit)))?;
+    } else {
+        for line in lines {
+            if stop::requested() { break; }
+            let decoded = decoder.parse_line(line, |raw_log| emit(decoder.decode(raw_log)?));
+            decoder.skip_error(decoded)?;
+        }
+    }
+    if !out.is_empty() { io::stdout().lock().write_all(&out)?; }
+    Ok(total)
+}
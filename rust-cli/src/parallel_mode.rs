@@ -0,0 +1,85 @@
+use std::io::{self, BufRead, Read};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+
+use evm_rust_decoder::decode::LogDecoder;
+use evm_rust_decoder::io::open_file;
+use evm_rust_decoder::output::Output;
+use evm_rust_decoder::parallel::{self, Placement, SplitStrategy, Workload};
+use evm_rust_decoder::{files, pipeline};
+
+/// A way of spreading one dataset over threads, compared by `--parallel-mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ParallelMode {
+    /// Load every file into one buffer and let --threads workers pull ~1 MiB chunks of it
+    Chunk,
+    /// One thread per file, --threads files at a time (as --file-parallelism)
+    File,
+    /// Reader, parser, decoder and output stages on their own threads (as --pipeline), over the files in turn
+    Pipeline,
+}
+
+/// What decides the winner: file parallelism needs at least as many files as threads and of similar sizes,
+/// chunking splits any input evenly but loads it first, and the pipeline is bound by its slowest stage.
+pub struct Shape {
+    pub files: usize,
+    /// On disk, so compressed for `.zst` files
+    pub bytes: u64,
+    /// The largest file's share of `bytes`, the most a single file thread can be left with
+    pub largest_share: f64,
+}
+
+impl Shape {
+    pub fn of(files: &[PathBuf]) -> Result<Shape> {
+        let sizes = files.iter()
+            .map(|path| Ok(std::fs::metadata(path).with_context(|| format!("Cannot stat {}", path.display()))?.len()))
+            .collect::<Result<Vec<u64>>>()?;
+        let bytes = sizes.iter().sum();
+        let largest = sizes.iter().copied().max().unwrap_or(0);
+        Ok(Shape { files: files.len(), bytes, largest_share: if bytes > 0 { largest as f64 / bytes as f64 } else { 0.0 } })
+    }
+}
+
+/// One mode's pass over the dataset.
+pub struct ModeRun {
+    pub mode: ParallelMode,
+    pub decoded: usize,
+    /// From opening the first file to the last decoded log, so chunk mode's loading counts too
+    pub elapsed: Duration,
+    /// Chunk mode only: reading the files into memory, part of `elapsed`
+    pub load: Option<Duration>,
+}
+
+/// Decodes all of `files` once with `mode`. The decoder's counters are reset first, so each pass sees the whole
+/// input.
+pub fn run(mode: ParallelMode, files: &[PathBuf], decoder: &LogDecoder, output: &Output, threads: usize, placement: &Placement) -> Result<ModeRun> {
+    decoder.reset_filter();
+    let start = Instant::now();
+    let mut load = None;
+    let decoded = match mode {
+        ParallelMode::Chunk => {
+            let workload = Workload::load(concat(files)?, SplitStrategy::ByteChunks)?;
+            load = Some(start.elapsed());
+            parallel::run(&workload, decoder, threads, placement, output)?
+        }
+        ParallelMode::File => files::run(files, decoder, output, threads)?.iter().map(|f| f.decoded).sum(),
+        ParallelMode::Pipeline => pipeline::run(concat(files)?, decoder, output)?.0,
+    };
+    Ok(ModeRun { mode, decoded, elapsed: start.elapsed(), load })
+}
+
+/// The files read one after another, with a newline between them in case one does not end with it.
+fn concat(files: &[PathBuf]) -> Result<Box<dyn BufRead + Send>> {
+    let mut reader: Box<dyn BufRead + Send> = Box::new(io::empty());
+    for path in files {
+        reader = Box::new(reader.chain(&b"\n"[..]).chain(open_file(path)?));
+    }
+    Ok(reader)
+}
+
+pub fn name(mode: ParallelMode) -> String {
+    mode.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+}
@@ -0,0 +1,78 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use evm_rust_decoder::decode::LogMeta;
+
+use crate::logging;
+
+/// Events listed per partition, most frequent first.
+const EVENTS_SHOWN: usize = 5;
+
+/// Log counts, decode time and events per range of `size` blocks, for `--partition-blocks`.
+pub struct PartitionStats {
+    size: u64,
+    /// Keyed by the first block of each range
+    partitions: BTreeMap<u64, Partition>,
+    logs: u64,
+    no_block: u64,
+}
+
+#[derive(Default)]
+struct Partition {
+    logs: u64,
+    time: Duration,
+    events: HashMap<String, u64>,
+}
+
+impl PartitionStats {
+    pub fn new(size: u64) -> PartitionStats {
+        PartitionStats { size, partitions: BTreeMap::new(), logs: 0, no_block: 0 }
+    }
+
+    /// Records the logs decoded from one input line, as (metadata, event name), splitting the line's time evenly.
+    pub fn record_line(&mut self, logs: &[(LogMeta, String)], time: Duration) {
+        if logs.is_empty() { return; }
+        let share = time / logs.len() as u32;
+        for (meta, event) in logs {
+            self.logs += 1;
+            let Some(block) = meta.block_number else { self.no_block += 1; continue };
+            let partition = self.partitions.entry(block - block % self.size).or_default();
+            partition.logs += 1;
+            partition.time += share;
+            *partition.events.entry(event.clone()).or_default() += 1;
+        }
+    }
+
+    /// Prints one row per block range that has logs, in block order.
+    pub fn print(&self) {
+        let json = logging::json();
+        if json {
+            tracing::info!(blocks = self.size, count = self.partitions.len(), logs = self.logs, no_block = self.no_block, "partitions");
+        } else {
+            eprintln!("partitions blocks={} count={} logs={} no_block={}", self.size, self.partitions.len(), self.logs, self.no_block);
+        }
+        if self.partitions.is_empty() { return; }
+        if !json {
+            eprintln!("  {:>12} {:>12} {:>10} {:>11} {:>14}  events", "first_block", "last_block", "logs", "decode_ms", "throughput_lps");
+        }
+        for (first, p) in &self.partitions {
+            let mut events: Vec<(&String, &u64)> = p.events.iter().collect();
+            events.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let mut shown: Vec<String> = events.iter().take(EVENTS_SHOWN).map(|(name, n)| format!("{}:{}", name, n)).collect();
+            if events.len() > EVENTS_SHOWN { shown.push(format!("+{} more", events.len() - EVENTS_SHOWN)); }
+            let secs = p.time.as_secs_f64();
+            let throughput = if secs > 0.0 { p.logs as f64 / secs } else { 0.0 };
+            if json {
+                tracing::info!(
+                    first_block = first, last_block = first + self.size - 1, logs = p.logs, decode_ms = logging::round(secs * 1000.0, 3),
+                    throughput_lps = throughput.round() as u64, events = %shown.join(","), "partition"
+                );
+                continue;
+            }
+            eprintln!(
+                "  {:>12} {:>12} {:>10} {:>11.3} {:>14.0}  {}",
+                first, first + self.size - 1, p.logs, secs * 1000.0, throughput, shown.join(",")
+            );
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, BufRead};
+use std::mem;
+use std::sync::mpsc::sync_channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tracing::{debug_span, Span};
+use crate::decode::{DecodedLog, LogDecoder, LogRecord};
+use crate::output::Output;
+use crate::stop;
+use crate::timing::{self, Phase};
+
+/// Lines handed from one stage to the next in a single channel message.
+const BATCH_LINES: usize = 1024;
+/// Batches each channel can buffer before the upstream stage blocks.
+const CHANNEL_DEPTH: usize = 16;
+
+/// Busy time per stage, excluding time spent blocked on neighbouring stages.
+#[derive(Default)]
+pub struct StageTimes {
+    pub read: Duration,
+    pub parse: Duration,
+    pub decode: Duration,
+    pub output: Duration,
+}
+
+impl StageTimes {
+    pub fn total(&self) -> Duration {
+        self.read + self.parse + self.decode + self.output
+    }
+}
+
+/// Runs read → JSON/hex parse → ABI decode → output as four threads connected by bounded channels.
+pub fn run(reader: Box<dyn BufRead + Send>, decoder: &LogDecoder, output_mode: &Output) -> Result<(usize, StageTimes)> {
+    let (line_tx, line_rx) = sync_channel::<Vec<String>>(CHANNEL_DEPTH);
+    let (raw_tx, raw_rx) = sync_channel::<Vec<LogRecord>>(CHANNEL_DEPTH);
+    let (log_tx, log_rx) = sync_channel::<Vec<DecodedLog>>(CHANNEL_DEPTH);
+
+    let span = &Span::current();
+    thread::scope(|s| {
+        let read_stage = s.spawn(move || -> Result<Duration> {
+            let _span = debug_span!(parent: span, "stage", name = "read").entered();
+            let mut busy = Duration::ZERO;
+            let mut batch = Vec::with_capacity(BATCH_LINES);
+            let mut lines = reader.lines();
+            while !stop::requested() {
+                let t = Instant::now();
+                let phase = timing::start();
+                let next = lines.next();
+                timing::record(Phase::Read, phase);
+                busy += t.elapsed();
+                let Some(line) = next else { break };
+                batch.push(line?);
+                if batch.len() == BATCH_LINES && line_tx.send(mem::replace(&mut batch, Vec::with_capacity(BATCH_LINES))).is_err() {
+                    return Ok(busy);
+                }
+            }
+            if !batch.is_empty() { let _ = line_tx.send(batch); }
+            timing::flush();
+            Ok(busy)
+        });
+
+        let parse_stage = s.spawn(move || -> Result<Duration> {
+            let _span = debug_span!(parent: span, "stage", name = "parse").entered();
+            let mut busy = Duration::ZERO;
+            for lines in line_rx {
+                let t = Instant::now();
+                let mut raws = Vec::with_capacity(lines.len());
+                for line in &lines {
+                    decoder.skip_error(decoder.parse_line(line, |raw| { raws.push(raw); Ok(()) }))?;
+                }
+                busy += t.elapsed();
+                if raw_tx.send(raws).is_err() { break; }
+            }
+            timing::flush();
+            Ok(busy)
+        });
+
+        let decode_stage = s.spawn(move || -> Result<Duration> {
+            let _span = debug_span!(parent: span, "stage", name = "decode").entered();
+            let mut busy = Duration::ZERO;
+            for raws in raw_rx {
+                let t = Instant::now();
+                let mut logs = Vec::with_capacity(raws.len());
+                for raw in raws {
+                    decoder.skip_error(decoder.decode(raw).map(|log| logs.push(log)))?;
+                }
+                busy += t.elapsed();
+                if log_tx.send(logs).is_err() { break; }
+            }
+            timing::flush();
+            Ok(busy)
+        });
+
+        let mut total: usize = 0;
+        let mut output = Duration::ZERO;
+        let output_result = (|| -> Result<()> {
+            let mut out = io::stdout().lock();
+            for logs in log_rx {
+                let t = Instant::now();
+                total += logs.len();
+                for log in &logs {
+                    if output_mode.admit(log) && output_mode.print { output_mode.write(&mut out, log)?; }
+                }
+                output += t.elapsed();
+            }
+            Ok(())
+        })();
+
+        let join = |h: thread::ScopedJoinHandle<'_, Result<Duration>>| h.join().map_err(|_| anyhow!("pipeline stage panicked"))?;
+        let read = join(read_stage);
+        let parse = join(parse_stage);
+        let decode = join(decode_stage);
+        let times = StageTimes { read: read?, parse: parse?, decode: decode?, output };
+        output_result?;
+        Ok((total, times))
+    })
+}
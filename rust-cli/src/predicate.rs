@@ -0,0 +1,399 @@
+//! `--where`: a small expression language over decoded logs, e.g.
+//! `value > 1e18 && to == 0x000000000000000000000000000000000000dead`.
+//!
+//! An expression compares parameters (by name) and literals with `==`, `!=`, `<`, `<=`, `>` and `>=`, and combines
+//! comparisons with `&&`, `||`, `!` and parentheses. Literals are decimal integers (`-5`, `1_000`, `1e18`), hex
+//! (`0xdead...`, read as a number next to an integer and as bytes next to an address or `bytes`), quoted strings
+//! and `true`/`false`; a bool parameter on its own is a condition too. `event`, `address` and `blockNumber` name the
+//! log's event, contract and block unless the event has a parameter of that name. A comparison with a parameter the
+//! event lacks, or between values of different kinds, is false.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use anyhow::{anyhow, bail, Result};
+use ethabi::Token;
+use ethereum_types::U256;
+
+use crate::decode::DecodedLog;
+use crate::timing::{self, Phase};
+use crate::value::I256;
+
+/// A parsed `--where` expression, with counts of the logs it was evaluated on and matched.
+#[derive(Debug)]
+pub struct Predicate {
+    expr: Expr,
+    evaluated: AtomicUsize,
+    matched: AtomicUsize,
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    /// A bare operand, true when it is the bool `true`
+    Truthy(Operand),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+enum Operand {
+    Field(String),
+    Number { negative: bool, magnitude: U256 },
+    Hex(Vec<u8>),
+    Str(String),
+    Bool(bool),
+}
+
+/// An operand's value for one log.
+pub(crate) enum Val<'a> {
+    Number { negative: bool, magnitude: U256 },
+    Bytes(Cow<'a, [u8]>),
+    /// A hex literal, a number or bytes depending on the other side
+    Hex(&'a [u8]),
+    Str(&'a str),
+    Bool(bool),
+}
+
+impl Predicate {
+    pub fn parse(source: &str) -> Result<Predicate> {
+        let tokens = lex(source)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() { bail!("--where: unexpected {} after the expression", token.describe()); }
+        Ok(Predicate { expr, evaluated: AtomicUsize::new(0), matched: AtomicUsize::new(0) })
+    }
+
+    /// Whether `log` satisfies the expression; counted, and timed as `Phase::Where`.
+    pub fn matches(&self, log: &DecodedLog) -> bool {
+        let t = timing::start();
+        let matched = self.expr.eval(log);
+        timing::record(Phase::Where, t);
+        self.evaluated.fetch_add(1, AtomicOrdering::Relaxed);
+        if matched { self.matched.fetch_add(1, AtomicOrdering::Relaxed); }
+        matched
+    }
+
+    /// The parameter and metadata names the expression refers to, for checking against the ABI.
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields = Vec::new();
+        self.expr.fields(&mut fields);
+        fields
+    }
+
+    /// Logs evaluated so far: every log decoded while the predicate was in use.
+    pub fn evaluated(&self) -> usize {
+        self.evaluated.load(AtomicOrdering::Relaxed)
+    }
+
+    pub fn matched(&self) -> usize {
+        self.matched.load(AtomicOrdering::Relaxed)
+    }
+}
+
+impl Expr {
+    fn eval(&self, log: &DecodedLog) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(log) && b.eval(log),
+            Expr::Or(a, b) => a.eval(log) || b.eval(log),
+            Expr::Not(e) => !e.eval(log),
+            Expr::Compare(a, op, b) => {
+                let (Some(a), Some(b)) = (a.value(log), b.value(log)) else { return false };
+                let Some(ord) = compare(&a, &b) else { return false };
+                match op {
+                    Op::Eq => ord == Ordering::Equal,
+                    Op::Ne => ord != Ordering::Equal,
+                    Op::Lt => ord == Ordering::Less,
+                    Op::Le => ord != Ordering::Greater,
+                    Op::Gt => ord == Ordering::Greater,
+                    Op::Ge => ord != Ordering::Less,
+                }
+            }
+            Expr::Truthy(operand) => matches!(operand.value(log), Some(Val::Bool(true))),
+        }
+    }
+
+    fn fields<'a>(&'a self, fields: &mut Vec<&'a str>) {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.fields(fields);
+                b.fields(fields);
+            }
+            Expr::Not(e) => e.fields(fields),
+            Expr::Compare(a, _, b) => fields.extend([a, b].into_iter().filter_map(Operand::field)),
+            Expr::Truthy(operand) => fields.extend(operand.field()),
+        }
+    }
+}
+
+impl Operand {
+    fn field(&self) -> Option<&str> {
+        match self {
+            Operand::Field(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn value<'a>(&'a self, log: &'a DecodedLog) -> Option<Val<'a>> {
+        Some(match self {
+            Operand::Field(name) => return field(log, name),
+            Operand::Number { negative, magnitude } => Val::Number { negative: *negative, magnitude: *magnitude },
+            Operand::Hex(bytes) => Val::Hex(bytes),
+            Operand::Str(s) => Val::Str(s),
+            Operand::Bool(b) => Val::Bool(*b),
+        })
+    }
+}
+
+/// Names that refer to log metadata when the event has no parameter of the name.
+pub const METADATA_FIELDS: [&str; 4] = ["event", "address", "blockNumber", "chainId"];
+
+/// The parameter `name` of `log`, or failing that its metadata of that name.
+pub(crate) fn field<'a>(log: &'a DecodedLog, name: &str) -> Option<Val<'a>> {
+    if let Some(param) = log.log.params.iter().find(|p| p.name == name) { return token_value(&param.value); }
+    match name {
+        "event" => Some(Val::Str(&log.event.name)),
+        "address" => {
+            let address = log.meta.address.as_deref()?;
+            hex::decode(address.strip_prefix("0x").unwrap_or(address)).ok().map(|b| Val::Bytes(Cow::Owned(b)))
+        }
+        "blockNumber" => log.meta.block_number.map(|n| Val::Number { negative: false, magnitude: U256::from(n) }),
+        "chainId" => log.meta.chain_id.map(|n| Val::Number { negative: false, magnitude: U256::from(n) }),
+        _ => None,
+    }
+}
+
+fn token_value(token: &Token) -> Option<Val<'_>> {
+    Some(match token {
+        Token::Uint(uint) => Val::Number { negative: false, magnitude: *uint },
+        Token::Int(int) => {
+            let int = I256(*int);
+            Val::Number { negative: int.is_negative(), magnitude: int.magnitude() }
+        }
+        Token::Address(address) => Val::Bytes(Cow::Borrowed(address.as_bytes())),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => Val::Bytes(Cow::Borrowed(bytes)),
+        Token::String(s) => Val::Str(s),
+        Token::Bool(b) => Val::Bool(*b),
+        Token::Array(_) | Token::FixedArray(_) | Token::Tuple(_) => return None,
+    })
+}
+
+/// How `a` orders against `b`, or `None` when they are of different kinds.
+fn compare(a: &Val, b: &Val) -> Option<Ordering> {
+    match (a, b) {
+        (Val::Number { negative: an, magnitude: am }, Val::Number { negative: bn, magnitude: bm }) => {
+            Some(signed_cmp(*an, am, *bn, bm))
+        }
+        (Val::Number { negative, magnitude }, Val::Hex(hex)) => Some(signed_cmp(*negative, magnitude, false, &hex_number(hex)?)),
+        (Val::Hex(hex), Val::Number { negative, magnitude }) => Some(signed_cmp(false, &hex_number(hex)?, *negative, magnitude)),
+        (Val::Bytes(a), Val::Bytes(b)) => Some(a.as_ref().cmp(b.as_ref())),
+        (Val::Bytes(a), Val::Hex(b)) => Some(a.as_ref().cmp(*b)),
+        (Val::Hex(a), Val::Bytes(b)) => Some(a.cmp(&b.as_ref())),
+        (Val::Hex(a), Val::Hex(b)) => Some(a.cmp(b)),
+        (Val::Str(a), Val::Str(b)) => Some(a.cmp(b)),
+        (Val::Bool(a), Val::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+pub(crate) fn signed_cmp(a_negative: bool, a: &U256, b_negative: bool, b: &U256) -> Ordering {
+    match (a_negative, b_negative) {
+        (false, false) => a.cmp(b),
+        (true, true) => b.cmp(a),
+        // -0 does not occur: literals and ints are only negative when nonzero
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    }
+}
+
+fn hex_number(hex: &[u8]) -> Option<U256> {
+    (hex.len() <= 32).then(|| U256::from_big_endian(hex))
+}
+
+#[derive(Debug, PartialEq)]
+enum Tok {
+    Ident(String),
+    Number { negative: bool, magnitude: U256 },
+    Hex(Vec<u8>),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Tok {
+    fn describe(&self) -> String {
+        match self {
+            Tok::Ident(name) => format!("`{}`", name),
+            Tok::Number { .. } | Tok::Hex(_) => "number".to_string(),
+            Tok::Str(_) => "string".to_string(),
+            Tok::Op(_) => "comparison".to_string(),
+            Tok::And => "`&&`".to_string(),
+            Tok::Or => "`||`".to_string(),
+            Tok::Not => "`!`".to_string(),
+            Tok::Open => "`(`".to_string(),
+            Tok::Close => "`)`".to_string(),
+        }
+    }
+}
+
+fn lex(source: &str) -> Result<Vec<Tok>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let rest = &source[i..];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let two = rest.get(..2).unwrap_or("");
+        let (token, len) = match two {
+            "&&" => (Tok::And, 2),
+            "||" => (Tok::Or, 2),
+            "==" => (Tok::Op(Op::Eq), 2),
+            "!=" => (Tok::Op(Op::Ne), 2),
+            "<=" => (Tok::Op(Op::Le), 2),
+            ">=" => (Tok::Op(Op::Ge), 2),
+            _ => match c {
+                b'<' => (Tok::Op(Op::Lt), 1),
+                b'>' => (Tok::Op(Op::Gt), 1),
+                b'!' => (Tok::Not, 1),
+                b'(' => (Tok::Open, 1),
+                b')' => (Tok::Close, 1),
+                b'"' | b'\'' => {
+                    let end = rest[1..].find(c as char).ok_or_else(|| anyhow!("--where: unterminated string at offset {}", i))?;
+                    (Tok::Str(rest[1..1 + end].to_string()), end + 2)
+                }
+                b'0'..=b'9' | b'-' => {
+                    let len = 1 + rest[1..].find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_')).unwrap_or(rest.len() - 1);
+                    (number(&rest[..len]).map_err(|e| anyhow!("--where: {} at offset {}", e, i))?, len)
+                }
+                c if c.is_ascii_alphabetic() || c == b'_' => {
+                    let len = rest.find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+                    (Tok::Ident(rest[..len].to_string()), len)
+                }
+                _ => bail!("--where: unexpected {:?} at offset {}", rest.chars().next().unwrap_or_default(), i),
+            },
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+/// The largest `e` exponent of a decimal literal, checked before its zeros are written out: 10^78 is already over
+/// 2^256.
+const MAX_EXPONENT: usize = 78;
+
+/// A numeric literal: `0x` hex, or a decimal integer with optional `-`, `_` separators and `e` exponent.
+fn number(text: &str) -> Result<Tok> {
+    if let Some(hex) = text.strip_prefix("0x") {
+        let padded = if hex.len() % 2 == 1 { format!("0{}", hex) } else { hex.to_string() };
+        return hex::decode(padded).map(Tok::Hex).map_err(|_| anyhow!("invalid hex literal {}", text));
+    }
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let digits = digits.replace('_', "");
+    let (mantissa, exponent) = match digits.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<usize>().map_err(|_| anyhow!("invalid number {}", text))?),
+        None => (digits.as_str(), 0),
+    };
+    let invalid = || anyhow!("invalid number {}", text);
+    if mantissa.is_empty() || !mantissa.bytes().all(|b| b.is_ascii_digit()) { return Err(invalid()); }
+    if exponent > MAX_EXPONENT { return Err(invalid()); }
+    let magnitude = U256::from_dec_str(&format!("{}{}", mantissa, "0".repeat(exponent))).map_err(|_| invalid())?;
+    Ok(Tok::Number { negative: negative && !magnitude.is_zero(), magnitude })
+}
+
+struct Parser<'t> {
+    tokens: &'t [Tok],
+    pos: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&'t Tok> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'t Tok> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Tok::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Tok::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Tok::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Tok::Open) => {
+                self.pos += 1;
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Tok::Close) => Ok(expr),
+                    other => bail!("--where: expected `)`, found {}", describe(other)),
+                }
+            }
+            _ => {
+                let left = self.operand()?;
+                let Some(&Tok::Op(op)) = self.peek() else { return Ok(Expr::Truthy(left)) };
+                self.pos += 1;
+                Ok(Expr::Compare(left, op, self.operand()?))
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        Ok(match self.next() {
+            Some(Tok::Ident(name)) if name == "true" => Operand::Bool(true),
+            Some(Tok::Ident(name)) if name == "false" => Operand::Bool(false),
+            Some(Tok::Ident(name)) => Operand::Field(name.clone()),
+            Some(Tok::Number { negative, magnitude }) => Operand::Number { negative: *negative, magnitude: *magnitude },
+            Some(Tok::Hex(bytes)) => Operand::Hex(bytes.clone()),
+            Some(Tok::Str(s)) => Operand::Str(s.clone()),
+            other => bail!("--where: expected a parameter or a value, found {}", describe(other)),
+        })
+    }
+}
+
+fn describe(token: Option<&Tok>) -> String {
+    token.map(Tok::describe).unwrap_or_else(|| "the end".to_string())
+}
@@ -0,0 +1,59 @@
+//! `--preset`: event sets of well-known standards and protocols, compiled into the binary as Solidity event
+//! declarations (`presets/*.sol`) and read with [`events_from_solidity`]. Presets whose calls are worth decoding
+//! too (`--mode traces`) also carry a JSON ABI of functions (`presets/*-functions.json`).
+
+use clap::ValueEnum;
+use ethabi::{Event, Function};
+
+use crate::abi::functions_from_json;
+use crate::sol::events_from_solidity;
+
+/// A built-in event set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// ERC-20 Transfer and Approval
+    Erc20,
+    /// ERC-721 Transfer, Approval and ApprovalForAll (token id indexed)
+    Erc721,
+    /// ERC-1155 TransferSingle, TransferBatch, ApprovalForAll and URI
+    Erc1155,
+    /// WETH9 Transfer, Approval, Deposit and Withdrawal
+    Weth,
+    /// Uniswap V2 pair Mint, Burn, Swap and Sync, and factory PairCreated
+    UniswapV2,
+    /// Uniswap V3 pool and factory events (Swap, Mint, Burn, Collect, Flash, PoolCreated, ...)
+    UniswapV3,
+    /// ERC-4337 EntryPoint (v0.6 and v0.7) UserOperationEvent, AccountDeployed, revert reasons and stake events;
+    /// its functions decode `handleOps` bundles and the calls of common smart accounts
+    Erc4337,
+}
+
+impl Preset {
+    /// The Solidity declarations of the preset.
+    pub fn source(self) -> &'static str {
+        match self {
+            Preset::Erc20 => include_str!("../presets/erc20.sol"),
+            Preset::Erc721 => include_str!("../presets/erc721.sol"),
+            Preset::Erc1155 => include_str!("../presets/erc1155.sol"),
+            Preset::Weth => include_str!("../presets/weth.sol"),
+            Preset::UniswapV2 => include_str!("../presets/uniswap-v2.sol"),
+            Preset::UniswapV3 => include_str!("../presets/uniswap-v3.sol"),
+            Preset::Erc4337 => include_str!("../presets/erc4337.sol"),
+        }
+    }
+
+    /// The events of the preset, in declaration order.
+    pub fn events(self) -> Vec<Event> {
+        events_from_solidity(self.source())
+    }
+
+    /// The functions of the preset, for `--mode traces`; empty for presets of events only.
+    pub fn functions(self) -> Vec<Function> {
+        let source = match self {
+            Preset::Erc4337 => include_str!("../presets/erc4337-functions.json"),
+            _ => return Vec::new(),
+        };
+        let json_value = serde_json::from_str(source).expect("preset function ABIs are valid JSON");
+        functions_from_json(&json_value).expect("preset function ABIs are arrays")
+    }
+}
@@ -0,0 +1,176 @@
+//! `--select`: a jq-style projection of each output record, so shaping the JSON costs a field lookup rather than a
+//! trip through jq at the end of the pipe.
+//!
+//! The supported subset: `.` (the record), paths such as `.args.value`, `.args["from"]` and `.args.ids[0]`, arrays
+//! `[.a, .b]`, objects `{value: .args.value, blockNumber}` (a bare key takes the field of that name), and a
+//! top-level `,` that prints each of its parts on a line of its own, as `jq -c` does. A path into a missing field
+//! or through a value of another kind gives `null` rather than an error. Object keys come out sorted, as in every
+//! other output record.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value};
+
+/// A parsed `--select` expression: one or more outputs per record.
+#[derive(Debug)]
+pub struct Projection {
+    outputs: Vec<Term>,
+}
+
+#[derive(Debug)]
+enum Term {
+    Path(Vec<Step>),
+    Array(Vec<Term>),
+    Object(Vec<(String, Term)>),
+}
+
+#[derive(Debug)]
+enum Step {
+    Field(String),
+    Index(usize),
+}
+
+impl Projection {
+    pub fn parse(source: &str) -> Result<Projection> {
+        let mut parser = Parser { source, pos: 0 };
+        let outputs = parser.list()?;
+        parser.skip_space();
+        if parser.pos < source.len() { bail!("--select: unexpected {:?} at offset {}", &source[parser.pos..], parser.pos); }
+        Ok(Projection { outputs })
+    }
+
+    /// Appends the projection of `record` to `line`, one JSON value per output separated by newlines (the caller
+    /// ends the last).
+    pub fn write(&self, line: &mut Vec<u8>, record: &Value) -> Result<()> {
+        for (i, term) in self.outputs.iter().enumerate() {
+            if i > 0 { line.push(b'\n'); }
+            serde_json::to_writer(&mut *line, &term.eval(record))?;
+        }
+        Ok(())
+    }
+}
+
+impl Term {
+    fn eval(&self, record: &Value) -> Value {
+        match self {
+            Term::Path(steps) => steps
+                .iter()
+                .try_fold(record, |value, step| match step {
+                    Step::Field(name) => value.get(name),
+                    Step::Index(i) => value.get(i),
+                })
+                .cloned()
+                .unwrap_or(Value::Null),
+            Term::Array(terms) => Value::Array(terms.iter().map(|t| t.eval(record)).collect()),
+            Term::Object(fields) => {
+                Value::Object(fields.iter().map(|(key, t)| (key.clone(), t.eval(record))).collect::<Map<_, _>>())
+            }
+        }
+    }
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.pos..]
+    }
+
+    fn skip_space(&mut self) {
+        self.pos = self.source.len() - self.rest().trim_start().len();
+    }
+
+    /// Skips whitespace, then takes `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        let next = self.rest().starts_with(c);
+        if next { self.pos += c.len_utf8(); }
+        next
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) { Ok(()) } else { Err(self.error(&format!("expected `{}`", c))) }
+    }
+
+    fn error(&self, what: &str) -> anyhow::Error {
+        anyhow!("--select: {} at offset {}", what, self.pos)
+    }
+
+    /// Comma-separated terms.
+    fn list(&mut self) -> Result<Vec<Term>> {
+        let mut terms = vec![self.term()?];
+        while self.eat(',') { terms.push(self.term()?); }
+        Ok(terms)
+    }
+
+    fn term(&mut self) -> Result<Term> {
+        if self.eat('[') {
+            let terms = self.list()?;
+            self.expect(']')?;
+            return Ok(Term::Array(terms));
+        }
+        if self.eat('{') {
+            let mut fields = Vec::new();
+            loop {
+                let key = self.key()?;
+                let value = if self.eat(':') { self.term()? } else { Term::Path(vec![Step::Field(key.clone())]) };
+                fields.push((key, value));
+                if !self.eat(',') { break; }
+            }
+            self.expect('}')?;
+            return Ok(Term::Object(fields));
+        }
+        self.path()
+    }
+
+    /// `.`, or steps of `.name`, `."name"`, `["name"]` and `[N]`.
+    fn path(&mut self) -> Result<Term> {
+        if !self.eat('.') { return Err(self.error("expected a path starting with `.`, `[` or `{`")); }
+        let mut steps = Vec::new();
+        if self.rest().starts_with(|c: char| c == '"' || c == '_' || c.is_ascii_alphabetic()) {
+            steps.push(Step::Field(self.key()?));
+        }
+        loop {
+            if self.rest().starts_with('.') {
+                self.pos += 1;
+                steps.push(Step::Field(self.key()?));
+            } else if self.rest().starts_with('[') {
+                self.pos += 1;
+                self.skip_space();
+                let step = if self.rest().starts_with('"') {
+                    Step::Field(self.string()?)
+                } else {
+                    let digits = self.rest().len() - self.rest().trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                    let index = self.rest()[..digits].parse().map_err(|_| self.error("expected an index or a quoted key"))?;
+                    self.pos += digits;
+                    Step::Index(index)
+                };
+                self.expect(']')?;
+                steps.push(step);
+            } else {
+                return Ok(Term::Path(steps));
+            }
+        }
+    }
+
+    /// An identifier or a quoted string.
+    fn key(&mut self) -> Result<String> {
+        self.skip_space();
+        if self.rest().starts_with('"') { return self.string(); }
+        let len = self.rest().find(|c: char| !(c == '_' || c.is_ascii_alphanumeric())).unwrap_or(self.rest().len());
+        if len == 0 { return Err(self.error("expected a field name")); }
+        let key = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(key)
+    }
+
+    /// A double-quoted string, without escapes.
+    fn string(&mut self) -> Result<String> {
+        let end = self.rest()[1..].find('"').ok_or_else(|| self.error("unterminated string"))?;
+        let s = self.rest()[1..1 + end].to_string();
+        self.pos += end + 2;
+        Ok(s)
+    }
+}
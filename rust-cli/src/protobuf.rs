@@ -0,0 +1,94 @@
+//! `--input-format protobuf`: length-delimited `sf.ethereum.type.v2.Log` messages (`proto/ethereum_log.proto`), the
+//! log type of Firehose and Substreams for Ethereum. Each message is preceded by its length as a varint, as written
+//! by prost's `encode_length_delimited` or Java's `writeDelimitedTo`.
+
+use std::io::{ErrorKind, Read, Write};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethabi::RawLog;
+use ethereum_types::H256;
+use prost::Message;
+
+use crate::decode::{LogMeta, LogRecord};
+use crate::stop;
+
+pub mod pb {
+    // prost escapes the `type` keyword in the generated file name
+    tonic::include_proto!("sf.ethereum.r#type.v2");
+}
+
+/// Streams the messages of `reader` in order, calling `f` with each log, or with the error of a message that does not
+/// decode, so `--on-error skip` can step over it; logs without topics are skipped like blank JSONL lines. A length
+/// prefix that runs past the end of the input fails the whole read. Reading ends early, without error, once
+/// [`stop::requested`] is set.
+pub fn for_each_log<R: Read>(mut reader: R, mut f: impl FnMut(Result<LogRecord>) -> Result<()>) -> Result<()> {
+    let mut message = 0usize;
+    let mut buf = Vec::new();
+    while !stop::requested() {
+        let Some(len) = read_length(&mut reader, message + 1)? else { return Ok(()) };
+        message += 1;
+        // Read through `take` rather than into a buffer of `len`, so a corrupt length cannot allocate more than the
+        // input holds
+        buf.clear();
+        (&mut reader).take(len).read_to_end(&mut buf)?;
+        if (buf.len() as u64) < len { bail!("Invalid protobuf message {}: input ends mid-message", message); }
+        let record = pb::Log::decode(&buf[..])
+            .with_context(|| format!("Invalid protobuf message {}", message))
+            .and_then(|log| to_record(log, None).with_context(|| format!("Invalid log in protobuf message {}", message)));
+        match record {
+            Ok(record) if record.raw.topics.is_empty() => {}
+            record => f(record)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes `record` as one length-delimited message.
+pub fn write_record(out: &mut impl Write, record: &LogRecord) -> Result<()> {
+    let address = match &record.meta.address {
+        Some(address) => hex::decode(address.strip_prefix("0x").unwrap_or(address)).with_context(|| format!("Invalid address {}", address))?,
+        None => Vec::new(),
+    };
+    let log = pb::Log {
+        address,
+        topics: record.raw.topics.iter().map(|t| t.as_bytes().to_vec()).collect(),
+        data: record.raw.data.clone(),
+        block_index: record.meta.log_index.unwrap_or(0) as u32,
+        ..Default::default()
+    };
+    out.write_all(&log.encode_length_delimited_to_vec())?;
+    Ok(())
+}
+
+/// Checks the topic and address lengths of `log`; a bare `Log` has no `block_number`, which belongs to its block.
+pub(crate) fn to_record(log: pb::Log, block_number: Option<u64>) -> Result<LogRecord> {
+    let topics = log.topics.iter().map(|t| {
+        <[u8; 32]>::try_from(&t[..]).map(H256).map_err(|_| anyhow!("topic of {} bytes, expected 32", t.len()))
+    }).collect::<Result<Vec<H256>>>()?;
+    let address = match log.address.len() {
+        0 => None,
+        20 => Some(format!("0x{}", hex::encode(&log.address))),
+        n => bail!("address of {} bytes, expected 20", n),
+    };
+    let meta = LogMeta { address, block_number, log_index: Some(log.block_index as u64), ..LogMeta::default() };
+    Ok(LogRecord { raw: RawLog { topics, data: log.data }, meta })
+}
+
+/// Reads a varint length prefix; `None` at a clean end of input.
+fn read_length(reader: &mut impl Read, message: usize) -> Result<Option<u64>> {
+    let (mut len, mut shift) = (0u64, 0u32);
+    loop {
+        let mut byte = [0u8; 1];
+        match reader.read(&mut byte) {
+            Ok(0) if shift == 0 => return Ok(None),
+            Ok(0) => bail!("Invalid protobuf message {}: input ends mid-length", message),
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+        if shift >= 64 { bail!("Invalid protobuf message {}: length prefix is not a varint", message); }
+        len |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 { return Ok(Some(len)); }
+        shift += 7;
+    }
+}
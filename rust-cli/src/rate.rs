@@ -0,0 +1,133 @@
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use evm_rust_decoder::decode::LogDecoder;
+use evm_rust_decoder::output::Output;
+use evm_rust_decoder::stop;
+
+use crate::logging;
+
+/// Sub-buckets per power of two in the latency histogram, giving about 6% resolution.
+const SUB_BUCKETS: usize = 16;
+/// Closer to the next token than this, the pacer spins instead of sleeping (sleeps overshoot by tens of µs).
+const SPIN_BELOW: Duration = Duration::from_micros(200);
+
+/// Latencies of a paced run, from when each line's token was issued to when its logs were decoded.
+pub struct LatencyReport {
+    pub decoded: usize,
+    pub lines: u64,
+    pub elapsed: Duration,
+    histogram: Histogram,
+}
+
+/// Decodes JSONL at a fixed offered load of `rate` lines per second (one log each for plain log lines).
+///
+/// Tokens accrue at `rate` from the start of the run and each line takes one. A line's latency is measured from
+/// when its token was issued, not from when the decoder got to it, so a decoder that falls behind accumulates
+/// queueing delay instead of silently lowering the offered load.
+pub fn run(reader: Box<dyn BufRead + Send>, decoder: &LogDecoder, output: &Output, rate: f64, out: &mut impl Write) -> Result<LatencyReport> {
+    let interval = Duration::from_secs_f64(1.0 / rate);
+    let mut histogram = Histogram::default();
+    let mut total: usize = 0;
+    let mut lines: u64 = 0;
+
+    let start = Instant::now();
+    let mut reader = reader.lines();
+    while !stop::requested() {
+        let Some(line) = reader.next() else { break };
+        let line = line?;
+        let due = start + interval.mul_f64(lines as f64);
+        wait_until(due);
+        let decoded = decoder.parse_line(&line, |raw_log| {
+            let log = decoder.decode(raw_log)?;
+            total += 1;
+            if output.admit(&log) && output.print { output.write(out, &log)?; }
+            Ok(())
+        });
+        decoder.skip_error(decoded)?;
+        histogram.record(due.elapsed());
+        lines += 1;
+    }
+
+    Ok(LatencyReport { decoded: total, lines, elapsed: start.elapsed(), histogram })
+}
+
+fn wait_until(due: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= due { return; }
+        let left = due - now;
+        if left > SPIN_BELOW { std::thread::sleep(left - SPIN_BELOW); } else { std::hint::spin_loop(); }
+    }
+}
+
+impl LatencyReport {
+    pub fn print(&self, rate: f64) {
+        let secs = self.elapsed.as_secs_f64();
+        let achieved = if secs > 0.0 { self.lines as f64 / secs } else { 0.0 };
+        let h = &self.histogram;
+        if logging::json() {
+            tracing::info!(
+                offered_lps = rate.round() as u64, achieved_lps = achieved.round() as u64, p50_us = logging::round(h.quantile(0.5), 1),
+                p90_us = logging::round(h.quantile(0.9), 1), p99_us = logging::round(h.quantile(0.99), 1),
+                p999_us = logging::round(h.quantile(0.999), 1), max_us = logging::round(h.max_ns as f64 / 1000.0, 1), "rate"
+            );
+        } else {
+            eprintln!(
+                "rate offered_lps={:.0} achieved_lps={:.0} latency_us p50={:.1} p90={:.1} p99={:.1} p999={:.1} max={:.1}",
+                rate, achieved, h.quantile(0.5), h.quantile(0.9), h.quantile(0.99), h.quantile(0.999), h.max_ns as f64 / 1000.0
+            );
+        }
+        // Latency grows with the length of the run at saturation, so the percentiles say more about the run
+        // length than about the decoder
+        if achieved < rate * 0.99 { tracing::warn!("decoder saturated, it kept up with {:.0} of {:.0} lines/s", achieved, rate); }
+    }
+}
+
+/// Log-linear histogram of nanosecond latencies: [`SUB_BUCKETS`] linear buckets per power of two.
+#[derive(Default)]
+struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+    max_ns: u64,
+}
+
+impl Histogram {
+    fn record(&mut self, latency: Duration) {
+        let ns = latency.as_nanos().min(u64::MAX as u128) as u64;
+        let i = bucket(ns);
+        if i >= self.counts.len() { self.counts.resize(i + 1, 0); }
+        self.counts[i] += 1;
+        self.total += 1;
+        self.max_ns = self.max_ns.max(ns);
+    }
+
+    /// Upper bound, in µs, of the bucket holding the `q` quantile.
+    fn quantile(&self, q: f64) -> f64 {
+        if self.total == 0 { return 0.0; }
+        let rank = ((q * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank { return upper_bound(i).min(self.max_ns) as f64 / 1000.0; }
+        }
+        self.max_ns as f64 / 1000.0
+    }
+}
+
+fn bucket(ns: u64) -> usize {
+    let sub = SUB_BUCKETS as u64;
+    if ns < sub { return ns as usize; }
+    let shift = 63 - ns.leading_zeros() as u64 - sub.trailing_zeros() as u64;
+    ((shift + 1) * sub + ((ns >> shift) - sub)) as usize
+}
+
+fn upper_bound(i: usize) -> u64 {
+    let sub = SUB_BUCKETS as u64;
+    let i = i as u64;
+    if i < sub { return i; }
+    let shift = i / sub - 1;
+    (((i % sub) + sub + 1) << shift) - 1
+}
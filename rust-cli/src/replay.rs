@@ -0,0 +1,103 @@
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+
+use evm_rust_decoder::decode::{DecodedLog, LogDecoder};
+use evm_rust_decoder::output::{Output, Serializer};
+use evm_rust_decoder::stop;
+
+use crate::logging;
+
+/// Serialization passes over logs decoded once, per serializer.
+pub struct ReplayReport {
+    pub decoded: usize,
+    pub decode: Duration,
+    pub passes: Vec<Passes>,
+}
+
+/// `passes` serializations of every cached log with one serializer.
+pub struct Passes {
+    pub serializer: Serializer,
+    pub bytes: usize,
+    pub times: Vec<Duration>,
+}
+
+/// `--replay N`: decodes the JSONL input once and keeps the decoded logs, then formats all of them `passes` times
+/// with each serializer into a buffer in memory, so the passes time serialization alone. Each serializer's output
+/// is checked against the first one's, since all of them print the same bytes.
+pub fn run<'d>(reader: Box<dyn BufRead + Send>, decoder: &'d LogDecoder, output: &Output, passes: usize) -> Result<ReplayReport> {
+    let start = Instant::now();
+    let mut logs: Vec<DecodedLog<'d>> = Vec::new();
+    for line in reader.lines() {
+        if stop::requested() { break; }
+        let line = line?;
+        let decoded = decoder.parse_line(&line, |raw_log| {
+            let log = decoder.decode(raw_log)?;
+            if output.admit(&log) { logs.push(log); }
+            Ok(())
+        });
+        decoder.skip_error(decoded)?;
+    }
+    let decode = start.elapsed();
+
+    let mut report = ReplayReport { decoded: logs.len(), decode, passes: Vec::new() };
+    let mut reference: Option<Vec<u8>> = None;
+    let mut buf = Vec::new();
+    for &serializer in Serializer::value_variants() {
+        let output = Output { serializer, ..output.clone() };
+        let mut times = Vec::with_capacity(passes);
+        for _ in 0..passes {
+            buf.clear();
+            let t = Instant::now();
+            for log in &logs { output.write(&mut buf, log)?; }
+            times.push(t.elapsed());
+            if stop::requested() { break; }
+        }
+        match &reference {
+            Some(reference) if *reference != buf => bail!("--replay: serializer {} printed different output", name(serializer)),
+            Some(_) => {}
+            None => reference = Some(buf.clone()),
+        }
+        report.passes.push(Passes { serializer, bytes: buf.len(), times });
+        if stop::requested() { break; }
+    }
+    Ok(report)
+}
+
+pub fn name(serializer: Serializer) -> String {
+    serializer.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+}
+
+impl ReplayReport {
+    /// One line per serializer, with its fastest and mean pass.
+    pub fn print(&self) {
+        let json = logging::json();
+        let decode_ms = self.decode.as_secs_f64() * 1000.0;
+        if json {
+            tracing::info!(decoded = self.decoded, decode_ms = logging::round(decode_ms, 3), "replay");
+        } else {
+            eprintln!("replay decoded={} decode_ms={:.3}", self.decoded, decode_ms);
+        }
+        for p in &self.passes {
+            let Some(best) = p.times.iter().min() else { continue };
+            let mean = p.times.iter().sum::<Duration>() / p.times.len() as u32;
+            let secs = best.as_secs_f64();
+            let throughput = if secs > 0.0 { self.decoded as f64 / secs } else { 0.0 };
+            let mb_s = if secs > 0.0 { p.bytes as f64 / secs / 1e6 } else { 0.0 };
+            if json {
+                tracing::info!(
+                    serializer = %name(p.serializer), passes = p.times.len(), bytes = p.bytes, best_ms = logging::round(secs * 1000.0, 3),
+                    mean_ms = logging::round(mean.as_secs_f64() * 1000.0, 3), throughput_lps = throughput.round() as u64,
+                    mb_s = logging::round(mb_s, 1), "replay"
+                );
+                continue;
+            }
+            eprintln!(
+                "replay serializer={} passes={} bytes={} best_ms={:.3} mean_ms={:.3} throughput_lps={:.0} mb_s={:.1}",
+                name(p.serializer), p.times.len(), p.bytes, secs * 1000.0, mean.as_secs_f64() * 1000.0, throughput, mb_s
+            );
+        }
+    }
+}
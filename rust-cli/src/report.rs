@@ -0,0 +1,111 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Layout of a rendered `--report`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// GitHub-flavoured Markdown tables with text bar charts
+    Markdown,
+    /// A standalone HTML page with tables and CSS bar charts
+    Html,
+}
+
+/// One table of the report. `bars` holds one throughput value per row, drawn as a bar scaled to the section maximum.
+pub struct Section {
+    pub title: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub bars: Vec<f64>,
+}
+
+pub struct Report {
+    pub title: String,
+    /// Run parameters listed under the title (ABI, event, preload, ...)
+    pub notes: Vec<String>,
+    pub sections: Vec<Section>,
+}
+
+const MARKDOWN_BAR_WIDTH: usize = 30;
+
+impl Report {
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.markdown(),
+            ReportFormat::Html => self.html(),
+        }
+    }
+
+    /// Writes the rendered report to `path`, or to stdout after the regular output when no path is given.
+    pub fn emit(&self, format: ReportFormat, path: Option<&PathBuf>) -> Result<()> {
+        let rendered = self.render(format);
+        match path {
+            Some(path) => std::fs::write(path, rendered).with_context(|| format!("Cannot write report {:?}", path)),
+            None => { print!("{}", rendered); Ok(()) }
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut s = format!("# {}\n\n", self.title);
+        for note in &self.notes { let _ = writeln!(s, "- {}", note); }
+        for section in &self.sections {
+            let _ = write!(s, "\n## {}\n\n| {} | throughput |\n|", section.title, section.columns.join(" | "));
+            for _ in 0..=section.columns.len() { s.push_str(" --- |"); }
+            s.push('\n');
+            let max = bar_max(&section.bars);
+            for (row, &bar) in section.rows.iter().zip(&section.bars) {
+                let width = if max > 0.0 { (bar / max * MARKDOWN_BAR_WIDTH as f64).round() as usize } else { 0 };
+                let _ = writeln!(s, "| {} | `{}` |", row.join(" | "), "█".repeat(width.max(1)));
+            }
+        }
+        s
+    }
+
+    fn html(&self) -> String {
+        let mut s = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(s, "<title>{}</title>", escape(&self.title));
+        s.push_str(concat!(
+            "<style>\n",
+            "body { font-family: sans-serif; margin: 2em; }\n",
+            "table { border-collapse: collapse; margin-bottom: 2em; }\n",
+            "th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }\n",
+            "th:first-child, td:first-child { text-align: left; }\n",
+            "td.bar { width: 320px; text-align: left; }\n",
+            "td.bar div { background: #4a7bd0; height: 14px; }\n",
+            "</style>\n</head>\n<body>\n",
+        ));
+        let _ = writeln!(s, "<h1>{}</h1>", escape(&self.title));
+        if !self.notes.is_empty() {
+            s.push_str("<ul>\n");
+            for note in &self.notes { let _ = writeln!(s, "<li>{}</li>", escape(note)); }
+            s.push_str("</ul>\n");
+        }
+        for section in &self.sections {
+            let _ = writeln!(s, "<h2>{}</h2>\n<table>\n<tr>", escape(&section.title));
+            for c in &section.columns { let _ = write!(s, "<th>{}</th>", escape(c)); }
+            s.push_str("<th>throughput</th></tr>\n");
+            let max = bar_max(&section.bars);
+            for (row, &bar) in section.rows.iter().zip(&section.bars) {
+                s.push_str("<tr>");
+                for cell in row { let _ = write!(s, "<td>{}</td>", escape(cell)); }
+                let pct = if max > 0.0 { bar / max * 100.0 } else { 0.0 };
+                let _ = writeln!(s, "<td class=\"bar\"><div style=\"width: {:.1}%\"></div></td></tr>", pct);
+            }
+            s.push_str("</table>\n");
+        }
+        s.push_str("</body>\n</html>\n");
+        s
+    }
+}
+
+fn bar_max(bars: &[f64]) -> f64 {
+    bars.iter().copied().fold(0.0, f64::max)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const DEFAULT_STORE: &str = "results/history.jsonl";
+const TREND_BAR_WIDTH: f64 = 40.0;
+
+#[derive(Args, Debug)]
+pub struct ResultsArgs {
+    #[command(subcommand)]
+    command: ResultsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum ResultsCommand {
+    /// Append the results of a `bench-all --json` file to the store
+    Record(RecordArgs),
+    /// Print throughput over time per implementation, dataset and machine, flagging drifts
+    Trend(TrendArgs),
+}
+
+#[derive(Args, Debug)]
+struct RecordArgs {
+    /// JSON written by `bench-all --json`
+    from: PathBuf,
+
+    /// JSONL store to append to
+    #[arg(long, default_value = DEFAULT_STORE)]
+    store: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct TrendArgs {
+    /// JSONL store to read
+    #[arg(long, default_value = DEFAULT_STORE)]
+    store: PathBuf,
+
+    /// Only show this implementation (e.g. rust_cli)
+    #[arg(long = "impl")]
+    implementation: Option<String>,
+
+    /// Only show datasets whose file name or hash prefix matches
+    #[arg(long)]
+    dataset: Option<String>,
+
+    /// Only show results recorded on this machine
+    #[arg(long, default_value_t = false)]
+    this_machine: bool,
+
+    /// Number of earlier runs the latest run is compared against (their median)
+    #[arg(long, default_value_t = 5)]
+    window: usize,
+
+    /// Throughput drop, in percent, reported as a drift
+    #[arg(long, default_value_t = 5.0)]
+    threshold: f64,
+
+    /// Exit non-zero when any series drifted
+    #[arg(long, default_value_t = false)]
+    fail_on_drift: bool,
+}
+
+/// One stored result: a single implementation on a single dataset, as one JSONL line.
+#[derive(Serialize, Deserialize, Debug)]
+struct StoredResult {
+    timestamp: u64,
+    git_sha: String,
+    git_dirty: bool,
+    machine: Machine,
+    dataset: Dataset,
+    #[serde(rename = "impl")]
+    implementation: String,
+    event: Option<String>,
+    preload: bool,
+    decoded: u64,
+    median_ms: f64,
+    throughput_lps: f64,
+    /// JS runtime of the harness (`bun/1.2.4`), for implementations that report one
+    #[serde(default)]
+    runtime: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Machine {
+    /// Hash of the fields below; results are only comparable within one fingerprint
+    fingerprint: String,
+    hostname: String,
+    cpu: String,
+    cores: usize,
+    os: String,
+    arch: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Dataset {
+    name: String,
+    sha256: String,
+}
+
+pub fn run(args: &ResultsArgs) -> Result<()> {
+    match &args.command {
+        ResultsCommand::Record(a) => record(a),
+        ResultsCommand::Trend(a) => trend(a),
+    }
+}
+
+fn record(args: &RecordArgs) -> Result<()> {
+    let doc: Value = serde_json::from_reader(File::open(&args.from).with_context(|| format!("Cannot open {:?}", args.from))?)
+        .with_context(|| format!("{:?} is not a bench-all --json file", args.from))?;
+    record_results(&doc, &args.store)
+}
+
+/// Appends the successful results of a `bench-all` JSON document to `store`.
+pub fn record_results(doc: &Value, store_path: &Path) -> Result<()> {
+    let results = doc.get("results").and_then(|r| r.as_array()).context("bench-all JSON has no `results` array")?;
+
+    let (git_sha, git_dirty) = git_state();
+    let machine = machine();
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut datasets: BTreeMap<String, Dataset> = BTreeMap::new();
+
+    if let Some(dir) = store_path.parent().filter(|d| !d.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+    let mut store = OpenOptions::new().create(true).append(true).open(store_path)
+        .with_context(|| format!("Cannot open store {:?}", store_path))?;
+    let mut recorded = 0;
+    for r in results {
+        if r.get("error").is_some() { continue; }
+        let input = r.get("input").and_then(|i| i.as_str()).context("result without `input`")?;
+        if !datasets.contains_key(input) { datasets.insert(input.to_string(), dataset(Path::new(input))?); }
+        let stored = StoredResult {
+            timestamp,
+            git_sha: git_sha.clone(),
+            git_dirty,
+            machine: machine.clone(),
+            dataset: datasets[input].clone(),
+            implementation: r.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string(),
+            event: r.get("event").and_then(|e| e.as_str()).map(String::from),
+            preload: doc.get("preload").and_then(|p| p.as_bool()).unwrap_or(false),
+            decoded: r.get("decoded").and_then(|d| d.as_u64()).unwrap_or(0),
+            median_ms: r.get("median_ms").and_then(|m| m.as_f64()).unwrap_or(0.0),
+            throughput_lps: r.get("throughput_lps").and_then(|t| t.as_f64()).unwrap_or(0.0),
+            runtime: r.get("runtime").and_then(|t| t.as_str()).map(String::from),
+        };
+        writeln!(store, "{}", serde_json::to_string(&stored)?)?;
+        recorded += 1;
+    }
+    println!(
+        "results recorded={} store={} git_sha={}{} machine={}",
+        recorded, store_path.display(), short(&git_sha), if git_dirty { "-dirty" } else { "" }, machine.fingerprint
+    );
+    Ok(())
+}
+
+fn trend(args: &TrendArgs) -> Result<()> {
+    let file = File::open(&args.store).with_context(|| format!("Cannot open store {:?}", args.store))?;
+    let this_machine = machine().fingerprint;
+    let mut series: BTreeMap<(String, String, String), Vec<StoredResult>> = BTreeMap::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        let r: StoredResult = serde_json::from_str(&line).with_context(|| format!("{:?} line {}: invalid record", args.store, i + 1))?;
+        if args.implementation.as_ref().is_some_and(|n| *n != r.implementation) { continue; }
+        if args.dataset.as_ref().is_some_and(|d| *d != r.dataset.name && !r.dataset.sha256.starts_with(d.as_str())) { continue; }
+        if args.this_machine && r.machine.fingerprint != this_machine { continue; }
+        let key = (r.implementation.clone(), r.dataset.sha256.clone(), r.machine.fingerprint.clone());
+        series.entry(key).or_default().push(r);
+    }
+    if series.is_empty() { bail!("No matching results in {:?}", args.store); }
+
+    let mut drifted = 0;
+    for runs in series.values_mut() {
+        runs.sort_by_key(|r| r.timestamp);
+        let first = &runs[0];
+        println!(
+            "{} dataset={} ({}) machine={} ({})",
+            first.implementation, first.dataset.name, short(&first.dataset.sha256), first.machine.hostname, first.machine.fingerprint
+        );
+        let max = runs.iter().map(|r| r.throughput_lps).fold(0.0, f64::max);
+        for r in runs.iter() {
+            let width = if max > 0.0 { (r.throughput_lps / max * TREND_BAR_WIDTH).round() as usize } else { 0 };
+            println!(
+                "  {} {:<14} {:>12.0} lps {}",
+                format_timestamp(r.timestamp), format!("{}{}", short(&r.git_sha), if r.git_dirty { "-dirty" } else { "" }),
+                r.throughput_lps, "#".repeat(width)
+            );
+        }
+
+        let (latest, earlier) = runs.split_last().expect("series are never empty");
+        let window = &earlier[earlier.len().saturating_sub(args.window)..];
+        if window.is_empty() { continue; }
+        let mut previous: Vec<f64> = window.iter().map(|r| r.throughput_lps).collect();
+        previous.sort_by(f64::total_cmp);
+        let baseline = previous[previous.len() / 2];
+        let change = if baseline > 0.0 { (latest.throughput_lps / baseline - 1.0) * 100.0 } else { 0.0 };
+        let flag = if change <= -args.threshold { drifted += 1; "  DRIFT" } else { "" };
+        println!("  latest vs median of previous {}: {:+.1}%{}", window.len(), change, flag);
+    }
+
+    if drifted > 0 && args.fail_on_drift { bail!("{} series dropped more than {}%", drifted, args.threshold); }
+    Ok(())
+}
+
+fn git_state() -> (String, bool) {
+    let git = |args: &[&str]| Command::new("git").args(args).output().ok().filter(|o| o.status.success());
+    let sha = git(&["rev-parse", "HEAD"]).map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|o| !o.stdout.is_empty());
+    (sha.unwrap_or_else(|| "unknown".to_string()), dirty)
+}
+
+fn machine() -> Machine {
+    let hostname = std::fs::read_to_string("/etc/hostname").ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default();
+    let cpu = std::fs::read_to_string("/proc/cpuinfo").ok()
+        .and_then(|info| info.lines().find(|l| l.starts_with("model name")).and_then(|l| l.split_once(':')).map(|(_, m)| m.trim().to_string()))
+        .unwrap_or_default();
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let (os, arch) = (std::env::consts::OS.to_string(), std::env::consts::ARCH.to_string());
+    let digest = Sha256::digest(format!("{}|{}|{}|{}|{}", hostname, cpu, cores, os, arch));
+    Machine { fingerprint: hex::encode(&digest[..6]), hostname, cpu, cores, os, arch }
+}
+
+fn dataset(path: &Path) -> Result<Dataset> {
+    let sha256 = crate::dataset::sha256_file(path).with_context(|| format!("Cannot hash dataset {:?}", path))?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    Ok(Dataset { name, sha256 })
+}
+
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(10)]
+}
+
+/// `YYYY-MM-DD HH:MM` in UTC.
+fn format_timestamp(secs: u64) -> String {
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, rem / 3600, rem % 3600 / 60)
+}
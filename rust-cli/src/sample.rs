@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use tracing::warn;
+
+use evm_rust_decoder::decode::LogMeta;
+
+use crate::logging;
+
+/// z for a two-sided 95% interval.
+const Z95: f64 = 1.96;
+/// Fewer sampled logs than this and the normal approximation behind the intervals is poor.
+const FEW_LOGS: u64 = 30;
+
+/// Which lines `--sample` decodes.
+#[derive(Clone, Copy, Debug)]
+pub enum SampleRate {
+    /// Each line independently with this probability, `--sample 1%`
+    Fraction(f64),
+    /// The first of every N lines, `--sample-every N`
+    Every(u64),
+}
+
+impl FromStr for SampleRate {
+    type Err = anyhow::Error;
+
+    /// `1%` or `0.01`.
+    fn from_str(s: &str) -> Result<Self> {
+        let fraction = match s.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+            None => s.parse::<f64>(),
+        };
+        match fraction {
+            Ok(f) if f > 0.0 && f <= 1.0 => Ok(SampleRate::Fraction(f)),
+            _ => bail!("expected a percentage such as 1% or a fraction in (0, 1], got {}", s),
+        }
+    }
+}
+
+/// Picks the input lines to decode and extrapolates what the sampled ones decode to over the whole input, for
+/// `--sample` and `--sample-every`.
+pub struct Sample {
+    rate: SampleRate,
+    /// Input lines seen, sampled or not
+    lines: u64,
+    sampled: u64,
+    logs: Tally,
+    events: HashMap<String, Tally>,
+    /// Decode time of the sampled lines
+    time: Duration,
+}
+
+/// Logs per sampled line: their sum and sum of squares, for the variance of the extrapolated total.
+#[derive(Default)]
+struct Tally {
+    sum: u64,
+    squares: u64,
+}
+
+impl Tally {
+    fn add(&mut self, n: u64) {
+        self.sum += n;
+        self.squares += n * n;
+    }
+}
+
+impl Sample {
+    pub fn new(rate: SampleRate) -> Sample {
+        Sample { rate, lines: 0, sampled: 0, logs: Tally::default(), events: HashMap::new(), time: Duration::ZERO }
+    }
+
+    /// Counts the next input line and says whether to decode it. `--sample` hashes the line number, so a rerun
+    /// over the same input decodes the same lines.
+    pub fn take(&mut self) -> bool {
+        let line = self.lines;
+        self.lines += 1;
+        let take = match self.rate {
+            SampleRate::Every(n) => line.is_multiple_of(n),
+            SampleRate::Fraction(f) => f >= 1.0 || (splitmix64(line) as f64) < f * u64::MAX as f64,
+        };
+        if take { self.sampled += 1; }
+        take
+    }
+
+    /// Records the logs decoded from one sampled line, as (metadata, event name), and the line's decode time.
+    pub fn record_line(&mut self, logs: &[(LogMeta, String)], time: Duration) {
+        self.logs.add(logs.len() as u64);
+        self.time += time;
+        let mut line_events: HashMap<&str, u64> = HashMap::new();
+        for (_, event) in logs { *line_events.entry(event).or_default() += 1; }
+        for (event, n) in line_events {
+            match self.events.get_mut(event) {
+                Some(tally) => tally.add(n),
+                None => { self.events.entry(event.to_string()).or_default().add(n); }
+            }
+        }
+    }
+
+    /// The whole input's total of what `tally` counts, and the half-width of its 95% interval: the sampled lines'
+    /// mean scaled to every line, with the variance of a simple random sample of lines.
+    fn estimate(&self, tally: &Tally) -> (f64, f64) {
+        let (n, total) = (self.sampled as f64, self.lines as f64);
+        if self.sampled == 0 { return (0.0, 0.0); }
+        let mean = tally.sum as f64 / n;
+        let variance = if self.sampled > 1 { (tally.squares as f64 - n * mean * mean).max(0.0) / (n - 1.0) } else { 0.0 };
+        let half_width = Z95 * total * (variance / n * (1.0 - n / total)).sqrt();
+        (mean * total, half_width)
+    }
+
+    /// Prints the sampled counts, their extrapolation to the whole input and 95% intervals, then one row per event,
+    /// most frequent first.
+    pub fn print(&self) {
+        let rate = match self.rate {
+            SampleRate::Fraction(f) => format!("{}%", f * 100.0),
+            SampleRate::Every(n) => format!("1/{}", n),
+        };
+        let scale = if self.sampled > 0 { self.lines as f64 / self.sampled as f64 } else { 0.0 };
+        let (logs, logs_ci) = self.estimate(&self.logs);
+        let json = logging::json();
+        let decode_ms = self.time.as_secs_f64() * 1000.0;
+        if json {
+            tracing::info!(
+                rate = %rate, lines = self.lines, sampled = self.sampled, decoded = self.logs.sum, estimated_logs = logs.round() as u64,
+                ci95 = logs_ci.round() as u64, decode_ms = logging::round(decode_ms, 3),
+                estimated_decode_ms = logging::round(decode_ms * scale, 3), "sample"
+            );
+        } else {
+            eprintln!(
+                "sample rate={} lines={} sampled={} decoded={} estimated_logs={:.0} ci95={:.0} decode_ms={:.3} estimated_decode_ms={:.3}",
+                rate, self.lines, self.sampled, self.logs.sum, logs, logs_ci, decode_ms, decode_ms * scale
+            );
+        }
+        let mut events: Vec<(&String, &Tally)> = self.events.iter().collect();
+        events.sort_by(|a, b| b.1.sum.cmp(&a.1.sum).then(a.0.cmp(b.0)));
+        for (event, tally) in events {
+            let (estimated, ci) = self.estimate(tally);
+            if json {
+                tracing::info!(event = %event, decoded = tally.sum, estimated = estimated.round() as u64, ci95 = ci.round() as u64, "sample");
+                continue;
+            }
+            eprintln!("sample event={} decoded={} estimated={:.0} ci95={:.0}", event, tally.sum, estimated, ci);
+        }
+        if self.logs.sum < FEW_LOGS {
+            warn!(decoded = self.logs.sum, "few sampled logs: the estimates are rough and their intervals unreliable");
+        }
+    }
+}
+
+/// An evenly distributed hash of `x` (the SplitMix64 finalizer).
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
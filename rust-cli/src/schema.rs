@@ -0,0 +1,175 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use ethabi::{Event, ParamType};
+use serde_json::{json, Map, Value};
+
+use evm_rust_decoder::abi::{decoded_kind, describe, AbiSet};
+use evm_rust_decoder::output::ENVELOPE_SCHEMA_VERSION;
+use evm_rust_decoder::presets::Preset;
+
+use crate::sink::{self, Dialect};
+
+/// Kind of schema `schema` prints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SchemaFormat {
+    /// `CREATE TABLE` statements, as `--sink` creates them
+    Sql,
+    /// Arrow schemas in the JSON form of the Arrow integration tests, one per event
+    Arrow,
+    /// A JSON Schema (draft 2020-12) of the `--print` output lines
+    Jsonschema,
+}
+
+#[derive(Args, Debug)]
+pub struct SchemaArgs {
+    /// ABI files (JSON, solc standard-json or `.sol`), as for decoding
+    #[arg(long, required_unless_present = "preset")]
+    abi: Vec<PathBuf>,
+
+    /// Built-in event sets, after the --abi events
+    #[arg(long, value_enum, value_name = "NAME")]
+    preset: Vec<Preset>,
+
+    /// With solc standard-json ABIs, only this contract's events
+    #[arg(long, value_name = "NAME")]
+    contract: Option<String>,
+
+    /// Only this event
+    #[arg(long)]
+    event: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = SchemaFormat::Sql)]
+    format: SchemaFormat,
+
+    /// SQL flavour of --format sql
+    #[arg(long, value_enum, default_value_t = Dialect::Postgres)]
+    dialect: Dialect,
+
+    /// Describe `--output-envelope` records instead of bare args (arrow and jsonschema)
+    #[arg(long, default_value_t = false)]
+    envelope: bool,
+}
+
+/// Prints the schema of each event's decoded output, so sinks and consumers can be provisioned to match it. Tables
+/// and definitions are named as `--sink` names its tables.
+pub fn run(args: &SchemaArgs) -> Result<()> {
+    let abis = AbiSet::load(&args.abi, &args.preset, None, args.contract.as_deref())?;
+    let mut events: Vec<Event> = abis.events.into_iter().map(|(_, e)| e).collect();
+    if let Some(name) = &args.event {
+        events.retain(|e| &e.name == name);
+        if events.is_empty() { bail!("Event '{}' not found in ABI", name); }
+    }
+
+    match args.format {
+        SchemaFormat::Sql => {
+            for statement in sink::create_statements(&events, args.dialect) { println!("{};", statement); }
+        }
+        SchemaFormat::Arrow => {
+            let schemas: Map<String, Value> = sink::table_names(&events).into_iter()
+                .map(|(name, event)| (name, arrow_schema(event, args.envelope)))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&schemas)?);
+        }
+        SchemaFormat::Jsonschema => {
+            let tables = sink::table_names(&events);
+            let defs: Map<String, Value> = tables.iter().map(|(name, event)| (name.clone(), json_schema(event, args.envelope))).collect();
+            let schema = json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "Decoded logs",
+                "anyOf": tables.iter().map(|(name, _)| json!({ "$ref": format!("#/$defs/{}", name) })).collect::<Vec<_>>(),
+                "$defs": defs,
+            });
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+    }
+    Ok(())
+}
+
+/// The output key of each parameter, as `log_to_json` names it, with the type it decodes to.
+fn arg_names(event: &Event) -> impl Iterator<Item = (String, ParamType)> + '_ {
+    event.inputs.iter().enumerate().map(|(i, p)| (if p.name.is_empty() { format!("arg{}", i) } else { p.name.clone() }, decoded_kind(p)))
+}
+
+fn arrow_schema(event: &Event, envelope: bool) -> Value {
+    let args: Vec<Value> = arg_names(event).map(|(name, kind)| arrow_field(&name, &kind, false)).collect();
+    let fields = if envelope {
+        vec![
+            json!({ "name": "event", "type": { "name": "utf8" }, "nullable": false, "children": [] }),
+            json!({ "name": "address", "type": { "name": "utf8" }, "nullable": true, "children": [] }),
+            json!({ "name": "blockNumber", "type": { "name": "int", "bitWidth": 64, "isSigned": false }, "nullable": true, "children": [] }),
+            json!({ "name": "chainId", "type": { "name": "int", "bitWidth": 64, "isSigned": false }, "nullable": true, "children": [] }),
+            json!({ "name": "l1BlockNumber", "type": { "name": "int", "bitWidth": 64, "isSigned": false }, "nullable": true, "children": [] }),
+            json!({ "name": "l1TxOrigin", "type": { "name": "utf8" }, "nullable": true, "children": [] }),
+            json!({ "name": "args", "type": { "name": "struct" }, "nullable": false, "children": args }),
+            json!({ "name": "schemaVersion", "type": { "name": "int", "bitWidth": 32, "isSigned": false }, "nullable": false, "children": [] }),
+        ]
+    } else {
+        args
+    };
+    json!({ "fields": fields, "metadata": [{ "key": "signature", "value": describe(event) }] })
+}
+
+/// Integers up to 64 bits keep their width; wider ones and addresses, bytes and strings are `utf8`, as printed.
+fn arrow_field(name: &str, kind: &ParamType, nullable: bool) -> Value {
+    let (ty, children) = match kind {
+        ParamType::Uint(bits) | ParamType::Int(bits) if *bits <= 64 => {
+            (json!({ "name": "int", "bitWidth": bits.next_power_of_two().max(8), "isSigned": matches!(kind, ParamType::Int(_)) }), vec![])
+        }
+        ParamType::Bool => (json!({ "name": "bool" }), vec![]),
+        ParamType::Array(inner) => (json!({ "name": "list" }), vec![arrow_field("item", inner, false)]),
+        ParamType::FixedArray(inner, len) => (json!({ "name": "fixedsizelist", "listSize": len }), vec![arrow_field("item", inner, false)]),
+        ParamType::Tuple(members) => {
+            (json!({ "name": "struct" }), members.iter().enumerate().map(|(i, m)| arrow_field(&i.to_string(), m, false)).collect())
+        }
+        _ => (json!({ "name": "utf8" }), vec![]),
+    };
+    json!({ "name": name, "type": ty, "nullable": nullable, "children": children })
+}
+
+fn json_schema(event: &Event, envelope: bool) -> Value {
+    let properties: Map<String, Value> = arg_names(event).map(|(name, kind)| (name, json_type(&kind))).collect();
+    let required: Vec<&String> = properties.keys().collect();
+    let args = json!({ "type": "object", "properties": properties, "required": required, "additionalProperties": false });
+    if !envelope {
+        let mut args = args;
+        args["description"] = json!(describe(event));
+        return args;
+    }
+    json!({
+        "description": describe(event),
+        "type": "object",
+        "properties": {
+            "event": { "const": event.name },
+            "address": { "type": ["string", "null"], "pattern": "^0x[0-9a-fA-F]{40}$" },
+            "blockNumber": { "type": ["integer", "null"], "minimum": 0 },
+            "chainId": { "type": "integer", "minimum": 1 },
+            "l1BlockNumber": { "type": "integer", "minimum": 0 },
+            "l1TxOrigin": { "type": "string", "pattern": "^0x[0-9a-fA-F]{40}$" },
+            "args": args,
+            "schemaVersion": { "const": ENVELOPE_SCHEMA_VERSION },
+        },
+        "required": ["event", "address", "blockNumber", "args", "schemaVersion"],
+    })
+}
+
+/// The JSON printed for a value of `kind` (see `token_to_json`).
+fn json_type(kind: &ParamType) -> Value {
+    match kind {
+        ParamType::Address => json!({ "type": "string", "pattern": "^0x[0-9a-f]{40}$" }),
+        ParamType::Uint(_) => json!({ "type": "string", "pattern": "^[0-9]+$" }),
+        ParamType::Int(_) => json!({ "type": "string", "pattern": "^-?[0-9]+$" }),
+        ParamType::Bool => json!({ "type": "boolean" }),
+        ParamType::FixedBytes(n) => json!({ "type": "string", "pattern": format!("^0x[0-9a-f]{{{}}}$", n * 2) }),
+        ParamType::Bytes => json!({ "type": "string", "pattern": "^0x([0-9a-f]{2})*$" }),
+        ParamType::String => json!({ "type": "string" }),
+        ParamType::Array(inner) => json!({ "type": "array", "items": json_type(inner) }),
+        ParamType::FixedArray(inner, len) => json!({ "type": "array", "items": json_type(inner), "minItems": len, "maxItems": len }),
+        ParamType::Tuple(members) => json!({
+            "type": "array",
+            "prefixItems": members.iter().map(json_type).collect::<Vec<_>>(),
+            "items": false,
+        }),
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+
+use evm_rust_decoder::abi::load_functions;
+use evm_rust_decoder::decode::snippet;
+use evm_rust_decoder::presets::Preset;
+
+use crate::logging;
+use crate::modes::{run_lines, LineDecoder, LineOptions};
+
+/// One histogram entry, as printed. Fields are in sorted order.
+#[derive(Serialize)]
+struct SelectorCount<'a> {
+    count: u64,
+    selector: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<&'a str>,
+}
+
+#[derive(Default)]
+struct Counts {
+    txs: usize,
+    resolved: usize,
+    /// Creations, whose input is init code
+    creations: usize,
+    /// Calls with less than a selector of input (plain value transfers)
+    no_selector: usize,
+}
+
+struct Histogram {
+    signatures: HashMap<[u8; 4], String>,
+    counts: HashMap<[u8; 4], u64>,
+    totals: Counts,
+}
+
+/// Counts the 4-byte selectors of transaction `input`s instead of decoding logs, with each selector resolved to
+/// a signature from the functions of `abi` and `presets` or from `signature_dbs`, and reports transactions per
+/// second. `--print` writes the whole histogram, most frequent first; the `top` selectors follow the summary.
+pub fn run(abi: &[PathBuf], presets: &[Preset], signature_dbs: &[PathBuf], top: usize, options: &LineOptions) -> Result<()> {
+    let mut signatures = HashMap::new();
+    for function in load_functions(abi)?.into_iter().chain(presets.iter().flat_map(|p| p.functions())) {
+        // `signature()` appends `:(outputs)` to functions that return something
+        let signature = function.signature();
+        let signature = signature.split_once(':').map_or(signature.as_str(), |(inputs, _)| inputs).to_string();
+        signatures.entry(function.short_signature()).or_insert(signature);
+    }
+    for path in signature_dbs {
+        let text = fs::read_to_string(path).with_context(|| format!("Cannot open signature database {:?}", path))?;
+        load_signatures(&text, &mut signatures).with_context(|| format!("Invalid signature database {:?}", path))?;
+    }
+    let mut histogram = Histogram { signatures, counts: HashMap::new(), totals: Counts::default() };
+    let run = run_lines(&LineOptions { print: false, ..*options }, &mut histogram)?;
+
+    let mut entries: Vec<([u8; 4], u64)> = histogram.counts.iter().map(|(s, &c)| (*s, c)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    if options.print {
+        let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+        for (selector, count) in &entries {
+            let entry = SelectorCount { count: *count, selector: format!("0x{}", hex::encode(selector)), signature: histogram.signatures.get(selector).map(String::as_str) };
+            serde_json::to_writer(&mut out, &entry)?;
+            std::io::Write::write_all(&mut out, b"\n")?;
+        }
+        std::io::Write::flush(&mut out)?;
+    }
+    let c = &histogram.totals;
+    if logging::json() {
+        tracing::info!(
+            txs = c.txs, selectors = entries.len(), resolved = c.resolved, unresolved = c.txs - c.resolved - c.creations - c.no_selector,
+            creations = c.creations, no_selector = c.no_selector, signatures = histogram.signatures.len(), errors = run.errors,
+            elapsed_ms = logging::round(run.elapsed_ms(), 3), throughput_tps = run.per_sec(c.txs).round() as u64,
+            mb_per_s = logging::round(run.mb_per_s(), 1), "selectors"
+        );
+        for (selector, count) in entries.iter().take(top) {
+            let signature = histogram.signatures.get(selector).map(String::as_str);
+            tracing::info!(count, selector = %format!("0x{}", hex::encode(selector)), signature, "selector");
+        }
+        return Ok(());
+    }
+    eprintln!(
+        "selectors txs={} selectors={} resolved={} unresolved={} creations={} no_selector={} signatures={} errors={} elapsed_ms={:.3} throughput_tps={:.0} mb_per_s={:.1}",
+        c.txs, entries.len(), c.resolved, c.txs - c.resolved - c.creations - c.no_selector, c.creations, c.no_selector, histogram.signatures.len(), run.errors,
+        run.elapsed_ms(), run.per_sec(c.txs), run.mb_per_s()
+    );
+    for (selector, count) in entries.iter().take(top) {
+        eprintln!("  selector {:>10}  0x{}  {}", count, hex::encode(selector), histogram.signatures.get(selector).map_or("?", String::as_str));
+    }
+    Ok(())
+}
+
+impl LineDecoder for Histogram {
+    fn decode_line(&mut self, line: &str, _printed: &mut Vec<u8>) -> Result<()> {
+        let value: Value = serde_json::from_str(line).context("invalid JSON")?;
+        if let Some(error) = value.get("error").filter(|_| value.get("jsonrpc").is_some()) { bail!("JSON-RPC error: {}", error); }
+        let result = value.get("result").unwrap_or(&value);
+        match result.get("transactions").unwrap_or(result) {
+            Value::Array(txs) => for tx in txs { self.add(tx)?; },
+            tx => self.add(tx)?,
+        }
+        Ok(())
+    }
+}
+
+impl Histogram {
+    fn add(&mut self, tx: &Value) -> Result<()> {
+        let tx = tx.as_object().ok_or_else(|| anyhow!("expected a transaction object, got {} (blocks need full transactions)", snippet(&tx.to_string())))?;
+        let input = tx.get("input").or_else(|| tx.get("data")).and_then(Value::as_str).ok_or_else(|| anyhow!("transaction without an `input`"))?;
+        self.totals.txs += 1;
+        if tx.get("to").is_some_and(Value::is_null) {
+            self.totals.creations += 1;
+            return Ok(());
+        }
+        let digits = input.strip_prefix("0x").unwrap_or(input);
+        let Some(prefix) = digits.get(..8) else {
+            self.totals.no_selector += 1;
+            return Ok(());
+        };
+        let mut selector = [0u8; 4];
+        hex::decode_to_slice(prefix, &mut selector).with_context(|| format!("Invalid input hex: {}", snippet(input)))?;
+        *self.counts.entry(selector).or_default() += 1;
+        if self.signatures.contains_key(&selector) { self.totals.resolved += 1; }
+        Ok(())
+    }
+}
+
+/// Adds the entries of a signature database that no ABI function took: a JSON object of selectors to a signature
+/// or a list of them (the first wins), or lines of `0xSELECTOR signature` or of bare signatures.
+fn load_signatures(text: &str, signatures: &mut HashMap<[u8; 4], String>) -> Result<()> {
+    if text.trim_start().starts_with('{') {
+        let db: HashMap<String, Value> = serde_json::from_str(text).context("invalid JSON")?;
+        for (selector, signature) in db {
+            let signature = match &signature {
+                Value::String(s) => s.as_str(),
+                Value::Array(items) => match items.first().and_then(Value::as_str) {
+                    Some(s) => s,
+                    None => continue,
+                },
+                _ => bail!("selector {} maps to {}, not a signature", selector, snippet(&signature.to_string())),
+            };
+            signatures.entry(parse_selector(&selector)?).or_insert_with(|| signature.to_string());
+        }
+        return Ok(());
+    }
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let (selector, signature) = match line.split_once(char::is_whitespace) {
+            Some((selector, signature)) => (parse_selector(selector).with_context(|| format!("line {}", i + 1))?, signature.trim()),
+            None => (Keccak256::digest(line.as_bytes())[..4].try_into().expect("4 bytes"), line),
+        };
+        signatures.entry(selector).or_insert_with(|| signature.to_string());
+    }
+    Ok(())
+}
+
+fn parse_selector(s: &str) -> Result<[u8; 4]> {
+    let mut selector = [0u8; 4];
+    hex::decode_to_slice(s.strip_prefix("0x").unwrap_or(s), &mut selector).map_err(|_| anyhow!("invalid selector {:?}", s))?;
+    Ok(selector)
+}
@@ -0,0 +1,62 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use evm_rust_decoder::abi::load_event;
+use evm_rust_decoder::decode::{InputSchema, LogDecoder};
+use evm_rust_decoder::output::{Output, Serializer};
+
+/// Runs every fixture directory under `dir`. A fixture holds `abi.json`, `input.jsonl`, `expected.jsonl`
+/// (enveloped output) and optionally `event.txt` with the event name to select; without it events are routed by topic0.
+pub fn run(dir: &Path) -> Result<()> {
+    let mut cases: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Cannot read fixtures directory {:?}", dir))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .collect();
+    cases.sort();
+    if cases.is_empty() { bail!("No fixtures found in {:?}", dir); }
+
+    let mut failed = 0;
+    for case in &cases {
+        let name = case.file_name().unwrap_or_default().to_string_lossy();
+        match run_case(case) {
+            Ok(n) => println!("ok   {} ({} logs)", name, n),
+            Err(e) => { failed += 1; println!("FAIL {}: {:#}", name, e); }
+        }
+    }
+    println!("self-test cases={} passed={} failed={}", cases.len(), cases.len() - failed, failed);
+    if failed > 0 { bail!("{} of {} self-test fixtures failed", failed, cases.len()); }
+    Ok(())
+}
+
+fn run_case(dir: &Path) -> Result<usize> {
+    let event = fs::read_to_string(dir.join("event.txt")).ok().map(|s| s.trim().to_string());
+    let (selected_event, all_events) = load_event(&dir.join("abi.json"), event.as_deref().unwrap_or(""))?;
+    let decoder = LogDecoder::new(selected_event, &all_events, event.is_none()).with_input_schema(InputSchema::Auto);
+    let output = Output { print: true, envelope: true, order: None, enrich: None, serializer: Serializer::Serde, filter: None, select: None, aggregate: None, topk: Vec::new() };
+
+    let input = File::open(dir.join("input.jsonl")).context("Cannot open input.jsonl")?;
+    let mut actual = Vec::new();
+    crate::run_sequential(Box::new(BufReader::new(input)), &decoder, &output, &mut actual, None, Default::default())?;
+
+    let expected = fs::read_to_string(dir.join("expected.jsonl")).context("Cannot read expected.jsonl")?;
+    let expected: Vec<&str> = expected.lines().filter(|l| !l.trim().is_empty()).collect();
+    let actual = String::from_utf8(actual)?;
+    let actual: Vec<&str> = actual.lines().collect();
+
+    for (i, (e, a)) in expected.iter().zip(&actual).enumerate() {
+        let ev: Value = serde_json::from_str(e).with_context(|| format!("expected.jsonl line {} is not JSON", i + 1))?;
+        let av: Value = serde_json::from_str(a)?;
+        if ev != av {
+            return Err(anyhow!("record {} differs\n  expected: {}\n  actual:   {}", i + 1, ev, av));
+        }
+    }
+    if expected.len() != actual.len() {
+        bail!("expected {} records, decoded {}", expected.len(), actual.len());
+    }
+    Ok(actual.len())
+}
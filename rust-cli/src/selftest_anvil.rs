@@ -0,0 +1,270 @@
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use ethabi::{Event, Token};
+use ethereum_types::{H160, U256};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+
+use evm_rust_decoder::abi::events_from_json;
+use evm_rust_decoder::decode::{InputSchema, LogDecoder};
+use evm_rust_decoder::output::{Output, Serializer};
+
+/// How long a started node gets to answer its first request, and a sent transaction to be mined.
+const NODE_TIMEOUT: Duration = Duration::from_secs(15);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Gas limit of the deployment; its constructor only copies code to memory and emits a few logs.
+const DEPLOY_GAS: &str = "0x989680";
+
+#[derive(Args, Debug)]
+pub struct SelftestAnvilArgs {
+    /// JSON-RPC endpoint of a running Anvil or Hardhat node with unlocked accounts. Without it, `--anvil` is started.
+    #[arg(long)]
+    rpc: Option<String>,
+
+    /// Anvil binary started when --rpc is not given
+    #[arg(long, default_value = "anvil")]
+    anvil: PathBuf,
+
+    /// Port the started Anvil listens on
+    #[arg(long, default_value_t = 18545)]
+    port: u16,
+}
+
+/// Events covering every parameter type the ABI loader supports, indexed and not.
+const TEST_ABI: &str = r#"[
+  {"type":"event","name":"Scalars","inputs":[
+    {"name":"from","type":"address","indexed":true},{"name":"amount","type":"uint256","indexed":true},
+    {"name":"flag","type":"bool","indexed":false},{"name":"delta","type":"int256","indexed":false},
+    {"name":"small","type":"uint8","indexed":false},{"name":"tick","type":"int24","indexed":false}]},
+  {"type":"event","name":"Fixed","inputs":[
+    {"name":"id","type":"bytes32","indexed":true},{"name":"on","type":"bool","indexed":true},
+    {"name":"selector","type":"bytes4","indexed":false},{"name":"digest","type":"bytes32","indexed":false},
+    {"name":"pair","type":"address[2]","indexed":false},{"name":"amounts","type":"uint256[3]","indexed":false}]},
+  {"type":"event","name":"Dynamic","inputs":[
+    {"name":"tag","type":"string","indexed":true},{"name":"blob","type":"bytes","indexed":true},
+    {"name":"text","type":"string","indexed":false},{"name":"data","type":"bytes","indexed":false},
+    {"name":"values","type":"uint256[]","indexed":false}]},
+  {"type":"event","name":"Nested","inputs":[
+    {"name":"names","type":"string[]","indexed":false},{"name":"pairs","type":"bytes32[2][]","indexed":false},
+    {"name":"deltas","type":"int256[]","indexed":false}]},
+  {"type":"event","name":"Empty","inputs":[]}
+]"#;
+
+/// Deploys a contract whose constructor emits one log of each test event, fetches the logs back with
+/// `eth_getLogs` and checks that decoding them yields the values that were encoded.
+pub fn run(args: &SelftestAnvilArgs) -> Result<()> {
+    let events = events_from_json(&serde_json::from_str(TEST_ABI)?)?;
+    let cases: Vec<(Event, Vec<Token>)> = events.iter().map(|e| (e.clone(), sample_values(&e.name))).collect();
+
+    let _node;
+    let rpc = match &args.rpc {
+        Some(rpc) => rpc.clone(),
+        None => {
+            let node = Node::start(&args.anvil, args.port)?;
+            let rpc = node.url.clone();
+            _node = node;
+            rpc
+        }
+    };
+    let chain_id = wait_for_node(&rpc)?;
+    let accounts = rpc_call(&rpc, "eth_accounts", json!([]))?;
+    let from = accounts.get(0).and_then(Value::as_str).context("the node has no unlocked account")?;
+
+    let init_code = constructor_emitting(&cases.iter().map(|(event, values)| encode_log(event, values)).collect::<Vec<_>>());
+    let tx = json!({ "from": from, "data": format!("0x{}", hex::encode(init_code)), "gas": DEPLOY_GAS });
+    let tx_hash = rpc_call(&rpc, "eth_sendTransaction", json!([tx]))?;
+    let receipt = wait_for_receipt(&rpc, tx_hash.as_str().context("eth_sendTransaction returned no hash")?)?;
+    if receipt.get("status").and_then(Value::as_str) != Some("0x1") { bail!("deployment reverted: {}", receipt); }
+    let address = receipt.get("contractAddress").and_then(Value::as_str).context("receipt without contractAddress")?;
+    let block = receipt.get("blockNumber").cloned().context("receipt without blockNumber")?;
+    let logs = rpc_call(&rpc, "eth_getLogs", json!([{ "fromBlock": block, "toBlock": block, "address": address }]))?;
+    let logs = logs.as_array().context("eth_getLogs did not return an array")?;
+    println!("node chain_id={} contract={} logs={}", chain_id, address, logs.len());
+
+    let decoder = LogDecoder::new(events[0].clone(), &events, true).with_input_schema(InputSchema::Log);
+    let output = Output { print: true, envelope: true, order: None, enrich: None, serializer: Serializer::Serde, filter: None, select: None, aggregate: None, topk: Vec::new() };
+    let mut failed = 0;
+    for (i, (event, expected)) in cases.iter().enumerate() {
+        match logs.get(i).context("log missing from eth_getLogs").and_then(|log| check_log(&decoder, &output, log, event, expected)) {
+            Ok(()) => println!("ok   {}", event.name),
+            Err(e) => { failed += 1; println!("FAIL {}: {:#}", event.name, e); }
+        }
+    }
+    if logs.len() > cases.len() { failed += 1; println!("FAIL {} unexpected extra logs", logs.len() - cases.len()); }
+    println!("selftest-anvil events={} passed={} failed={}", cases.len(), cases.len().saturating_sub(failed), failed);
+    if failed > 0 { bail!("{} of {} round-trip checks failed", failed, cases.len()); }
+    Ok(())
+}
+
+/// Decodes one fetched log as the CLI would (JSON line in, enveloped JSON out) and compares its parameters.
+fn check_log(decoder: &LogDecoder, output: &Output, log: &Value, event: &Event, expected: &[Token]) -> Result<()> {
+    let mut decoded = Vec::new();
+    let mut rendered = Vec::new();
+    decoder.parse_line(&log.to_string(), |record| {
+        let log = decoder.decode(record)?;
+        output.write(&mut rendered, &log)?;
+        decoded.push((log.event.name.clone(), log.log.params.into_iter().map(|p| p.value).collect::<Vec<Token>>()));
+        Ok(())
+    })?;
+    let [(name, values)] = &decoded[..] else { bail!("decoded {} logs from one log", decoded.len()) };
+    if name != &event.name { bail!("decoded as {}", name); }
+    let expected = expected_tokens(event, expected);
+    for ((param, want), got) in event.inputs.iter().zip(&expected).zip(values) {
+        if want != got { bail!("{} differs\n  expected: {:?}\n  decoded:  {:?}", param.name, want, got); }
+    }
+    if values.len() != expected.len() { bail!("decoded {} parameters, expected {}", values.len(), expected.len()); }
+    serde_json::from_slice::<Value>(&rendered).context("decoded output is not JSON")?;
+    Ok(())
+}
+
+/// Values logged for each test event, in parameter order.
+fn sample_values(event: &str) -> Vec<Token> {
+    let address = |n: u64| Token::Address(H160::from_low_u64_be(n));
+    let uint = |n: u64| Token::Uint(U256::from(n));
+    let neg = |n: u64| Token::Int(U256::MAX - U256::from(n) + 1);
+    let word = |b: u8| Token::FixedBytes(vec![b; 32]);
+    match event {
+        "Scalars" => vec![address(0xdeadbeef), Token::Uint(U256::MAX), Token::Bool(true), neg(12345), uint(255), neg(1)],
+        "Fixed" => vec![
+            word(0xab), Token::Bool(false), Token::FixedBytes(vec![0xa9, 0x05, 0x9c, 0xbb]), word(0x01),
+            Token::FixedArray(vec![address(1), address(2)]), Token::FixedArray(vec![uint(0), uint(1), uint(u64::MAX)]),
+        ],
+        "Dynamic" => vec![
+            Token::String("indexed tag".into()), Token::Bytes(vec![1, 2, 3]), Token::String("unicode ✓ and a string longer than one 32-byte word".into()),
+            Token::Bytes((0..70).collect()), Token::Array(vec![uint(7), Token::Uint(U256::MAX), uint(0)]),
+        ],
+        "Nested" => vec![
+            Token::Array(vec![Token::String("a".into()), Token::String(String::new()), Token::String("ccc".into())]),
+            Token::Array(vec![Token::FixedArray(vec![word(1), word(2)]), Token::FixedArray(vec![word(3), word(4)])]),
+            Token::Array(vec![neg(1), Token::Int(U256::from(5)), neg(1 << 40)]),
+        ],
+        _ => vec![],
+    }
+}
+
+/// What decoding should yield: indexed strings and bytes only survive as the keccak256 hash in their topic.
+fn expected_tokens(event: &Event, values: &[Token]) -> Vec<Token> {
+    event.inputs.iter().zip(values).map(|(param, value)| match value {
+        Token::String(s) if param.indexed => Token::FixedBytes(keccak(s.as_bytes()).to_vec()),
+        Token::Bytes(b) if param.indexed => Token::FixedBytes(keccak(b).to_vec()),
+        value => value.clone(),
+    }).collect()
+}
+
+/// Topics and data of the log Solidity emits for `event` with `values`.
+fn encode_log(event: &Event, values: &[Token]) -> (Vec<[u8; 32]>, Vec<u8>) {
+    let mut topics = vec![event.signature().0];
+    let mut data = Vec::new();
+    for (param, value) in event.inputs.iter().zip(values) {
+        if !param.indexed { data.push(value.clone()); continue; }
+        topics.push(match value {
+            Token::String(s) => keccak(s.as_bytes()),
+            Token::Bytes(b) => keccak(b),
+            value => ethabi::encode(std::slice::from_ref(value)).try_into().expect("static topic is one word"),
+        });
+    }
+    (topics, ethabi::encode(&data))
+}
+
+/// Init code that emits each log and deploys no runtime code. Log data is appended after the code and copied to
+/// memory with CODECOPY before each LOGn.
+fn constructor_emitting(logs: &[(Vec<[u8; 32]>, Vec<u8>)]) -> Vec<u8> {
+    const PUSH1: u8 = 0x60;
+    const PUSH2: u8 = 0x61;
+    const PUSH32: u8 = 0x7f;
+    const CODECOPY: u8 = 0x39;
+    const LOG0: u8 = 0xa0;
+    const STOP: u8 = 0x00;
+    let push2 = |code: &mut Vec<u8>, n: usize| { code.push(PUSH2); code.extend_from_slice(&(n as u16).to_be_bytes()); };
+
+    // Every instruction has a fixed width, so the code length is known before the data offsets
+    let code_len: usize = logs.iter().map(|(topics, _)| 3 + 3 + 2 + 1 + topics.len() * 33 + 3 + 2 + 1).sum::<usize>() + 1;
+    let mut code = Vec::with_capacity(code_len);
+    let mut data_offset = code_len;
+    for (topics, data) in logs {
+        push2(&mut code, data.len());
+        push2(&mut code, data_offset);
+        code.extend_from_slice(&[PUSH1, 0, CODECOPY]);
+        for topic in topics.iter().rev() {
+            code.push(PUSH32);
+            code.extend_from_slice(topic);
+        }
+        push2(&mut code, data.len());
+        code.extend_from_slice(&[PUSH1, 0, LOG0 + topics.len() as u8]);
+        data_offset += data.len();
+    }
+    code.push(STOP);
+    debug_assert_eq!(code.len(), code_len);
+    for (_, data) in logs { code.extend_from_slice(data); }
+    code
+}
+
+fn keccak(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// An Anvil process, killed when dropped.
+struct Node {
+    child: Child,
+    url: String,
+}
+
+impl Node {
+    fn start(anvil: &PathBuf, port: u16) -> Result<Node> {
+        let child = Command::new(anvil)
+            .args(["--port", &port.to_string(), "--silent"])
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Cannot start {:?} (install Foundry, or pass --rpc)", anvil))?;
+        Ok(Node { child, url: format!("http://127.0.0.1:{}", port) })
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn wait_for_node(rpc: &str) -> Result<u64> {
+    let deadline = Instant::now() + NODE_TIMEOUT;
+    loop {
+        match rpc_call(rpc, "eth_chainId", json!([])) {
+            Ok(id) => return parse_quantity(&id),
+            Err(e) if Instant::now() >= deadline => return Err(e.context(format!("node at {} did not answer", rpc))),
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+fn wait_for_receipt(rpc: &str, tx_hash: &str) -> Result<Value> {
+    let deadline = Instant::now() + NODE_TIMEOUT;
+    loop {
+        let receipt = rpc_call(rpc, "eth_getTransactionReceipt", json!([tx_hash]))?;
+        if !receipt.is_null() { return Ok(receipt); }
+        if Instant::now() >= deadline { bail!("transaction {} was not mined (is automine off?)", tx_hash); }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn rpc_call(rpc: &str, method: &str, params: Value) -> Result<Value> {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response = ureq::post(rpc)
+        .set("content-type", "application/json")
+        .send_string(&request.to_string())
+        .with_context(|| format!("{} failed", method))?;
+    let mut body: Value = serde_json::from_str(&response.into_string()?)?;
+    if let Some(error) = body.get("error") { return Err(anyhow!("{}: {}", method, error)); }
+    Ok(body["result"].take())
+}
+
+fn parse_quantity(value: &Value) -> Result<u64> {
+    let hex = value.as_str().and_then(|s| s.strip_prefix("0x")).context("expected a hex quantity")?;
+    u64::from_str_radix(hex, 16).with_context(|| format!("invalid quantity {}", value))
+}
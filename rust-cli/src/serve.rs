@@ -0,0 +1,343 @@
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use evm_rust_decoder::abi::{AbiSet, CollisionPolicy};
+use evm_rust_decoder::core_decode::Limits;
+use evm_rust_decoder::decode::{to_raw_log, InputSchema, LogDecoder, LogFilter, LogLine, LogRecord, OnError};
+use evm_rust_decoder::output::{envelope_json, log_to_json, Output};
+use evm_rust_decoder::topic0_map::MapImpl;
+
+use crate::metrics::Metrics;
+
+/// Largest accepted frame (and `serve-http` body); a bigger length prefix means the stream is out of sync.
+pub const MAX_FRAME_BYTES: usize = 256 << 20;
+
+/// One request frame. `decode` takes raw input lines (any --input-schema shape) and/or log objects.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Request {
+    Decode {
+        #[serde(default)]
+        lines: Vec<String>,
+        #[serde(default)]
+        logs: Vec<LogLine>,
+        /// Return the decoded records, not just the count
+        #[serde(default)]
+        records: bool,
+    },
+    /// Replace the loaded ABI; `event` omitted routes by topic0
+    Reload { abi: PathBuf, event: Option<String> },
+    Stats,
+    Shutdown,
+}
+
+/// Result of one decode batch.
+pub struct DecodeOutcome {
+    pub decoded: u64,
+    /// Logs dropped for taking over the per-log timeout, plus lines and logs left undecoded at the batch timeout
+    pub timed_out: u64,
+    pub elapsed: Duration,
+    pub records: Vec<Value>,
+}
+
+/// Counters reported by the `stats` request.
+pub struct Stats {
+    pub requests: u64,
+    pub decoded: u64,
+    pub errors: u64,
+    pub timed_out: u64,
+    pub decode: Duration,
+    pub uptime: Duration,
+}
+
+/// Decoder settings fixed for the lifetime of the server; `reload` builds its decoder with them too.
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    pub input_schema: InputSchema,
+    pub filter: LogFilter,
+    pub limits: Limits,
+    pub collision_policy: CollisionPolicy,
+    pub map_impl: MapImpl,
+    pub abi_cache: Option<PathBuf>,
+    /// With [`OnError::Skip`], a line or log that does not decode is counted and left out of its batch
+    pub on_error: OnError,
+    pub timeouts: Timeouts,
+    pub output: Output,
+}
+
+/// Time budgets of a decode request. They are checked between logs, so they cannot interrupt a log being decoded:
+/// a log over `per_log` is dropped once it finishes, and a batch stops at the first log after `batch` has passed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timeouts {
+    pub per_log: Option<Duration>,
+    pub batch: Option<Duration>,
+}
+
+impl Timeouts {
+    /// From `--per-log-timeout-us` and `--batch-timeout` (milliseconds).
+    pub fn new(per_log_us: Option<u64>, batch_ms: Option<u64>) -> Timeouts {
+        Timeouts { per_log: per_log_us.map(Duration::from_micros), batch: batch_ms.map(Duration::from_millis) }
+    }
+}
+
+struct Loaded {
+    decoder: LogDecoder,
+    abi: PathBuf,
+    event: Option<String>,
+}
+
+/// A resident decoder shared by all connections, with the counters behind `stats` and `/metrics`.
+pub struct Server {
+    loaded: RwLock<Loaded>,
+    options: ServeOptions,
+    metrics: Arc<Metrics>,
+}
+
+impl Server {
+    pub fn new(abi: PathBuf, event: Option<String>, options: ServeOptions) -> Result<Server> {
+        let loaded = load(abi, event, &options)?;
+        Ok(Server {
+            loaded: RwLock::new(loaded),
+            options,
+            metrics: Arc::new(Metrics::new("request")),
+        })
+    }
+
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Answers one request. Failures become `{"ok":false,"error":...}` so one bad batch does not end the session.
+    pub fn handle(&self, request: Request) -> Value {
+        self.metrics.request();
+        let response = match request {
+            Request::Decode { lines, logs, records } => self.decode_batch(&lines, &logs, records).map(|outcome| {
+                let mut response = json!({
+                    "ok": true,
+                    "decoded": outcome.decoded,
+                    "timed_out": outcome.timed_out,
+                    "elapsed_us": outcome.elapsed.as_secs_f64() * 1e6,
+                });
+                if records { response["records"] = Value::Array(outcome.records); }
+                response
+            }),
+            Request::Reload { abi, event } => self.reload(abi, event),
+            Request::Stats => Ok(self.stats_json()),
+            Request::Shutdown => Ok(json!({ "ok": true })),
+        };
+        response.unwrap_or_else(|e| {
+            self.metrics.error();
+            json!({ "ok": false, "error": format!("{:#}", e) })
+        })
+    }
+
+    /// Counts a request that could not be parsed and returns its error response.
+    pub fn invalid_request(&self, error: impl std::fmt::Display) -> Value {
+        self.metrics.request();
+        self.metrics.error();
+        json!({ "ok": false, "error": format!("Invalid request: {}", error) })
+    }
+
+    /// Decodes `lines` (raw input lines) then `logs` as one counted request; fails on the first log that does not decode
+    /// unless errors are skipped.
+    pub fn decode(&self, lines: &[String], logs: &[LogLine], records: bool) -> Result<DecodeOutcome> {
+        self.metrics.request();
+        let outcome = self.decode_batch(lines, logs, records);
+        if outcome.is_err() { self.metrics.error(); }
+        outcome
+    }
+
+    fn decode_batch(&self, lines: &[String], logs: &[LogLine], records: bool) -> Result<DecodeOutcome> {
+        let loaded = self.loaded.read().map_err(|_| anyhow!("decoder lock poisoned"))?;
+        let decoder = &loaded.decoder;
+        let mut out = Vec::new();
+        let mut total: u64 = 0;
+        let mut timed_out: u64 = 0;
+        let Timeouts { per_log, batch } = self.options.timeouts;
+        let start = Instant::now();
+        let expired = || batch.is_some_and(|batch| start.elapsed() >= batch);
+        let mut keep = |raw: LogRecord| -> Result<()> {
+            if expired() {
+                timed_out += 1;
+                return Ok(());
+            }
+            let t = Instant::now();
+            let log = decoder.decode(raw)?;
+            if per_log.is_some_and(|per_log| t.elapsed() > per_log) {
+                timed_out += 1;
+                return Ok(());
+            }
+            total += 1;
+            self.metrics.decoded(&log.event.name);
+            if records { out.push(if self.options.output.envelope { envelope_json(&log) } else { log_to_json(&log.log) }); }
+            Ok(())
+        };
+
+        // Past the batch deadline, each line or log left is counted once rather than parsed
+        let mut unread = lines.len() + logs.len();
+        for (i, line) in lines.iter().enumerate() {
+            if expired() { break; }
+            unread -= 1;
+            decoder.skip_error(decoder.parse_line(line, &mut keep)).with_context(|| format!("lines[{}]", i))?;
+        }
+        for (i, log) in logs.iter().enumerate() {
+            if expired() { break; }
+            unread -= 1;
+            if !decoder.admit(log) { continue; }
+            let result = to_raw_log(log).and_then(|raw| raw.map_or(Ok(()), &mut keep));
+            decoder.skip_error(result).with_context(|| format!("logs[{}]", i))?;
+        }
+        let elapsed = start.elapsed();
+        let timed_out = timed_out + unread as u64;
+
+        self.metrics.observe(elapsed);
+        self.metrics.timed_out(timed_out);
+        Ok(DecodeOutcome { decoded: total, timed_out, elapsed, records: out })
+    }
+
+    fn reload(&self, abi: PathBuf, event: Option<String>) -> Result<Value> {
+        let loaded = load(abi, event, &self.options)?;
+        *self.loaded.write().map_err(|_| anyhow!("decoder lock poisoned"))? = loaded;
+        Ok(json!({ "ok": true }))
+    }
+
+    pub fn stats(&self) -> Stats {
+        let m = &self.metrics;
+        Stats {
+            requests: m.requests(),
+            decoded: m.decoded_total(),
+            errors: m.errors(),
+            timed_out: m.timed_out_total(),
+            decode: m.decode_time(),
+            uptime: m.uptime(),
+        }
+    }
+
+    fn stats_json(&self) -> Value {
+        let loaded = self.loaded.read().ok();
+        let stats = self.stats();
+        json!({
+            "ok": true,
+            "requests": stats.requests,
+            "decoded": stats.decoded,
+            "errors": stats.errors,
+            "timed_out": stats.timed_out,
+            "decode_ms": stats.decode.as_secs_f64() * 1000.0,
+            "uptime_ms": stats.uptime.as_secs_f64() * 1000.0,
+            "abi": loaded.as_ref().map(|l| l.abi.display().to_string()),
+            "event": loaded.as_ref().and_then(|l| l.event.clone()),
+        })
+    }
+}
+
+/// Builds the decoder the way a decoding run does: topic0 collisions are resolved (or refused) by the collision
+/// policy, and the parsed ABI may come from the cache.
+fn load(abi: PathBuf, event: Option<String>, options: &ServeOptions) -> Result<Loaded> {
+    let abis = AbiSet::load(std::slice::from_ref(&abi), &[], options.abi_cache.as_deref(), None)
+        .with_context(|| format!("Failed to load event(s) from {:?}", abi))?;
+    if event.is_none() { crate::check_collisions(&abis, options.collision_policy)?; }
+    let selected_event = abis.select(event.as_deref().unwrap_or(""))?;
+    let decoder = LogDecoder::from_abi_set(selected_event, &abis, event.is_none(), options.collision_policy)
+        .with_input_schema(options.input_schema)
+        .with_filter(options.filter)
+        .with_limits(options.limits)
+        .with_on_error(options.on_error)
+        .with_map_impl(options.map_impl);
+    Ok(Loaded { decoder, abi, event })
+}
+
+/// Serves requests over stdin/stdout, or over a Unix socket at `socket` with one thread per connection.
+pub fn run(server: Server, socket: Option<PathBuf>) -> Result<()> {
+    match socket {
+        None => {
+            tracing::info!("serve ready on stdio");
+            serve_stream(&server, io::stdin().lock(), io::stdout().lock())?;
+            Ok(())
+        }
+        Some(path) => serve_socket(server, path),
+    }
+}
+
+#[cfg(unix)]
+fn serve_socket(server: Server, path: PathBuf) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a previous run would make bind fail
+    if std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket()) { std::fs::remove_file(&path)?; }
+    let listener = UnixListener::bind(&path).with_context(|| format!("Cannot listen on {:?}", path))?;
+    tracing::info!("serve listening on {}", path.display());
+    std::thread::scope(|s| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => { tracing::warn!("serve accept failed: {}", e); continue; }
+            };
+            let (server, path) = (&server, &path);
+            s.spawn(move || {
+                let reader = match stream.try_clone() {
+                    Ok(reader) => reader,
+                    Err(e) => return tracing::warn!("serve connection failed: {}", e),
+                };
+                match serve_stream(server, reader, stream) {
+                    Ok(true) => {
+                        let _ = std::fs::remove_file(path);
+                        std::process::exit(0);
+                    }
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("serve connection closed: {:#}", e),
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_server: Server, _path: PathBuf) -> Result<()> {
+    anyhow::bail!("--serve-socket needs Unix domain sockets; use --serve over stdio on this platform")
+}
+
+/// Answers frames until the peer closes the stream. Returns true when it asked the server to shut down.
+fn serve_stream(server: &Server, mut reader: impl Read, mut writer: impl Write) -> Result<bool> {
+    while let Some(frame) = read_frame(&mut reader)? {
+        let (response, shutdown) = match serde_json::from_slice::<Request>(&frame) {
+            Ok(request) => {
+                let shutdown = matches!(request, Request::Shutdown);
+                (server.handle(request), shutdown)
+            }
+            Err(e) => (server.invalid_request(e), false),
+        };
+        write_frame(&mut writer, &serde_json::to_vec(&response)?)?;
+        if shutdown { return Ok(true); }
+    }
+    Ok(false)
+}
+
+/// Reads one frame: a big-endian u32 length, then that many bytes of JSON. `None` on a clean end of stream.
+fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_BYTES { return Err(anyhow!("Frame of {} bytes exceeds the {} byte limit", len, MAX_FRAME_BYTES)); }
+    let mut frame = vec![0u8; len];
+    reader.read_exact(&mut frame).context("Stream ended inside a frame")?;
+    Ok(Some(frame))
+}
+
+fn write_frame(writer: &mut impl Write, payload: &[u8]) -> Result<()> {
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::Args;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status, Streaming};
+
+use evm_rust_decoder::abi::CollisionPolicy;
+use evm_rust_decoder::core_decode::Limits;
+use evm_rust_decoder::decode::{InputSchema, LogFilter, LogLine, OnError};
+use evm_rust_decoder::output::{Output, Serializer};
+use evm_rust_decoder::topic0_map::MapImpl;
+
+use crate::serve::{Timeouts, ServeOptions, Server, MAX_FRAME_BYTES};
+
+/// Code generated from `proto/decoder.proto`.
+pub mod proto {
+    tonic::include_proto!("evm.decoder.v1");
+}
+
+use proto::decoder_server::{Decoder, DecoderServer};
+use proto::{DecodeRequest, DecodeResponse, StatsRequest, StatsResponse};
+
+/// Responses a `DecodeStream` call may queue before the server waits for the client to read them.
+const STREAM_BUFFER: usize = 16;
+
+#[derive(Args, Debug)]
+pub struct ServeGrpcArgs {
+    /// Path to ABI JSON file
+    #[arg(long)]
+    abi: PathBuf,
+
+    /// Event to decode; omit to decode every ABI event by topic0
+    #[arg(long)]
+    event: Option<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+
+    /// Shape of each entry of `DecodeRequest.lines`
+    #[arg(long, value_enum, default_value_t = InputSchema::Auto)]
+    input_schema: InputSchema,
+
+    /// Return records as {"event","address","blockNumber","args","schemaVersion":1}
+    #[arg(long, default_value_t = false)]
+    output_envelope: bool,
+
+    #[arg(long, default_value_t = false)]
+    dedupe: bool,
+
+    #[arg(long, default_value_t = false)]
+    drop_removed: bool,
+
+    /// Reject logs whose data is longer than this many bytes, before decoding them
+    #[arg(long, value_name = "BYTES")]
+    max_data_bytes: Option<usize>,
+
+    /// Reject logs with an array of more than N elements anywhere in their data, before decoding them
+    #[arg(long, value_name = "N")]
+    max_array_len: Option<usize>,
+
+    /// Drop a log that takes longer than this many microseconds to decode, counting it as timed out
+    #[arg(long, value_name = "US")]
+    per_log_timeout_us: Option<u64>,
+
+    /// Stop decoding a request after this many milliseconds, counting its remaining lines and logs as timed out
+    #[arg(long, value_name = "MS")]
+    batch_timeout: Option<u64>,
+
+    /// Serve Prometheus metrics on http://ADDR/metrics
+    #[arg(long, value_name = "ADDR")]
+    metrics_listen: Option<SocketAddr>,
+
+    /// Worker threads of the gRPC runtime (default: one per core)
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+struct Service(Arc<Server>);
+
+impl Service {
+    fn decode(&self, request: DecodeRequest) -> Result<DecodeResponse> {
+        let logs: Vec<LogLine> = request.logs.into_iter().map(|log| LogLine {
+            topics: log.topics,
+            data: log.data,
+            address: log.address,
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+            log_index: log.log_index,
+            removed: log.removed,
+            chain_id: None,
+            l1_block_number: None,
+            l1_tx_origin: None,
+        }).collect();
+        let outcome = self.0.decode(&request.lines, &logs, request.records)?;
+        let records = outcome.records.iter().map(|r| r.to_string()).collect();
+        Ok(DecodeResponse { decoded: outcome.decoded, elapsed_us: outcome.elapsed.as_secs_f64() * 1e6, records, timed_out: outcome.timed_out })
+    }
+}
+
+#[tonic::async_trait]
+impl Decoder for Service {
+    async fn decode(&self, request: Request<DecodeRequest>) -> Result<Response<DecodeResponse>, Status> {
+        self.decode(request.into_inner()).map(Response::new).map_err(invalid_argument)
+    }
+
+    type DecodeStreamStream = Pin<Box<dyn Stream<Item = Result<DecodeResponse, Status>> + Send>>;
+
+    async fn decode_stream(&self, request: Request<Streaming<DecodeRequest>>) -> Result<Response<Self::DecodeStreamStream>, Status> {
+        let mut inbound = request.into_inner();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let service = Service(self.0.clone());
+        tokio::spawn(async move {
+            // A failed batch ends the stream with its status, like a failed unary call
+            while let Some(batch) = inbound.message().await.transpose() {
+                let response = match batch {
+                    Ok(batch) => service.decode(batch).map_err(invalid_argument),
+                    Err(status) => Err(status),
+                };
+                let failed = response.is_err();
+                if tx.send(response).await.is_err() || failed { break; }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn stats(&self, _request: Request<StatsRequest>) -> Result<Response<StatsResponse>, Status> {
+        let stats = self.0.stats();
+        Ok(Response::new(StatsResponse {
+            requests: stats.requests,
+            decoded: stats.decoded,
+            errors: stats.errors,
+            decode_ms: stats.decode.as_secs_f64() * 1000.0,
+            uptime_ms: stats.uptime.as_secs_f64() * 1000.0,
+            timed_out: stats.timed_out,
+        }))
+    }
+}
+
+fn invalid_argument(e: anyhow::Error) -> Status {
+    Status::invalid_argument(format!("{:#}", e))
+}
+
+/// Serves the `evm.decoder.v1.Decoder` service of `proto/decoder.proto`.
+pub fn run(args: &ServeGrpcArgs) -> Result<()> {
+    let options = ServeOptions {
+        input_schema: args.input_schema,
+        filter: LogFilter { dedupe: args.dedupe, drop_removed: args.drop_removed },
+        limits: Limits { max_data_bytes: args.max_data_bytes, max_array_len: args.max_array_len },
+        collision_policy: CollisionPolicy::First,
+        map_impl: MapImpl::Std,
+        abi_cache: None,
+        on_error: OnError::Abort,
+        timeouts: Timeouts::new(args.per_log_timeout_us, args.batch_timeout),
+        output: Output { print: true, envelope: args.output_envelope, order: None, enrich: None, serializer: Serializer::Serde, filter: None, select: None, aggregate: None, topk: Vec::new() },
+    };
+    let server = Arc::new(Server::new(args.abi.clone(), args.event.clone(), options)?);
+    if let Some(addr) = args.metrics_listen { crate::metrics::spawn_endpoint(addr, server.metrics())?; }
+    let service = DecoderServer::new(Service(server)).max_decoding_message_size(MAX_FRAME_BYTES).max_encoding_message_size(MAX_FRAME_BYTES);
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(threads) = args.threads { runtime.worker_threads(threads.max(1)); }
+    runtime.enable_all().build()?.block_on(async {
+        tracing::info!("serve-grpc listening on {}", args.listen);
+        tonic::transport::Server::builder()
+            .add_service(service)
+            .serve(args.listen)
+            .await
+            .with_context(|| format!("gRPC server on {} failed", args.listen))
+    })
+}
@@ -0,0 +1,139 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::Args;
+use serde::Deserialize;
+use serde_json::Value;
+
+use evm_rust_decoder::abi::CollisionPolicy;
+use evm_rust_decoder::core_decode::Limits;
+use evm_rust_decoder::decode::{InputSchema, LogFilter, LogLine, OnError};
+use evm_rust_decoder::output::{Output, Serializer};
+use evm_rust_decoder::topic0_map::MapImpl;
+
+use crate::serve::{Timeouts, Request, ServeOptions, Server, MAX_FRAME_BYTES};
+
+#[derive(Args, Debug)]
+pub struct ServeHttpArgs {
+    /// Path to ABI JSON file
+    #[arg(long)]
+    abi: PathBuf,
+
+    /// Event to decode; omit to decode every ABI event by topic0
+    #[arg(long)]
+    event: Option<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// Shape of each line of an `application/x-ndjson` body
+    #[arg(long, value_enum, default_value_t = InputSchema::Auto)]
+    input_schema: InputSchema,
+
+    /// Return records as {"event","address","blockNumber","args","schemaVersion":1}
+    #[arg(long, default_value_t = false)]
+    output_envelope: bool,
+
+    #[arg(long, default_value_t = false)]
+    dedupe: bool,
+
+    #[arg(long, default_value_t = false)]
+    drop_removed: bool,
+
+    /// Reject logs whose data is longer than this many bytes, before decoding them
+    #[arg(long, value_name = "BYTES")]
+    max_data_bytes: Option<usize>,
+
+    /// Reject logs with an array of more than N elements anywhere in their data, before decoding them
+    #[arg(long, value_name = "N")]
+    max_array_len: Option<usize>,
+
+    /// Drop a log that takes longer than this many microseconds to decode, counting it as timed out
+    #[arg(long, value_name = "US")]
+    per_log_timeout_us: Option<u64>,
+
+    /// Stop decoding a request after this many milliseconds, counting its remaining lines and logs as timed out
+    #[arg(long, value_name = "MS")]
+    batch_timeout: Option<u64>,
+
+    /// Worker threads of the HTTP runtime (default: one per core)
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct DecodeQuery {
+    /// `records=false` answers with counts only
+    records: Option<bool>,
+}
+
+/// `POST /decode` takes one log object, a JSON array of logs, or an `application/x-ndjson` body of input lines, and
+/// answers with the same JSON as a `--serve` decode request. `GET /metrics` serves the counters for Prometheus.
+pub fn run(args: &ServeHttpArgs) -> Result<()> {
+    let options = ServeOptions {
+        input_schema: args.input_schema,
+        filter: LogFilter { dedupe: args.dedupe, drop_removed: args.drop_removed },
+        limits: Limits { max_data_bytes: args.max_data_bytes, max_array_len: args.max_array_len },
+        collision_policy: CollisionPolicy::First,
+        map_impl: MapImpl::Std,
+        abi_cache: None,
+        on_error: OnError::Abort,
+        timeouts: Timeouts::new(args.per_log_timeout_us, args.batch_timeout),
+        output: Output { print: true, envelope: args.output_envelope, order: None, enrich: None, serializer: Serializer::Serde, filter: None, select: None, aggregate: None, topk: Vec::new() },
+    };
+    let server = Arc::new(Server::new(args.abi.clone(), args.event.clone(), options)?);
+    let app = Router::new()
+        .route("/decode", post(decode))
+        .route("/metrics", get(metrics))
+        .layer(DefaultBodyLimit::max(MAX_FRAME_BYTES))
+        .with_state(server);
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(threads) = args.threads { runtime.worker_threads(threads.max(1)); }
+    runtime.enable_all().build()?.block_on(async {
+        let listener = tokio::net::TcpListener::bind(args.listen).await.with_context(|| format!("Cannot listen on {}", args.listen))?;
+        tracing::info!("serve-http listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, app).await.context("HTTP server failed")
+    })
+}
+
+async fn decode(State(server): State<Arc<Server>>, Query(query): Query<DecodeQuery>, headers: HeaderMap, body: Bytes) -> Response {
+    let records = query.records.unwrap_or(true);
+    let ndjson = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).is_some_and(|v| v.starts_with("application/x-ndjson"));
+    let request = if ndjson {
+        std::str::from_utf8(&body)
+            .map(|text| Request::Decode { lines: text.lines().map(String::from).collect(), logs: Vec::new(), records })
+            .map_err(|e| e.to_string())
+    } else {
+        parse_logs(&body).map(|logs| Request::Decode { lines: Vec::new(), logs, records }).map_err(|e| e.to_string())
+    };
+    // Decoding is CPU-bound and short; it runs on the runtime's worker thread like any other handler
+    let response = match request {
+        Ok(request) => server.handle(request),
+        Err(e) => server.invalid_request(e),
+    };
+    let status = if response["ok"] == Value::Bool(true) { StatusCode::OK } else { StatusCode::BAD_REQUEST };
+    (status, Json(response)).into_response()
+}
+
+/// A body starting with `[` is a batch; anything else a single log.
+fn parse_logs(body: &[u8]) -> serde_json::Result<Vec<LogLine>> {
+    if body.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[') {
+        serde_json::from_slice(body)
+    } else {
+        serde_json::from_slice(body).map(|log| vec![log])
+    }
+}
+
+async fn metrics(State(server): State<Arc<Server>>) -> impl IntoResponse {
+    crate::metrics::endpoint(&server.metrics())
+}
@@ -0,0 +1,137 @@
+//! Event declarations read straight from Solidity source, for users who have contracts but no compiled ABI.
+//!
+//! This is a scanner, not a compiler: it finds `event Name(...)` declarations anywhere in the file and resolves
+//! their parameter types, including enums (`uint8`), contract and interface types (`address`) and user-defined
+//! value types (`type Price is uint128;`). Events with struct parameters or arrays sized by constants are skipped.
+
+use std::collections::HashMap;
+
+use ethabi::{Event, EventParam};
+
+use crate::abi::parse_param_type;
+
+/// The events declared in `source`, in declaration order, without repeating an event declared twice (e.g. in an
+/// interface and in the contract implementing it).
+pub fn events_from_solidity(source: &str) -> Vec<Event> {
+    let code = strip_comments_and_strings(source);
+    let aliases = type_aliases(&code);
+    let mut events: Vec<Event> = Vec::new();
+    for (name, params, anonymous) in event_declarations(&code) {
+        match parse_params(&params, &aliases) {
+            Ok(inputs) => {
+                let event = Event { name, inputs, anonymous };
+                if !events.contains(&event) { events.push(event); }
+            }
+            Err(ty) => tracing::warn!("skipping event {}: unsupported parameter type `{}`", name, ty),
+        }
+    }
+    events
+}
+
+/// `source` with comments and string literals blanked out, so their contents are never taken for declarations.
+fn strip_comments_and_strings(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &source[i..];
+        let skip = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            comment.find("*/").map(|end| end + 4).unwrap_or(rest.len())
+        } else if rest.starts_with('"') || rest.starts_with('\'') {
+            let quote = bytes[i];
+            let mut end = 1;
+            while end < rest.len() && rest.as_bytes()[end] != quote {
+                end += if rest.as_bytes()[end] == b'\\' { 2 } else { 1 };
+            }
+            (end + 1).min(rest.len())
+        } else {
+            let c = rest.chars().next().unwrap_or(' ');
+            out.push(c);
+            i += c.len_utf8();
+            continue;
+        };
+        out.push(' ');
+        i += skip;
+    }
+    out
+}
+
+/// Canonical types of the names that can stand for an ABI type in event parameters.
+fn type_aliases(code: &str) -> HashMap<String, String> {
+    let words: Vec<&str> = code.split(|c: char| !is_ident_char(c)).filter(|w| !w.is_empty()).collect();
+    let mut aliases = HashMap::new();
+    for pair in words.windows(2) {
+        match pair[0] {
+            "contract" | "interface" | "library" => { aliases.insert(pair[1].to_string(), "address".to_string()); }
+            "enum" => { aliases.insert(pair[1].to_string(), "uint8".to_string()); }
+            // Tuples are not supported; mapping struct names to an unknown type keeps e.g. `internalInfo` from reading as `int256`
+            "struct" => { aliases.insert(pair[1].to_string(), "struct".to_string()); }
+            _ => {}
+        }
+    }
+    // `type Name is underlying;`
+    for (start, _) in code.match_indices("type") {
+        if !at_word(code, start, "type") { continue; }
+        let decl: Vec<&str> = code[start + 4..].split(';').next().unwrap_or("").split_whitespace().collect();
+        if let [name, "is", underlying] = decl[..] { aliases.insert(name.to_string(), underlying.to_string()); }
+    }
+    aliases
+}
+
+/// (name, parameter list, anonymous) of each `event` declaration.
+fn event_declarations(code: &str) -> Vec<(String, String, bool)> {
+    let mut declarations = Vec::new();
+    for (start, _) in code.match_indices("event") {
+        if !at_word(code, start, "event") { continue; }
+        let rest = code[start + 5..].trim_start();
+        let name: String = rest.chars().take_while(|&c| is_ident_char(c)).collect();
+        let after_name = rest[name.len()..].trim_start();
+        if name.is_empty() || !after_name.starts_with('(') { continue; }
+        let Some(close) = after_name.find(')') else { continue };
+        let tail = after_name[close + 1..].split(';').next().unwrap_or("");
+        declarations.push((name, after_name[1..close].to_string(), tail.split_whitespace().any(|w| w == "anonymous")));
+    }
+    declarations
+}
+
+/// Parses `type [indexed] [name], ...`; on failure returns the type that could not be resolved.
+fn parse_params(params: &str, aliases: &HashMap<String, String>) -> Result<Vec<EventParam>, String> {
+    let mut inputs = Vec::new();
+    for param in params.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        // Join array suffixes written with spaces, e.g. `uint256 [ ]`
+        let param = param.replace(" [", "[").replace("[ ", "[").replace(" ]", "]");
+        let words: Vec<&str> = param.split_whitespace().filter(|&w| w != "payable").collect();
+        let ty = words[0];
+        let indexed = words[1..].contains(&"indexed");
+        let name = words[1..].iter().rfind(|&&w| w != "indexed").copied().unwrap_or("");
+        let kind = resolve_type(ty, aliases).ok_or_else(|| ty.to_string())?;
+        inputs.push(EventParam { name: name.to_string(), kind, indexed });
+    }
+    Ok(inputs)
+}
+
+fn resolve_type(ty: &str, aliases: &HashMap<String, String>) -> Option<ethabi::ParamType> {
+    let (base, arrays) = ty.split_at(ty.find('[').unwrap_or(ty.len()));
+    // `IERC20.Kind` names a type declared in another contract
+    let base = base.rsplit('.').next().unwrap_or(base);
+    let base = match base {
+        "uint" => "uint256",
+        "int" => "int256",
+        "byte" => "bytes1",
+        _ => aliases.get(base).map(String::as_str).unwrap_or(base),
+    };
+    parse_param_type(&format!("{}{}", base, arrays))
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Whether `word` at byte `start` of `code` is a whole identifier rather than part of a longer one.
+fn at_word(code: &str, start: usize, word: &str) -> bool {
+    let before = code[..start].chars().next_back().is_some_and(is_ident_char);
+    let after = code[start + word.len()..].chars().next().is_some_and(is_ident_char);
+    !before && !after
+}
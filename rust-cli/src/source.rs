@@ -0,0 +1,386 @@
+//! `--source`: input that arrives over a socket instead of from a file, for harnesses whose log generators run in
+//! another process (or language) and stream logs straight into the decoder without staging them on disk.
+//!
+//! `unix:PATH` listens on a Unix domain socket at `PATH` and reads one connection to its end: the generator
+//! connects, writes input in the `--input-format` framing (newline-delimited logs for JSONL) and closes, and the run
+//! reports on what it sent. The time the decoder spends waiting on the generator is counted as I/O wait, so a run
+//! shows whether the generator or the decoder set the pace.
+//!
+//! `zmq://HOST:PORT` connects a ZeroMQ SUB socket to a PUB (or XPUB) socket bound at `tcp://HOST:PORT`, speaking
+//! ZMTP 3.0 with the NULL mechanism, and reads the last frame of each message as one or more JSONL lines (earlier
+//! frames are topics). A publisher stream has no end, so the run lasts until `--exit-after` or an interrupt. When the
+//! connection drops or cannot be made, it is retried with backoff, as libzmq does; what the publisher sends in the
+//! meantime is lost, as with any SUB socket.
+
+use std::fmt;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::io::{Input, IoWait};
+use crate::stop;
+
+/// How long a blocked read waits before checking for a stop, and the first reconnect delay.
+const POLL: Duration = Duration::from_millis(100);
+/// Longest delay between reconnect attempts.
+const MAX_RECONNECT: Duration = Duration::from_secs(5);
+/// Time allowed for connecting and the ZMTP handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest data frame accepted. A longer one fails the connection before any of it is buffered, so a peer cannot
+/// make the decoder hold up to 2^64 bytes for a frame it announces.
+const MAX_FRAME: usize = 1 << 24;
+
+/// Where `--source` reads from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A Unix domain socket to listen on
+    Unix(PathBuf),
+    /// A ZeroMQ publisher to subscribe to, with the topic prefix to subscribe with (empty for every message)
+    Zmq { address: String, subscribe: Vec<u8> },
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = s.strip_prefix("zmq://") {
+            let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+            if address.rsplit_once(':').is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err()) {
+                return Err(format!("zmq:// needs a HOST:PORT publisher address, as in zmq://127.0.0.1:5556, not {:?}", address));
+            }
+            let subscribe = match query.split_once('=') {
+                None if query.is_empty() => Vec::new(),
+                Some(("subscribe", prefix)) => prefix.as_bytes().to_vec(),
+                _ => return Err(format!("unsupported zmq:// option {:?}; expected ?subscribe=PREFIX", query)),
+            };
+            return Ok(Source::Zmq { address: address.to_string(), subscribe });
+        }
+        match s.split_once(':') {
+            Some(("unix", path)) if !path.is_empty() => Ok(Source::Unix(path.into())),
+            Some(("unix", _)) => Err("unix: needs a socket path, as in unix:/tmp/logs.sock".into()),
+            _ => Err(format!("unsupported source {:?}; expected unix:PATH or zmq://HOST:PORT", s)),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Unix(path) => write!(f, "unix:{}", path.display()),
+            Source::Zmq { address, subscribe } if subscribe.is_empty() => write!(f, "zmq://{}", address),
+            Source::Zmq { address, subscribe } => write!(f, "zmq://{}?subscribe={}", address, String::from_utf8_lossy(subscribe)),
+        }
+    }
+}
+
+/// Counts of a message source, shared with the reader.
+#[derive(Clone, Debug, Default)]
+pub struct SourceCounts {
+    messages: Arc<AtomicU64>,
+    reconnects: Arc<AtomicU64>,
+}
+
+impl SourceCounts {
+    /// Messages received.
+    pub fn messages(&self) -> u64 {
+        self.messages.load(Ordering::Relaxed)
+    }
+
+    /// Times the connection was lost and retried, however many attempts each outage took.
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+}
+
+impl Source {
+    /// Waits for the producer and returns its stream as the input, with the time spent blocked on it, and the
+    /// message counts of sources that have messages.
+    pub fn open(&self) -> Result<(Input, Option<SourceCounts>)> {
+        match self {
+            Source::Unix(path) => Ok((open_unix(path.clone())?, None)),
+            Source::Zmq { address, subscribe } => {
+                let counts = SourceCounts::default();
+                let subscriber = Subscriber::connect(address.clone(), subscribe.clone(), counts.clone())?;
+                let io_wait = IoWait::default();
+                let reader = WaitReader { inner: subscriber, io_wait: io_wait.clone() };
+                Ok((Input { reader: Box::new(BufReader::with_capacity(1 << 16, reader)), io_wait: Some(io_wait) }, Some(counts)))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn open_unix(path: PathBuf) -> Result<Input> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a previous run would make bind fail
+    if std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket()) { std::fs::remove_file(&path)?; }
+    let listener = UnixListener::bind(&path).with_context(|| format!("Cannot listen on {:?}", path))?;
+    tracing::info!("source listening on unix:{}", path.display());
+    let (stream, _) = listener.accept().with_context(|| format!("Cannot accept a connection on {:?}", path))?;
+    // Nothing else may connect, and the path is free for the next run
+    drop(listener);
+    let _ = std::fs::remove_file(&path);
+    tracing::info!("source connected on unix:{}", path.display());
+    let io_wait = IoWait::default();
+    let reader = WaitReader { inner: stream, io_wait: io_wait.clone() };
+    Ok(Input { reader: Box::new(BufReader::with_capacity(1 << 16, reader)), io_wait: Some(io_wait) })
+}
+
+#[cfg(not(unix))]
+fn open_unix(_path: PathBuf) -> Result<Input> {
+    bail!("--source unix: needs Unix domain sockets, which this platform does not have")
+}
+
+/// A reader that counts the time spent blocked in `read` as I/O wait.
+struct WaitReader<R> {
+    inner: R,
+    io_wait: IoWait,
+}
+
+impl<R: Read> Read for WaitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let waited = Instant::now();
+        let n = self.inner.read(buf);
+        self.io_wait.add(waited.elapsed());
+        n
+    }
+}
+
+/// ZMTP frame flags.
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+
+/// A ZMTP 3.0 SUB peer of one publisher, reading the payloads of its messages as a byte stream.
+struct Subscriber {
+    address: String,
+    subscribe: Vec<u8>,
+    counts: SourceCounts,
+    stream: Option<TcpStream>,
+    /// Bytes received and not yet parsed into frames
+    inbox: Vec<u8>,
+    /// The last frame of the message being received
+    frame: Vec<u8>,
+    /// Payload bytes of complete messages, not yet read
+    payload: Vec<u8>,
+    pos: usize,
+}
+
+impl Subscriber {
+    /// Makes the first connection, so a wrong address fails the run instead of being retried forever.
+    fn connect(address: String, subscribe: Vec<u8>, counts: SourceCounts) -> Result<Subscriber> {
+        let stream = handshake(&address, &subscribe).with_context(|| format!("Cannot subscribe to zmq://{}", address))?;
+        tracing::info!("source subscribed to zmq://{}", address);
+        Ok(Subscriber { address, subscribe, counts, stream: Some(stream), inbox: Vec::new(), frame: Vec::new(), payload: Vec::new(), pos: 0 })
+    }
+
+    /// Reconnects with a delay that doubles from [`POLL`] up to [`MAX_RECONNECT`]; false once a stop is requested.
+    fn reconnect(&mut self) -> bool {
+        let mut delay = POLL;
+        while !stop::requested() {
+            match handshake(&self.address, &self.subscribe) {
+                Ok(stream) => {
+                    tracing::info!("source resubscribed to zmq://{}", self.address);
+                    self.stream = Some(stream);
+                    return true;
+                }
+                // One warning per outage, not one per attempt
+                Err(e) if delay == POLL => tracing::warn!("source zmq://{} unavailable, retrying: {:#}", self.address, e),
+                Err(e) => tracing::debug!("source zmq://{} unavailable, retrying in {:?}: {:#}", self.address, delay, e),
+            }
+            let until = Instant::now() + delay;
+            while Instant::now() < until && !stop::requested() { std::thread::sleep(POLL.min(until - Instant::now())); }
+            delay = (delay * 2).min(MAX_RECONNECT);
+        }
+        false
+    }
+
+    /// Moves the complete messages of `inbox` to `payload`; fails on a frame no publisher sends or one over
+    /// [`MAX_FRAME`].
+    fn parse(&mut self) -> io::Result<()> {
+        let mut at = 0;
+        while let Some((flags, body, len)) = frame_at(&self.inbox[at..])? {
+            let body = &self.inbox[at + body.start..at + body.end];
+            if flags & COMMAND == 0 {
+                self.frame.clear();
+                self.frame.extend_from_slice(body);
+                if flags & MORE == 0 {
+                    self.counts.messages.fetch_add(1, Ordering::Relaxed);
+                    self.payload.extend_from_slice(&self.frame);
+                    if !self.frame.ends_with(b"\n") { self.payload.push(b'\n'); }
+                }
+            } else if command_name(body) == Some(b"ERROR") {
+                return Err(io::Error::other(format!("publisher sent ERROR: {}", String::from_utf8_lossy(&body[6..]))));
+            }
+            at += len;
+        }
+        self.inbox.drain(..at);
+        Ok(())
+    }
+}
+
+impl Read for Subscriber {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; 1 << 16];
+        while self.pos == self.payload.len() {
+            self.payload.clear();
+            self.pos = 0;
+            if stop::requested() { return Ok(0); }
+            let Some(stream) = &mut self.stream else {
+                if !self.reconnect() { return Ok(0); }
+                continue;
+            };
+            match stream.read(&mut chunk) {
+                Ok(0) => {
+                    tracing::warn!("source zmq://{} closed the connection", self.address);
+                    self.drop_connection();
+                }
+                Ok(n) => {
+                    self.inbox.extend_from_slice(&chunk[..n]);
+                    if let Err(e) = self.parse() {
+                        tracing::warn!("source zmq://{}: {}", self.address, e);
+                        self.drop_connection();
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+                Err(e) => {
+                    tracing::warn!("source zmq://{} connection lost: {}", self.address, e);
+                    self.drop_connection();
+                }
+            }
+        }
+        let n = buf.len().min(self.payload.len() - self.pos);
+        buf[..n].copy_from_slice(&self.payload[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Subscriber {
+    /// Forgets the connection and the message it was cut off in, counting a reconnect.
+    fn drop_connection(&mut self) {
+        self.counts.reconnects.fetch_add(1, Ordering::Relaxed);
+        self.stream = None;
+        self.inbox.clear();
+    }
+}
+
+/// Connects to `address`, exchanges ZMTP greetings and READY commands as a SUB socket and subscribes to `prefix`.
+fn handshake(address: &str, prefix: &[u8]) -> Result<TcpStream> {
+    let addr = address.to_socket_addrs()?.next().ok_or_else(|| anyhow!("{} resolves to no address", address))?;
+    let mut stream = TcpStream::connect_timeout(&addr, HANDSHAKE_TIMEOUT)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+
+    // signature, version 3.0, the NULL mechanism, as-server = 0 and filler
+    let mut greeting = [0u8; 64];
+    greeting[0] = 0xff;
+    greeting[9] = 0x7f;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    stream.write_all(&greeting)?;
+    let mut peer = [0u8; 64];
+    stream.read_exact(&mut peer).context("no ZMTP greeting")?;
+    if peer[0] != 0xff || peer[9] & 1 != 1 { bail!("the peer is not a ZMTP socket"); }
+    if peer[10] < 3 { bail!("the peer speaks ZMTP {}.{}, before 3.0", peer[10], peer[11]); }
+    if &peer[12..16] != b"NULL" || peer[16..32].iter().any(|&b| b != 0) {
+        bail!("the peer wants the {} security mechanism; only NULL is supported", String::from_utf8_lossy(&peer[12..32]).trim_end_matches('\0'));
+    }
+
+    write_frame(&mut stream, COMMAND, &ready_command())?;
+    let (flags, body) = read_frame(&mut stream)?;
+    match command_name(&body).filter(|_| flags & COMMAND != 0) {
+        Some(b"READY") => match property(&body[6..], b"Socket-Type") {
+            Some(b"PUB") | Some(b"XPUB") => {}
+            Some(kind) => bail!("the peer is a {} socket, not a publisher", String::from_utf8_lossy(kind)),
+            None => bail!("the peer's READY has no Socket-Type"),
+        },
+        Some(b"ERROR") => bail!("the peer refused the connection: {}", String::from_utf8_lossy(&body[6..])),
+        _ => bail!("the peer did not send READY"),
+    }
+    // A 3.0 subscription is a message of 0x01 followed by the prefix
+    write_frame(&mut stream, 0, &[&[1][..], prefix].concat())?;
+    stream.set_read_timeout(Some(POLL))?;
+    Ok(stream)
+}
+
+fn ready_command() -> Vec<u8> {
+    let mut body = vec![5];
+    body.extend_from_slice(b"READY");
+    body.push(11);
+    body.extend_from_slice(b"Socket-Type");
+    body.extend_from_slice(&3u32.to_be_bytes());
+    body.extend_from_slice(b"SUB");
+    body
+}
+
+fn write_frame(stream: &mut impl Write, flags: u8, body: &[u8]) -> io::Result<()> {
+    match u8::try_from(body.len()) {
+        Ok(len) => stream.write_all(&[flags, len])?,
+        Err(_) => {
+            stream.write_all(&[flags | LONG])?;
+            stream.write_all(&(body.len() as u64).to_be_bytes())?;
+        }
+    }
+    stream.write_all(body)
+}
+
+/// Reads one frame during the handshake, when nothing but a command is expected.
+fn read_frame(stream: &mut impl Read) -> Result<(u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head)?;
+    let len = match head[0] & LONG {
+        0 => head[1] as usize,
+        _ => {
+            let mut len = [0u8; 8];
+            len[0] = head[1];
+            stream.read_exact(&mut len[1..])?;
+            usize::try_from(u64::from_be_bytes(len)).ok().filter(|&n| n <= 1 << 16).ok_or_else(|| anyhow!("handshake frame too large"))?
+        }
+    };
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    Ok((head[0], body))
+}
+
+/// The flags, body range and length of the complete frame at the start of `bytes`, if there is one; fails as soon
+/// as the header announces a body over [`MAX_FRAME`].
+fn frame_at(bytes: &[u8]) -> io::Result<Option<(u8, std::ops::Range<usize>, usize)>> {
+    let (start, len) = match (bytes.first(), bytes.get(1), bytes.get(1..9)) {
+        (Some(flags), Some(&len), _) if flags & LONG == 0 => (2, len as u64),
+        (Some(_), _, Some(len)) => (9, u64::from_be_bytes(len.try_into().expect("8 bytes"))),
+        _ => return Ok(None),
+    };
+    if len > MAX_FRAME as u64 {
+        return Err(io::Error::other(format!("publisher sent a frame of {} bytes, over the {} accepted", len, MAX_FRAME)));
+    }
+    let end = start + len as usize;
+    Ok((bytes.len() >= end).then_some((bytes[0], start..end, end)))
+}
+
+/// The name of a command frame's body.
+fn command_name(body: &[u8]) -> Option<&[u8]> {
+    let (&len, rest) = body.split_first()?;
+    rest.get(..len as usize)
+}
+
+/// The value of metadata property `name` among `properties` (one-byte name length, name, four-byte value length,
+/// value).
+fn property<'a>(mut properties: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    while let Some((&len, rest)) = properties.split_first() {
+        let (key, rest) = rest.split_at_checked(len as usize)?;
+        let (size, rest) = rest.split_at_checked(4)?;
+        let (value, rest) = rest.split_at_checked(u32::from_be_bytes(size.try_into().ok()?) as usize)?;
+        if key.eq_ignore_ascii_case(name) { return Some(value); }
+        properties = rest;
+    }
+    None
+}
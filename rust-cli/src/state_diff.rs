@@ -0,0 +1,185 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethereum_types::{H160, H256};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use evm_rust_decoder::decode::snippet;
+use evm_rust_decoder::storage::StorageLayout;
+use evm_rust_decoder::value::DecodedValue;
+
+use crate::logging;
+use crate::modes::{run_lines, LineDecoder, LineOptions};
+
+/// `--storage-layout [0xADDRESS=]PATH`: the layout of one contract, or of every account without its own.
+#[derive(Clone, Debug)]
+pub struct LayoutArg {
+    pub address: Option<H160>,
+    pub path: PathBuf,
+}
+
+impl FromStr for LayoutArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((address, path)) if address.starts_with("0x") => {
+                let address = H160::from_str(address).map_err(|e| format!("invalid address {:?}: {}", address, e))?;
+                Ok(LayoutArg { address: Some(address), path: path.into() })
+            }
+            _ => Ok(LayoutArg { address: None, path: s.into() }),
+        }
+    }
+}
+
+/// One decoded variable, as printed: `before` and `after` for a diff, `value` for a prestate. Fields are in sorted
+/// order, as in `--output-envelope` records.
+#[derive(Serialize)]
+struct DecodedSlot<'a> {
+    address: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<DecodedValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<DecodedValue>,
+    slot: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<DecodedValue>,
+    variable: &'a str,
+}
+
+#[derive(Default)]
+struct Counts {
+    traces: usize,
+    accounts: usize,
+    /// Accounts with storage but no layout
+    no_layout: usize,
+    slots: usize,
+    decoded: usize,
+    /// Slots outside the layout: mapping entries, dynamic array elements and long `string`/`bytes` data
+    unknown_slots: usize,
+}
+
+struct Differ {
+    by_address: HashMap<H160, StorageLayout>,
+    default: Option<StorageLayout>,
+    print: bool,
+    counts: Counts,
+}
+
+/// Translates the storage of `prestateTracer` output (with or without `diffMode`) into the state variables of
+/// `--storage-layout`, instead of decoding logs, and reports slots decoded per second. `contract` picks the
+/// contract of solc standard-json layouts.
+pub fn run(layouts: &[LayoutArg], contract: Option<&str>, options: &LineOptions) -> Result<()> {
+    let mut differ = Differ { by_address: HashMap::new(), default: None, print: options.print, counts: Counts::default() };
+    for layout in layouts {
+        let bytes = fs::read(&layout.path).with_context(|| format!("Cannot open storage layout {:?}", layout.path))?;
+        let json_value: Value = serde_json::from_slice(&bytes).with_context(|| format!("Invalid storage layout JSON in {:?}", layout.path))?;
+        let parsed = StorageLayout::from_json(&json_value, contract).with_context(|| format!("Failed to load the storage layout in {:?}", layout.path))?;
+        match layout.address {
+            Some(address) => { differ.by_address.insert(address, parsed); }
+            None if differ.default.is_some() => bail!("--storage-layout {:?}: only one layout may apply to every account; prefix the others with `0xADDRESS=`", layout.path),
+            None => differ.default = Some(parsed),
+        }
+    }
+    let run = run_lines(options, &mut differ)?;
+
+    let c = &differ.counts;
+    if logging::json() {
+        tracing::info!(
+            traces = c.traces, accounts = c.accounts, no_layout = c.no_layout, slots = c.slots, decoded = c.decoded,
+            unknown_slots = c.unknown_slots, errors = run.errors, elapsed_ms = logging::round(run.elapsed_ms(), 3),
+            throughput_sps = run.per_sec(c.slots).round() as u64, mb_per_s = logging::round(run.mb_per_s(), 1), "state_diff"
+        );
+        return Ok(());
+    }
+    eprintln!(
+        "state_diff traces={} accounts={} no_layout={} slots={} decoded={} unknown_slots={} errors={} elapsed_ms={:.3} throughput_sps={:.0} mb_per_s={:.1}",
+        c.traces, c.accounts, c.no_layout, c.slots, c.decoded, c.unknown_slots, run.errors, run.elapsed_ms(), run.per_sec(c.slots), run.mb_per_s()
+    );
+    Ok(())
+}
+
+impl LineDecoder for Differ {
+    fn decode_line(&mut self, line: &str, printed: &mut Vec<u8>) -> Result<()> {
+        let value: Value = serde_json::from_str(line).context("invalid JSON")?;
+        if let Some(error) = value.get("error").filter(|_| value.get("jsonrpc").is_some()) { bail!("JSON-RPC error: {}", error); }
+        let result = value.get("result").unwrap_or(&value);
+        let traces: Vec<&Value> = match result {
+            Value::Array(items) => items.iter().map(|item| item.get("result").unwrap_or(item)).collect(),
+            _ => vec![result],
+        };
+        for trace in traces {
+            self.counts.traces += 1;
+            let trace = trace.as_object().ok_or_else(|| anyhow!("expected a prestateTracer result, got {}", snippet(&trace.to_string())))?;
+            match (trace.get("pre").and_then(Value::as_object), trace.get("post").and_then(Value::as_object)) {
+                (Some(pre), Some(post)) => {
+                    let addresses: BTreeSet<&String> = pre.keys().chain(post.keys()).collect();
+                    for address in addresses { self.account(address, pre.get(address), Some(post.get(address)), printed)?; }
+                }
+                _ => for (address, account) in trace { self.account(address, Some(account), None, printed)?; },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Differ {
+    /// Decodes the storage of one account; `post` is `Some` in diff mode, where a slot missing from one side was
+    /// zero before or is cleared after.
+    fn account(&mut self, address: &str, pre: Option<&Value>, post: Option<Option<&Value>>, printed: &mut Vec<u8>) -> Result<()> {
+        let (pre_storage, post_storage) = (storage(pre), post.and_then(storage));
+        if pre_storage.is_none() && post_storage.is_none() { return Ok(()); }
+        self.counts.accounts += 1;
+        let parsed = H160::from_str(address).map_err(|_| anyhow!("invalid account address {}", snippet(address)))?;
+        let Some(layout) = self.by_address.get(&parsed).or(self.default.as_ref()) else {
+            self.counts.no_layout += 1;
+            return Ok(());
+        };
+        let empty = Map::new();
+        let (pre_storage, post_storage) = (pre_storage.unwrap_or(&empty), post_storage.unwrap_or(&empty));
+        let slots: BTreeSet<&String> = pre_storage.keys().chain(post_storage.keys()).collect();
+        for slot in slots {
+            self.counts.slots += 1;
+            let key = word(slot).context("storage slot")?;
+            let read = |storage: &Map<String, Value>| -> Result<H256> {
+                match storage.get(slot) {
+                    Some(value) => word(value.as_str().ok_or_else(|| anyhow!("storage value of {} is not a hex string", slot))?),
+                    None => Ok(H256::zero()),
+                }
+            };
+            let before = read(pre_storage)?;
+            let after = post.is_some().then(|| read(post_storage)).transpose()?;
+            let variables = match after {
+                Some(after) => layout.decode(&key, &before).into_iter().zip(layout.decode(&key, &after)).map(|((name, b), (_, a))| (name, None, Some(b), Some(a))).collect::<Vec<_>>(),
+                None => layout.decode(&key, &before).into_iter().map(|(name, v)| (name, Some(v), None, None)).collect(),
+            };
+            if variables.is_empty() { self.counts.unknown_slots += 1; }
+            self.counts.decoded += variables.len();
+            if !self.print { continue; }
+            // Variables packed next to a changed one are decoded but not printed unless they changed too
+            for (variable, value, before, after) in variables.into_iter().filter(|(_, _, before, after)| after.is_none() || before != after) {
+                serde_json::to_writer(&mut *printed, &DecodedSlot { address, after, before, slot, value, variable })?;
+                printed.push(b'\n');
+            }
+        }
+        Ok(())
+    }
+}
+
+fn storage(account: Option<&Value>) -> Option<&Map<String, Value>> {
+    account?.get("storage")?.as_object()
+}
+
+/// A 32-byte storage word; nodes pad slots and values to 64 hex digits, though some leave out leading zeros.
+fn word(s: &str) -> Result<H256> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    if digits.len() > 64 { bail!("{} is longer than 32 bytes", snippet(s)); }
+    let mut word = H256::zero();
+    let padded = format!("{:0>64}", digits);
+    hex::decode_to_slice(&padded, word.as_bytes_mut()).with_context(|| format!("Invalid storage word: {}", snippet(s)))?;
+    Ok(word)
+}
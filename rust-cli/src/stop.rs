@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// Exit status of a run cut short by SIGINT/SIGTERM, as a shell reports a process killed by SIGINT.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Traps SIGINT and SIGTERM: the first one asks the decoding loops to stop at the next line so the run can report
+/// what it processed; a second one exits immediately.
+pub fn install() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if SIGNALLED.swap(true, Ordering::Relaxed) { std::process::exit(INTERRUPTED_EXIT_CODE); }
+        STOP.store(true, Ordering::Relaxed);
+        tracing::warn!("interrupted, finishing the current lines (interrupt again to exit now)");
+    })
+    .context("Cannot install the SIGINT/SIGTERM handler")
+}
+
+/// Asks the decoding loops to stop at the next line, as a signal would (used by `--exit-after`).
+pub fn request() {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+/// Whether the loops should stop; checked once per input line.
+#[inline]
+pub fn requested() -> bool {
+    STOP.load(Ordering::Relaxed)
+}
+
+/// Whether a signal arrived, as opposed to a stop requested by the run itself.
+pub fn interrupted() -> bool {
+    SIGNALLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,220 @@
+//! Storage slots of a contract translated to its state variables, from solc's `storageLayout` output. Variables
+//! stored in place are decoded from their slot and offset: value types (packed ones included) and the fields and
+//! elements of in-place structs and fixed-size arrays. A dynamic array's slot holds its length and a `string` or
+//! `bytes` slot holds the value itself when it is shorter than 32 bytes, or its length. Mapping entries and
+//! dynamic array elements live at hashed slots, which cannot be traced back to a variable without their keys.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethereum_types::{H256, U256};
+use serde_json::{Map, Value};
+
+use crate::value::{DecodedValue, I256};
+
+/// Elements of an in-place fixed-size array expanded into variables; larger arrays are left out.
+const MAX_ARRAY_ELEMENTS: usize = 4096;
+
+/// The state variables of one contract by slot.
+#[derive(Debug, Default)]
+pub struct StorageLayout {
+    slots: HashMap<H256, Vec<Variable>>,
+    variables: usize,
+}
+
+/// A variable stored in place, as `name`, `s.field` or `a[2]`.
+#[derive(Clone, Debug)]
+struct Variable {
+    label: String,
+    /// Bytes from the low-order end of the slot
+    offset: usize,
+    size: usize,
+    kind: Kind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Address,
+    Bool,
+    Uint,
+    Int,
+    FixedBytes,
+    /// `string` or `bytes`: inline when short, else the length
+    Bytes { string: bool },
+    /// A dynamic array's length
+    Length,
+}
+
+impl StorageLayout {
+    /// Reads the layout of a `storageLayout` document (`{"storage","types"}`, as `forge inspect` prints it), an
+    /// object holding one under `storageLayout`, or solc standard-json output, where `contract` (`Name` or
+    /// `path/File.sol:Name`) picks the contract when several have a layout.
+    pub fn from_json(json_value: &Value, contract: Option<&str>) -> Result<StorageLayout> {
+        let standard_output = json_value.get("output").unwrap_or(json_value);
+        let layout = match standard_output.get("contracts").and_then(Value::as_object) {
+            Some(sources) => standard_json_layout(sources, contract)?,
+            None => json_value.get("storageLayout").unwrap_or(json_value),
+        };
+        let storage = layout.get("storage").and_then(Value::as_array).ok_or_else(|| anyhow!("storage layout without a `storage` array"))?;
+        let types = layout.get("types").and_then(Value::as_object);
+        let empty = Map::new();
+        let types = types.unwrap_or(&empty);
+        let mut out = StorageLayout::default();
+        for entry in storage {
+            let label = entry.get("label").and_then(Value::as_str).context("storage entry without a label")?;
+            let (slot, offset) = position(entry).with_context(|| format!("variable {}", label))?;
+            let kind = entry.get("type").and_then(Value::as_str).with_context(|| format!("variable {} without a type", label))?;
+            out.add(types, label.to_string(), slot, offset, kind).with_context(|| format!("variable {}", label))?;
+        }
+        Ok(out)
+    }
+
+    /// The variables stored in place, counting each struct field and array element.
+    pub fn variables(&self) -> usize {
+        self.variables
+    }
+
+    /// The variables stored in `slot` with their values in `word`; empty for a slot outside the layout.
+    pub fn decode(&self, slot: &H256, word: &H256) -> Vec<(&str, DecodedValue)> {
+        let Some(variables) = self.slots.get(slot) else { return Vec::new() };
+        variables.iter().map(|v| (v.label.as_str(), v.decode(word))).collect()
+    }
+
+    fn add(&mut self, types: &Map<String, Value>, label: String, slot: U256, offset: usize, type_id: &str) -> Result<()> {
+        let ty = types.get(type_id).ok_or_else(|| anyhow!("type {} is not in the layout's types", type_id))?;
+        let size: usize = number(ty, "numberOfBytes")?;
+        let type_label = ty.get("label").and_then(Value::as_str).unwrap_or(type_id);
+        match ty.get("encoding").and_then(Value::as_str).unwrap_or("inplace") {
+            "mapping" => {}
+            "dynamic_array" => self.push(slot, Variable { label: format!("{}.length", label), offset: 0, size: 32, kind: Kind::Length }),
+            "bytes" => self.push(slot, Variable { label, offset: 0, size: 32, kind: Kind::Bytes { string: type_label == "string" } }),
+            "inplace" if ty.get("members").is_some() => {
+                for member in ty["members"].as_array().context("struct members are not an array")? {
+                    let name = member.get("label").and_then(Value::as_str).context("struct member without a label")?;
+                    let (member_slot, member_offset) = position(member)?;
+                    let member_type = member.get("type").and_then(Value::as_str).context("struct member without a type")?;
+                    self.add(types, format!("{}.{}", label, name), slot + member_slot, member_offset, member_type)?;
+                }
+            }
+            "inplace" if ty.get("base").is_some() => {
+                let base = ty["base"].as_str().context("array base is not a type")?;
+                let base_size: usize = number(types.get(base).ok_or_else(|| anyhow!("type {} is not in the layout's types", base))?, "numberOfBytes")?;
+                if base_size == 0 { bail!("array base {} has no size", base); }
+                // Elements of up to 16 bytes share slots; larger ones start a slot each
+                let (per_slot, slots_each) = if base_size <= 16 { (32 / base_size, 1) } else { (1, base_size.div_ceil(32)) };
+                // The label's `[N]` is exact; the size only bounds the length when the last slot is not full
+                let len = type_label
+                    .strip_suffix(']')
+                    .and_then(|t| t.rsplit_once('['))
+                    .and_then(|(_, n)| n.parse().ok())
+                    .unwrap_or((size.div_ceil(32) / slots_each) * per_slot);
+                for i in 0..len.min(MAX_ARRAY_ELEMENTS) {
+                    let (element_slot, element_offset) = (slot + (i / per_slot) * slots_each, (i % per_slot) * base_size);
+                    self.add(types, format!("{}[{}]", label, i), element_slot, element_offset, base)?;
+                }
+            }
+            "inplace" => {
+                if size == 0 || offset + size > 32 { bail!("{} bytes at offset {} do not fit a slot", size, offset); }
+                let kind = value_kind(type_label);
+                if kind == Kind::Address && size != 20 { bail!("{} takes {} bytes, not 20", type_label, size); }
+                self.push(slot, Variable { label, offset, size, kind });
+            }
+            other => bail!("unsupported storage encoding {}", other),
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, slot: U256, variable: Variable) {
+        let mut key = H256::zero();
+        slot.to_big_endian(key.as_bytes_mut());
+        self.slots.entry(key).or_default().push(variable);
+        self.variables += 1;
+    }
+}
+
+impl Variable {
+    fn decode(&self, word: &H256) -> DecodedValue {
+        let bytes = &word.as_bytes()[32 - self.offset - self.size..32 - self.offset];
+        match self.kind {
+            Kind::Address => DecodedValue::Address(ethereum_types::Address::from_slice(bytes)),
+            Kind::Bool => DecodedValue::Bool(bytes.iter().any(|&b| b != 0)),
+            Kind::Uint | Kind::Length => DecodedValue::Uint(U256::from_big_endian(bytes)),
+            Kind::Int => {
+                let mut word = [if bytes[0] & 0x80 != 0 { 0xff } else { 0 }; 32];
+                word[32 - bytes.len()..].copy_from_slice(bytes);
+                DecodedValue::Int(I256(U256::from_big_endian(&word)))
+            }
+            Kind::FixedBytes => DecodedValue::FixedBytes(bytes.to_vec()),
+            Kind::Bytes { string } => {
+                let last = bytes[31];
+                if last & 1 == 1 {
+                    // Long form: the slot holds `length * 2 + 1` and the data starts at keccak(slot)
+                    return DecodedValue::Uint((U256::from_big_endian(bytes) - 1) >> 1);
+                }
+                let short = bytes[..(last as usize / 2).min(31)].to_vec();
+                match string {
+                    true => DecodedValue::String(String::from_utf8_lossy(&short).into_owned()),
+                    false => DecodedValue::Bytes(short),
+                }
+            }
+        }
+    }
+}
+
+/// The value type of a layout type label: `address`, `contract Token`, `bool`, `uintN`, `intN`, `enum E` or
+/// `bytesN`.
+fn value_kind(label: &str) -> Kind {
+    match label {
+        "address" | "address payable" => Kind::Address,
+        _ if label.starts_with("contract ") || label.starts_with("interface ") => Kind::Address,
+        "bool" => Kind::Bool,
+        _ if label.starts_with("uint") || label.starts_with("enum ") => Kind::Uint,
+        _ if label.starts_with("int") => Kind::Int,
+        _ if label.starts_with("bytes") => Kind::FixedBytes,
+        // User-defined value types keep their underlying type's bytes; read them as unsigned
+        _ => Kind::Uint,
+    }
+}
+
+/// The `slot` (a decimal string) and `offset` of a storage entry or struct member.
+fn position(entry: &Value) -> Result<(U256, usize)> {
+    let slot = match entry.get("slot") {
+        Some(Value::String(s)) => U256::from_dec_str(s).map_err(|_| anyhow!("invalid slot {:?}", s))?,
+        Some(Value::Number(n)) => U256::from(n.as_u64().ok_or_else(|| anyhow!("invalid slot {}", n))?),
+        _ => bail!("storage entry without a slot"),
+    };
+    Ok((slot, number(entry, "offset").unwrap_or(0)))
+}
+
+/// A count solc prints as a decimal string (`numberOfBytes`) or a number (`offset`).
+fn number(value: &Value, key: &str) -> Result<usize> {
+    match value.get(key) {
+        Some(Value::String(s)) => s.parse().map_err(|_| anyhow!("invalid {} {:?}", key, s)),
+        Some(Value::Number(n)) => n.as_u64().map(|n| n as usize).ok_or_else(|| anyhow!("invalid {} {}", key, n)),
+        _ => bail!("missing {}", key),
+    }
+}
+
+fn standard_json_layout<'a>(sources: &'a Map<String, Value>, contract: Option<&str>) -> Result<&'a Value> {
+    let mut found = Vec::new();
+    for (source, contracts) in sources {
+        let Some(contracts) = contracts.as_object() else { continue };
+        for (name, output) in contracts {
+            let Some(layout) = output.get("storageLayout") else { continue };
+            let qualified = format!("{}:{}", source, name);
+            if contract.is_some_and(|wanted| wanted == name || wanted == qualified) { return Ok(layout); }
+            found.push((qualified, layout));
+        }
+    }
+    match (contract, &found[..]) {
+        (None, [(_, layout)]) => Ok(layout),
+        (None, []) => bail!("no contract in the standard-json output has a storageLayout (add it to outputSelection)"),
+        (wanted, found) => {
+            let names: Vec<&str> = found.iter().map(|(name, _)| name.as_str()).collect();
+            match wanted {
+                Some(wanted) => bail!("No contract {} with a storageLayout in the standard-json output (it has {})", wanted, names.join(", ")),
+                None => bail!("several contracts have a storageLayout; pick one with --contract ({})", names.join(", ")),
+            }
+        }
+    }
+}
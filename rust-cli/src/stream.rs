@@ -0,0 +1,94 @@
+//! Decoding as a pull-based sequence, for Rust programs that embed the decoder with their own sources and sinks:
+//! [`DecodedLogIter`] over any [`BufRead`] and [`DecodedLogStream`], a [`Stream`] over any Tokio [`AsyncBufRead`].
+//!
+//! Both read JSONL in the decoder's `--input-schema` and yield one item per log, in input order. An unreadable or
+//! unparsable line and a log that does not decode each yield an `Err` and the sequence carries on with the next
+//! one, unless the decoder skips such errors ([`OnError::Skip`](crate::decode::OnError::Skip)), in which case they
+//! are only counted. Read errors are always yielded.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, Lines};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use anyhow::Result;
+use futures_core::Stream;
+use tokio::io::AsyncBufRead;
+
+use crate::decode::{DecodedLog, LogDecoder};
+
+/// Decoded logs of the lines read so far that have not been yielded yet.
+struct Pending<'d> {
+    decoder: &'d LogDecoder,
+    queue: VecDeque<Result<DecodedLog<'d>>>,
+}
+
+impl<'d> Pending<'d> {
+    fn push_line(&mut self, line: &str) {
+        let Pending { decoder, queue } = self;
+        let parsed = decoder.parse_line(line, |record| {
+            match decoder.decode(record) {
+                Ok(log) => queue.push_back(Ok(log)),
+                Err(e) => if let Err(e) = decoder.skip_error(Err(e)) { queue.push_back(Err(e)); },
+            }
+            Ok(())
+        });
+        if let Err(e) = decoder.skip_error(parsed) { queue.push_back(Err(e)); }
+    }
+}
+
+/// `Iterator<Item = Result<DecodedLog>>` over the lines of a reader.
+pub struct DecodedLogIter<'d, R> {
+    lines: Lines<R>,
+    pending: Pending<'d>,
+}
+
+impl<'d, R: BufRead> DecodedLogIter<'d, R> {
+    pub fn new(decoder: &'d LogDecoder, reader: R) -> Self {
+        DecodedLogIter { lines: reader.lines(), pending: Pending { decoder, queue: VecDeque::new() } }
+    }
+}
+
+impl<'d, R: BufRead> Iterator for DecodedLogIter<'d, R> {
+    type Item = Result<DecodedLog<'d>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.queue.pop_front() { return Some(item); }
+            match self.lines.next()? {
+                Ok(line) => self.pending.push_line(&line),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+/// [`Stream`] of decoded logs over the lines of an async reader. Each line is decoded on the polling task, so
+/// decoding takes the same CPU time as with [`DecodedLogIter`]; polls return whenever the reader has no full line.
+pub struct DecodedLogStream<'d, R> {
+    lines: tokio::io::Lines<R>,
+    pending: Pending<'d>,
+}
+
+impl<'d, R: AsyncBufRead + Unpin> DecodedLogStream<'d, R> {
+    pub fn new(decoder: &'d LogDecoder, reader: R) -> Self {
+        use tokio::io::AsyncBufReadExt;
+        DecodedLogStream { lines: reader.lines(), pending: Pending { decoder, queue: VecDeque::new() } }
+    }
+}
+
+impl<'d, R: AsyncBufRead + Unpin> Stream for DecodedLogStream<'d, R> {
+    type Item = Result<DecodedLog<'d>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(item) = this.pending.queue.pop_front() { return Poll::Ready(Some(item)); }
+            match ready!(Pin::new(&mut this.lines).poll_next_line(cx)) {
+                Ok(Some(line)) => this.pending.push_line(&line),
+                Ok(None) => return Poll::Ready(None),
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Phases tracked by `--phase-timing`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Read = 0,
+    Json = 1,
+    Hex = 2,
+    Abi = 3,
+    Output = 4,
+    Enrich = 5,
+    /// Resolving each log's event from its topic0
+    Lookup = 6,
+    /// Integers to decimal strings, within `Output`
+    Format = 7,
+    /// Evaluating `--where` on decoded logs
+    Where = 8,
+    /// Updating `--aggregate` groups and `--topk` sketches
+    Aggregate = 9,
+}
+
+const PHASES: usize = 10;
+
+/// Per-log events counted like the phase times, per thread and flushed with them, whether or not timing is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Count {
+    /// Topic0s `--prefilter` let through to the topic0 map
+    PrefilterPassed = 0,
+    /// Topic0s `--prefilter` turned away
+    PrefilterRejected = 1,
+    /// Topic0s `--prefilter` let through that the map did not know
+    PrefilterFalsePositive = 2,
+}
+
+const COUNTS: usize = 3;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TOTALS: Mutex<[u64; PHASES]> = Mutex::new([0; PHASES]);
+static COUNT_TOTALS: Mutex<[u64; COUNTS]> = Mutex::new([0; COUNTS]);
+
+thread_local! {
+    static LOCAL: Cell<[u64; PHASES]> = const { Cell::new([0; PHASES]) };
+    static LOCAL_COUNTS: Cell<[u64; COUNTS]> = const { Cell::new([0; COUNTS]) };
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Starts timing a phase; returns `None` (and reads no clock) when phase timing is off.
+#[inline]
+pub fn start() -> Option<Instant> {
+    if ENABLED.load(Ordering::Relaxed) { Some(Instant::now()) } else { None }
+}
+
+/// Adds the time since `started` to this thread's counter for `phase`.
+#[inline]
+pub fn record(phase: Phase, started: Option<Instant>) {
+    if let Some(t) = started {
+        let nanos = t.elapsed().as_nanos() as u64;
+        LOCAL.with(|c| {
+            let mut v = c.get();
+            v[phase as usize] += nanos;
+            c.set(v);
+        });
+    }
+}
+
+/// Adds one to this thread's `count`.
+#[inline]
+pub fn count(count: Count) {
+    LOCAL_COUNTS.with(|c| {
+        let mut v = c.get();
+        v[count as usize] += 1;
+        c.set(v);
+    });
+}
+
+/// Moves this thread's counters into the process-wide totals. Every thread that records phases calls this before exiting.
+pub fn flush() {
+    let counts = LOCAL_COUNTS.with(|c| c.replace([0; COUNTS]));
+    if counts.iter().any(|&n| n != 0) {
+        let mut totals = COUNT_TOTALS.lock().unwrap();
+        for (t, l) in totals.iter_mut().zip(counts) { *t += l; }
+    }
+    let local = LOCAL.with(|c| c.replace([0; PHASES]));
+    if local.iter().all(|&n| n == 0) { return; }
+    let mut totals = TOTALS.lock().unwrap();
+    for (t, l) in totals.iter_mut().zip(local) { *t += l; }
+}
+
+/// Process-wide totals, summed over threads (so they can exceed wall-clock time when running in parallel).
+pub fn totals() -> [Duration; PHASES] {
+    TOTALS.lock().unwrap().map(Duration::from_nanos)
+}
+
+/// Process-wide [`Count`] totals, summed over threads.
+pub fn counts() -> [u64; COUNTS] {
+    *COUNT_TOTALS.lock().unwrap()
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
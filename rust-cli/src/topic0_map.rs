@@ -0,0 +1,174 @@
+//! `--map-impl`: the topic0 → event index behind topic0 routing. Topic0s are keccak hashes, so hashing them again
+//! with SipHash (the std default, built to resist chosen keys) buys nothing; the other implementations trade that
+//! resistance for a cheaper lookup.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use clap::ValueEnum;
+use ethereum_types::H256;
+
+use crate::core_decode::EventLayout;
+
+/// How the topic0 index is built.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MapImpl {
+    /// std `HashMap` with SipHash-1-3
+    #[default]
+    Std,
+    /// `HashMap` with FxHash (rustc-hash)
+    Fx,
+    /// `HashMap` with aHash
+    Ahash,
+    /// A perfect hash over the known topic0s, built at startup: two table reads and one comparison per lookup
+    Perfect,
+}
+
+/// The topic0 index in one of the [`MapImpl`] layouts.
+pub enum Topic0Map {
+    Std(HashMap<H256, EventLayout>),
+    Fx(HashMap<H256, EventLayout, rustc_hash::FxBuildHasher>),
+    Ahash(HashMap<H256, EventLayout, ahash::RandomState>),
+    Perfect(PerfectMap),
+}
+
+impl Topic0Map {
+    /// Rebuilds `self` as `map_impl`. A perfect hash that cannot be built (topic0s sharing their first 8 bytes)
+    /// falls back to FxHash with a warning.
+    pub fn into_impl(self, map_impl: MapImpl) -> Topic0Map {
+        let entries = self.into_entries();
+        match map_impl {
+            MapImpl::Std => Topic0Map::Std(entries.into_iter().collect()),
+            MapImpl::Fx => Topic0Map::Fx(collect(entries)),
+            MapImpl::Ahash => Topic0Map::Ahash(collect(entries)),
+            MapImpl::Perfect => match PerfectMap::new(entries) {
+                Ok(map) => Topic0Map::Perfect(map),
+                Err(entries) => {
+                    tracing::warn!("no perfect hash for these topic0s; using --map-impl fx");
+                    Topic0Map::Fx(collect(entries))
+                }
+            },
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, topic0: &H256) -> Option<&EventLayout> {
+        match self {
+            Topic0Map::Std(map) => map.get(topic0),
+            Topic0Map::Fx(map) => map.get(topic0),
+            Topic0Map::Ahash(map) => map.get(topic0),
+            Topic0Map::Perfect(map) => map.get(topic0),
+        }
+    }
+
+    pub fn for_each_layout(&mut self, f: impl FnMut(&mut EventLayout)) {
+        match self {
+            Topic0Map::Std(map) => map.values_mut().for_each(f),
+            Topic0Map::Fx(map) => map.values_mut().for_each(f),
+            Topic0Map::Ahash(map) => map.values_mut().for_each(f),
+            Topic0Map::Perfect(map) => map.entries.iter_mut().map(|(_, layout)| layout).for_each(f),
+        }
+    }
+
+    pub fn topic0s(&self) -> Vec<H256> {
+        match self {
+            Topic0Map::Std(map) => map.keys().copied().collect(),
+            Topic0Map::Fx(map) => map.keys().copied().collect(),
+            Topic0Map::Ahash(map) => map.keys().copied().collect(),
+            Topic0Map::Perfect(map) => map.entries.iter().map(|(topic0, _)| *topic0).collect(),
+        }
+    }
+
+    fn into_entries(self) -> Vec<(H256, EventLayout)> {
+        match self {
+            Topic0Map::Std(map) => map.into_iter().collect(),
+            Topic0Map::Fx(map) => map.into_iter().collect(),
+            Topic0Map::Ahash(map) => map.into_iter().collect(),
+            Topic0Map::Perfect(map) => map.entries,
+        }
+    }
+}
+
+fn collect<S: BuildHasher + Default>(entries: Vec<(H256, EventLayout)>) -> HashMap<H256, EventLayout, S> {
+    let mut map = HashMap::with_capacity_and_hasher(entries.len(), S::default());
+    map.extend(entries);
+    map
+}
+
+/// Hash-and-displace perfect hash keyed by the first 8 bytes of each topic0: the top bits pick a bucket, and each
+/// bucket has a seed that sends its topic0s to distinct slots of a table twice the size of the key set.
+pub struct PerfectMap {
+    seeds: Vec<u32>,
+    /// Index into `entries` per slot, `EMPTY` when unused
+    slots: Vec<u32>,
+    entries: Vec<(H256, EventLayout)>,
+    bucket_bits: u32,
+    slot_bits: u32,
+}
+
+impl PerfectMap {
+    const EMPTY: u32 = u32::MAX;
+    /// Seeds tried per bucket before giving up; with half the slots free a bucket of 4 keys needs a few dozen
+    const MAX_SEED: u32 = 1 << 16;
+
+    /// Returns the entries back when two topic0s share their first 8 bytes, which no seed can separate.
+    fn new(entries: Vec<(H256, EventLayout)>) -> Result<PerfectMap, Vec<(H256, EventLayout)>> {
+        let bucket_bits = (entries.len() / 4).max(1).next_power_of_two().trailing_zeros();
+        let slot_bits = (entries.len() * 2).max(1).next_power_of_two().trailing_zeros();
+        let mut map = PerfectMap {
+            seeds: vec![0; 1 << bucket_bits],
+            slots: vec![Self::EMPTY; 1 << slot_bits],
+            entries,
+            bucket_bits,
+            slot_bits,
+        };
+        let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); map.seeds.len()];
+        for (i, (topic0, _)) in map.entries.iter().enumerate() { buckets[map.bucket(prefix(topic0))].push(i as u32); }
+        // fullest buckets first, while the table is emptiest
+        let mut order: Vec<usize> = (0..buckets.len()).collect();
+        order.sort_by_key(|&b| std::cmp::Reverse(buckets[b].len()));
+
+        let mut taken = Vec::new();
+        for b in order.into_iter().take_while(|&b| !buckets[b].is_empty()) {
+            let placed = (0..Self::MAX_SEED).find(|&seed| {
+                taken.clear();
+                buckets[b].iter().all(|&i| {
+                    let slot = map.slot(prefix(&map.entries[i as usize].0), seed);
+                    let free = map.slots[slot] == Self::EMPTY && !taken.contains(&slot);
+                    taken.push(slot);
+                    free
+                })
+            });
+            let Some(seed) = placed else { return Err(map.entries) };
+            map.seeds[b] = seed;
+            for (&i, &slot) in buckets[b].iter().zip(&taken) { map.slots[slot] = i; }
+        }
+        Ok(map)
+    }
+
+    #[inline]
+    fn bucket(&self, prefix: u64) -> usize {
+        prefix.checked_shr(64 - self.bucket_bits).unwrap_or(0) as usize
+    }
+
+    #[inline]
+    fn slot(&self, prefix: u64, seed: u32) -> usize {
+        let mixed = (prefix ^ u64::from(seed).wrapping_mul(0x9e37_79b9_7f4a_7c15)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        mixed.checked_shr(64 - self.slot_bits).unwrap_or(0) as usize
+    }
+
+    #[inline]
+    pub fn get(&self, topic0: &H256) -> Option<&EventLayout> {
+        let prefix = prefix(topic0);
+        let i = self.slots[self.slot(prefix, self.seeds[self.bucket(prefix)])];
+        match self.entries.get(i as usize) {
+            Some((known, layout)) if known == topic0 => Some(layout),
+            _ => None,
+        }
+    }
+}
+
+#[inline]
+fn prefix(topic0: &H256) -> u64 {
+    u64::from_be_bytes(topic0[..8].try_into().expect("8 bytes"))
+}
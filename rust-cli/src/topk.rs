@@ -0,0 +1,143 @@
+//! `--topk to:20`: the most frequent values of a decoded field, estimated in bounded memory with the space-saving
+//! algorithm (Metwally et al.). The sketch keeps `k * COUNTERS_PER_K` counters; a value that is not counted takes
+//! over the counter with the lowest count and inherits that count as its possible overestimate (`error`). Any
+//! value seen more than `N / counters` times in `N` logs is guaranteed a counter, and its true count lies between
+//! `count - error` and `count`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+
+use crate::aggregate::Key;
+use crate::decode::DecodedLog;
+use crate::predicate::field;
+use crate::timing::{self, Phase};
+
+/// Counters kept per reported value.
+const COUNTERS_PER_K: usize = 100;
+/// `--topk field` without `:k`
+const DEFAULT_K: usize = 10;
+
+/// One `--topk field[:k]` and its sketch, shared by all decoding threads.
+#[derive(Debug)]
+pub struct TopK {
+    pub field: String,
+    pub k: usize,
+    sketch: Mutex<SpaceSaving>,
+}
+
+/// One reported value.
+#[derive(Clone, Debug)]
+pub struct HeavyHitter {
+    pub value: String,
+    pub count: u64,
+    /// How much of `count` may have been inherited from values the counter tracked before
+    pub error: u64,
+}
+
+impl TopK {
+    /// Parses `field` or `field:k`.
+    pub fn parse(spec: &str) -> Result<TopK> {
+        let (field, k) = match spec.split_once(':') {
+            Some((field, k)) => (field, k.parse().ok().filter(|&k| k > 0).ok_or_else(|| anyhow!("--topk: invalid count in {:?}", spec))?),
+            None => (spec, DEFAULT_K),
+        };
+        Ok(TopK { field: field.to_string(), k, sketch: Mutex::new(SpaceSaving::new(k * COUNTERS_PER_K)) })
+    }
+
+    /// Counts `log`'s value of the field, if it has one; timed as `Phase::Aggregate`.
+    pub fn add(&self, log: &DecodedLog) {
+        let t = timing::start();
+        if let Some(value) = field(log, &self.field) {
+            self.sketch.lock().expect("topk sketch poisoned").add(Key::of(Some(value)));
+        }
+        timing::record(Phase::Aggregate, t);
+    }
+
+    /// The `k` values with the highest counts, highest first.
+    pub fn top(&self) -> Vec<HeavyHitter> {
+        let sketch = self.sketch.lock().expect("topk sketch poisoned");
+        sketch.counters.iter().take(self.k).map(|c| {
+            let value = match c.key.to_json() {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            HeavyHitter { value, count: c.count, error: c.error }
+        }).collect()
+    }
+
+    /// Logs counted: the total the counts are estimated from.
+    pub fn total(&self) -> u64 {
+        self.sketch.lock().expect("topk sketch poisoned").total
+    }
+}
+
+/// The counters in a vector sorted by count, highest first, so the minimum is the last one and an increment moves
+/// a counter at most to the front of its run of equal counts: every update is O(1).
+#[derive(Debug)]
+struct SpaceSaving {
+    capacity: usize,
+    counters: Vec<Counter>,
+    /// Position of each counted key in `counters`
+    index: HashMap<Key, usize>,
+    /// Position of the first counter of each count
+    first: HashMap<u64, usize>,
+    total: u64,
+}
+
+#[derive(Debug)]
+struct Counter {
+    key: Key,
+    count: u64,
+    error: u64,
+}
+
+impl SpaceSaving {
+    fn new(capacity: usize) -> Self {
+        SpaceSaving { capacity, counters: Vec::new(), index: HashMap::new(), first: HashMap::new(), total: 0 }
+    }
+
+    fn add(&mut self, key: Key) {
+        self.total += 1;
+        let i = match self.index.get(&key) {
+            Some(&i) => i,
+            None if self.counters.len() < self.capacity => {
+                // a new count of 0 sorts last, and the increment below makes it 1
+                let i = self.counters.len();
+                self.counters.push(Counter { key: key.clone(), count: 0, error: 0 });
+                self.first.entry(0).or_insert(i);
+                self.index.insert(key, i);
+                i
+            }
+            None => {
+                // take over the minimum, the last counter
+                let i = self.counters.len() - 1;
+                let min = &mut self.counters[i];
+                self.index.remove(&min.key);
+                min.error = min.count;
+                min.key = key.clone();
+                self.index.insert(key, i);
+                i
+            }
+        };
+        self.increment(i);
+    }
+
+    /// Adds one to `counters[i]`, first swapping it with the first counter of the same count so the order holds.
+    fn increment(&mut self, i: usize) {
+        let count = self.counters[i].count;
+        let front = self.first[&count];
+        if front != i {
+            self.counters.swap(front, i);
+            for j in [i, front] { *self.index.get_mut(&self.counters[j].key).expect("counted key") = j; }
+        }
+        // `front` leaves the run of `count`, and joins the end of the run of `count + 1`
+        match self.counters.get(front + 1) {
+            Some(next) if next.count == count => { self.first.insert(count, front + 1); }
+            _ => { self.first.remove(&count); }
+        }
+        self.counters[front].count += 1;
+        self.first.entry(count + 1).or_insert(front);
+    }
+}
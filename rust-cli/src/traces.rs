@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethabi::{Function, Param, Token};
+use serde::Serialize;
+use serde_json::Value;
+
+use evm_rust_decoder::abi::load_functions;
+use evm_rust_decoder::core_decode::parse_data_into;
+use evm_rust_decoder::presets::Preset;
+use evm_rust_decoder::value::DecodedValue;
+
+use crate::logging;
+use crate::modes::{run_lines, LineDecoder, LineOptions};
+
+/// One decoded call frame, as printed. Fields are in sorted order, as in `--output-envelope` records.
+#[derive(Serialize)]
+struct DecodedCall<'a> {
+    args: BTreeMap<String, DecodedValue>,
+    depth: usize,
+    from: Option<&'a str>,
+    function: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outputs: Option<BTreeMap<String, DecodedValue>>,
+    to: Option<&'a str>,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(rename = "userOps", skip_serializing_if = "Vec::is_empty")]
+    user_ops: Vec<DecodedUserOp<'a>>,
+}
+
+/// An ERC-4337 user operation of a `handleOps` bundle, with its `callData` decoded when its selector is known.
+#[derive(Serialize)]
+struct DecodedUserOp<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<BTreeMap<String, DecodedValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function: Option<&'a str>,
+    nonce: DecodedValue,
+    sender: DecodedValue,
+}
+
+#[derive(Default)]
+struct Counts {
+    traces: usize,
+    calls: usize,
+    decoded: usize,
+    outputs: usize,
+    /// User operations found in decoded calls, and those whose `callData` decoded
+    user_ops: usize,
+    user_ops_decoded: usize,
+    unknown_selector: usize,
+    /// Creations, self-destructs and calls with less than a selector of input (plain value transfers)
+    no_selector: usize,
+}
+
+struct Tracer {
+    functions: HashMap<[u8; 4], Function>,
+    print: bool,
+    counts: Counts,
+    /// Reused hex buffers of the input and the output of a frame
+    input: Vec<u8>,
+    output: Vec<u8>,
+}
+
+/// Decodes `debug_traceTransaction` callTracer output instead of logs: every call frame, nested ones included, has
+/// its input decoded against the ABI function of its selector, and its output too unless the call failed. The
+/// functions of `presets` follow those of `abi`.
+pub fn run(abi: &[PathBuf], presets: &[Preset], options: &LineOptions) -> Result<()> {
+    let mut functions = HashMap::new();
+    for function in load_functions(abi)?.into_iter().chain(presets.iter().flat_map(|p| p.functions())) {
+        functions.entry(function.short_signature()).or_insert(function);
+    }
+    if functions.is_empty() { bail!("--mode traces decodes calls against the ABI's functions, and it has none"); }
+    let mut tracer = Tracer { functions, print: options.print, counts: Counts::default(), input: Vec::new(), output: Vec::new() };
+    let run = run_lines(options, &mut tracer)?;
+
+    let c = &tracer.counts;
+    if logging::json() {
+        tracing::info!(
+            traces = c.traces, calls = c.calls, decoded = c.decoded, outputs = c.outputs, user_ops = c.user_ops,
+            user_ops_decoded = c.user_ops_decoded, unknown_selector = c.unknown_selector, no_selector = c.no_selector, errors = run.errors,
+            elapsed_ms = logging::round(run.elapsed_ms(), 3), throughput_cps = run.per_sec(c.calls).round() as u64,
+            mb_per_s = logging::round(run.mb_per_s(), 1), "traces"
+        );
+        return Ok(());
+    }
+    eprintln!(
+        "traces={} calls={} decoded={} outputs={} user_ops={} user_ops_decoded={} unknown_selector={} no_selector={} errors={} elapsed_ms={:.3} throughput_cps={:.0} mb_per_s={:.1}",
+        c.traces, c.calls, c.decoded, c.outputs, c.user_ops, c.user_ops_decoded, c.unknown_selector, c.no_selector, run.errors, run.elapsed_ms(), run.per_sec(c.calls), run.mb_per_s()
+    );
+    Ok(())
+}
+
+impl LineDecoder for Tracer {
+    fn decode_line(&mut self, line: &str, printed: &mut Vec<u8>) -> Result<()> {
+        let value: Value = serde_json::from_str(line).context("invalid JSON")?;
+        for root in roots(&value)? {
+            self.counts.traces += 1;
+            self.frame(root, 0, printed)?;
+        }
+        Ok(())
+    }
+}
+
+impl Tracer {
+    fn frame(&mut self, frame: &Value, depth: usize, printed: &mut Vec<u8>) -> Result<()> {
+        let kind = frame.get("type").and_then(Value::as_str).ok_or_else(|| anyhow!("call frame without a type"))?;
+        self.counts.calls += 1;
+        let input = frame.get("input").and_then(Value::as_str).unwrap_or("0x");
+        parse_data_into(input, &mut self.input).context("frame input")?;
+        let selector = match &self.input[..] {
+            // Creation input is init code, not calldata
+            _ if kind.starts_with("CREATE") || kind == "SELFDESTRUCT" => None,
+            [a, b, c, d, ..] => Some([*a, *b, *c, *d]),
+            _ => None,
+        };
+        let function = match selector {
+            Some(selector) => {
+                let function = self.functions.get(&selector);
+                if function.is_none() { self.counts.unknown_selector += 1; }
+                function
+            }
+            None => {
+                self.counts.no_selector += 1;
+                None
+            }
+        };
+        if let Some(function) = function {
+            let args = function.decode_input(&self.input[4..]).with_context(|| format!("{} input at depth {}", function.name, depth))?;
+            self.counts.decoded += 1;
+            // A failed call's output is its revert data, not the function's return values
+            let output = frame.get("output").and_then(Value::as_str).filter(|_| frame.get("error").is_none() && !function.outputs.is_empty());
+            let outputs = match output {
+                Some(output) => {
+                    parse_data_into(output, &mut self.output).context("frame output")?;
+                    let outputs = function.decode_output(&self.output).with_context(|| format!("{} output at depth {}", function.name, depth))?;
+                    self.counts.outputs += 1;
+                    Some(outputs)
+                }
+                None => None,
+            };
+            let mut ops = Vec::new();
+            args.iter().for_each(|arg| user_ops(arg, &mut ops));
+            self.counts.user_ops += ops.len();
+            let mut user_ops = Vec::with_capacity(ops.len());
+            for (i, (sender, nonce, call_data)) in ops.into_iter().enumerate() {
+                let inner = call_data.get(..4).and_then(|selector| self.functions.get(selector));
+                let inner_args = match inner {
+                    Some(inner) => Some(inner.decode_input(&call_data[4..]).with_context(|| format!("userOp {} {} callData at depth {}", i, inner.name, depth))?),
+                    None => None,
+                };
+                if inner.is_some() { self.counts.user_ops_decoded += 1; }
+                if self.print {
+                    user_ops.push(DecodedUserOp {
+                        args: inner.zip(inner_args).map(|(inner, args)| named(&inner.inputs, &args)),
+                        function: inner.map(|f| f.name.as_str()),
+                        nonce: DecodedValue::from(nonce),
+                        sender: DecodedValue::from(sender),
+                    });
+                }
+            }
+            if self.print {
+                let call = DecodedCall {
+                    args: named(&function.inputs, &args),
+                    depth,
+                    from: frame.get("from").and_then(Value::as_str),
+                    function: &function.name,
+                    outputs: outputs.map(|tokens| named(&function.outputs, &tokens)),
+                    to: frame.get("to").and_then(Value::as_str),
+                    kind,
+                    user_ops,
+                };
+                serde_json::to_writer(&mut *printed, &call)?;
+                printed.push(b'\n');
+            }
+        }
+        for call in frame.get("calls").and_then(Value::as_array).into_iter().flatten() {
+            self.frame(call, depth + 1, printed)?;
+        }
+        Ok(())
+    }
+}
+
+/// The top-level call frames of a line: a callTracer frame, a JSON-RPC response holding one, or the per-transaction
+/// results of `debug_traceBlockByNumber` (`[{"txHash","result"}]`), bare or in a response.
+fn roots(value: &Value) -> Result<Vec<&Value>> {
+    if let Some(error) = value.get("error").filter(|_| value.get("jsonrpc").is_some()) { bail!("JSON-RPC error: {}", error); }
+    let result = value.get("result").unwrap_or(value);
+    match result {
+        Value::Array(items) => Ok(items.iter().map(|item| item.get("result").unwrap_or(item)).collect()),
+        Value::Object(_) => Ok(vec![result]),
+        _ => bail!("expected a callTracer frame, got {}", result),
+    }
+}
+
+/// The `(sender, nonce, callData)` of the ERC-4337 user operations among decoded values: tuples shaped as a v0.6
+/// `UserOperation` (11 fields) or a v0.7 `PackedUserOperation` (9), which both start with `address sender, uint256
+/// nonce, bytes initCode, bytes callData`, found anywhere in arrays and tuples (as in `handleAggregatedOps`).
+fn user_ops<'t>(token: &'t Token, out: &mut Vec<(&'t Token, &'t Token, &'t [u8])>) {
+    match token {
+        Token::Tuple(fields) => match &fields[..] {
+            [sender @ Token::Address(_), nonce @ Token::Uint(_), Token::Bytes(_), Token::Bytes(call_data), ..] if fields.len() == 9 || fields.len() == 11 => {
+                out.push((sender, nonce, call_data));
+            }
+            _ => fields.iter().for_each(|field| user_ops(field, out)),
+        },
+        Token::Array(items) | Token::FixedArray(items) => items.iter().for_each(|item| user_ops(item, out)),
+        _ => {}
+    }
+}
+
+/// Decoded values keyed by parameter name, `argN` for unnamed ones (such as most return values).
+fn named(params: &[Param], tokens: &[Token]) -> BTreeMap<String, DecodedValue> {
+    params.iter().zip(tokens).enumerate().map(|(i, (param, token))| {
+        let key = if param.name.is_empty() { format!("arg{}", i) } else { param.name.clone() };
+        (key, DecodedValue::from(token))
+    }).collect()
+}
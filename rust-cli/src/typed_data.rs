@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use evm_rust_decoder::eip712::hash_typed_data;
+
+use crate::logging;
+use crate::modes::{run_lines, LineDecoder, LineOptions};
+
+/// One hashed document, as printed, with the hashes as `0x` hex. Fields are in sorted order.
+#[derive(Serialize)]
+struct HashedDocument<'a> {
+    digest: String,
+    #[serde(rename = "domainSeparator")]
+    domain_separator: String,
+    #[serde(rename = "primaryType")]
+    primary_type: &'a str,
+    #[serde(rename = "structHash", skip_serializing_if = "Option::is_none")]
+    struct_hash: Option<String>,
+}
+
+#[derive(Default)]
+struct Hasher {
+    print: bool,
+    documents: usize,
+    structs: usize,
+}
+
+/// Hashes EIP-712 typed-data documents, one per line, instead of decoding logs: the domain separator, the
+/// primary type's struct hash and the signed digest of each, reported as documents hashed per second.
+pub fn run(options: &LineOptions) -> Result<()> {
+    let mut hasher = Hasher { print: options.print, ..Hasher::default() };
+    let run = run_lines(options, &mut hasher)?;
+    if logging::json() {
+        tracing::info!(
+            documents = hasher.documents, structs = hasher.structs, errors = run.errors, elapsed_ms = logging::round(run.elapsed_ms(), 3),
+            throughput_dps = run.per_sec(hasher.documents).round() as u64, mb_per_s = logging::round(run.mb_per_s(), 1), "eip712"
+        );
+        return Ok(());
+    }
+    eprintln!(
+        "eip712 documents={} structs={} errors={} elapsed_ms={:.3} throughput_dps={:.0} mb_per_s={:.1}",
+        hasher.documents, hasher.structs, run.errors, run.elapsed_ms(), run.per_sec(hasher.documents), run.mb_per_s()
+    );
+    Ok(())
+}
+
+impl LineDecoder for Hasher {
+    fn decode_line(&mut self, line: &str, printed: &mut Vec<u8>) -> Result<()> {
+        let doc: Value = serde_json::from_str(line).context("invalid JSON")?;
+        // `eth_signTypedData_v4` requests carry the document, often as a string, after the signer's address
+        let doc = match doc.get("params").and_then(|p| p.get(1)) {
+            Some(Value::String(text)) => serde_json::from_str(text).context("invalid typed-data JSON in params")?,
+            Some(inner) => inner.clone(),
+            None => doc,
+        };
+        let hash = hash_typed_data(&doc)?;
+        self.documents += 1;
+        self.structs += hash.structs;
+        if self.print {
+            let hex = |h: &ethereum_types::H256| format!("{:?}", h);
+            let record = HashedDocument {
+                digest: hex(&hash.digest),
+                domain_separator: hex(&hash.domain_separator),
+                primary_type: doc.get("primaryType").and_then(Value::as_str).unwrap_or(""),
+                struct_hash: hash.struct_hash.as_ref().map(hex),
+            };
+            serde_json::to_writer(&mut *printed, &record)?;
+            printed.push(b'\n');
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+//! Typed decoded logs for library users: [`DecodedLog`] and its [`DecodedValue`]s serialize to exactly the JSON the
+//! CLI prints (bare args or `--output-envelope` records), and deserialize from it, so downstream code reads values
+//! as addresses and integers rather than picking through `serde_json::Value`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use ethabi::{Log, ParamType, Token};
+use ethereum_types::{Address, U256};
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::decode;
+use crate::output::ENVELOPE_SCHEMA_VERSION;
+use crate::timing::{self, Phase};
+
+/// A signed `intN` value, held as its sign-extended two's complement in 256 bits as ABI decoding returns it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct I256(pub U256);
+
+impl I256 {
+    pub fn is_negative(&self) -> bool {
+        self.0.bit(255)
+    }
+
+    /// The absolute value; `2^255` for the most negative value.
+    pub fn magnitude(&self) -> U256 {
+        if self.is_negative() { (!self.0).overflowing_add(U256::one()).0 } else { self.0 }
+    }
+}
+
+impl fmt::Display for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_negative() { f.write_str("-")?; }
+        f.write_str(u256_decimal(&self.magnitude(), &mut [0; U256_DECIMAL_LEN]))
+    }
+}
+
+/// Digits in the decimal form of `U256::MAX`.
+pub const U256_DECIMAL_LEN: usize = 78;
+/// The largest power of ten below 2^64, the chunk size of [`u256_decimal`].
+const CHUNK: u64 = 10_000_000_000_000_000_000;
+const CHUNK_DIGITS: usize = 19;
+
+/// Formats `uint` in decimal into `buf` and returns the digits, counted as format time by `--phase-timing`. Values
+/// that fit 64 bits take one `itoa`; larger ones are cut into 19-digit chunks by long division of the limbs by
+/// 10^19, where `U256`'s `Display` divides the whole 256-bit value by ten once per digit.
+pub fn u256_decimal<'b>(uint: &U256, buf: &'b mut [u8; U256_DECIMAL_LEN]) -> &'b str {
+    let t = timing::start();
+    let mut limbs = uint.0;
+    // least significant first; 2^256 < 10^(19 * 5)
+    let mut chunks = [0u64; 5];
+    let mut n = 0;
+    while limbs[1..].iter().any(|&l| l != 0) {
+        let mut rem: u128 = 0;
+        for limb in limbs.iter_mut().rev() {
+            let cur = (rem << 64) | u128::from(*limb);
+            *limb = (cur / u128::from(CHUNK)) as u64;
+            rem = cur % u128::from(CHUNK);
+        }
+        chunks[n] = rem as u64;
+        n += 1;
+    }
+    let mut itoa = itoa::Buffer::new();
+    let lead = itoa.format(limbs[0]).as_bytes();
+    let mut len = lead.len();
+    buf[..len].copy_from_slice(lead);
+    for &chunk in chunks[..n].iter().rev() {
+        let digits = itoa.format(chunk).as_bytes();
+        let pad = CHUNK_DIGITS - digits.len();
+        buf[len..len + pad].fill(b'0');
+        buf[len + pad..len + CHUNK_DIGITS].copy_from_slice(digits);
+        len += CHUNK_DIGITS;
+    }
+    timing::record(Phase::Format, t);
+    std::str::from_utf8(&buf[..len]).expect("ASCII digits")
+}
+
+impl FromStr for I256 {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        let magnitude = U256::from_dec_str(digits).map_err(|_| anyhow!("invalid integer: {}", s))?;
+        let limit = U256::one() << 255;
+        if magnitude > limit || (magnitude == limit && !negative) { bail!("integer out of int256 range: {}", s); }
+        Ok(I256(if negative { (!magnitude).overflowing_add(U256::one()).0 } else { magnitude }))
+    }
+}
+
+/// One decoded parameter value, with the variants of [`ethabi::Token`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodedValue {
+    Address(Address),
+    Uint(U256),
+    Int(I256),
+    Bool(bool),
+    /// `bytesN`
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    /// `T[]`
+    Array(Vec<DecodedValue>),
+    /// `T[N]`
+    FixedArray(Vec<DecodedValue>),
+    Tuple(Vec<DecodedValue>),
+}
+
+impl From<&Token> for DecodedValue {
+    fn from(token: &Token) -> Self {
+        let all = |tokens: &[Token]| tokens.iter().map(DecodedValue::from).collect();
+        match token {
+            Token::Address(addr) => DecodedValue::Address(*addr),
+            Token::Uint(uint) => DecodedValue::Uint(*uint),
+            Token::Int(int) => DecodedValue::Int(I256(*int)),
+            Token::Bool(b) => DecodedValue::Bool(*b),
+            Token::FixedBytes(b) => DecodedValue::FixedBytes(b.clone()),
+            Token::Bytes(b) => DecodedValue::Bytes(b.clone()),
+            Token::String(s) => DecodedValue::String(s.clone()),
+            Token::Array(arr) => DecodedValue::Array(all(arr)),
+            Token::FixedArray(arr) => DecodedValue::FixedArray(all(arr)),
+            Token::Tuple(arr) => DecodedValue::Tuple(all(arr)),
+        }
+    }
+}
+
+/// As printed: addresses and bytes as `0x` hex, integers as decimal strings (256-bit values do not fit a JSON
+/// number), arrays and tuples as JSON arrays.
+impl Serialize for DecodedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DecodedValue::Address(addr) => serializer.collect_str(&format_args!("0x{}", hex::encode(addr.as_bytes()))),
+            DecodedValue::Uint(uint) => serializer.serialize_str(u256_decimal(uint, &mut [0; U256_DECIMAL_LEN])),
+            DecodedValue::Int(int) => serializer.collect_str(int),
+            DecodedValue::Bool(b) => serializer.serialize_bool(*b),
+            DecodedValue::FixedBytes(b) | DecodedValue::Bytes(b) => serializer.collect_str(&format_args!("0x{}", hex::encode(b))),
+            DecodedValue::String(s) => serializer.serialize_str(s),
+            DecodedValue::Array(arr) | DecodedValue::FixedArray(arr) | DecodedValue::Tuple(arr) => {
+                let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+                for value in arr { seq.serialize_element(value)?; }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// The printed JSON does not carry the ABI types, so each variant is inferred from the value alone: `0x` hex of 20
+/// bytes is an `Address` and other `0x` hex `Bytes`, decimal strings are a `Uint` (an `Int` when negative), arrays
+/// are an `Array`, and any other string a `String`. `FixedBytes`, `FixedArray` and `Tuple` are never produced, and
+/// a `string` parameter that looks like hex or a number reads back as one; match on the event's inputs when the
+/// exact type matters.
+impl<'de> Deserialize<'de> for DecodedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DecodedValue::from_json(Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+impl DecodedValue {
+    fn from_json(value: Value) -> Result<Self> {
+        Ok(match value {
+            Value::Bool(b) => DecodedValue::Bool(b),
+            Value::Number(n) => match n.as_u64() {
+                Some(n) => DecodedValue::Uint(U256::from(n)),
+                None => DecodedValue::Int(n.to_string().parse()?),
+            },
+            Value::String(s) => {
+                if let Some(bytes) = s.strip_prefix("0x").and_then(|h| hex::decode(h).ok()) {
+                    if bytes.len() == 20 { DecodedValue::Address(Address::from_slice(&bytes)) } else { DecodedValue::Bytes(bytes) }
+                } else if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+                    U256::from_dec_str(&s).map_or(DecodedValue::String(s), DecodedValue::Uint)
+                } else if s.strip_prefix('-').is_some_and(|d| !d.is_empty() && d.bytes().all(|b| b.is_ascii_digit())) {
+                    s.parse().map_or(DecodedValue::String(s), DecodedValue::Int)
+                } else {
+                    DecodedValue::String(s)
+                }
+            }
+            Value::Array(arr) => DecodedValue::Array(arr.into_iter().map(DecodedValue::from_json).collect::<Result<_>>()?),
+            other => bail!("not a decoded value: {}", other),
+        })
+    }
+
+    pub fn as_address(&self) -> Option<Address> {
+        match self { DecodedValue::Address(addr) => Some(*addr), _ => None }
+    }
+
+    pub fn as_uint(&self) -> Option<U256> {
+        match self { DecodedValue::Uint(uint) => Some(*uint), _ => None }
+    }
+
+    pub fn as_int(&self) -> Option<I256> {
+        match self { DecodedValue::Int(int) => Some(*int), _ => None }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self { DecodedValue::FixedBytes(b) | DecodedValue::Bytes(b) => Some(b), _ => None }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self { DecodedValue::String(s) => Some(s), _ => None }
+    }
+}
+
+/// The token of `kind` that a printed value stands for: the inverse of the [`DecodedValue`] serialization, typed by
+/// the ABI rather than inferred. Integers may also be JSON numbers, and must fit their `uintN`/`intN`; tuples are
+/// arrays of their fields, as printed.
+pub fn token_from_json(value: &Value, kind: &ParamType) -> Result<Token> {
+    let hex = |len: Option<usize>| -> Result<Vec<u8>> {
+        let text = value.as_str().ok_or_else(|| anyhow!("expected a 0x hex string, got {}", value))?;
+        let bytes = hex::decode(text.strip_prefix("0x").ok_or_else(|| anyhow!("expected a 0x hex string, got {}", value))?)
+            .map_err(|e| anyhow!("invalid hex {:?}: {}", text, e))?;
+        match len {
+            Some(len) if bytes.len() != len => bail!("expected {} bytes, got {} in {:?}", len, bytes.len(), text),
+            _ => Ok(bytes),
+        }
+    };
+    let items = |len: Option<usize>| -> Result<&Vec<Value>> {
+        let items = value.as_array().ok_or_else(|| anyhow!("expected an array, got {}", value))?;
+        match len {
+            Some(len) if items.len() != len => bail!("expected {} elements, got {}", len, items.len()),
+            _ => Ok(items),
+        }
+    };
+    let integer = || -> Result<String> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Number(n) if n.is_u64() || n.is_i64() => Ok(n.to_string()),
+            _ => bail!("expected a decimal integer, got {}", value),
+        }
+    };
+    Ok(match kind {
+        ParamType::Address => Token::Address(Address::from_slice(&hex(Some(20))?)),
+        ParamType::Uint(bits) => {
+            let text = integer()?;
+            let uint = U256::from_dec_str(&text).map_err(|_| anyhow!("invalid uint{}: {}", bits, text))?;
+            if uint.bits() > *bits { bail!("{} does not fit uint{}", text, bits); }
+            Token::Uint(uint)
+        }
+        ParamType::Int(bits) => {
+            let text = integer()?;
+            let int: I256 = text.parse()?;
+            let limit = U256::one() << (bits - 1);
+            if int.magnitude() > limit || (int.magnitude() == limit && !int.is_negative()) { bail!("{} does not fit int{}", text, bits); }
+            Token::Int(int.0)
+        }
+        ParamType::Bool => Token::Bool(value.as_bool().ok_or_else(|| anyhow!("expected true or false, got {}", value))?),
+        ParamType::FixedBytes(len) => Token::FixedBytes(hex(Some(*len))?),
+        ParamType::Bytes => Token::Bytes(hex(None)?),
+        ParamType::String => Token::String(value.as_str().ok_or_else(|| anyhow!("expected a string, got {}", value))?.to_string()),
+        ParamType::Array(inner) => Token::Array(items(None)?.iter().map(|v| token_from_json(v, inner)).collect::<Result<_>>()?),
+        ParamType::FixedArray(inner, len) => {
+            Token::FixedArray(items(Some(*len))?.iter().map(|v| token_from_json(v, inner)).collect::<Result<_>>()?)
+        }
+        ParamType::Tuple(kinds) => {
+            Token::Tuple(items(Some(kinds.len()))?.iter().zip(kinds).map(|(v, k)| token_from_json(v, k)).collect::<Result<_>>()?)
+        }
+    })
+}
+
+/// The decoded parameters of `log`, keyed by name (`argN` for unnamed ones), as printed without `--output-envelope`.
+pub fn params(log: &Log) -> BTreeMap<String, DecodedValue> {
+    log.params.iter().enumerate().map(|(i, param)| {
+        let key = if param.name.is_empty() { format!("arg{}", i) } else { param.name.clone() };
+        (key, DecodedValue::from(&param.value))
+    }).collect()
+}
+
+/// One `--output-envelope` record. Fields are declared in the order they have always been printed in (sorted).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedLog {
+    pub address: Option<String>,
+    #[serde(rename = "args")]
+    pub params: BTreeMap<String, DecodedValue>,
+    #[serde(rename = "blockNumber")]
+    pub block_number: Option<u64>,
+    /// The chain of the log, printed only when the input or `--chain-id` gives it
+    #[serde(rename = "chainId", default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    pub event: String,
+    /// The L1 origin fields of L2 logs, printed only when the input has them
+    #[serde(rename = "l1BlockNumber", default, skip_serializing_if = "Option::is_none")]
+    pub l1_block_number: Option<u64>,
+    #[serde(rename = "l1TxOrigin", default, skip_serializing_if = "Option::is_none")]
+    pub l1_tx_origin: Option<String>,
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+}
+
+impl From<&decode::DecodedLog<'_>> for DecodedLog {
+    fn from(decoded: &decode::DecodedLog<'_>) -> Self {
+        DecodedLog {
+            address: decoded.meta.address.clone(),
+            params: params(&decoded.log),
+            block_number: decoded.meta.block_number,
+            chain_id: decoded.meta.chain_id,
+            event: decoded.event.name.clone(),
+            l1_block_number: decoded.meta.l1_block_number,
+            l1_tx_origin: decoded.meta.l1_tx_origin.clone(),
+            schema_version: ENVELOPE_SCHEMA_VERSION,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use ethereum_types::U256;
+use serde_json::Value;
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Decoded output (JSONL) of the first implementation
+    left: PathBuf,
+
+    /// Decoded output (JSONL) of the second implementation
+    right: PathBuf,
+
+    /// Number of mismatching records to print before stopping the report
+    #[arg(long, default_value_t = 10)]
+    max_mismatches: usize,
+
+    /// Sort both sides after normalization, for outputs produced without a stable order (e.g. --threads)
+    #[arg(long, default_value_t = false)]
+    unordered: bool,
+}
+
+/// Compares two decoded-output files record by record after normalizing number and hex formats.
+pub fn run(args: &VerifyArgs) -> Result<()> {
+    let mut left = read_normalized(&args.left)?;
+    let mut right = read_normalized(&args.right)?;
+    if args.unordered {
+        left.sort_by_cached_key(|v| v.to_string());
+        right.sort_by_cached_key(|v| v.to_string());
+    }
+
+    let mut mismatched = 0usize;
+    for (i, (l, r)) in left.iter().zip(&right).enumerate() {
+        let mut diffs = Vec::new();
+        diff_values("$", l, r, &mut diffs);
+        if diffs.is_empty() { continue; }
+        mismatched += 1;
+        if mismatched <= args.max_mismatches {
+            println!("record {}:", i + 1);
+            for d in diffs { println!("  {}", d); }
+        }
+    }
+    if left.len() != right.len() {
+        println!("record count differs: left={} right={}", left.len(), right.len());
+    }
+    println!(
+        "verify compared={} mismatched={} left_records={} right_records={}",
+        left.len().min(right.len()), mismatched, left.len(), right.len()
+    );
+
+    if mismatched > 0 || left.len() != right.len() {
+        bail!("Outputs are not equivalent");
+    }
+    Ok(())
+}
+
+fn read_normalized(path: &Path) -> Result<Vec<Value>> {
+    let file = File::open(path).with_context(|| format!("Cannot open {:?}", path))?;
+    let mut out = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        let v: Value = serde_json::from_str(&line)
+            .with_context(|| format!("{:?} line {}: invalid JSON", path, i + 1))?;
+        out.push(normalize(v));
+    }
+    Ok(out)
+}
+
+/// Canonical form used for comparison: integers (JSON numbers, decimal strings, `123n` bigint strings) become
+/// decimal strings and `0x` hex strings are lowercased, so `"0xAbC.."` == `"0xabc.."` and `1000` == `"1000"`.
+fn normalize(v: Value) -> Value {
+    match v {
+        Value::Number(n) => match n.as_i64().map(|i| i.to_string()).or_else(|| n.as_u64().map(|u| u.to_string())) {
+            Some(s) => Value::String(s),
+            None => Value::Number(n),
+        },
+        Value::String(s) => Value::String(normalize_str(s)),
+        Value::Array(arr) => Value::Array(arr.into_iter().map(normalize).collect()),
+        Value::Object(obj) => Value::Object(obj.into_iter().map(|(k, v)| (k, normalize(v))).collect()),
+        other => other,
+    }
+}
+
+fn normalize_str(s: String) -> String {
+    if s.starts_with("0x") || s.starts_with("0X") { return s.to_ascii_lowercase(); }
+    let digits = s.strip_suffix('n').unwrap_or(&s);
+    let (neg, abs) = match digits.strip_prefix('-') { Some(rest) => (true, rest), None => (false, digits) };
+    if abs.is_empty() || !abs.bytes().all(|b| b.is_ascii_digit()) { return s; }
+    match U256::from_dec_str(abs) {
+        Ok(n) if neg && !n.is_zero() => format!("-{}", n),
+        Ok(n) => n.to_string(),
+        Err(_) => s,
+    }
+}
+
+fn diff_values(path: &str, l: &Value, r: &Value, out: &mut Vec<String>) {
+    match (l, r) {
+        (Value::Object(lo), Value::Object(ro)) => {
+            for (k, lv) in lo {
+                let p = format!("{}.{}", path, k);
+                match ro.get(k) {
+                    Some(rv) => diff_values(&p, lv, rv, out),
+                    None => out.push(format!("{}: only in left ({})", p, lv)),
+                }
+            }
+            for (k, rv) in ro {
+                if !lo.contains_key(k) { out.push(format!("{}.{}: only in right ({})", path, k, rv)); }
+            }
+        }
+        (Value::Array(la), Value::Array(ra)) => {
+            if la.len() != ra.len() { out.push(format!("{}: array length left={} right={}", path, la.len(), ra.len())); }
+            for (i, (lv, rv)) in la.iter().zip(ra).enumerate() {
+                diff_values(&format!("{}[{}]", path, i), lv, rv, out);
+            }
+        }
+        _ if l != r => out.push(format!("{}: left {} != right {}", path, l, r)),
+        _ => {}
+    }
+}
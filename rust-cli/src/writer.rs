@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread::{Scope, ScopedJoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+use crate::decode::DecodedLog;
+use crate::output::Output;
+
+/// Logs handed to the writer in a single channel message.
+const BATCH_LOGS: usize = 256;
+/// Batches the channel can buffer before the decoding thread blocks.
+const CHANNEL_DEPTH: usize = 16;
+/// `--output-buffer-size` default: formatted bytes collected before each write to stdout.
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Where the writer thread's time went, and how long the decoding thread waited for it.
+#[derive(Clone, Debug, Default)]
+pub struct WriterStats {
+    pub logs: usize,
+    /// Serializing logs to JSON
+    pub format: Duration,
+    /// Writing formatted bytes to stdout
+    pub write: Duration,
+    /// Waiting for the next batch
+    pub idle: Duration,
+    /// Decoding-thread time spent blocked on a full channel, i.e. while output was the bottleneck
+    pub blocked: Duration,
+}
+
+/// The sending end of `--writer-thread`: decoded logs go to a dedicated thread over a bounded channel, so JSON
+/// formatting and stdout writes no longer interleave with decoding.
+pub struct OutputWriter<'scope, 'a> {
+    tx: Option<SyncSender<Vec<DecodedLog<'a>>>>,
+    batch: Vec<DecodedLog<'a>>,
+    blocked: Duration,
+    thread: ScopedJoinHandle<'scope, Result<WriterStats>>,
+}
+
+impl<'scope, 'a: 'scope> OutputWriter<'scope, 'a> {
+    /// Starts the writer thread in `scope`; it formats logs as `output` does and writes them to stdout in runs of
+    /// `buffer_size` bytes.
+    pub fn spawn<'env>(scope: &'scope Scope<'scope, 'env>, output: &'scope Output, buffer_size: usize) -> Self {
+        let (tx, rx) = sync_channel::<Vec<DecodedLog<'a>>>(CHANNEL_DEPTH);
+        let thread = scope.spawn(move || write_batches(rx, output, buffer_size.max(1)));
+        OutputWriter { tx: Some(tx), batch: Vec::with_capacity(BATCH_LOGS), blocked: Duration::ZERO, thread }
+    }
+
+    /// Queues one log. If the writer has stopped (e.g. stdout closed) this returns an I/O error, which
+    /// [`OutputWriter::finish`] replaces with the writer's own.
+    pub fn push(&mut self, log: DecodedLog<'a>) -> Result<()> {
+        self.batch.push(log);
+        if self.batch.len() >= BATCH_LOGS { self.send()?; }
+        Ok(())
+    }
+
+    fn send(&mut self) -> Result<()> {
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(BATCH_LOGS));
+        let tx = self.tx.as_ref().expect("writer open until finish");
+        let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "output writer stopped");
+        match tx.try_send(batch) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(batch)) => {
+                let t = Instant::now();
+                let sent = tx.send(batch);
+                self.blocked += t.elapsed();
+                sent.map_err(|_| stopped().into())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(stopped().into()),
+        }
+    }
+
+    /// Sends what is left, waits for the writer to write it out and returns its stats, or its error.
+    pub fn finish(mut self) -> Result<WriterStats> {
+        let sent = if self.batch.is_empty() { Ok(()) } else { self.send() };
+        drop(self.tx.take());
+        let mut stats = self.thread.join().map_err(|_| anyhow!("writer thread panicked"))??;
+        sent?;
+        stats.blocked = self.blocked;
+        Ok(stats)
+    }
+}
+
+fn write_batches(rx: Receiver<Vec<DecodedLog<'_>>>, output: &Output, buffer_size: usize) -> Result<WriterStats> {
+    let mut stats = WriterStats::default();
+    let mut out = io::stdout().lock();
+    let mut buf = Vec::with_capacity(buffer_size);
+    loop {
+        let t = Instant::now();
+        let Ok(batch) = rx.recv() else { break };
+        stats.idle += t.elapsed();
+        for log in &batch {
+            let t = Instant::now();
+            output.write(&mut buf, log)?;
+            stats.format += t.elapsed();
+            if buf.len() >= buffer_size {
+                let t = Instant::now();
+                out.write_all(&buf)?;
+                stats.write += t.elapsed();
+                buf.clear();
+            }
+        }
+        stats.logs += batch.len();
+    }
+    let t = Instant::now();
+    out.write_all(&buf)?;
+    out.flush()?;
+    stats.write += t.elapsed();
+    Ok(stats)
+}
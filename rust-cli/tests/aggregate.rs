@@ -0,0 +1,99 @@
+//! `--aggregate` over hand-built decoded logs: grouped sums, exact signed sums across zero, bare and field counts,
+//! and the errors of malformed specs.
+
+use ethabi::{Event, EventParam, Log, LogParam, ParamType, Token};
+use ethereum_types::{H160, U256};
+use evm_rust_decoder::aggregate::Aggregator;
+use evm_rust_decoder::decode::{DecodedLog, LogMeta};
+use serde_json::{json, Value};
+
+/// `Moved(address to, uint256 value, int256 delta)`
+fn event() -> Event {
+    let param = |name: &str, kind| EventParam { name: name.to_string(), kind, indexed: false };
+    Event {
+        name: "Moved".to_string(),
+        inputs: vec![param("to", ParamType::Address), param("value", ParamType::Uint(256)), param("delta", ParamType::Int(256))],
+        anonymous: false,
+    }
+}
+
+/// Two's complement of `delta`, negative when `negative`.
+fn int(negative: bool, magnitude: U256) -> Token {
+    Token::Int(if negative { !magnitude + 1 } else { magnitude })
+}
+
+fn log(event: &Event, to: u64, value: u64, delta: Token) -> DecodedLog<'_> {
+    let values = [Token::Address(H160::from_low_u64_be(to)), Token::Uint(U256::from(value)), delta];
+    let params = event.inputs.iter().zip(values).map(|(p, value)| LogParam { name: p.name.clone(), value }).collect();
+    DecodedLog { event, log: Log { params }, meta: LogMeta::default() }
+}
+
+fn address(n: u64) -> String {
+    format!("{:#x}", H160::from_low_u64_be(n))
+}
+
+/// The groups `spec` prints after adding `logs`, one JSON value per line.
+fn aggregate(spec: &str, logs: &[DecodedLog]) -> Vec<Value> {
+    let aggregator = Aggregator::parse(spec).unwrap();
+    for log in logs { aggregator.add(log); }
+    let mut out = Vec::new();
+    aggregator.write(&mut out).unwrap();
+    String::from_utf8(out).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn sums_are_grouped_by_field_in_key_order() {
+    let event = event();
+    let zero = int(false, U256::zero());
+    let logs = [
+        log(&event, 3, 1, zero.clone()),
+        log(&event, 1, 5, zero.clone()),
+        log(&event, 2, 7, zero.clone()),
+        log(&event, 1, 10, zero.clone()),
+        log(&event, 3, 2, zero),
+    ];
+    assert_eq!(aggregate("sum(value), count, max(value) by to", &logs), vec![
+        json!({ "to": address(1), "sum(value)": "15", "count": 2, "max(value)": "10" }),
+        json!({ "to": address(2), "sum(value)": "7", "count": 1, "max(value)": "7" }),
+        json!({ "to": address(3), "sum(value)": "3", "count": 2, "max(value)": "2" }),
+    ]);
+    // Without `by`, every log is in one group
+    assert_eq!(aggregate("sum(value)", &logs), vec![json!({ "sum(value)": "25" })]);
+}
+
+#[test]
+fn signed_sums_cross_zero_exactly() {
+    let event = event();
+    let delta = |negative, magnitude: u64| log(&event, 1, 0, int(negative, U256::from(magnitude)));
+    let spec = "sum(delta), min(delta), max(delta)";
+    assert_eq!(aggregate(spec, &[delta(false, 5), delta(true, 8)]), vec![json!({ "sum(delta)": "-3", "min(delta)": "-8", "max(delta)": "5" })]);
+    assert_eq!(aggregate(spec, &[delta(false, 5), delta(true, 8), delta(false, 3)]), vec![json!({ "sum(delta)": "0", "min(delta)": "-8", "max(delta)": "5" })]);
+    assert_eq!(aggregate(spec, &[delta(true, 8), delta(false, 9)]), vec![json!({ "sum(delta)": "1", "min(delta)": "-8", "max(delta)": "9" })]);
+
+    // Two int256 minimums sum to -2^256, past what 256 bits hold
+    let min = U256::one() << 255;
+    let logs = [log(&event, 1, 0, int(true, min)), log(&event, 1, 0, int(true, min))];
+    let two_pow_256 = "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+    assert_eq!(aggregate("sum(delta)", &logs), vec![json!({ "sum(delta)": format!("-{}", two_pow_256) })]);
+}
+
+#[test]
+fn bare_count_counts_every_log() {
+    let event = event();
+    let logs = [log(&event, 1, 1, int(false, U256::zero())), log(&event, 2, 2, int(true, U256::one()))];
+    assert_eq!(aggregate("count, count(value), count(missing), min(missing)", &logs), vec![
+        json!({ "count": 2, "count(value)": 2, "count(missing)": 0, "min(missing)": null }),
+    ]);
+    // A group field the event lacks keys every log as null
+    assert_eq!(aggregate("count by missing", &logs), vec![json!({ "missing": null, "count": 2 })]);
+}
+
+#[test]
+fn malformed_specs_are_errors() {
+    for spec in ["avg(value)", "sum(value", "sum()", "sum", "count(a b)", "", "count by ", "sum(value) by to,"] {
+        let error = Aggregator::parse(spec).unwrap_err().to_string();
+        assert!(error.starts_with("--aggregate: "), "{:?}: {}", spec, error);
+    }
+    assert!(Aggregator::parse("sum(value) by to,").unwrap_err().to_string().contains("invalid group field \"\""));
+    assert!(Aggregator::parse(" min( value ) , count by to, event ").is_ok());
+}
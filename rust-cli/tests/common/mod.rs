@@ -0,0 +1,29 @@
+//! Helpers shared by the integration tests.
+
+use std::io::Read;
+
+/// A reader over `data` that returns at most the next of `splits` bytes per read, cycling through them, so framed
+/// input arrives cut at arbitrary points as it does from a pipe or socket.
+pub struct Chunked {
+    data: Vec<u8>,
+    pos: usize,
+    splits: Vec<usize>,
+    read: usize,
+}
+
+impl Chunked {
+    pub fn new(data: Vec<u8>, splits: &[usize]) -> Chunked {
+        Chunked { data, pos: 0, splits: splits.to_vec(), read: 0 }
+    }
+}
+
+impl Read for Chunked {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let split = self.splits[self.read % self.splits.len()];
+        self.read += 1;
+        let n = split.min(buf.len()).min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
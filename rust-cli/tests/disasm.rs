@@ -0,0 +1,19 @@
+//! Disassembly of random bytecode: instructions tile the code and the jump destinations are exactly its JUMPDESTs.
+
+use evm_rust_decoder::disasm::{analyze, instructions};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn disassembly_covers_code_and_finds_jumpdests(code in prop::collection::vec(any::<u8>(), 0..600)) {
+        // Instructions tile the code, and the jump destinations are the JUMPDESTs among them
+        let (mut pc, mut dests) = (0, Vec::new());
+        for instruction in instructions(&code) {
+            prop_assert_eq!(instruction.pc, pc);
+            if instruction.opcode == 0x5b { dests.push(pc); }
+            pc += 1 + instruction.immediate.len();
+        }
+        prop_assert_eq!(pc, code.len());
+        prop_assert_eq!(analyze(&code).jumpdests, dests);
+    }
+}
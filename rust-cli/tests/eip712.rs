@@ -0,0 +1,36 @@
+//! EIP-712 typed-data hashing against a hash built by hand from ABI encoding, over random messages and every
+//! integer form wallets send.
+
+use ethabi::{encode, Token};
+use ethereum_types::{H160, H256, U256};
+use evm_rust_decoder::eip712::hash_typed_data;
+use proptest::prelude::*;
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+
+proptest! {
+    #[test]
+    fn typed_data_hashes_match_abi_encoding(v in any::<[u8; 32]>(), addr in any::<[u8; 20]>(), name in "[a-z ]{0,40}", form in 0..3usize) {
+        // Any of the integer forms wallets send must hash as `keccak256(typeHash ‖ abi.encode(fields))`
+        let amount = U256::from_big_endian(&v);
+        let value = match form {
+            0 => json!(amount.to_string()),
+            1 => json!(format!("{:#x}", amount)),
+            _ => json!(amount.low_u64()),
+        };
+        let amount = if form == 2 { U256::from(amount.low_u64()) } else { amount };
+        let doc = json!({
+            "types": { "EIP712Domain": [{ "name": "name", "type": "string" }], "Pay": [{ "name": "to", "type": "address" }, { "name": "amount", "type": "uint256" }, { "name": "memo", "type": "string" }] },
+            "primaryType": "Pay",
+            "domain": { "name": "Bench" },
+            "message": { "to": format!("0x{}", hex::encode(addr)), "amount": value, "memo": name },
+        });
+        let hashed = hash_typed_data(&doc).unwrap();
+        let type_hash = Keccak256::digest(b"Pay(address to,uint256 amount,string memo)").to_vec();
+        let fields = encode(&[Token::Address(H160(addr)), Token::Uint(amount), Token::FixedBytes(Keccak256::digest(name.as_bytes()).to_vec())]);
+        prop_assert_eq!(hashed.struct_hash, Some(H256(Keccak256::digest([type_hash, fields].concat()).into())));
+        let digest = Keccak256::digest([&[0x19, 0x01][..], hashed.domain_separator.as_bytes(), hashed.struct_hash.unwrap().as_bytes()].concat());
+        prop_assert_eq!(hashed.digest, H256(digest.into()));
+        prop_assert_eq!(hashed.structs, 2);
+    }
+}
@@ -0,0 +1,115 @@
+//! `--input-format era1` over random era1 files: e2store entries of snappy-framed headers and receipts, legacy and
+//! typed, built here from RLP. Every log comes back with its block number and block-wide log index.
+
+use ethereum_types::H256;
+use evm_rust_decoder::era1;
+use proptest::prelude::*;
+use rlp::RlpStream;
+
+/// A log of an era1 receipt: address, topics and data.
+type Era1Log = ([u8; 20], Vec<[u8; 32]>, Vec<u8>);
+
+/// A snappy framed stream of `data`, in compressed chunks that copy runs of a repeated byte and spell the rest out
+/// in literals of up to 256 bytes.
+fn snappy_framed(data: &[u8]) -> Vec<u8> {
+    let mut framed = vec![0xff, 6, 0, 0];
+    framed.extend_from_slice(b"sNaPpY");
+    for chunk in data.chunks(65536) {
+        let mut block = Vec::new();
+        let mut len = chunk.len();
+        while len >= 0x80 {
+            block.push(len as u8 | 0x80);
+            len >>= 7;
+        }
+        block.push(len as u8);
+        let run_at = |i: usize| chunk[i..].iter().take(64).take_while(|&&b| i > 0 && b == chunk[i - 1]).count();
+        let mut i = 0;
+        while i < chunk.len() {
+            let run = run_at(i);
+            if run >= 4 {
+                block.extend([((run - 1) << 2 | 2) as u8, 1, 0]);
+                i += run;
+                continue;
+            }
+            let n = (i..chunk.len()).take(256).take_while(|&j| j == i || run_at(j) < 4).count();
+            match n {
+                1..=60 => block.push(((n - 1) << 2) as u8),
+                _ => block.extend([60 << 2, (n - 1) as u8]),
+            }
+            block.extend_from_slice(&chunk[i..i + n]);
+            i += n;
+        }
+        framed.push(0x00);
+        framed.extend_from_slice(&(block.len() as u32 + 4).to_le_bytes()[..3]);
+        framed.extend_from_slice(&[0; 4]);
+        framed.extend_from_slice(&block);
+    }
+    framed
+}
+
+fn e2store_entry(file: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    file.extend_from_slice(&kind.to_le_bytes());
+    file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    file.extend_from_slice(&[0, 0]);
+    file.extend_from_slice(data);
+}
+
+/// An era1 file of `blocks` from `first`, each a list of receipts as (typed, logs).
+fn era1_file(first: u64, blocks: &[Vec<(bool, Vec<Era1Log>)>]) -> Vec<u8> {
+    let mut file = Vec::new();
+    e2store_entry(&mut file, 0x3265, &[]);
+    for (n, receipts) in blocks.iter().enumerate() {
+        let mut header = RlpStream::new_list(9);
+        for _ in 0..8 { header.append_empty_data(); }
+        header.append(&(first + n as u64));
+        e2store_entry(&mut file, 0x03, &snappy_framed(&header.out()));
+        e2store_entry(&mut file, 0x04, &snappy_framed(&[0xc2, 0xc0, 0xc0]));
+        let mut list = RlpStream::new_list(receipts.len());
+        for (typed, logs) in receipts {
+            let mut receipt = RlpStream::new_list(4);
+            receipt.append(&1u8).append(&21000u64).append(&vec![0u8; 256]);
+            receipt.begin_list(logs.len());
+            for (address, topics, data) in logs {
+                receipt.begin_list(3).append(&address.to_vec());
+                receipt.begin_list(topics.len());
+                for topic in topics { receipt.append(&topic.to_vec()); }
+                receipt.append(data);
+            }
+            match typed {
+                true => { list.append(&[&[0x02][..], &receipt.out()].concat()); }
+                false => { list.append_raw(&receipt.out(), 1); }
+            }
+        }
+        e2store_entry(&mut file, 0x05, &snappy_framed(&list.out()));
+        e2store_entry(&mut file, 0x06, &[0; 32]);
+    }
+    e2store_entry(&mut file, 0x07, &[0; 32]);
+    let index: Vec<u8> = [first].into_iter().chain(blocks.iter().map(|_| 0)).chain([blocks.len() as u64]).flat_map(u64::to_le_bytes).collect();
+    e2store_entry(&mut file, 0x3266, &index);
+    file
+}
+
+proptest! {
+    #[test]
+    fn era1_receipt_logs_are_extracted(
+        first in 0..20_000_000u64,
+        blocks in prop::collection::vec(prop::collection::vec((any::<bool>(), prop::collection::vec((any::<[u8; 20]>(), prop::collection::vec(any::<[u8; 32]>(), 0..5), prop::collection::vec(0..3u8, 0..300)), 0..4)), 0..4), 0..4),
+    ) {
+        // Every log with topics comes back in order, with its block number and block-wide log index
+        let mut expected = Vec::new();
+        for (n, receipts) in blocks.iter().enumerate() {
+            for (index, (address, topics, data)) in receipts.iter().flat_map(|(_, logs)| logs).enumerate() {
+                if topics.is_empty() { continue; }
+                let topics: Vec<H256> = topics.iter().map(|t| H256(*t)).collect();
+                expected.push((first + n as u64, index as u64, format!("0x{}", hex::encode(address)), topics, data.clone()));
+            }
+        }
+        let mut logs = Vec::new();
+        era1::for_each_log(&era1_file(first, &blocks)[..], |record| {
+            let record = record?;
+            logs.push((record.meta.block_number.unwrap(), record.meta.log_index.unwrap(), record.meta.address.unwrap(), record.raw.topics, record.raw.data));
+            Ok(())
+        }).unwrap();
+        prop_assert_eq!(logs, expected);
+    }
+}
@@ -0,0 +1,139 @@
+//! `--input-format firehose` over random dbin merged-blocks files built with prost: the logs of every block come
+//! back in order with their block numbers whatever the read boundaries, and a file cut anywhere but between blocks
+//! is an error.
+
+mod common;
+
+use common::Chunked;
+use ethereum_types::H256;
+use evm_rust_decoder::firehose::{self, bstream, ETHEREUM_BLOCK};
+use evm_rust_decoder::protobuf::pb;
+use prost::Message;
+use proptest::prelude::*;
+
+/// (address, topics, data, block index) of one log.
+type Log = (Option<[u8; 20]>, Vec<[u8; 32]>, Vec<u8>, u32);
+
+/// A block: its number, whether its payload is in the legacy `payload_buffer`, and its transactions, each with a
+/// receipt of logs or none.
+type Block = (u64, bool, Vec<Option<Vec<Log>>>);
+
+/// A log as read back: block number, address, topics, data and log index.
+type ReadLog = (Option<u64>, Option<String>, Vec<H256>, Vec<u8>, Option<u64>);
+
+fn blocks() -> impl Strategy<Value = Vec<Block>> {
+    let log = (any::<Option<[u8; 20]>>(), prop::collection::vec(any::<[u8; 32]>(), 0..4), prop::collection::vec(any::<u8>(), 0..100), any::<u32>());
+    let receipt = prop::option::of(prop::collection::vec(log, 0..4));
+    prop::collection::vec((any::<u64>(), any::<bool>(), prop::collection::vec(receipt, 0..4)), 0..6)
+}
+
+/// The dbin header: version 0 with `ETH`, or version 1 with `ETH` or the payload type URL.
+fn header(version: usize) -> Vec<u8> {
+    match version {
+        0 => b"dbin\x00ETH\x00\x01".to_vec(),
+        1 => b"dbin\x01\x00\x03ETH".to_vec(),
+        _ => [&b"dbin\x01"[..], &(ETHEREUM_BLOCK.len() as u16).to_be_bytes(), ETHEREUM_BLOCK.as_bytes()].concat(),
+    }
+}
+
+/// One dbin message: the `sf.bstream.v1.Block` wrapping `block`, after its big-endian length.
+fn message((number, legacy, traces): &Block) -> Vec<u8> {
+    let block = pb::Block {
+        number: *number,
+        transaction_traces: traces.iter().map(|receipt| pb::TransactionTrace {
+            receipt: receipt.as_ref().map(|logs| pb::TransactionReceipt {
+                logs: logs.iter().map(|(address, topics, data, index)| pb::Log {
+                    address: address.map(|a| a.to_vec()).unwrap_or_default(),
+                    topics: topics.iter().map(|t| t.to_vec()).collect(),
+                    data: data.clone(),
+                    block_index: *index,
+                    ..Default::default()
+                }).collect(),
+            }),
+            ..Default::default()
+        }).collect(),
+        ..Default::default()
+    };
+    let payload = block.encode_to_vec();
+    let wrapper = match legacy {
+        true => bstream::Block { number: *number, payload_buffer: payload, ..Default::default() },
+        false => bstream::Block { number: *number, payload: Some(bstream::Any { type_url: ETHEREUM_BLOCK.to_string(), value: payload }), ..Default::default() },
+    };
+    let bytes = wrapper.encode_to_vec();
+    [&(bytes.len() as u32).to_be_bytes()[..], &bytes].concat()
+}
+
+fn expected(blocks: &[Block]) -> Vec<ReadLog> {
+    blocks.iter().flat_map(|(number, _, traces)| {
+        traces.iter().flatten().flatten().filter(|(_, topics, ..)| !topics.is_empty()).map(move |(address, topics, data, index)| {
+            (Some(*number), address.map(|a| format!("0x{}", hex::encode(a))), topics.iter().map(|t| H256(*t)).collect(), data.clone(), Some(u64::from(*index)))
+        })
+    }).collect()
+}
+
+/// The logs read back from `input`, stopping at the first error.
+fn read(input: impl std::io::Read) -> anyhow::Result<Vec<ReadLog>> {
+    let mut read = Vec::new();
+    firehose::for_each_log(input, |record| {
+        let record = record?;
+        read.push((record.meta.block_number, record.meta.address, record.raw.topics, record.raw.data, record.meta.log_index));
+        Ok(())
+    })?;
+    Ok(read)
+}
+
+proptest! {
+    #[test]
+    fn block_logs_are_read_across_any_split(blocks in blocks(), version in 0..3usize, splits in prop::collection::vec(1..64usize, 1..6)) {
+        let file = [header(version), blocks.iter().flat_map(message).collect()].concat();
+        prop_assert_eq!(read(Chunked::new(file, &splits)).unwrap(), expected(&blocks));
+    }
+
+    #[test]
+    fn truncated_files_are_an_error(blocks in blocks(), cut in any::<prop::sample::Index>(), splits in prop::collection::vec(1..64usize, 1..6)) {
+        // Cut between blocks, the file is a shorter valid one; cut in the header or a block, the read fails without
+        // panicking
+        let (header, messages) = (header(0), blocks.iter().map(message).collect::<Vec<_>>());
+        let file = [header.clone(), messages.concat()].concat();
+        let cut = cut.index(file.len() + 1);
+        let boundaries: Vec<usize> = messages.iter().scan(header.len(), |end, m| { *end += m.len(); Some(*end) }).collect();
+        let result = read(Chunked::new(file[..cut].to_vec(), &splits));
+        if cut < header.len() {
+            prop_assert!(result.unwrap_err().to_string().contains("shorter than its header"));
+        } else if cut == header.len() || boundaries.contains(&cut) {
+            let whole = boundaries.iter().take_while(|&&end| end <= cut).count();
+            prop_assert_eq!(result.unwrap(), expected(&blocks[..whole]));
+        } else {
+            let error = result.unwrap_err().to_string();
+            prop_assert!(error.contains("input ends mid-"), "{}", error);
+        }
+    }
+}
+
+#[test]
+fn foreign_payloads_fail_their_block_only() {
+    // A block of another chain is reported and skipped, and the next block still reads
+    let log = (None, vec![[7; 32]], vec![1, 2], 0);
+    let foreign = bstream::Block { number: 1, payload: Some(bstream::Any { type_url: "type.googleapis.com/sf.near.type.v1.Block".to_string(), value: vec![] }), ..Default::default() };
+    let foreign = foreign.encode_to_vec();
+    let file = [header(0), (foreign.len() as u32).to_be_bytes().to_vec(), foreign, message(&(2, false, vec![Some(vec![log])]))].concat();
+    let mut results = Vec::new();
+    firehose::for_each_log(&file[..], |record| {
+        results.push(record.map(|r| r.meta.block_number).map_err(|e| format!("{:#}", e)));
+        Ok(())
+    }).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].as_ref().unwrap_err().contains("payload is type.googleapis.com/sf.near.type.v1.Block"), "{:?}", results[0]);
+    assert_eq!(results[1], Ok(Some(2)));
+}
+
+#[test]
+fn malformed_headers_and_lengths_are_errors() {
+    let error = |file: &[u8]| read(file).unwrap_err().to_string();
+    assert!(error(b"nbid\x00ETH\x00\x01").contains("missing the dbin magic"));
+    assert!(error(b"dbin\x02").contains("unsupported version 2"));
+    assert!(error(b"dbin\x00SOL\x00\x01").contains("holds SOL blocks"));
+    // A length of 4 GiB - 1 over a few bytes fails without allocating it
+    assert!(error(&[header(0), vec![0xff; 4], vec![1, 2, 3]].concat()).contains("input ends mid-message"));
+    assert!(error(&[header(0), vec![0, 0]].concat()).contains("input ends mid-length"));
+}
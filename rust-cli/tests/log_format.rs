@@ -0,0 +1,104 @@
+//! `--log-format json` end to end: every line the CLI writes to stderr is a JSON event, result lines included, so
+//! the log can be read line by line whatever flags a run combines.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// Eight Transfers from two contracts over blocks 101-108, then a log of no ABI event and a line that is not JSON.
+fn logs() -> String {
+    let word = |n: u64| format!("0x{:064x}", n);
+    let mut lines: Vec<String> = (1..=8u64).map(|n| json!({
+        "address": format!("0x{:040x}", 0xa0 + n % 2),
+        "blockNumber": format!("{:#x}", 100 + n),
+        "logIndex": "0x1",
+        "topics": [TRANSFER, word(1), word(2)],
+        "data": word(n * 1000),
+    }).to_string()).collect();
+    lines.push(json!({ "topics": [word(0x11)], "data": "0x" }).to_string());
+    lines.push("not json".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// A fresh directory under the system temp dir for one test.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("evm-log-format-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn abi() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../abi/erc20.json")
+}
+
+/// Runs the CLI with `--log-format json` and `args`, asserting every stderr line parses as a JSON object; returns
+/// the success of the run and the `message` of each event.
+fn run(args: &[&str]) -> (bool, Vec<String>) {
+    let output = Command::new(env!("CARGO_BIN_EXE_evm_rust_decoder"))
+        .args(["--log-format", "json", "--abi"])
+        .arg(abi())
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let messages = stderr.lines().map(|line| {
+        let event: Value = serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {:?} in\n{}", e, line, stderr));
+        event["message"].as_str().unwrap_or_else(|| panic!("no message in {:?}", line)).to_string()
+    }).collect();
+    (output.status.success(), messages)
+}
+
+fn assert_has(messages: &[String], expected: &[&str]) {
+    let seen: HashSet<&str> = messages.iter().map(String::as_str).collect();
+    for message in expected {
+        assert!(seen.contains(message), "no {:?} event in {:?}", message, messages);
+    }
+}
+
+#[test]
+fn single_threaded_results_are_events() {
+    let dir = scratch("sequential");
+    let input = dir.join("logs.jsonl");
+    fs::write(&input, logs()).unwrap();
+    let path = |name: &str| dir.join(name).display().to_string();
+    let (ok, messages) = run(&[
+        "--input", &path("logs.jsonl"), "--print", "--on-error", "skip", "--dedupe", "--where", "value > 2000", "--topk", "to:2",
+        "--phase-timing", "--prefilter", "--sink", &format!("sqlite:{}", path("logs.db")), "--group-by", "address",
+        "--partition-blocks", "4", "--error-log", &path("errors.jsonl"), "--checkpoint", &path("checkpoint.json"),
+    ]);
+    assert!(ok);
+    assert_has(&messages, &[
+        "summary", "sink", "contracts", "partitions", "partition", "error_log", "checkpoint", "filtered", "skipped", "where",
+        "topk", "prefilter", "phases",
+    ]);
+    let (ok, messages) = run(&["--input", &path("logs.jsonl"), "--print", "--on-error", "skip", "--writer-thread", "--sample-every", "2"]);
+    assert!(ok);
+    assert_has(&messages, &["summary", "writer", "sample", "skipped"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pipeline_files_and_failures_are_events() {
+    let dir = scratch("modes");
+    let valid: String = logs().lines().take(8).map(|line| format!("{}\n", line)).collect();
+    for name in ["a.jsonl", "b.jsonl"] { fs::write(dir.join(name), &valid).unwrap(); }
+    let path = |name: &str| dir.join(name).display().to_string();
+    let (ok, messages) = run(&["--input", &path("a.jsonl"), "--pipeline"]);
+    assert!(ok);
+    assert_has(&messages, &["summary", "pipeline"]);
+    let (ok, messages) = run(&["--input", &path("a.jsonl"), "--input", &path("b.jsonl")]);
+    assert!(ok);
+    assert_has(&messages, &["file", "files", "summary"]);
+    // A run that fails reports the error as an event too
+    fs::write(dir.join("bad.jsonl"), logs()).unwrap();
+    let (ok, messages) = run(&["--input", &path("bad.jsonl")]);
+    assert!(!ok);
+    assert_has(&messages, &["failed"]);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,142 @@
+//! `--where` expressions evaluated against hand-built decoded logs: precedence, literal kinds against parameter
+//! types, signed comparisons, missing parameters, and the errors of malformed expressions.
+
+use ethabi::{Event, EventParam, Log, LogParam, ParamType, Token};
+use ethereum_types::{H160, U256};
+use evm_rust_decoder::decode::{DecodedLog, LogMeta};
+use evm_rust_decoder::predicate::Predicate;
+
+/// An event of three bools `a`, `b`, `c`, an `int256 amount`, an `address to` and a `uint256 value`.
+fn event() -> Event {
+    let param = |name: &str, kind| EventParam { name: name.to_string(), kind, indexed: false };
+    Event {
+        name: "Sample".to_string(),
+        inputs: vec![
+            param("a", ParamType::Bool),
+            param("b", ParamType::Bool),
+            param("c", ParamType::Bool),
+            param("amount", ParamType::Int(256)),
+            param("to", ParamType::Address),
+            param("value", ParamType::Uint(256)),
+        ],
+        anonymous: false,
+    }
+}
+
+/// A log of [`event`] with the bools `a`, `b`, `c` and `amount`; `to` is `0x…dead` and `value` is 1e18.
+fn log(event: &Event, (a, b, c): (bool, bool, bool), amount: i64) -> DecodedLog<'_> {
+    let amount = if amount < 0 { !U256::from(amount.unsigned_abs()) + 1 } else { U256::from(amount) };
+    let values = [
+        Token::Bool(a),
+        Token::Bool(b),
+        Token::Bool(c),
+        Token::Int(amount),
+        Token::Address(H160::from_low_u64_be(0xdead)),
+        Token::Uint(U256::exp10(18)),
+    ];
+    let params = event.inputs.iter().zip(values).map(|(p, value)| LogParam { name: p.name.clone(), value }).collect();
+    DecodedLog { event, log: Log { params }, meta: LogMeta::default() }
+}
+
+fn eval(expression: &str, log: &DecodedLog) -> bool {
+    Predicate::parse(expression).unwrap().matches(log)
+}
+
+fn parse_error(expression: &str) -> String {
+    Predicate::parse(expression).unwrap_err().to_string()
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let event = event();
+    // `a || (b && c)` is true where `(a || b) && c` is not
+    let log = log(&event, (true, false, false), 0);
+    assert!(eval("a || b && c", &log));
+    assert!(!eval("(a || b) && c", &log));
+    assert!(eval("b && c || a", &log));
+}
+
+#[test]
+fn not_applies_to_the_nearest_operand() {
+    let event = event();
+    let log = log(&event, (true, true, false), 0);
+    assert!(eval("!a || b", &log));
+    assert!(!eval("!(a || b)", &log));
+    assert!(eval("!c && a", &log));
+    assert!(eval("!!a", &log));
+    assert!(!eval("!(value == 1e18)", &log));
+}
+
+#[test]
+fn hex_literals_compare_as_numbers_or_bytes_in_any_case() {
+    let event = event();
+    let log = log(&event, (false, false, false), 0);
+    // 1e18 is 0x0de0b6b3a7640000
+    assert!(eval("value == 0xDE0B6B3A7640000", &log));
+    assert!(eval("value == 0x0de0b6b3a7640000", &log));
+    assert!(eval("value > 0x0De0b6b3a763FFFF", &log));
+    assert!(eval("0xde0b6b3a7640001 > value", &log));
+    assert!(eval("to == 0x000000000000000000000000000000000000DeaD", &log));
+    assert!(eval("to == 0x000000000000000000000000000000000000dead", &log));
+    assert!(!eval("to == 0x000000000000000000000000000000000000beef", &log));
+    // Bytes compare byte by byte: a shorter hex literal is not a zero-padded address
+    assert!(!eval("to == 0xdead", &log));
+}
+
+#[test]
+fn signed_ints_compare_by_sign() {
+    let event = event();
+    let negative = log(&event, (false, false, false), -10);
+    assert!(eval("amount < -5", &negative));
+    assert!(eval("amount <= -10", &negative));
+    assert!(!eval("amount < -10", &negative));
+    assert!(eval("amount > -11", &negative));
+    assert!(eval("amount < 0", &negative));
+    assert!(eval("amount == -1_0", &negative));
+    let positive = log(&event, (false, false, false), 3);
+    assert!(!eval("amount < -5", &positive));
+    assert!(eval("amount > -5", &positive));
+    // -0 reads as 0
+    let zero = log(&event, (false, false, false), 0);
+    assert!(eval("amount == -0", &zero));
+}
+
+#[test]
+fn comparisons_with_missing_parameters_are_false() {
+    let event = event();
+    let log = log(&event, (true, false, false), 0);
+    assert!(!eval("missing == 1", &log));
+    assert!(!eval("missing != 1", &log));
+    assert!(!eval("missing", &log));
+    assert!(eval("!(missing == 1)", &log));
+    assert!(eval("missing == 1 || a", &log));
+    // A parameter against a literal of another kind is false the same way
+    assert!(!eval("value == 'one'", &log));
+    assert!(!eval("a == 1", &log));
+}
+
+#[test]
+fn malformed_expressions_are_errors() {
+    assert!(parse_error("a ||").contains("found the end"), "{}", parse_error("a ||"));
+    assert!(parse_error("a || || b").contains("found `||`"));
+    assert!(parse_error("(a || b").contains("expected `)`"));
+    assert!(parse_error("a b").contains("unexpected `b`"));
+    assert!(parse_error("event == 'Transfer").contains("unterminated string at offset 9"));
+    assert!(parse_error("value > 1x5").contains("invalid number 1x5"));
+    assert!(parse_error("to == 0xzz").contains("invalid hex literal"));
+    assert!(parse_error("a # b").contains("unexpected '#'"));
+}
+
+#[test]
+fn exponents_past_u256_are_invalid_numbers() {
+    let event = event();
+    let log = log(&event, (false, false, false), 0);
+    for huge in ["1e18446744073709551615", "1e999999999999999", "1e79", "0e79"] {
+        let expression = format!("value > {}", huge);
+        assert!(parse_error(&expression).contains(&format!("invalid number {}", huge)), "{}", parse_error(&expression));
+    }
+    // 10^78 is over 2^256, 10^77 still fits
+    assert!(parse_error("value > 1e78").contains("invalid number 1e78"));
+    assert!(eval("value < 1e77", &log));
+    assert!(eval("value == 1E18", &log));
+}
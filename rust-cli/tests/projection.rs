@@ -0,0 +1,71 @@
+//! `--select` projections of an output record: several outputs per log, missing paths, array indexing, array and
+//! object constructors, and the errors of malformed expressions.
+
+use evm_rust_decoder::projection::Projection;
+use serde_json::{json, Value};
+
+fn record() -> Value {
+    json!({
+        "event": "TransferBatch",
+        "blockNumber": 18_000_000,
+        "args": { "from": "0x01", "to": "0x02", "ids": ["1", "2", "3"], "values": [["7"], ["8", "9"]] },
+    })
+}
+
+/// The lines `expression` writes for [`record`], each parsed back.
+fn select(expression: &str) -> Vec<Value> {
+    let mut line = Vec::new();
+    Projection::parse(expression).unwrap().write(&mut line, &record()).unwrap();
+    String::from_utf8(line).unwrap().split('\n').map(|v| serde_json::from_str(v).unwrap()).collect()
+}
+
+fn parse_error(expression: &str) -> String {
+    Projection::parse(expression).unwrap_err().to_string()
+}
+
+#[test]
+fn top_level_commas_emit_one_value_each() {
+    assert_eq!(select(".args.from, .args.to, .blockNumber"), vec![json!("0x01"), json!("0x02"), json!(18_000_000)]);
+    assert_eq!(select("."), vec![record()]);
+    // Commas inside a constructor build one value
+    assert_eq!(select("[.args.from, .args.to], .event"), vec![json!(["0x01", "0x02"]), json!("TransferBatch")]);
+}
+
+#[test]
+fn missing_paths_give_null() {
+    assert_eq!(select(".args.missing"), vec![Value::Null]);
+    assert_eq!(select(".nothing.below.here"), vec![Value::Null]);
+    // A field of a string, or an index past the end or into an object, is missing too
+    assert_eq!(select(".event.name, .args.ids[3], .args[0]"), vec![Value::Null, Value::Null, Value::Null]);
+    assert_eq!(select("{value: .args.value, event}"), vec![json!({ "value": null, "event": "TransferBatch" })]);
+}
+
+#[test]
+fn arrays_are_indexed_at_any_depth() {
+    assert_eq!(select(".args.ids[0], .args.ids[ 2 ]"), vec![json!("1"), json!("3")]);
+    assert_eq!(select(".args.values[1][0]"), vec![json!("8")]);
+    assert_eq!(select(".args[\"ids\"][1], .\"args\".to"), vec![json!("2"), json!("0x02")]);
+}
+
+#[test]
+fn objects_take_bare_keys_and_paths() {
+    assert_eq!(select("{blockNumber, \"first id\": .args.ids[0], ids: [.args.ids[0], .args.ids[1]]}"), vec![json!({
+        "blockNumber": 18_000_000,
+        "first id": "1",
+        "ids": ["1", "2"],
+    })]);
+}
+
+#[test]
+fn malformed_expressions_are_errors() {
+    assert!(parse_error("").contains("expected a path starting with `.`, `[` or `{` at offset 0"));
+    assert!(parse_error("args.from").contains("expected a path"));
+    assert!(parse_error(".args.").contains("expected a field name at offset 6"));
+    assert!(parse_error(".args.ids[x]").contains("expected an index or a quoted key at offset 10"));
+    assert!(parse_error(".args.ids[0").contains("expected `]`"));
+    assert!(parse_error("[.a, .b").contains("expected `]`"));
+    assert!(parse_error("{a: .a").contains("expected `}`"));
+    assert!(parse_error(".args[\"from]").contains("unterminated string"));
+    assert!(parse_error(".a, ").contains("expected a path"));
+    assert!(parse_error(".a .b").contains("unexpected \".b\" at offset 3"));
+}
@@ -0,0 +1,120 @@
+//! `--input-format protobuf` over random length-delimited `sf.ethereum.type.v2.Log` streams built with prost: every
+//! log comes back whatever the read boundaries, and input cut anywhere but between messages is an error.
+
+mod common;
+
+use common::Chunked;
+use ethereum_types::H256;
+use evm_rust_decoder::decode::{LogMeta, LogRecord};
+use evm_rust_decoder::protobuf::{self, pb};
+use ethabi::RawLog;
+use prost::Message;
+use proptest::prelude::*;
+
+/// (address, topics, data, block index) of one log; addresses are absent or 20 bytes as Firehose writes them.
+type Log = (Option<[u8; 20]>, Vec<[u8; 32]>, Vec<u8>, u32);
+
+fn logs() -> impl Strategy<Value = Vec<Log>> {
+    prop::collection::vec(
+        (any::<Option<[u8; 20]>>(), prop::collection::vec(any::<[u8; 32]>(), 0..5), prop::collection::vec(any::<u8>(), 0..300), any::<u32>()),
+        0..12,
+    )
+}
+
+fn message((address, topics, data, index): &Log) -> pb::Log {
+    pb::Log {
+        address: address.map(|a| a.to_vec()).unwrap_or_default(),
+        topics: topics.iter().map(|t| t.to_vec()).collect(),
+        data: data.clone(),
+        block_index: *index,
+        ..Default::default()
+    }
+}
+
+/// A log as read back: address, topics, data and log index.
+type ReadLog = (Option<String>, Vec<H256>, Vec<u8>, Option<u64>);
+
+/// The logs read back from `input`, stopping at the first error.
+fn read(input: impl std::io::Read) -> anyhow::Result<Vec<ReadLog>> {
+    let mut read = Vec::new();
+    protobuf::for_each_log(input, |record| {
+        let record = record?;
+        read.push((record.meta.address, record.raw.topics, record.raw.data, record.meta.log_index));
+        Ok(())
+    })?;
+    Ok(read)
+}
+
+proptest! {
+    #[test]
+    fn messages_are_read_across_any_split(logs in logs(), splits in prop::collection::vec(1..64usize, 1..6)) {
+        // Logs without topics are skipped, the others come back in order
+        let stream: Vec<u8> = logs.iter().flat_map(|log| message(log).encode_length_delimited_to_vec()).collect();
+        let expected: Vec<_> = logs
+            .iter()
+            .filter(|(_, topics, ..)| !topics.is_empty())
+            .map(|(address, topics, data, index)| {
+                (address.map(|a| format!("0x{}", hex::encode(a))), topics.iter().map(|t| H256(*t)).collect(), data.clone(), Some(u64::from(*index)))
+            })
+            .collect();
+        prop_assert_eq!(read(Chunked::new(stream, &splits)).unwrap(), expected);
+    }
+
+    #[test]
+    fn written_records_read_back(logs in logs()) {
+        let records: Vec<LogRecord> = logs.iter().filter(|(_, topics, ..)| !topics.is_empty()).map(|(address, topics, data, index)| LogRecord {
+            raw: RawLog { topics: topics.iter().map(|t| H256(*t)).collect(), data: data.clone() },
+            meta: LogMeta { address: address.map(|a| format!("0x{}", hex::encode(a))), log_index: Some(u64::from(*index)), ..LogMeta::default() },
+        }).collect();
+        let mut stream = Vec::new();
+        for record in &records { protobuf::write_record(&mut stream, record).unwrap(); }
+        let expected: Vec<_> = records.into_iter().map(|r| (r.meta.address, r.raw.topics, r.raw.data, r.meta.log_index)).collect();
+        prop_assert_eq!(read(&stream[..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn truncated_input_is_an_error(logs in logs(), cut in any::<prop::sample::Index>(), splits in prop::collection::vec(1..64usize, 1..6)) {
+        // Cut between messages, the stream is a shorter valid one; cut anywhere else, the read fails without panicking
+        let messages: Vec<Vec<u8>> = logs.iter().map(|log| message(log).encode_length_delimited_to_vec()).collect();
+        let stream: Vec<u8> = messages.concat();
+        let cut = cut.index(stream.len() + 1);
+        let boundaries: Vec<usize> = messages.iter().scan(0, |end, m| { *end += m.len(); Some(*end) }).collect();
+        let result = read(Chunked::new(stream[..cut].to_vec(), &splits));
+        if cut == 0 || boundaries.contains(&cut) {
+            let whole = boundaries.iter().take_while(|&&end| end <= cut).count();
+            let expected = logs[..whole].iter().filter(|(_, topics, ..)| !topics.is_empty()).count();
+            prop_assert_eq!(result.unwrap().len(), expected);
+        } else {
+            let error = result.unwrap_err().to_string();
+            prop_assert!(error.contains("input ends mid-"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn malformed_logs_are_reported_one_by_one(topic_len in (0..64usize).prop_filter("not 32", |&n| n != 32), address_len in (1..64usize).prop_filter("not 20", |&n| n != 20)) {
+        // A log whose topic or address has the wrong length fails on its own, and the next one still reads
+        let good = message(&(None, vec![[1; 32]], vec![2], 3));
+        let bad_topic = pb::Log { topics: vec![vec![0; topic_len]], ..good.clone() };
+        let bad_address = pb::Log { address: vec![0; address_len], ..good.clone() };
+        let stream: Vec<u8> = [bad_topic, bad_address, good].iter().flat_map(|m| m.encode_length_delimited_to_vec()).collect();
+        let mut results = Vec::new();
+        protobuf::for_each_log(&stream[..], |record| {
+            results.push(record.map(|r| r.raw.data).map_err(|e| format!("{:#}", e)));
+            Ok(())
+        }).unwrap();
+        prop_assert_eq!(results.len(), 3);
+        let (topic_error, address_error) = (format!("topic of {} bytes, expected 32", topic_len), format!("address of {} bytes, expected 20", address_len));
+        prop_assert!(results[0].as_ref().unwrap_err().contains(&topic_error));
+        prop_assert!(results[1].as_ref().unwrap_err().contains(&address_error));
+        prop_assert_eq!(results[2].as_ref().unwrap(), &vec![2u8]);
+    }
+}
+
+#[test]
+fn corrupt_lengths_fail_without_allocating_them() {
+    // A length prefix near 2^63 followed by a few bytes, and one that never ends
+    let mut huge = vec![0xff; 8];
+    huge.extend([0x7f, 1, 2, 3]);
+    assert!(read(&huge[..]).unwrap_err().to_string().contains("input ends mid-message"));
+    assert!(read(&[0xff; 11][..]).unwrap_err().to_string().contains("length prefix is not a varint"));
+}
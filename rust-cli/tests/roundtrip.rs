@@ -0,0 +1,192 @@
+//! Encode-then-decode round trips over random events: random param types and tokens are ABI-encoded into
+//! topics/data, written out as an ABI document plus a JSONL log, and pushed back through every decode path the
+//! CLI has (selected event, topic0 routing, whole-document streaming, chain-scoped ABIs). Each must return the
+//! original tokens, as must the printed args parsed back, hex data in either case and signed ints formatted.
+
+use anyhow::Result;
+use ethabi::{encode, Event, EventParam, Log, LogParam, ParamType, Token};
+use ethereum_types::{H160, U256};
+use evm_rust_decoder::abi::{events_from_json, AbiSet, CollisionPolicy};
+use evm_rust_decoder::core_decode::parse_data_into;
+use evm_rust_decoder::decode::{to_raw_log, LogDecoder};
+use evm_rust_decoder::json_stream;
+use evm_rust_decoder::output::int_to_string;
+use evm_rust_decoder::value::{params, token_from_json};
+use proptest::prelude::*;
+use serde_json::json;
+use std::time::Duration;
+
+fn param_type() -> impl Strategy<Value = ParamType> {
+    let leaf = prop_oneof![
+        Just(ParamType::Address),
+        Just(ParamType::Bool),
+        (1..=32usize).prop_map(|n| ParamType::Uint(n * 8)),
+        (1..=32usize).prop_map(|n| ParamType::Int(n * 8)),
+        (1..=32usize).prop_map(ParamType::FixedBytes),
+        Just(ParamType::Bytes),
+        Just(ParamType::String),
+    ];
+    leaf.prop_recursive(2, 8, 3, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|t| ParamType::Array(Box::new(t))),
+            (inner, 1..=3usize).prop_map(|(t, n)| ParamType::FixedArray(Box::new(t), n)),
+        ]
+    })
+}
+
+/// A random value of `kind`, kept within its bit width (ints sign-extended) as ethabi produces on decode.
+fn token(kind: &ParamType) -> BoxedStrategy<Token> {
+    match kind {
+        ParamType::Address => any::<[u8; 20]>().prop_map(|b| Token::Address(H160::from(b))).boxed(),
+        ParamType::Bool => any::<bool>().prop_map(Token::Bool).boxed(),
+        ParamType::Uint(bits) => {
+            let bits = *bits;
+            any::<[u8; 32]>().prop_map(move |b| Token::Uint(truncate(U256::from_big_endian(&b), bits))).boxed()
+        }
+        ParamType::Int(bits) => {
+            let bits = *bits;
+            any::<[u8; 32]>()
+                .prop_map(move |b| {
+                    let v = truncate(U256::from_big_endian(&b), bits);
+                    Token::Int(if bits < 256 && v.bit(bits - 1) { v | (U256::MAX << bits) } else { v })
+                })
+                .boxed()
+        }
+        ParamType::FixedBytes(n) => proptest::collection::vec(any::<u8>(), *n).prop_map(Token::FixedBytes).boxed(),
+        ParamType::Bytes => proptest::collection::vec(any::<u8>(), 0..64).prop_map(Token::Bytes).boxed(),
+        ParamType::String => "\\PC{0,16}".prop_map(Token::String).boxed(),
+        ParamType::Array(inner) => proptest::collection::vec(token(inner), 0..4).prop_map(Token::Array).boxed(),
+        ParamType::FixedArray(inner, n) => proptest::collection::vec(token(inner), *n).prop_map(Token::FixedArray).boxed(),
+        ParamType::Tuple(_) => unreachable!("tuples are not generated"),
+    }
+}
+
+fn truncate(v: U256, bits: usize) -> U256 {
+    if bits >= 256 { v } else { v & ((U256::one() << bits) - 1) }
+}
+
+/// An event with up to six params and the tokens of one log. Only elementary value types are indexed: arrays
+/// and dynamic types are stored hashed in topics and cannot be decoded back.
+fn event_with_tokens() -> impl Strategy<Value = (Event, Vec<Token>)> {
+    proptest::collection::vec((param_type(), any::<bool>()), 0..6)
+        .prop_map(|params| {
+            let mut indexed_count = 0;
+            let inputs = params
+                .into_iter()
+                .enumerate()
+                .map(|(i, (kind, want_indexed))| {
+                    let elementary = !matches!(kind, ParamType::Bytes | ParamType::String | ParamType::Array(_) | ParamType::FixedArray(..));
+                    let indexed = want_indexed && elementary && indexed_count < 3;
+                    if indexed { indexed_count += 1; }
+                    EventParam { name: format!("p{}", i), kind, indexed }
+                })
+                .collect();
+            Event { name: "Random".to_string(), inputs, anonymous: false }
+        })
+        .prop_flat_map(|event| {
+            let tokens: Vec<_> = event.inputs.iter().map(|p| token(&p.kind)).collect();
+            (Just(event), tokens)
+        })
+}
+
+/// The ABI document and JSONL line the CLI would read for this log.
+fn to_inputs(event: &Event, tokens: &[Token]) -> (serde_json::Value, String) {
+    let inputs: Vec<_> = event
+        .inputs
+        .iter()
+        .map(|p| json!({ "name": p.name, "type": p.kind.to_string(), "indexed": p.indexed }))
+        .collect();
+    let abi = json!([{ "type": "event", "name": event.name, "inputs": inputs, "anonymous": false }]);
+
+    let hex = |b: &[u8]| format!("0x{}", hex::encode(b));
+    let mut topics = vec![hex(event.signature().as_bytes())];
+    let mut data_tokens = Vec::new();
+    for (param, token) in event.inputs.iter().zip(tokens) {
+        if param.indexed { topics.push(hex(&encode(std::slice::from_ref(token)))); } else { data_tokens.push(token.clone()); }
+    }
+    let line = json!({ "topics": topics, "data": hex(&encode(&data_tokens)) }).to_string();
+    (abi, line)
+}
+
+fn decoded_tokens(decoder: &LogDecoder, line: &str) -> Result<Vec<Vec<Token>>> {
+    let mut out = Vec::new();
+    decoder.parse_line(line, |record| {
+        let decoded = decoder.decode(record)?;
+        out.push(decoded.log.params.into_iter().map(|p| p.value).collect());
+        Ok(())
+    })?;
+    Ok(out)
+}
+proptest! {
+    #[test]
+    fn encode_then_decode_round_trips((event, tokens) in event_with_tokens()) {
+        let (abi, line) = to_inputs(&event, &tokens);
+        let events = events_from_json(&abi).unwrap();
+        prop_assert_eq!(&events, &vec![event.clone()], "ABI types did not survive the JSON round trip");
+
+        for by_topic0 in [false, true] {
+            let decoder = LogDecoder::new(events[0].clone(), &events, by_topic0);
+            prop_assert_eq!(decoded_tokens(&decoder, &line).unwrap(), vec![tokens.clone()], "by_topic0={}", by_topic0);
+        }
+
+        let decoder = LogDecoder::new(events[0].clone(), &events, true);
+        let mut streamed = Vec::new();
+        json_stream::for_each_log(format!("[{}]", line).as_bytes(), |parsed| {
+            if let Some(record) = to_raw_log(&parsed)? {
+                streamed.push(decoder.decode(record)?.log.params.into_iter().map(|p| p.value).collect::<Vec<_>>());
+            }
+            Ok(())
+        }).unwrap();
+        prop_assert_eq!(streamed, vec![tokens]);
+    }
+
+    #[test]
+    fn chain_scoped_abis_decode_only_their_chain((event, tokens) in event_with_tokens(), chain in 1..100_000u64, other in 1..100_000u64) {
+        prop_assume!(chain != other);
+        let (_, line) = to_inputs(&event, &tokens);
+        let on_chain = |id: u64| {
+            let mut log: serde_json::Value = serde_json::from_str(&line).unwrap();
+            log["chainId"] = json!(id);
+            log.to_string()
+        };
+        let set = AbiSet { events: vec![(event.signature(), event.clone())], files: 1, cache_hits: 0, load_time: Duration::ZERO };
+        let decoder = LogDecoder::new(event.clone(), &[], true).with_chain_abis(&[(chain, set)], CollisionPolicy::First);
+        prop_assert_eq!(decoded_tokens(&decoder, &on_chain(chain)).unwrap(), vec![tokens.clone()]);
+        prop_assert!(decoded_tokens(&decoder, &on_chain(other)).is_err());
+        prop_assert!(decoded_tokens(&decoder, &line).is_err());
+        // `--chain-id` routes the logs that carry no chain
+        let decoder = decoder.with_chain_id(Some(chain));
+        prop_assert_eq!(decoded_tokens(&decoder, &line).unwrap(), vec![tokens]);
+    }
+
+    #[test]
+    fn signed_ints_format_as_decimal(v in any::<i128>()) {
+        let (mut word, fill) = ([0u8; 32], if v < 0 { 0xff } else { 0 });
+        word[..16].fill(fill);
+        word[16..].copy_from_slice(&v.to_be_bytes());
+        prop_assert_eq!(int_to_string(&U256::from_big_endian(&word)), v.to_string());
+    }
+
+    #[test]
+    fn hex_data_decodes_in_any_case(bytes in prop::collection::vec(any::<u8>(), 0..100), upper in any::<bool>(), bad in any::<Option<prop::sample::Index>>()) {
+        let mut hex = hex::encode(&bytes);
+        if upper { hex.make_ascii_uppercase(); }
+        if let (Some(at), false) = (bad, hex.is_empty()) { hex.replace_range(at.index(hex.len())..=at.index(hex.len()), "g"); }
+        let mut buf = Vec::new();
+        match parse_data_into(&format!("0x{}", hex), &mut buf) {
+            Ok(()) => { prop_assert!(bad.is_none() || bytes.is_empty()); prop_assert_eq!(buf, bytes); }
+            Err(e) => prop_assert_eq!(e.to_string(), hex::decode(&hex).unwrap_err().to_string()),
+        }
+    }
+
+    #[test]
+    fn printed_args_parse_back_to_tokens((event, tokens) in event_with_tokens()) {
+        let log = Log {
+            params: event.inputs.iter().zip(&tokens).map(|(p, t)| LogParam { name: p.name.clone(), value: t.clone() }).collect(),
+        };
+        let printed = serde_json::to_value(params(&log)).unwrap();
+        for (param, token) in event.inputs.iter().zip(&tokens) {
+            prop_assert_eq!(&token_from_json(&printed[&param.name], &param.kind).unwrap(), token, "{}", param.name);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! `--serve` over stdio end to end: the resident decoder is built with `--collision-policy` and `--on-error`, as a
+//! decoding run's is.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde_json::{json, Value};
+
+const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// A fresh directory under the system temp dir for one test.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("evm-serve-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Sends `requests` as frames to `--serve` with `args`, then closes stdin; returns the success of the run, the
+/// responses and stderr.
+fn serve(abi: &Path, args: &[&str], requests: &[Value]) -> (bool, Vec<Value>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_evm_rust_decoder"))
+        .arg("--abi")
+        .arg(abi)
+        .arg("--serve")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for request in requests {
+        let frame = serde_json::to_vec(request).unwrap();
+        // The server may already have exited, e.g. on a refused ABI
+        let _ = stdin.write_all(&(frame.len() as u32).to_be_bytes()).and_then(|_| stdin.write_all(&frame));
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    let mut stdout = &output.stdout[..];
+    let mut responses = Vec::new();
+    let mut len = [0u8; 4];
+    while stdout.read_exact(&mut len).is_ok() {
+        let mut frame = vec![0u8; u32::from_be_bytes(len) as usize];
+        stdout.read_exact(&mut frame).unwrap();
+        responses.push(serde_json::from_slice(&frame).unwrap());
+    }
+    (output.status.success(), responses, String::from_utf8(output.stderr).unwrap())
+}
+
+/// A Transfer log; `data` of a value word, or none, which does not decode.
+fn transfer(value: Option<u64>) -> Value {
+    let word = |n: u64| format!("0x{:064x}", n);
+    json!({ "topics": [TRANSFER, word(1), word(2)], "data": value.map_or("0x".to_string(), word) })
+}
+
+#[test]
+fn collision_policy_is_applied() {
+    // The ERC-20 and ERC-721 Transfers share a topic0 but index different parameters
+    let dir = scratch("collision");
+    let abi = dir.join("abi.json");
+    let input = |name: &str, kind: &str, indexed: bool| json!({ "name": name, "type": kind, "indexed": indexed });
+    fs::write(&abi, json!([
+        { "type": "event", "name": "Transfer", "anonymous": false, "inputs": [input("from", "address", true), input("to", "address", true), input("value", "uint256", false)] },
+        { "type": "event", "name": "Transfer", "anonymous": false, "inputs": [input("from", "address", true), input("to", "address", true), input("tokenId", "uint256", true)] },
+    ]).to_string()).unwrap();
+    let (ok, responses, stderr) = serve(&abi, &["--collision-policy", "error"], &[json!({ "op": "stats" })]);
+    assert!(!ok);
+    assert!(responses.is_empty());
+    assert!(stderr.contains("topic0s are shared by events with different indexed parameters"), "{}", stderr);
+    // By topic count, a log with three topics decodes as the ERC-20 Transfer
+    let request = json!({ "op": "decode", "logs": [transfer(Some(5))], "records": true });
+    let (ok, responses, _) = serve(&abi, &["--collision-policy", "by-topic-count"], &[request]);
+    assert!(ok);
+    assert_eq!(responses[0]["records"][0]["value"], "5");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn on_error_skip_leaves_out_logs_that_do_not_decode() {
+    let abi = Path::new(env!("CARGO_MANIFEST_DIR")).join("../abi/erc20.json");
+    let request = json!({ "op": "decode", "lines": [transfer(Some(1)).to_string(), "not json"], "logs": [transfer(None), transfer(Some(2))] });
+    let (ok, responses, _) = serve(&abi, &[], std::slice::from_ref(&request));
+    assert!(ok);
+    assert_eq!(responses[0]["ok"], false);
+    assert!(responses[0]["error"].as_str().unwrap().starts_with("lines[1]"), "{}", responses[0]);
+    let (ok, responses, _) = serve(&abi, &["--on-error", "skip"], &[request]);
+    assert!(ok);
+    assert_eq!(responses[0]["ok"], true);
+    assert_eq!(responses[0]["decoded"], 2);
+}
@@ -0,0 +1,200 @@
+//! `--sink sqlite:PATH` end to end: the CLI decodes a JSONL file into per-event tables, in batches, and the tables
+//! read back with the columns, declared types and values the ABI implies; flags that drop parameters are refused.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use ethabi::{encode, Event, Token};
+use ethereum_types::{H160, U256};
+use evm_rust_decoder::abi::events_from_json;
+use rusqlite::types::Value as Sql;
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+
+/// `Moved` with a parameter of every column kind and one that clashes with an envelope column, and an overload:
+/// sharing a name, both get their topic0 in the table name.
+fn abi() -> serde_json::Value {
+    let input = |name: &str, kind: &str, indexed: bool| json!({ "name": name, "type": kind, "indexed": indexed });
+    json!([
+        { "type": "event", "name": "Moved", "anonymous": false, "inputs": [
+            input("from", "address", true),
+            input("memo", "string", true),
+            input("value", "uint256", false),
+            input("small", "uint8", false),
+            input("delta", "int64", false),
+            input("flag", "bool", false),
+            input("data", "bytes", false),
+            input("ids", "uint256[]", false),
+            input("log_index", "uint32", false),
+        ] },
+        { "type": "event", "name": "Moved", "anonymous": false, "inputs": [input("amount", "uint128", false)] },
+    ])
+}
+
+/// A JSONL line for `event`, indexed `tokens` as topics and the rest as data.
+fn line(event: &Event, tokens: &[Token], block: u64) -> String {
+    let hex = |b: &[u8]| format!("0x{}", hex::encode(b));
+    let mut topics = vec![hex(event.signature().as_bytes())];
+    let mut data = Vec::new();
+    for (param, token) in event.inputs.iter().zip(tokens) {
+        match (param.indexed, token) {
+            (true, Token::String(s)) => topics.push(hex(&Keccak256::digest(s.as_bytes()))),
+            (true, token) => topics.push(hex(&encode(std::slice::from_ref(token)))),
+            (false, token) => data.push(token.clone()),
+        }
+    }
+    json!({ "address": "0x00000000000000000000000000000000000000aa", "blockNumber": format!("{:#x}", block), "logIndex": "0x1", "topics": topics, "data": hex(&encode(&data)) }).to_string()
+}
+
+fn moved(events: &[Event], n: u64) -> String {
+    let tokens = [
+        Token::Address(H160::from_low_u64_be(n)),
+        Token::String(format!("memo {}", n)),
+        Token::Uint(U256::MAX - n),
+        Token::Uint(U256::from(n)),
+        Token::Int(if n.is_multiple_of(2) { U256::from(n) } else { !U256::from(n) + 1 }),
+        Token::Bool(n.is_multiple_of(2)),
+        Token::Bytes(vec![n as u8; 3]),
+        Token::Array(vec![Token::Uint(U256::from(n)), Token::Uint(U256::from(n + 1))]),
+        Token::Uint(U256::from(7)),
+    ];
+    line(&events[0], &tokens, 100 + n)
+}
+
+/// A fresh directory under the system temp dir for one test.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("evm-sink-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn sqlite_tables_round_trip_columns_and_values() {
+    let dir = scratch("sqlite");
+    let abi = abi();
+    let events = events_from_json(&abi).unwrap();
+    let mut lines: Vec<String> = (1..=5).map(|n| moved(&events, n)).collect();
+    lines.push(line(&events[1], &[Token::Uint(U256::from(42))], 200));
+    fs::write(dir.join("abi.json"), abi.to_string()).unwrap();
+    fs::write(dir.join("logs.jsonl"), lines.join("\n")).unwrap();
+    let db_path = dir.join("logs.db");
+
+    // Batches of 2 leave a partial batch of `Moved` and one of the overload for the final flush
+    let output = Command::new(env!("CARGO_BIN_EXE_evm_rust_decoder"))
+        .arg("--abi").arg(dir.join("abi.json"))
+        .arg("--input").arg(dir.join("logs.jsonl"))
+        .arg("--sink").arg(format!("sqlite:{}", db_path.display()))
+        .args(["--sink-batch", "2"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("sink target=sqlite rows=6 batches=4 "), "{}", stderr);
+
+    let db = rusqlite::Connection::open(&db_path).unwrap();
+    let columns = |table: &str| -> Vec<(String, String)> {
+        let mut statement = db.prepare(&format!("SELECT name, type FROM pragma_table_info('{}') ORDER BY cid", table)).unwrap();
+        statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    };
+    let owned = |columns: &[(&str, &str)]| -> Vec<(String, String)> {
+        columns.iter().map(|(n, t)| (n.to_string(), t.to_string())).collect()
+    };
+    let table = |event: &Event| format!("Moved_{}", &hex::encode(event.signature().as_bytes())[..8]);
+    let (moved, overload) = (table(&events[0]), table(&events[1]));
+    assert_eq!(columns(&moved), owned(&[
+        ("block_number", "INTEGER"),
+        ("log_index", "INTEGER"),
+        ("contract_address", "TEXT"),
+        ("from", "TEXT"),
+        ("memo", "BLOB"),
+        ("value", "TEXT"),
+        ("small", "INTEGER"),
+        ("delta", "INTEGER"),
+        ("flag", "INTEGER"),
+        ("data", "BLOB"),
+        ("ids", "TEXT"),
+        ("arg_log_index", "INTEGER"),
+    ]));
+    assert_eq!(columns(&overload), owned(&[("block_number", "INTEGER"), ("log_index", "INTEGER"), ("contract_address", "TEXT"), ("amount", "TEXT")]));
+
+    let mut statement = db.prepare(&format!("SELECT * FROM \"{}\" ORDER BY block_number", moved)).unwrap();
+    let rows: Vec<Vec<Sql>> = statement
+        .query_map([], |row| (0..12).map(|i| row.get::<_, Sql>(i)).collect())
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap();
+    assert_eq!(rows.len(), 5);
+    let memo = |n: u64| Keccak256::digest(format!("memo {}", n).as_bytes()).to_vec();
+    assert_eq!(rows[0], vec![
+        Sql::Integer(101),
+        Sql::Integer(1),
+        Sql::Text("0x00000000000000000000000000000000000000aa".to_string()),
+        Sql::Text(format!("{:#x}", H160::from_low_u64_be(1))),
+        Sql::Blob(memo(1)),
+        Sql::Text((U256::MAX - 1).to_string()),
+        Sql::Integer(1),
+        Sql::Integer(-1),
+        Sql::Integer(0),
+        Sql::Blob(vec![1; 3]),
+        Sql::Text("[\"1\",\"2\"]".to_string()),
+        Sql::Integer(7),
+    ]);
+    assert_eq!((rows[3][7].clone(), rows[3][8].clone()), (Sql::Integer(4), Sql::Integer(1)));
+    let amount: String = db.query_row(&format!("SELECT amount FROM \"{}\"", overload), [], |row| row.get(0)).unwrap();
+    assert_eq!(amount, "42");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn postgres_and_clickhouse_tables_type_each_column() {
+    // `schema --format sql` prints the statements `--sink` runs, so the servers are not needed to check them
+    let dir = scratch("ddl");
+    fs::write(dir.join("abi.json"), json!([abi()[0]]).to_string()).unwrap();
+    let ddl = |dialect: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_evm_rust_decoder"))
+            .args(["schema", "--format", "sql", "--dialect", dialect, "--abi"])
+            .arg(dir.join("abi.json"))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(ddl("postgres"), concat!(
+        r#"CREATE TABLE IF NOT EXISTS "Moved" ("block_number" BIGINT, "log_index" BIGINT, "contract_address" TEXT, "#,
+        r#""from" TEXT, "memo" BYTEA, "value" NUMERIC(78), "small" BIGINT, "delta" BIGINT, "flag" BOOLEAN, "data" BYTEA, "#,
+        r#""ids" JSONB, "arg_log_index" BIGINT);"#, "\n",
+    ));
+    assert_eq!(ddl("clickhouse"), concat!(
+        r#"CREATE TABLE IF NOT EXISTS "Moved" ("block_number" Nullable(Int64), "log_index" Nullable(Int64), "#,
+        r#""contract_address" Nullable(String), "from" String, "memo" String, "value" String, "small" Int64, "#,
+        r#""delta" Int64, "flag" Bool, "data" String, "ids" String, "arg_log_index" Int64) ENGINE = MergeTree ORDER BY tuple();"#, "\n",
+    ));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn logs_without_every_parameter_are_refused() {
+    // Rows take one value per ABI parameter, so flags that leave parameters out cannot feed a sink
+    let dir = scratch("partial");
+    fs::write(dir.join("abi.json"), abi().to_string()).unwrap();
+    fs::write(dir.join("logs.jsonl"), moved(&events_from_json(&abi()).unwrap(), 1)).unwrap();
+    let run = |flags: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_evm_rust_decoder"))
+            .arg("--abi").arg(dir.join("abi.json"))
+            .arg("--input").arg(dir.join("logs.jsonl"))
+            .arg("--sink").arg(format!("sqlite:{}", dir.join("logs.db").display()))
+            .args(flags)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    let stderr = run(&["--skip-decode"]);
+    assert!(stderr.contains("cannot be used with '--skip-decode'"), "{}", stderr);
+    let stderr = run(&["--fields", "value"]);
+    assert!(stderr.contains("cannot be used with '--fields <NAMES>'"), "{}", stderr);
+    assert!(!dir.join("logs.db").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
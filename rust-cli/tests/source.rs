@@ -0,0 +1,103 @@
+//! Input sources: `--exit-after` stops reading at its last line, and `zmq://` inputs speak ZMTP to a publisher
+//! written here, returning one line per message whatever the frame and write boundaries.
+
+use evm_rust_decoder::io::limit_lines;
+use evm_rust_decoder::source::Source;
+use proptest::prelude::*;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A reader with nothing to read that records being read, standing in for an input that has not sent its next line.
+struct Tripwire(Arc<AtomicBool>);
+
+impl Read for Tripwire {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.store(true, Ordering::Relaxed);
+        Ok(0)
+    }
+}
+
+fn zmtp_frame(out: &mut Vec<u8>, flags: u8, body: &[u8]) {
+    match u8::try_from(body.len()) {
+        Ok(len) => out.extend([flags, len]),
+        Err(_) => {
+            out.push(flags | 0x02);
+            out.extend((body.len() as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(body);
+}
+
+/// A ZeroMQ PUB socket on a free port that takes one subscriber and sends it `messages` (topic frames, then the
+/// payload) in writes of `splits` bytes, so frames arrive cut anywhere.
+fn zmq_publisher(messages: &[(usize, Vec<u8>)], splits: &[usize]) -> (Source, std::thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let source = format!("zmq://{}", listener.local_addr().unwrap()).parse().unwrap();
+    let mut stream = Vec::new();
+    for (topics, payload) in messages {
+        for _ in 0..*topics { zmtp_frame(&mut stream, 0x01, b"logs"); }
+        zmtp_frame(&mut stream, 0x00, payload);
+    }
+    let splits = splits.to_vec();
+    let publisher = std::thread::spawn(move || {
+        let (mut conn, _) = listener.accept().unwrap();
+        conn.set_nodelay(true).unwrap();
+        let mut greeting = [0u8; 64];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        conn.write_all(&greeting).unwrap();
+        conn.read_exact(&mut greeting).unwrap();
+        let mut ready = Vec::new();
+        zmtp_frame(&mut ready, 0x04, &[&b"\x05READY\x0bSocket-Type"[..], &[0, 0, 0, 3], b"PUB"].concat());
+        conn.write_all(&ready).unwrap();
+        // the subscriber's READY and its subscription to everything
+        conn.read_exact(&mut [0u8; 27 + 3]).unwrap();
+        let mut rest = &stream[..];
+        for &n in splits.iter().cycle() {
+            if rest.is_empty() { break; }
+            let (chunk, tail) = rest.split_at(n.min(rest.len()));
+            conn.write_all(chunk).unwrap();
+            conn.flush().unwrap();
+            rest = tail;
+        }
+        // stay connected until the subscriber has read everything
+        let _ = conn.read(&mut [0u8; 1]);
+    });
+    (source, publisher)
+}
+
+proptest! {
+    #[test]
+    fn exit_after_stops_without_reading_past_the_last_line(lines in prop::collection::vec("[a-z0-9{}\":,]{0,80}", 0..30), extra in 0..3usize, capacity in 1..100usize) {
+        // The first `limit` lines come back, and once they are read nothing more is asked of the input
+        let limit = lines.len().saturating_sub(extra);
+        let input: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let read_past = Arc::new(AtomicBool::new(false));
+        let inner = BufReader::with_capacity(capacity, std::io::Cursor::new(input.into_bytes()).chain(Tripwire(read_past.clone())));
+        let limited: Vec<String> = limit_lines(Box::new(inner), limit).lines().collect::<std::io::Result<_>>().unwrap();
+        prop_assert_eq!(&limited[..], &lines[..limit]);
+        prop_assert!(!read_past.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn zmq_messages_are_read_across_any_split(
+        messages in prop::collection::vec((0..3usize, "[a-z0-9{}\":,]{0,400}\n?"), 1..20),
+        splits in prop::collection::vec(1..700usize, 1..8),
+    ) {
+        // Each message's payload comes back as one line, whatever the topic frames and write boundaries
+        let messages: Vec<(usize, Vec<u8>)> = messages.into_iter().map(|(topics, payload)| (topics, payload.into_bytes())).collect();
+        let expected: Vec<u8> = messages.iter().flat_map(|(_, p)| if p.ends_with(b"\n") { p.clone() } else { [&p[..], b"\n"].concat() }).collect();
+        let (source, publisher) = zmq_publisher(&messages, &splits);
+        let (mut input, counts) = source.open().unwrap();
+        let mut read = vec![0u8; expected.len()];
+        input.reader.read_exact(&mut read).unwrap();
+        drop(input);
+        publisher.join().unwrap();
+        prop_assert_eq!(read, expected);
+        prop_assert_eq!(counts.unwrap().messages(), messages.len() as u64);
+    }
+}
@@ -0,0 +1,35 @@
+//! Storage-layout decoding of packed slots: random `uintN` variables packed into slots the way solc lays them out
+//! come back under their labels with their values.
+
+use ethereum_types::H256;
+use evm_rust_decoder::storage::StorageLayout;
+use proptest::prelude::*;
+use serde_json::json;
+
+proptest! {
+    #[test]
+    fn packed_storage_variables_decode(values in prop::collection::vec((1..=16usize, any::<u128>()), 1..8)) {
+        // Packs `uintN` variables into slot 0 from its low-order end, spilling into slot 1 as solc does
+        let (mut storage, mut words, mut expected) = (Vec::new(), [[0u8; 32]; 2], Vec::new());
+        let (mut slot, mut offset) = (0usize, 0usize);
+        for (i, (size, value)) in values.iter().enumerate() {
+            if offset + size > 32 { (slot, offset) = (slot + 1, 0); }
+            if slot > 1 { break; }
+            let value = value & (u128::MAX >> (128 - size * 8));
+            words[slot][32 - offset - size..32 - offset].copy_from_slice(&value.to_be_bytes()[16 - size..]);
+            storage.push(json!({ "label": format!("v{}", i), "offset": offset, "slot": slot.to_string(), "type": format!("t_uint{}", size * 8) }));
+            expected.push((slot, format!("v{}", i), value.to_string()));
+            offset += size;
+        }
+        let types: serde_json::Map<_, _> = (1..=16).map(|n| (format!("t_uint{}", n * 8), json!({ "encoding": "inplace", "label": format!("uint{}", n * 8), "numberOfBytes": n.to_string() }))).collect();
+        let layout = StorageLayout::from_json(&json!({ "storage": storage, "types": types }), None).unwrap();
+        let mut decoded = Vec::new();
+        for (slot, word) in words.iter().enumerate() {
+            let key = H256::from_low_u64_be(slot as u64);
+            for (name, value) in layout.decode(&key, &H256(*word)) {
+                decoded.push((slot, name.to_string(), serde_json::to_value(&value).unwrap().as_str().unwrap().to_string()));
+            }
+        }
+        prop_assert_eq!(decoded, expected);
+    }
+}
@@ -0,0 +1,48 @@
+//! `--topk` over random skewed streams, against exact counts: the space-saving guarantees hold however counters
+//! are taken over and reordered.
+
+use std::collections::HashMap;
+
+use ethabi::{Event, EventParam, Log, LogParam, ParamType, Token};
+use ethereum_types::U256;
+use evm_rust_decoder::decode::{DecodedLog, LogMeta};
+use evm_rust_decoder::topk::TopK;
+use proptest::prelude::*;
+
+fn log(event: &Event, value: u64) -> DecodedLog<'_> {
+    let params = vec![LogParam { name: "v".to_string(), value: Token::Uint(U256::from(value)) }];
+    DecodedLog { event, log: Log { params }, meta: LogMeta::default() }
+}
+
+/// A stream mostly of a few hot values, with enough distinct cold ones to run out of counters when `k` is small.
+fn stream() -> impl Strategy<Value = Vec<u64>> {
+    prop::collection::vec(prop_oneof![3 => 0..8u64, 2 => 0..600u64], 0..3000)
+}
+
+proptest! {
+    #[test]
+    fn counts_bound_true_counts_and_keep_heavy_hitters(values in stream(), k in 1..4usize) {
+        let event = Event { name: "E".to_string(), inputs: vec![EventParam { name: "v".to_string(), kind: ParamType::Uint(256), indexed: false }], anonymous: false };
+        let topk = TopK::parse(&format!("v:{}", k)).unwrap();
+        let mut exact: HashMap<String, u64> = HashMap::new();
+        for &value in &values {
+            topk.add(&log(&event, value));
+            *exact.entry(value.to_string()).or_default() += 1;
+        }
+        prop_assert_eq!(topk.total(), values.len() as u64);
+
+        let top = topk.top();
+        prop_assert!(top.len() <= k);
+        prop_assert!(top.windows(2).all(|w| w[0].count >= w[1].count), "not sorted: {:?}", top);
+        for hitter in &top {
+            let actual = exact[&hitter.value];
+            prop_assert!(hitter.count >= actual && hitter.count - hitter.error <= actual, "{:?} counted {} times", hitter, actual);
+        }
+        // Fewer than k values can each take more than N/k of the stream, so all of them make the top k
+        for (value, &count) in &exact {
+            if count as usize * k > values.len() {
+                prop_assert!(top.iter().any(|h| &h.value == value), "{} seen {} of {} times is missing from {:?}", value, count, values.len(), top);
+            }
+        }
+    }
+}
@@ -1,13 +1,17 @@
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use ethereum_types::H256;
-use ethabi::{Event, EventParam, ParamType, RawLog};
-use hex::FromHex;
+use ethabi::{Event, Log, RawLog};
+use evm_rust_decoder::abi::{self, AbiSet, CollisionPolicy};
+use evm_rust_decoder::decode::{self, parse_h256, parse_hex_bytes, to_raw_log, LogDecoder, LogLine, LogMeta, LogRecord};
+use evm_rust_decoder::error::DecodeError;
+use evm_rust_decoder::output::{envelope_json, log_to_json};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use serde_json::Value;
@@ -15,120 +19,753 @@ use serde_json::Value;
 #[napi(object)]
 pub struct DecodeResult {
 	pub decoded: u32,
-	pub elapsedMs: f64,
+	pub elapsed_ms: f64,
+	/// Logs decoded per event, in the order the events were first seen; present with `stats: true`
+	pub events: Option<Vec<EventStats>>,
+	/// Lines that failed to decode, in input order; present with `onError: 'skip'`
+	pub errors: Option<Vec<LineError>>,
+	/// Reading the file, including the read done before the timer with `preload` and the `parallel` engine. This and
+	/// the phases below are present with `phaseTiming: true`, summed over threads like the CLI's `--phase-timing`.
+	pub read_ms: Option<f64>,
+	pub json_parse_ms: Option<f64>,
+	/// Hex decoding of topics and data
+	pub hex_decode_ms: Option<f64>,
+	pub abi_decode_ms: Option<f64>,
+	/// Time inside the native call outside reading and decoding: option handling, opening the file, starting
+	/// threads, assembling the result. Converting arguments and the result to and from JS happens outside Rust, so
+	/// the full crossing cost is the caller's own timing minus the native total.
+	pub ffi_overhead_ms: Option<f64>,
+	/// The decoded logs as JS objects, built when the result is returned; present with `output: 'objects'`
+	pub logs: Option<Vec<DecodedLog>>,
+	/// The decoded logs serialized in Rust as NDJSON, one `{"event","args"}` object per line; present with
+	/// `output: 'ndjson'`
+	pub ndjson: Option<Buffer>,
 }
 
-#[napi]
-pub fn decode_file(abi_path: String, event_name: String, input_path: String) -> Result<DecodeResult> {
-	let abi_path = PathBuf::from(abi_path);
-	let (selected_event, events) = load_event(&abi_path, if event_name.is_empty() { "" } else { &event_name })
-		.map_err(|e| Error::from_reason(e.to_string()))?;
+#[napi(object)]
+pub struct EventStats {
+	pub event: String,
+	pub decoded: u32,
+}
+
+#[napi(object)]
+pub struct LineError {
+	/// 1-based line number in the input file, or record number for binary input
+	pub line: u32,
+	/// The kind of failure: `UNKNOWN_TOPIC0`, `HEX_PARSE`, `JSON_PARSE` or `ABI_MISMATCH`
+	#[napi(ts_type = "'UNKNOWN_TOPIC0' | 'HEX_PARSE' | 'JSON_PARSE' | 'ABI_MISMATCH'")]
+	pub code: String,
+	pub message: String,
+}
+
+/// The JS side of the core crate's [`DecodeError`]: its kind as JS sees it, as `code` in `errors` entries and, since
+/// a napi error only carries a `Status`, as the `CODE: ` start of a thrown error's message.
+trait Kind {
+	/// The summary name of the kind in upper case, e.g. `UNKNOWN_TOPIC0`.
+	fn code(self) -> String;
+	fn failure(self, message: impl ToString) -> Failure;
+	fn error(self, message: impl ToString) -> Error;
+}
 
-	let mut topic0_to_event: Option<HashMap<H256, Event>> = None;
-	if event_name.is_empty() {
-		let mut map = HashMap::new();
-		for ev in &events { map.insert(ev.signature(), ev.clone()); }
-		topic0_to_event = Some(map);
+impl Kind for DecodeError {
+	fn code(self) -> String {
+		self.name().to_ascii_uppercase()
 	}
 
-	let file = File::open(&input_path)
-		.with_context(|| format!("Cannot open input file: {}", input_path))
-		.map_err(|e| Error::from_reason(e.to_string()))?;
-	let reader = BufReader::new(file);
-
-	let start = Instant::now();
-	let mut total: u32 = 0;
-	for line in reader.lines() {
-		let line = line.map_err(|e| Error::from_reason(e.to_string()))?;
-		if line.is_empty() { continue; }
-		let v: Value = serde_json::from_str(&line).map_err(|e| Error::from_reason(e.to_string()))?;
-		let topics_v = v.get("topics").and_then(|t| t.as_array()).ok_or_else(|| Error::from_reason("no topics".to_string()))?;
-		let data_s = v.get("data").and_then(|d| d.as_str()).ok_or_else(|| Error::from_reason("no data".to_string()))?;
-
-		let topics: Vec<H256> = topics_v
-			.iter()
-			.map(|tv| tv.as_str().ok_or_else(|| Error::from_reason("topic not string".to_string())) )
-			.collect::<std::result::Result<Vec<&str>, Error>>()?
-			.into_iter()
-			.map(|s| parse_h256(s))
-			.collect::<anyhow::Result<Vec<H256>>>()
-			.map_err(|e| Error::from_reason(e.to_string()))?;
-		if topics.is_empty() { continue; }
-
-		let event = if let Some(map) = &topic0_to_event {
-			match map.get(&topics[0]) { Some(ev) => ev.clone(), None => return Err(Error::from_reason("unknown topic0".to_string())) }
-		} else { selected_event.clone() };
-
-		let data = parse_hex_bytes(data_s).map_err(|e| Error::from_reason(e.to_string()))?;
-		let raw = RawLog { topics, data };
-		let _ = event.parse_log(raw).map_err(|e| Error::from_reason(e.to_string()))?;
-		total = total.saturating_add(1);
-	}
-	let elapsed = start.elapsed();
-
-	Ok(DecodeResult { decoded: total, elapsedMs: elapsed.as_secs_f64() * 1000.0 })
-}
-
-fn load_event(path: &PathBuf, event_name: &str) -> anyhow::Result<(Event, Vec<Event>)> {
-	let file = File::open(path).with_context(|| format!("Cannot open ABI file: {:?}", path))?;
-	let json_value: Value = serde_json::from_reader(file)?;
-
-	let events: Vec<Event> = if json_value.is_array() {
-		let arr = json_value.as_array().unwrap();
-		arr.iter().filter_map(|v| parse_event_from_value(v)).collect()
-	} else if json_value.is_object() {
-		if let Some(arr) = json_value.get("abi").and_then(|v| v.as_array()) {
-			arr.iter().filter_map(|v| parse_event_from_value(v)).collect()
-		} else if let Some(arr) = json_value.get("events").and_then(|v| v.as_array()) {
-			arr.iter().filter_map(|v| parse_event_from_value(v)).collect()
-		} else { return Err(anyhow!("Unsupported ABI JSON structure")); }
-	} else { return Err(anyhow!("Unsupported ABI JSON structure")); };
-
-	let event = if event_name.is_empty() {
-		events.first().cloned().ok_or_else(|| anyhow!("No events in ABI"))?
+	fn failure(self, message: impl ToString) -> Failure {
+		Failure { kind: self, message: message.to_string() }
+	}
+
+	fn error(self, message: impl ToString) -> Error {
+		self.failure(message).into()
+	}
+}
+
+/// A failure and its kind, kept apart until it reaches JS.
+struct Failure {
+	kind: DecodeError,
+	message: String,
+}
+
+impl Failure {
+	/// The failure of a core decoder error, of the kind the CLI counts it as. The addon sets no decode limits, so an
+	/// error of no kind can only be an ABI mismatch.
+	fn of(error: anyhow::Error) -> Failure {
+		DecodeError::of(&error).unwrap_or(DecodeError::AbiMismatch).failure(format!("{:#}", error))
+	}
+}
+
+impl From<Failure> for Error {
+	fn from(failure: Failure) -> Self {
+		Error::new(Status::GenericFailure, format!("{}: {}", failure.kind.code(), failure.message))
+	}
+}
+
+/// Mirrors the CLI's tuning flags, so Node-driven runs can sweep configurations without spawning the CLI.
+#[napi(object)]
+pub struct DecodeOptions {
+	/// `'sequential'` (default) decodes line by line on the calling thread; `'parallel'` reads the file, then splits
+	/// its lines between `threads` Rust threads (`--threads`); `'pipeline'` reads on a separate thread while the
+	/// calling thread decodes (`--pipeline`).
+	#[napi(ts_type = "'sequential' | 'parallel' | 'pipeline'")]
+	pub engine: Option<String>,
+	/// Threads of the `parallel` engine, 0 for one per CPU (default). Above 1 it implies `engine: 'parallel'`.
+	pub threads: Option<u32>,
+	/// Read and split the whole file before starting the timer so `elapsedMs` excludes file I/O.
+	pub preload: Option<bool>,
+	/// Count decoded logs per event in `events`.
+	pub stats: Option<bool>,
+	/// `'skip'` records failing lines in `errors` and carries on; the default `'abort'` fails on the first one.
+	#[napi(ts_type = "'abort' | 'skip'")]
+	pub on_error: Option<String>,
+	/// Time reading, JSON parsing, hex decoding and ABI decoding separately and report them in the result.
+	pub phase_timing: Option<bool>,
+	/// Return the decoded logs: `'none'` (default) only counts them; `'objects'` returns one JS object per log in
+	/// `logs`; `'ndjson'` returns them as one `Buffer` in `ndjson`, which skips creating a JS object per log.
+	/// `elapsedMs` includes serializing `ndjson` but not converting `logs`, which happens as the call returns.
+	#[napi(ts_type = "'none' | 'objects' | 'ndjson'")]
+	pub output: Option<String>,
+}
+
+/// Decoded logs kept for the result, per `DecodeOptions.output`.
+enum Output {
+	None,
+	Objects(Vec<DecodedLog>),
+	Ndjson(Vec<u8>),
+}
+
+impl Output {
+	fn of(options: Option<&DecodeOptions>) -> Result<Output> {
+		Ok(match options.and_then(|o| o.output.as_deref()) {
+			None | Some("none") => Output::None,
+			Some("objects") => Output::Objects(Vec::new()),
+			Some("ndjson") => Output::Ndjson(Vec::new()),
+			Some(other) => return Err(Error::from_reason(format!("output must be 'none', 'objects' or 'ndjson', not '{}'", other))),
+		})
+	}
+
+	fn push(&mut self, event: &Event, log: &Log) {
+		match self {
+			Output::None => {}
+			Output::Objects(logs) => logs.push(DecodedLog { event: event.name.clone(), args: log_to_json(log) }),
+			Output::Ndjson(buf) => {
+				// Written by hand to keep `event` first; writing to a Vec cannot fail
+				buf.extend_from_slice(b"{\"event\":");
+				let _ = serde_json::to_writer(&mut *buf, &event.name);
+				buf.extend_from_slice(b",\"args\":");
+				let _ = serde_json::to_writer(&mut *buf, &log_to_json(log));
+				buf.extend_from_slice(b"}\n");
+			}
+		}
+	}
+
+	fn append(&mut self, other: Output) {
+		match (self, other) {
+			(Output::Objects(logs), Output::Objects(more)) => logs.extend(more),
+			(Output::Ndjson(buf), Output::Ndjson(more)) => buf.extend(more),
+			_ => {}
+		}
+	}
+}
+
+enum Engine {
+	Sequential,
+	Parallel(usize),
+	Pipeline,
+}
+
+impl Engine {
+	fn of(options: Option<&DecodeOptions>) -> Result<Engine> {
+		let threads = options.and_then(|o| o.threads).unwrap_or(0) as usize;
+		Ok(match options.and_then(|o| o.engine.as_deref()) {
+			None if threads > 1 => Engine::Parallel(threads),
+			None | Some("sequential") => Engine::Sequential,
+			Some("parallel") => Engine::Parallel(threads),
+			Some("pipeline") => Engine::Pipeline,
+			Some(other) => return Err(Error::from_reason(format!("engine must be 'sequential', 'parallel' or 'pipeline', not '{}'", other))),
+		})
+	}
+}
+
+#[napi(object)]
+pub struct DecodedLog {
+	pub event: String,
+	/// Decoded parameters by name (`argN` for unnamed ones), in the CLI's `--print` format: integers as decimal
+	/// strings, addresses and bytes as 0x-hex, arrays and tuples as arrays
+	#[napi(ts_type = "Record<string, string | boolean | Array<any>>")]
+	pub args: Value,
+}
+
+#[cfg(all(feature = "alloc-jemalloc", feature = "alloc-mimalloc"))]
+compile_error!("the `alloc-jemalloc` and `alloc-mimalloc` features are mutually exclusive");
+
+#[cfg(all(feature = "alloc-jemalloc", not(target_env = "msvc")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "alloc-mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+/// The allocator the addon was built with: `jemalloc`, `mimalloc` or `system`, as in the CLI's summary.
+#[napi]
+pub fn allocator() -> String {
+	let name = if cfg!(all(feature = "alloc-jemalloc", not(target_env = "msvc"))) {
+		"jemalloc"
+	} else if cfg!(feature = "alloc-mimalloc") {
+		"mimalloc"
 	} else {
-		events.iter().find(|e| e.name == event_name).cloned().ok_or_else(|| anyhow!("Event not found"))?
+		"system"
 	};
-	Ok((event, events))
+	name.to_string()
+}
+
+#[napi]
+pub fn decode_file(env: Env, abi_path: String, event_name: String, input_path: String, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+	let decoder = load_decoder(&PathBuf::from(abi_path), &event_name).map_err(|e| DecodeError::AbiLoad.error(format!("{:#}", e)))?;
+	Decoder::from(decoder).decode_file(env, input_path, options)
+}
+
+/// An ABI parsed once, with its topic0 map built, for decoding many files or logs without paying for either again.
+/// The parsed ABI is immutable and shared by reference with the threads of `decodeFileParallel`.
+#[napi]
+pub struct Decoder {
+	decoder: LogDecoder,
+	memory: Arc<Memory>,
+}
+
+#[napi(object)]
+pub struct MemoryStats {
+	/// `decodeFile`, `decodeBuffer` and `decodeFileParallel` calls so far
+	pub calls: i64,
+	/// Input read whole into native memory by `preload` and the `parallel` engine, summed over calls
+	pub preloaded_bytes: i64,
+	/// Logs handed back as JS objects with `output: 'objects'`, summed over calls
+	pub objects_returned: i64,
+	/// NDJSON serialized for `output: 'ndjson'`, summed over calls
+	pub ndjson_bytes: i64,
+	/// Native memory behind the `ndjson` Buffers that are not yet garbage collected; it is reported to V8 with
+	/// `adjust_external_memory`, so it counts towards V8's GC heuristics
+	pub external_bytes: i64,
+	pub live_buffers: i64,
 }
 
-fn parse_event_from_value(v: &Value) -> Option<Event> {
-	if v.get("type").and_then(|t| t.as_str()) != Some("event") { return None; }
-	let name = v.get("name")?.as_str()?.to_string();
-	let inputs_v = v.get("inputs")?.as_array()?.clone();
-	let mut inputs: Vec<EventParam> = Vec::with_capacity(inputs_v.len());
-	for i in inputs_v {
-		let name_i = i.get("name").and_then(|s| s.as_str()).unwrap_or("").to_string();
-		let indexed = i.get("indexed").and_then(|b| b.as_bool()).unwrap_or(false);
-		let type_str = i.get("type").and_then(|s| s.as_str()).unwrap_or("");
-		let param_type = match parse_param_type(type_str) { Some(t) => t, None => return None };
-		inputs.push(EventParam { name: name_i, kind: param_type, indexed });
+/// Counters behind `Decoder.stats()`, shared with the finalizers of the Buffers the decoder returned.
+#[derive(Default)]
+struct Memory {
+	calls: AtomicI64,
+	preloaded_bytes: AtomicI64,
+	objects_returned: AtomicI64,
+	ndjson_bytes: AtomicI64,
+	external_bytes: AtomicI64,
+	live_buffers: AtomicI64,
+}
+
+impl Memory {
+	/// Hands `data` to JS as a Buffer without copying, registered as external memory until it is collected.
+	fn external_buffer(self: &Arc<Self>, env: &Env, data: Vec<u8>) -> Result<Buffer> {
+		if data.is_empty() { return Ok(data.into()); }
+		let len = data.len() as i64;
+		let mut data = std::mem::ManuallyDrop::new(data);
+		let (ptr, length, capacity) = (data.as_mut_ptr(), data.len(), data.capacity());
+		env.adjust_external_memory(len)?;
+		self.external_bytes.fetch_add(len, Ordering::Relaxed);
+		self.live_buffers.fetch_add(1, Ordering::Relaxed);
+		// SAFETY: the Vec's allocation is only freed by the finalizer, which rebuilds the Vec it came from
+		let slice = unsafe {
+			BufferSlice::from_external(env, ptr, length, self.clone(), move |env, memory: Arc<Memory>| {
+				drop(Vec::from_raw_parts(ptr, length, capacity));
+				let _ = env.adjust_external_memory(-len);
+				memory.external_bytes.fetch_sub(len, Ordering::Relaxed);
+				memory.live_buffers.fetch_sub(1, Ordering::Relaxed);
+			})
+		}?;
+		slice.into_buffer(env)
 	}
-	Some(Event { name, inputs, anonymous: false })
 }
 
-fn parse_param_type(s: &str) -> Option<ParamType> {
-	match s {
-		"address" => Some(ParamType::Address),
-		"bool" => Some(ParamType::Bool),
-		"string" => Some(ParamType::String),
-		"bytes" => Some(ParamType::Bytes),
-		_ if s.starts_with("bytes") => { let n: usize = s[5..].parse().ok()?; Some(ParamType::FixedBytes(n)) }
-		_ if s.starts_with("uint") => { let n: usize = s[4..].parse().unwrap_or(256); Some(ParamType::Uint(n)) }
-		_ if s.starts_with("int") => { let n: usize = s[3..].parse().unwrap_or(256); Some(ParamType::Int(n)) }
-		_ if s.ends_with("[]") => { let inner = &s[..s.len()-2]; let inner_t = parse_param_type(inner)?; Some(ParamType::Array(Box::new(inner_t))) }
-		_ => None,
+// The worker threads of `decode_file_parallel` borrow the decoder
+const _: fn() = || {
+	fn shared<T: Send + Sync>() {}
+	shared::<Decoder>();
+};
+
+#[napi]
+impl Decoder {
+	/// `abiJson` is the ABI document itself (array, or object with `abi`/`events`). Without `eventName` every ABI
+	/// event is decoded by topic0.
+	#[napi(constructor)]
+	pub fn new(abi_json: String, event_name: Option<String>) -> Result<Self> {
+		let event_name = event_name.unwrap_or_default();
+		let json_value: Value = serde_json::from_str(&abi_json).map_err(|e| DecodeError::AbiLoad.error(format!("Invalid ABI JSON: {}", e)))?;
+		let events = abi::events_from_json(&json_value).map_err(|e| DecodeError::AbiLoad.error(e))?;
+		let selected_event = abi::select_event(&events, &event_name).map_err(|e| DecodeError::AbiLoad.error(e))?;
+		Ok(Decoder::from(LogDecoder::new(selected_event, &events, event_name.is_empty())))
+	}
+
+	/// Native allocations behind this decoder's results, to line up with GC pauses seen on the Node side.
+	#[napi]
+	pub fn stats(&self) -> MemoryStats {
+		let get = |counter: &AtomicI64| counter.load(Ordering::Relaxed);
+		let m = &self.memory;
+		MemoryStats {
+			calls: get(&m.calls),
+			preloaded_bytes: get(&m.preloaded_bytes),
+			objects_returned: get(&m.objects_returned),
+			ndjson_bytes: get(&m.ndjson_bytes),
+			external_bytes: get(&m.external_bytes),
+			live_buffers: get(&m.live_buffers),
+		}
+	}
+
+	/// Decodes a JSONL file like the `decodeFile` function, with the engine chosen in `options`; `elapsedMs` covers
+	/// the decoding loop only.
+	#[napi]
+	pub fn decode_file(&self, env: Env, input_path: String, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+		match Engine::of(options.as_ref())? {
+			Engine::Sequential => self.decode_sequential(&env, &input_path, options.as_ref()),
+			Engine::Parallel(workers) => self.decode_parallel(&env, &input_path, workers, options.as_ref()),
+			Engine::Pipeline => self.decode_pipeline(&env, &input_path, options.as_ref()),
+		}
+	}
+
+	/// Decodes JSONL held in a Node `Buffer`, read in place without copying it into Rust; `elapsedMs` covers decoding.
+	#[napi]
+	pub fn decode_buffer(&self, env: Env, buffer: Buffer, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+		let content = std::str::from_utf8(&buffer).map_err(|e| Error::from_reason(format!("Input is not UTF-8: {}", e)))?;
+		let mut tally = Tally::new(self, options.as_ref())?;
+		let start = Instant::now();
+		for line in content.lines() {
+			tally.line(line)?;
+		}
+		tally.finish(&env, start.elapsed())
+	}
+
+	/// Shorthand for `decodeFile` with `{ engine: 'parallel', threads: workers }`: reads a JSONL file, then decodes
+	/// it on `workers` Rust threads (0: one per CPU), each taking an equal run of lines, while the JavaScript thread
+	/// waits for them. `elapsedMs` covers decoding only, as with `preload`. Per-event counts and errors are merged
+	/// in input order.
+	#[napi]
+	pub fn decode_file_parallel(&self, env: Env, input_path: String, workers: u32, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+		self.decode_parallel(&env, &input_path, workers as usize, options.as_ref())
+	}
+
+	/// Decodes binary records laid out as the CLI's `--input-format bin` (u8 topic count, 32-byte topics, u32 LE data
+	/// length, data), so no JSON parsing or hex decoding is measured; `elapsedMs` covers decoding.
+	#[napi]
+	pub fn decode_binary(&self, env: Env, records: Uint8Array, options: Option<DecodeOptions>) -> Result<DecodeResult> {
+		let mut tally = Tally::new(self, options.as_ref())?;
+		let mut rest: &[u8] = &records;
+		let start = Instant::now();
+		while !rest.is_empty() {
+			let (topics, data) = next_record(&mut rest, tally.lines + 1)?;
+			tally.record(topics, data)?;
+		}
+		tally.finish(&env, start.elapsed())
+	}
+
+	/// Decodes a single log given its topics (32 bytes each) and data as `Buffer`s or `Uint8Array`s.
+	#[napi]
+	pub fn decode_raw_log(&self, topics: Vec<Uint8Array>, data: Uint8Array) -> Result<DecodedLog> {
+		let topics: Vec<H256> = topics.iter().map(|t| {
+			<[u8; 32]>::try_from(&t[..]).map(H256).map_err(|_| DecodeError::HexParse.error(format!("topic of {} bytes, expected 32", t.len())))
+		}).collect::<Result<Vec<H256>>>()?;
+		self.decode_one(topics, data.to_vec())
+	}
+
+	/// Decodes a single log given its hex topics and data.
+	#[napi]
+	pub fn decode_log(&self, topics: Vec<String>, data: String) -> Result<DecodedLog> {
+		let topics: Vec<H256> = topics.iter().map(|t| parse_h256(t)).collect::<anyhow::Result<Vec<H256>>>()
+			.map_err(|e| DecodeError::HexParse.error(format!("{:#}", e)))?;
+		let data = parse_hex_bytes(&data).map_err(|e| DecodeError::HexParse.error(format!("{:#}", e)))?;
+		self.decode_one(topics, data)
+	}
+}
+
+impl Decoder {
+	fn decode_sequential(&self, env: &Env, input_path: &str, options: Option<&DecodeOptions>) -> Result<DecodeResult> {
+		let preload = options.and_then(|o| o.preload).unwrap_or(false);
+		let mut tally = Tally::new(self, options)?;
+		let elapsed = if preload {
+			let content = tally.preread(input_path)?;
+			let lines: Vec<&str> = content.lines().collect();
+
+			let start = Instant::now();
+			for line in lines {
+				tally.line(line)?;
+			}
+			start.elapsed()
+		} else {
+			let file = File::open(input_path)
+				.with_context(|| format!("Cannot open input file: {}", input_path))
+				.map_err(|e| DecodeError::Io.error(format!("{:#}", e)))?;
+			let mut lines = BufReader::new(file).lines();
+
+			let start = Instant::now();
+			loop {
+				let read = tally.phases.is_some().then(Instant::now);
+				let Some(line) = lines.next() else { break };
+				if let (Some(phases), Some(read)) = (&mut tally.phases, read) { phases.read += read.elapsed(); }
+				let line = line.map_err(|e| DecodeError::Io.error(e))?;
+				tally.line(&line)?;
+			}
+			start.elapsed()
+		};
+
+		tally.finish(env, elapsed)
+	}
+
+	fn decode_parallel(&self, env: &Env, input_path: &str, workers: usize, options: Option<&DecodeOptions>) -> Result<DecodeResult> {
+		// Also validates the options before any thread starts
+		let mut total = Tally::new(self, options)?;
+		let content = total.preread(input_path)?;
+		let lines: Vec<&str> = content.lines().collect();
+		let workers = match workers {
+			0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+			n => n,
+		};
+		let chunk_size = lines.len().div_ceil(workers).max(1);
+
+		let start = Instant::now();
+		let tallies = std::thread::scope(|scope| {
+			let handles: Vec<_> = lines.chunks(chunk_size).enumerate().map(|(i, chunk)| {
+				scope.spawn(move || -> std::result::Result<Tally<'_>, String> {
+					let mut tally = Tally::new(self, options).map_err(|e| e.reason.clone())?;
+					tally.lines = (i * chunk_size) as u32;
+					for line in chunk {
+						tally.line(line).map_err(|f| Error::from(Failure { message: format!("line {}: {}", tally.lines, f.message), ..f }).reason)?;
+					}
+					Ok(tally)
+				})
+			}).collect();
+			handles.into_iter().map(|h| h.join().unwrap_or_else(|_| Err("decoder thread panicked".to_string()))).collect::<Vec<_>>()
+		});
+		let elapsed = start.elapsed();
+
+		for tally in tallies {
+			total.merge(tally.map_err(Error::from_reason)?);
+		}
+		total.finish(env, elapsed)
+	}
+
+	/// Reads batches of lines on a separate thread, so file I/O overlaps with decoding on the calling thread.
+	fn decode_pipeline(&self, env: &Env, input_path: &str, options: Option<&DecodeOptions>) -> Result<DecodeResult> {
+		const BATCH: usize = 1024;
+		let file = File::open(input_path)
+			.with_context(|| format!("Cannot open input file: {}", input_path))
+			.map_err(|e| DecodeError::Io.error(format!("{:#}", e)))?;
+		let mut tally = Tally::new(self, options)?;
+		let timed = tally.phases.is_some();
+
+		let start = Instant::now();
+		let (read_time, decoded) = std::thread::scope(|scope| {
+			let (tx, rx) = std::sync::mpsc::sync_channel::<std::io::Result<Vec<String>>>(4);
+			let reader = scope.spawn(move || {
+				let mut read_time = Duration::ZERO;
+				let mut lines = BufReader::new(file).lines();
+				loop {
+					let read = timed.then(Instant::now);
+					let batch: std::io::Result<Vec<String>> = lines.by_ref().take(BATCH).collect();
+					if let Some(read) = read { read_time += read.elapsed(); }
+					let done = batch.as_ref().map_or(true, |b| b.len() < BATCH);
+					// The decoder hung up after an error
+					if tx.send(batch).is_err() || done { break; }
+				}
+				read_time
+			});
+			let decoded = rx.iter().try_for_each(|batch| {
+				let batch = batch.map_err(|e| DecodeError::Io.error(e))?;
+				batch.iter().try_for_each(|line| tally.line(line).map_err(Error::from))
+			});
+			drop(rx);
+			(reader.join().unwrap_or_default(), decoded)
+		});
+		let elapsed = start.elapsed();
+		decoded?;
+
+		if let Some(phases) = &mut tally.phases { phases.read += read_time; }
+		tally.finish(env, elapsed)
+	}
+
+	fn decode_one(&self, topics: Vec<H256>, data: Vec<u8>) -> Result<DecodedLog> {
+		if topics.is_empty() { return Err(DecodeError::AbiMismatch.error("no topics")); }
+		let decoded = self.decoder.decode(LogRecord { raw: RawLog { topics, data }, meta: LogMeta::default() }).map_err(Failure::of)?;
+		Ok(DecodedLog { event: decoded.event.name.clone(), args: log_to_json(&decoded.log) })
 	}
 }
 
-fn parse_h256(s: &str) -> anyhow::Result<H256> {
-	let s = s.strip_prefix("0x").unwrap_or(s);
-	let bytes = <[u8; 32]>::from_hex(s)?;
-	Ok(H256::from(bytes))
+impl From<LogDecoder> for Decoder {
+	fn from(decoder: LogDecoder) -> Self {
+		Decoder { decoder, memory: Arc::default() }
+	}
+}
+
+/// Builds a `DecodeResult` line by line, keeping the per-event counts and the error list only when asked for.
+struct Tally<'a> {
+	decoder: &'a Decoder,
+	lines: u32,
+	decoded: u32,
+	events: Option<Vec<EventStats>>,
+	errors: Option<Vec<LineError>>,
+	phases: Option<Phases>,
+	output: Output,
+	/// When the native call started, for `ffiOverheadMs`
+	entered: Instant,
+}
+
+impl<'a> Tally<'a> {
+	fn new(decoder: &'a Decoder, options: Option<&DecodeOptions>) -> Result<Self> {
+		let skip = match options.and_then(|o| o.on_error.as_deref()) {
+			None | Some("abort") => false,
+			Some("skip") => true,
+			Some(other) => return Err(Error::from_reason(format!("onError must be 'abort' or 'skip', not '{}'", other))),
+		};
+		let stats = options.and_then(|o| o.stats).unwrap_or(false);
+		let phase_timing = options.and_then(|o| o.phase_timing).unwrap_or(false);
+		Ok(Tally {
+			decoder,
+			lines: 0,
+			decoded: 0,
+			events: stats.then(Vec::new),
+			errors: skip.then(Vec::new),
+			phases: phase_timing.then(Phases::default),
+			output: Output::of(options)?,
+			entered: Instant::now(),
+		})
+	}
+
+	/// Reads the whole input before the timer starts, charging the read to the read phase.
+	fn preread(&mut self, input_path: &str) -> Result<String> {
+		let started = Instant::now();
+		let content = read_input(input_path)?;
+		if let Some(phases) = &mut self.phases { phases.preread += started.elapsed(); }
+		self.decoder.memory.preloaded_bytes.fetch_add(content.len() as i64, Ordering::Relaxed);
+		Ok(content)
+	}
+
+	/// Decodes one JSONL line; blank lines and lines without topics are not counted.
+	fn line(&mut self, line: &str) -> std::result::Result<(), Failure> {
+		self.lines += 1;
+		let lap = Lap::start(self.phases.as_mut());
+		let decoded = decode_line(&self.decoder.decoder, line, lap);
+		self.count(decoded.map(|decoded| decoded.map(|d| (d.event, d.log))))
+	}
+
+	/// Decodes one binary record, counted in `lines`.
+	fn record(&mut self, topics: Vec<H256>, data: Vec<u8>) -> std::result::Result<(), Failure> {
+		self.lines += 1;
+		if topics.is_empty() { return Ok(()); }
+		let mut lap = Lap::start(self.phases.as_mut());
+		let record = LogRecord { raw: RawLog { topics, data }, meta: LogMeta::default() };
+		let decoded = self.decoder.decoder.decode(record).map(|d| Some((d.event, d.log))).map_err(Failure::of);
+		lap.mark(|p| &mut p.abi);
+		self.count(decoded)
+	}
+
+	fn count(&mut self, decoded: std::result::Result<Option<(&'a Event, Log)>, Failure>) -> std::result::Result<(), Failure> {
+		match decoded {
+			Ok(Some((event, log))) => {
+				self.decoded = self.decoded.saturating_add(1);
+				self.output.push(event, &log);
+				// A handful of events per ABI, so a linear scan is cheaper than hashing
+				if let Some(events) = &mut self.events {
+					match events.iter_mut().find(|s| s.event == event.name) {
+						Some(stats) => stats.decoded += 1,
+						None => events.push(EventStats { event: event.name.clone(), decoded: 1 }),
+					}
+				}
+			}
+			Ok(None) => {}
+			Err(e) => match &mut self.errors {
+				Some(errors) => errors.push(LineError { line: self.lines, code: e.kind.code(), message: e.message }),
+				None => return Err(e),
+			},
+		}
+		Ok(())
+	}
+
+	/// Adds the counts of `other`, which decoded the lines after this tally's.
+	fn merge(&mut self, other: Tally<'a>) {
+		self.decoded = self.decoded.saturating_add(other.decoded);
+		if let (Some(events), Some(other)) = (&mut self.events, other.events) {
+			for stats in other {
+				match events.iter_mut().find(|s| s.event == stats.event) {
+					Some(mine) => mine.decoded += stats.decoded,
+					None => events.push(stats),
+				}
+			}
+		}
+		if let (Some(errors), Some(other)) = (&mut self.errors, other.errors) { errors.extend(other); }
+		self.output.append(other.output);
+		if let (Some(phases), Some(other)) = (&mut self.phases, other.phases) {
+			phases.read += other.read;
+			phases.json += other.json;
+			phases.hex += other.hex;
+			phases.abi += other.abi;
+		}
+	}
+
+	fn finish(mut self, env: &Env, elapsed: Duration) -> Result<DecodeResult> {
+		let memory = &self.decoder.memory;
+		memory.calls.fetch_add(1, Ordering::Relaxed);
+		let ndjson = match &mut self.output {
+			Output::Ndjson(buf) => {
+				memory.ndjson_bytes.fetch_add(buf.len() as i64, Ordering::Relaxed);
+				Some(memory.external_buffer(env, std::mem::take(buf))?)
+			}
+			_ => None,
+		};
+		if let Output::Objects(logs) = &self.output { memory.objects_returned.fetch_add(logs.len() as i64, Ordering::Relaxed); }
+		let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+		let native = self.entered.elapsed();
+		let phase = |f: fn(&Phases) -> Duration| self.phases.as_ref().map(|p| ms(f(p)));
+		Ok(DecodeResult {
+			decoded: self.decoded,
+			elapsed_ms: ms(elapsed),
+			read_ms: phase(|p| p.read + p.preread),
+			json_parse_ms: phase(|p| p.json),
+			hex_decode_ms: phase(|p| p.hex),
+			abi_decode_ms: phase(|p| p.abi),
+			ffi_overhead_ms: self.phases.as_ref().map(|p| ms(native.saturating_sub(elapsed + p.preread))),
+			events: self.events,
+			errors: self.errors,
+			logs: match &mut self.output { Output::Objects(logs) => Some(std::mem::take(logs)), _ => None },
+			ndjson,
+		})
+	}
 }
 
-fn parse_hex_bytes(s: &str) -> anyhow::Result<Vec<u8>> {
-	let s = s.strip_prefix("0x").unwrap_or(s);
-	let bytes = Vec::from_hex(s)?;
-	Ok(bytes)
+#[derive(Default)]
+struct Phases {
+	/// Reading inside the timed loop
+	read: Duration,
+	/// Reading before the timer starts
+	preread: Duration,
+	json: Duration,
+	hex: Duration,
+	abi: Duration,
+}
+
+/// Charges the time since the previous mark to a phase; reads no clock without `phaseTiming`.
+struct Lap<'p> {
+	phases: Option<(&'p mut Phases, Instant)>,
+}
+
+impl<'p> Lap<'p> {
+	fn start(phases: Option<&'p mut Phases>) -> Self {
+		Lap { phases: phases.map(|p| (p, Instant::now())) }
+	}
+
+	fn off() -> Self {
+		Lap { phases: None }
+	}
+
+	#[inline]
+	fn mark(&mut self, phase: fn(&mut Phases) -> &mut Duration) {
+		if let Some((phases, last)) = &mut self.phases {
+			let now = Instant::now();
+			*phase(phases) += now - *last;
+			*last = now;
+		}
+	}
+}
+
+/// Parses and decodes one JSONL line with the CLI's decoder, timing each step; `None` for a blank line or a log
+/// without topics.
+fn decode_line<'a>(decoder: &'a LogDecoder, line: &str, mut lap: Lap) -> std::result::Result<Option<decode::DecodedLog<'a>>, Failure> {
+	// `lines()` strips each `\r\n`, but keeps the `\r` of a CRLF file's last line when no newline follows it
+	let line = line.strip_suffix('\r').unwrap_or(line);
+	if line.is_empty() { return Ok(None); }
+	let parsed: LogLine = serde_json::from_str(line).map_err(|e| DecodeError::JsonParse.failure(e))?;
+	lap.mark(|p| &mut p.json);
+	let Some(record) = to_raw_log(&parsed).map_err(Failure::of)? else { return Ok(None) };
+	lap.mark(|p| &mut p.hex);
+	let decoded = decoder.decode(record).map_err(Failure::of)?;
+	lap.mark(|p| &mut p.abi);
+	Ok(Some(decoded))
+}
+
+#[napi(object)]
+pub struct SelfTestResult {
+	pub cases: u32,
+	pub passed: u32,
+}
+
+/// Runs the golden fixtures shared with the CLI's `--self-test` and fails with every mismatch in the error message.
+#[napi]
+pub fn self_test(fixtures_dir: String) -> Result<SelfTestResult> {
+	let mut cases: Vec<PathBuf> = std::fs::read_dir(&fixtures_dir)
+		.with_context(|| format!("Cannot read fixtures directory {}", fixtures_dir))
+		.map_err(|e| Error::from_reason(e.to_string()))?
+		.filter_map(|e| e.ok().map(|e| e.path()))
+		.filter(|p| p.is_dir())
+		.collect();
+	cases.sort();
+	if cases.is_empty() { return Err(Error::from_reason(format!("No fixtures found in {}", fixtures_dir))); }
+
+	let mut failures = Vec::new();
+	for case in &cases {
+		if let Err(e) = run_fixture(case) {
+			failures.push(format!("{}: {}", case.file_name().unwrap_or_default().to_string_lossy(), e.reason));
+		}
+	}
+	if !failures.is_empty() {
+		return Err(Error::from_reason(format!("{} of {} self-test fixtures failed\n{}", failures.len(), cases.len(), failures.join("\n"))));
+	}
+	Ok(SelfTestResult { cases: cases.len() as u32, passed: cases.len() as u32 })
+}
+
+fn run_fixture(dir: &Path) -> Result<()> {
+	let read = |name: &str| std::fs::read_to_string(dir.join(name)).map_err(|e| Error::from_reason(format!("{}: {}", name, e)));
+	let event_name = read("event.txt").ok().map(|s| s.trim().to_string());
+	let decoder = load_decoder(&dir.join("abi.json"), event_name.as_deref().unwrap_or(""))
+		.map_err(|e| DecodeError::AbiLoad.error(format!("{:#}", e)))?;
+
+	let input = read("input.jsonl")?;
+	let mut actual = Vec::new();
+	for line in input.lines() {
+		if let Some(decoded) = decode_line(&decoder, line, Lap::off())? { actual.push(envelope_json(&decoded)); }
+	}
+
+	let expected = read("expected.jsonl")?;
+	let expected: Vec<&str> = expected.lines().filter(|l| !l.trim().is_empty()).collect();
+	for (i, (e, a)) in expected.iter().zip(&actual).enumerate() {
+		let e: Value = serde_json::from_str(e).map_err(|err| Error::from_reason(format!("expected.jsonl line {}: {}", i + 1, err)))?;
+		if &e != a { return Err(Error::from_reason(format!("record {} differs\n  expected: {}\n  actual:   {}", i + 1, e, a))); }
+	}
+	if expected.len() != actual.len() {
+		return Err(Error::from_reason(format!("expected {} records, decoded {}", expected.len(), actual.len())));
+	}
+	Ok(())
+}
+
+/// A decoder of the ABI file at `path`, loaded as the CLI's `--abi`: every event is routed by topic0 when
+/// `event_name` is empty, else only that event is decoded.
+fn load_decoder(path: &Path, event_name: &str) -> anyhow::Result<LogDecoder> {
+	let abis = AbiSet::load(&[path.to_path_buf()], &[], None, None)?;
+	let selected_event = abis.select(event_name)?;
+	Ok(LogDecoder::from_abi_set(selected_event, &abis, event_name.is_empty(), CollisionPolicy::First))
+}
+
+/// Splits the binary record at the start of `rest` into topics and data.
+fn next_record(rest: &mut &[u8], record: u32) -> Result<(Vec<H256>, Vec<u8>)> {
+	let truncated = || Error::from_reason(format!("Invalid binary record {}: input ends mid-record", record));
+	let mut take = |n: usize| -> Result<&[u8]> {
+		if rest.len() < n { return Err(truncated()); }
+		let (head, tail) = rest.split_at(n);
+		*rest = tail;
+		Ok(head)
+	};
+	let count = take(1)?[0] as usize;
+	if count > 4 { return Err(Error::from_reason(format!("Invalid binary record {}: {} topics (at most 4)", record, count))); }
+	let topics = (0..count).map(|_| take(32).map(H256::from_slice)).collect::<Result<Vec<H256>>>()?;
+	let len = take(4)?.iter().rev().fold(0usize, |len, &b| len << 8 | b as usize);
+	Ok((topics, take(len)?.to_vec()))
+}
+
+fn read_input(input_path: &str) -> Result<String> {
+	std::fs::read_to_string(input_path)
+		.with_context(|| format!("Cannot open input file: {}", input_path))
+		.map_err(|e| DecodeError::Io.error(format!("{:#}", e)))
 }
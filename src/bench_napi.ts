@@ -1,23 +1,43 @@
 import { resolve } from 'node:path';
 import { createRequire } from 'node:module';
+import { performance } from 'node:perf_hooks';
+import { runtime } from './runtime.js';
 
 const require = createRequire(import.meta.url);
+// Types come from the index.d.ts that `npm run build:napi` generates
 // eslint-disable-next-line @typescript-eslint/no-var-requires
-const addon = require('../rust-napi') as {
-	decodeFile(abiPath: string, eventName: string, inputPath: string): {
-		decoded: number;
-		elapsedMs: number;
-	};
-};
+const addon = require('../rust-napi') as typeof import('../rust-napi/index.js');
 
 function run() {
 	const inputPath = resolve(process.env.IN || 'data/logs.jsonl');
-	const abiPath = resolve('abi/erc20.json');
-	const res = addon.decodeFile(abiPath, 'Transfer', inputPath);
+	const abiPath = resolve(process.env.ABI || 'abi/erc20.json');
+	// EVENT='' decodes every ABI event by topic0 (set by `bench-all` when --event is omitted)
+	const eventName = process.env.EVENT ?? 'Transfer';
+	const preload = process.env.PRELOAD === '1' || process.env.PRELOAD === 'true';
+	const engine = process.env.ENGINE as 'sequential' | 'parallel' | 'pipeline' | undefined;
+	const threads = process.env.THREADS ? Number(process.env.THREADS) : undefined;
+	const phaseTiming = process.env.PHASE_TIMING === '1' || process.env.PHASE_TIMING === 'true';
+	// OUTPUT=objects|ndjson also returns the decoded logs; compare call_ms to see the cost of building JS objects
+	const output = process.env.OUTPUT as 'none' | 'objects' | 'ndjson' | undefined;
+	const called = performance.now();
+	const res = addon.decodeFile(abiPath, eventName, inputPath, { preload, engine, threads, phaseTiming, output });
+	const callMs = performance.now() - called;
+	if (output && output !== 'none') {
+		const returned = res.logs?.length ?? res.ndjson?.length ?? 0;
+		console.log(`napi_ethabi output=${output} returned=${returned} call_ms=${callMs.toFixed(3)}`);
+	}
+	if (phaseTiming) {
+		const ms = (v?: number | null) => (v ?? 0).toFixed(3);
+		console.log(
+			`napi_ethabi phases read_ms=${ms(res.readMs)} json_ms=${ms(res.jsonParseMs)} hex_ms=${ms(res.hexDecodeMs)} abi_ms=${ms(
+				res.abiDecodeMs
+			)} ffi_ms=${ms(res.ffiOverheadMs)} call_ms=${callMs.toFixed(3)}`
+		);
+	}
 	console.log(
 		`napi_ethabi decoded=${res.decoded} elapsed_ms=${res.elapsedMs.toFixed(3)} throughput_lps=${(
 			res.decoded / (res.elapsedMs / 1000)
-		).toFixed(0)}`
+		).toFixed(0)} allocator=${addon.allocator()} runtime=${runtime()}`
 	);
 }
 
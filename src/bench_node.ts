@@ -0,0 +1,106 @@
+import { existsSync, readFileSync, writeFileSync } from 'node:fs';
+import { resolve } from 'node:path';
+import { performance } from 'node:perf_hooks';
+import { Interface } from 'ethers';
+import { decodeEventLog, toEventSelector, type Abi } from 'viem';
+import { Web3 } from 'web3';
+import { runtime } from './runtime.js';
+
+// Decodes one file with the JS libraries users compare the addon against, each in this process, and prints a
+// `js_<lib> decoded=N elapsed_ms=X` line per library, so each can also be a `bench-all --impl`. With JSON=FILE the
+// results are merged into that `bench-all --json` document, replacing earlier entries of the same name and input.
+
+type Lib = 'viem' | 'ethers' | 'web3';
+type Log = { topics: `0x${string}`[]; data: `0x${string}` };
+type Decode = (log: Log) => void;
+
+const LIBS: Lib[] = ['viem', 'ethers', 'web3'];
+
+// A decoder per library over `events`; logs whose topic0 is not one of them throw, as in the CLI.
+function decoders(events: any[]): Record<Lib, Decode> {
+	const iface = new Interface(events);
+	const web3Abi = new Web3().eth.abi;
+	const byTopic0 = new Map(events.map((e) => [toEventSelector(e), e]));
+	const event = (log: Log) => {
+		const e = byTopic0.get(log.topics[0]);
+		if (!e) throw new Error(`unknown topic0 ${log.topics[0]}`);
+		return e;
+	};
+	return {
+		viem: ({ topics, data }) => void decodeEventLog({ abi: events as Abi, data, topics: topics as any, strict: true }),
+		ethers: ({ topics, data }) => {
+			if (!iface.parseLog({ topics, data })) throw new Error(`unknown topic0 ${topics[0]}`);
+		},
+		// web3 takes the indexed topics without topic0
+		web3: (log) => void web3Abi.decodeLog(event(log).inputs, log.data, log.topics.slice(1)),
+	};
+}
+
+function median(ms: number[]): number {
+	const sorted = [...ms].sort((a, b) => a - b);
+	const mid = sorted.length >> 1;
+	return sorted.length % 2 ? sorted[mid] : (sorted[mid - 1] + sorted[mid]) / 2;
+}
+
+// Merges `results` into the `bench-all --json` document at `path`, or starts one.
+function merge(path: string, results: any[]): void {
+	const doc = existsSync(path) ? JSON.parse(readFileSync(path, 'utf8')) : { results: [] };
+	const fresh = new Set(results.map((r) => `${r.name}\0${r.input}`));
+	doc.results = (doc.results ?? []).filter((r: any) => !fresh.has(`${r.name}\0${r.input}`)).concat(results);
+	writeFileSync(path, JSON.stringify(doc, null, 2));
+}
+
+function run() {
+	const inputPath = resolve(process.env.IN || 'data/logs.jsonl');
+	const abiPath = resolve(process.env.ABI || 'abi/erc20.json');
+	// EVENT='' decodes every ABI event by topic0 (set by `bench-all` when --event is omitted)
+	const eventName = process.env.EVENT ?? 'Transfer';
+	const runs = Number(process.env.RUNS || '3');
+	const libs = (process.env.LIBS ? process.env.LIBS.split(',') : LIBS) as Lib[];
+	const unknown = libs.filter((l) => !LIBS.includes(l));
+	if (unknown.length) throw new Error(`LIBS: unknown ${unknown.join(',')}, expected ${LIBS.join(',')}`);
+
+	const doc = JSON.parse(readFileSync(abiPath, 'utf8'));
+	const all = (Array.isArray(doc) ? doc : doc.abi ?? doc.events).filter((i: any) => i.type === 'event');
+	const events = eventName ? all.filter((e: any) => e.name === eventName) : all;
+	if (!events.length) throw new Error(`event ${eventName} not in ${abiPath}`);
+	const decode = decoders(events);
+	// Read before the timers, as with PRELOAD; JSON parsing is timed, as in the other harnesses
+	const lines = readFileSync(inputPath, 'utf8').split(/\r?\n/).filter(Boolean);
+
+	const results = [];
+	for (const lib of libs) {
+		const runsMs: number[] = [];
+		let decoded = 0;
+		for (let i = 0; i < runs; i++) {
+			decoded = 0;
+			const t0 = performance.now();
+			for (const line of lines) {
+				const log = JSON.parse(line) as Log;
+				if (!log.topics?.length) continue;
+				decode[lib](log);
+				decoded++;
+			}
+			runsMs.push(performance.now() - t0);
+		}
+		const ms = median(runsMs);
+		const lps = decoded / (ms / 1000);
+		console.log(`js_${lib} decoded=${decoded} elapsed_ms=${ms.toFixed(3)} throughput_lps=${lps.toFixed(0)} runtime=${runtime()}`);
+		results.push({
+			name: `js_${lib}`,
+			input: inputPath,
+			abi: abiPath,
+			event: eventName || null,
+			command: `LIBS=${lib} ts-node src/bench_node.ts`,
+			decoded,
+			median_ms: ms,
+			best_ms: Math.min(...runsMs),
+			throughput_lps: Math.round(lps),
+			runs_ms: runsMs,
+			runtime: runtime(),
+		});
+	}
+	if (process.env.JSON) merge(resolve(process.env.JSON), results);
+}
+
+run();
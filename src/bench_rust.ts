@@ -5,7 +5,7 @@ import { spawnSync } from 'node:child_process';
 function run() {
   const inputPath = resolve(process.env.IN || 'data/logs.jsonl');
   const abiPath = resolve('abi/erc20.json');
-  const bin = resolve('rust-cli/target/release/evm_rust_decoder');
+  const bin = resolve(`rust-cli/target/release/evm_rust_decoder${process.platform === 'win32' ? '.exe' : ''}`);
 
   const t0 = performance.now();
   const out = spawnSync(bin, ['--abi', abiPath, '--event', 'Transfer', '--input', inputPath], {
@@ -0,0 +1,84 @@
+import { readFileSync } from 'node:fs';
+import { resolve } from 'node:path';
+import { performance } from 'node:perf_hooks';
+import { spawn, type ChildProcessWithoutNullStreams } from 'node:child_process';
+
+// Talks to `evm_rust_decoder --serve`: each message is a big-endian u32 length followed by that many bytes of JSON.
+function frame(msg: object): Buffer {
+  const body = Buffer.from(JSON.stringify(msg));
+  const len = Buffer.alloc(4);
+  len.writeUInt32BE(body.length);
+  return Buffer.concat([len, body]);
+}
+
+class ServeClient {
+  private buf = Buffer.alloc(0);
+  private waiting: ((msg: any) => void)[] = [];
+
+  constructor(private child: ChildProcessWithoutNullStreams) {
+    child.stdout.on('data', (chunk: Buffer) => {
+      this.buf = Buffer.concat([this.buf, chunk]);
+      while (this.buf.length >= 4) {
+        const len = this.buf.readUInt32BE(0);
+        if (this.buf.length < 4 + len) break;
+        const msg = JSON.parse(this.buf.subarray(4, 4 + len).toString('utf8'));
+        this.buf = this.buf.subarray(4 + len);
+        this.waiting.shift()?.(msg);
+      }
+    });
+  }
+
+  async request(msg: object): Promise<any> {
+    const res = await new Promise<any>((done) => {
+      this.waiting.push(done);
+      this.child.stdin.write(frame(msg));
+    });
+    if (!res.ok) throw new Error(`serve: ${res.error}`);
+    return res;
+  }
+}
+
+async function run() {
+  const inputPath = resolve(process.env.IN || 'data/logs.jsonl');
+  const abiPath = resolve(process.env.ABI || 'abi/erc20.json');
+  // EVENT='' decodes every ABI event by topic0
+  const eventName = process.env.EVENT ?? 'Transfer';
+  const batchSize = Number(process.env.BATCH || '10000');
+  const bin = resolve(`rust-cli/target/release/evm_rust_decoder${process.platform === 'win32' ? '.exe' : ''}`);
+
+  const args = ['--abi', abiPath, '--serve'];
+  if (eventName) args.push('--event', eventName);
+  const child = spawn(bin, args, { stdio: ['pipe', 'pipe', 'inherit'] });
+  const client = new ServeClient(child);
+
+  const lines = readFileSync(inputPath, 'utf8').split(/\r?\n/).filter(Boolean);
+  const batches: string[][] = [];
+  for (let i = 0; i < lines.length; i += batchSize) batches.push(lines.slice(i, i + batchSize));
+
+  // Warm-up request so the first timed batch does not pay for page faults and lazy initialisation
+  if (batches.length > 0) await client.request({ op: 'decode', lines: batches[0].slice(0, 1000) });
+
+  let decoded = 0;
+  let serverUs = 0;
+  const t0 = performance.now();
+  for (const batch of batches) {
+    const res = await client.request({ op: 'decode', lines: batch });
+    decoded += res.decoded;
+    serverUs += res.elapsed_us;
+  }
+  const ms = performance.now() - t0;
+
+  await client.request({ op: 'shutdown' });
+  child.stdin.end();
+  // elapsed_ms is the client-side round trip; server_ms is the decoding time reported by the server
+  console.log(
+    `rust_serve decoded=${decoded} elapsed_ms=${ms.toFixed(3)} throughput_lps=${(decoded / (ms / 1000)).toFixed(0)} server_ms=${(
+      serverUs / 1000
+    ).toFixed(3)} batches=${batches.length}`
+  );
+}
+
+run().catch((e) => {
+  console.error(e);
+  process.exit(1);
+});
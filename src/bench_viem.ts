@@ -14,7 +14,7 @@ const transfer = ERC20_ABI.find(
 function run() {
   const inputPath = resolve(process.env.IN || 'data/logs.jsonl');
   const lines = readFileSync(inputPath, 'utf8')
-    .split('\n')
+    .split(/\r?\n/)
     .filter(Boolean);
 
   let decoded = 0;
@@ -1,7 +1,8 @@
-import { createWriteStream, mkdirSync } from 'node:fs';
+import { createWriteStream, mkdirSync, readFileSync } from 'node:fs';
 import { dirname, resolve } from 'node:path';
 import { randomBytes } from 'node:crypto';
-import { keccak256, toHex } from 'viem';
+import { encodeAbiParameters, encodeEventTopics, keccak256, toHex } from 'viem';
+import type { Abi, AbiEvent, AbiParameter } from 'viem';
 
 const TRANSFER_SIG = 'Transfer(address,address,uint256)';
 const APPROVAL_SIG = 'Approval(address,address,uint256)';
@@ -66,19 +67,229 @@ function generateErc1155TransferSingle(): string {
   return JSON.stringify({ topics, data });
 }
 
+// Stress profiles: events of abi/stress.json that exercise what Transfer-only datasets never reach, picked uniformly.
+const STRESS_PROFILES: Record<string, string[]> = {
+  // long strings and byte blobs, and a string array
+  'dynamic-heavy': ['Message', 'Metadata'],
+  // every parameter in topics, empty data (a string hashed into its topic)
+  'indexed-only': ['Vote', 'Tagged'],
+  // nested arrays, tuple arrays and a tuple holding a tuple array
+  'deep-nesting': ['Batch', 'Order'],
+};
+
+const MAX_STRING = 512;
+const MAX_BYTES = 1024;
+const MAX_ARRAY = 4;
+
+function randomInt(below: number): number {
+  return Math.floor(Math.random() * below);
+}
+
+function randomString(length = randomInt(MAX_STRING + 1)): string {
+  const chars = 'abcdefghijklmnopqrstuvwxyz0123456789 ';
+  let s = '';
+  for (let i = length; i > 0; i--) s += chars[randomInt(chars.length)];
+  return s;
+}
+
+// A random value of an ABI parameter, in the shape viem encodes: bigints, hex strings, arrays, and objects for tuples.
+function randomValue(param: AbiParameter): unknown {
+  const array = param.type.match(/^(.*)\[(\d*)\]$/);
+  if (array) {
+    const length = array[2] === '' ? randomInt(MAX_ARRAY + 1) : Number(array[2]);
+    return Array.from({ length }, () => randomValue({ ...param, type: array[1] } as AbiParameter));
+  }
+  if (param.type === 'tuple') {
+    const components = (param as { components: readonly AbiParameter[] }).components;
+    return Object.fromEntries(components.map((c) => [c.name, randomValue(c)]));
+  }
+  if (param.type === 'address') return randomHex(20);
+  if (param.type === 'bool') return Math.random() < 0.5;
+  if (param.type === 'string') return randomString();
+  if (param.type === 'bytes') return randomHex(randomInt(MAX_BYTES + 1));
+  if (param.type.startsWith('bytes')) return randomHex(Number(param.type.slice(5)));
+  if (param.type.startsWith('uint')) return BigInt(randomHex(32)) >> BigInt(randomInt(256));
+  throw new Error(`generator: unsupported parameter type ${param.type}`);
+}
+
+function encodeStressLog(event: AbiEvent, args: Record<string, unknown>): { topics: string[]; data: string } {
+  const topics = encodeEventTopics({ abi: [event], eventName: event.name, args } as Parameters<typeof encodeEventTopics>[0]);
+  const dataParams = event.inputs.filter((p) => !p.indexed);
+  const data = encodeAbiParameters(dataParams, dataParams.map((p) => args[p.name!]));
+  return { topics: topics as string[], data };
+}
+
+function generateStressLog(event: AbiEvent): string {
+  const args = Object.fromEntries(event.inputs.map((p) => [p.name, randomValue(p)]));
+  return JSON.stringify(encodeStressLog(event, args));
+}
+
+function loadStressAbi(): Map<string, AbiEvent> {
+  const abi = JSON.parse(readFileSync(resolve(process.env.STRESS_ABI || 'abi/stress.json'), 'utf8')) as Abi;
+  return new Map(abi.filter((item): item is AbiEvent => item.type === 'event').map((e) => [e.name, e]));
+}
+
+function stressEvents(profile: string): AbiEvent[] {
+  const names = STRESS_PROFILES[profile];
+  if (!names) {
+    const known = ['transfer', 'mixed', 'adversarial', ...Object.keys(STRESS_PROFILES)].join(', ');
+    throw new Error(`Unknown profile ${profile} (expected one of ${known})`);
+  }
+  const events = loadStressAbi();
+  return names.map((name) => {
+    const event = events.get(name);
+    if (!event) throw new Error(`No event ${name} in the stress ABI`);
+    return event;
+  });
+}
+
+// Adversarial profile: pathological-but-valid logs a decoder must accept and invalid ones it must reject, over the
+// stress ABI, in turn. Each line's case and expected outcome go to a sidecar file so that a run with
+// `--on-error skip` can be checked against it.
+type AdversarialCase = { name: string; expect: 'ok' | 'error'; line: (events: Map<string, AbiEvent>) => string };
+
+const HUGE_STRING = 4096;
+const HUGE_ARRAY = 1000;
+// Unused bytes between the head and a dynamic value; still valid ABI, since the offset points past them
+const MAX_OFFSET_GAP = 16384;
+
+// One 32-byte word of hex, without `0x`
+function word(n: number | bigint): string {
+  return BigInt(n).toString(16).padStart(64, '0');
+}
+
+// The tail of a `bytes` or `string` value (hex without `0x`): its length, then the bytes padded to a word
+function dynamicTail(hex: string): string {
+  const length = hex.length / 2;
+  return word(length) + hex + '00'.repeat((32 - (length % 32)) % 32);
+}
+
+function messageTopics(events: Map<string, AbiEvent>): string[] {
+  return encodeStressLog(events.get('Message')!, { sender: randomHex(20), text: '', payload: '0x' }).topics;
+}
+
+function message(events: Map<string, AbiEvent>, text: string, payload: string): { topics: string[]; data: string } {
+  return encodeStressLog(events.get('Message')!, { sender: randomHex(20), text, payload });
+}
+
+// A valid Message log with other data
+function messageWith(events: Map<string, AbiEvent>, data: string): string {
+  return JSON.stringify({ topics: messageTopics(events), data });
+}
+
+// Valid Message data (100 characters, 50 bytes) for the invalid cases to break
+function goodData(events: Map<string, AbiEvent>): string {
+  return message(events, randomString(100), randomHex(50)).data;
+}
+
+const ADVERSARIAL_CASES: AdversarialCase[] = [
+  { name: 'huge-string', expect: 'ok', line: (e) => JSON.stringify(message(e, randomString(HUGE_STRING), randomHex(HUGE_STRING))) },
+  {
+    name: 'huge-string-array',
+    expect: 'ok',
+    line: (e) => {
+      const tags = Array.from({ length: HUGE_ARRAY }, () => randomString(8));
+      return JSON.stringify(encodeStressLog(e.get('Metadata')!, { id: 7n, uri: 'uri', tags }));
+    },
+  },
+  {
+    name: 'huge-uint-array',
+    expect: 'ok',
+    line: (e) => {
+      const ids = [Array.from({ length: HUGE_ARRAY }, (_, i) => BigInt(i))];
+      return JSON.stringify(encodeStressLog(e.get('Batch')!, { operator: randomHex(20), ids, calls: [] }));
+    },
+  },
+  {
+    name: 'max-offset',
+    expect: 'ok',
+    line: (e) => {
+      const text = dynamicTail(toHex('hi').slice(2));
+      const head = word(64 + MAX_OFFSET_GAP) + word(64 + MAX_OFFSET_GAP + text.length / 2);
+      return messageWith(e, '0x' + head + '00'.repeat(MAX_OFFSET_GAP) + text + dynamicTail('01'));
+    },
+  },
+  { name: 'empty-dynamic', expect: 'ok', line: (e) => JSON.stringify(message(e, '', '0x')) },
+  // decoded with U+FFFD in place of the invalid bytes
+  { name: 'invalid-utf8', expect: 'ok', line: (e) => messageWith(e, '0x' + word(64) + word(128) + dynamicTail('fffe') + word(0)) },
+  { name: 'truncated-data', expect: 'error', line: (e) => messageWith(e, goodData(e).slice(0, 2 + 64 * 3 + 10)) },
+  { name: 'offset-out-of-range', expect: 'error', line: (e) => messageWith(e, '0x' + word(4096) + word(64) + dynamicTail('78')) },
+  { name: 'offset-overflow', expect: 'error', line: (e) => messageWith(e, '0x' + 'f'.repeat(64) + word(64) + dynamicTail('78')) },
+  { name: 'length-overflow', expect: 'error', line: (e) => messageWith(e, '0x' + word(64) + word(96) + 'f'.repeat(64) + word(0)) },
+  { name: 'length-past-end', expect: 'error', line: (e) => messageWith(e, '0x' + word(64) + word(128) + word(1000) + word(0) + word(0)) },
+  { name: 'missing-topic', expect: 'error', line: (e) => JSON.stringify({ topics: messageTopics(e).slice(0, 1), data: goodData(e) }) },
+  {
+    name: 'extra-topic',
+    expect: 'error',
+    line: (e) => JSON.stringify({ topics: [...messageTopics(e), addressToTopic(randomHex(20))], data: goodData(e) }),
+  },
+  {
+    name: 'unknown-topic0',
+    expect: 'error',
+    line: (e) => JSON.stringify({ topics: [randomHex(32), ...messageTopics(e).slice(1)], data: goodData(e) }),
+  },
+  { name: 'odd-hex-data', expect: 'error', line: (e) => messageWith(e, goodData(e) + '0') },
+  { name: 'non-hex-data', expect: 'error', line: (e) => messageWith(e, '0xzz' + goodData(e).slice(4)) },
+  {
+    name: 'short-topic',
+    expect: 'error',
+    line: (e) => JSON.stringify({ topics: [messageTopics(e)[0], randomHex(31)], data: goodData(e) }),
+  },
+  { name: 'invalid-json', expect: 'error', line: (e) => JSON.stringify(message(e, 'cut', '0x')).slice(0, 40) },
+];
+
+// data/logs.jsonl -> data/logs.expected.jsonl
+function expectedPath(outPath: string): string {
+  return outPath.replace(/\.jsonl$/, '') + '.expected.jsonl';
+}
+
+async function generateAdversarial(count: number, outPath: string) {
+  const events = loadStressAbi();
+  const sidecar = expectedPath(outPath);
+  const ws = createWriteStream(outPath);
+  const expected = createWriteStream(sidecar);
+  console.log(`Generating ${count} adversarial logs (${ADVERSARIAL_CASES.length} cases in turn) to ${outPath}...`);
+  let errors = 0;
+  for (let i = 0; i < count; i++) {
+    const c = ADVERSARIAL_CASES[i % ADVERSARIAL_CASES.length];
+    ws.write(c.line(events) + '\n');
+    expected.write(JSON.stringify({ line: i + 1, case: c.name, expect: c.expect }) + '\n');
+    if (c.expect === 'error') errors++;
+  }
+  await Promise.all([new Promise((r) => ws.end(r)), new Promise((r) => expected.end(r))]);
+  console.log(`Expected outcomes in ${sidecar}: with --abi abi/stress.json --on-error skip,`);
+  console.log(`decoded=${count - errors} and skipped errors=${errors}.`);
+}
+
+// `--profile NAME` on the command line, else PROFILE, else MIXED=1 for `mixed`
+function profileName(): string {
+  const flag = process.argv.indexOf('--profile');
+  if (flag >= 0 && process.argv[flag + 1]) return process.argv[flag + 1];
+  if (process.env.PROFILE) return process.env.PROFILE;
+  return process.env.MIXED === '1' || process.env.MIXED === 'true' ? 'mixed' : 'transfer';
+}
+
 async function main() {
   const count = Number(process.env.COUNT || '200000');
   const outPath = resolve(process.env.OUT || 'data/logs.jsonl');
-  const mixed = process.env.MIXED === '1' || process.env.MIXED === 'true';
+  const profile = profileName();
   mkdirSync(dirname(outPath), { recursive: true });
+  if (profile === 'adversarial') return generateAdversarial(count, outPath);
+  const mixed = profile === 'mixed';
+  const stress = profile === 'transfer' || mixed ? null : stressEvents(profile);
   const ws = createWriteStream(outPath);
 
-  console.log(
-    `Generating ${count} ${mixed ? 'MIXED (ERC20/Approval/ERC1155.Single)' : 'ERC20 Transfer'} logs to ${outPath}...`
-  );
+  const description = stress
+    ? `${profile} (${stress.map((e) => e.name).join('/')})`
+    : mixed
+      ? 'MIXED (ERC20/Approval/ERC1155.Single)'
+      : 'ERC20 Transfer';
+  console.log(`Generating ${count} ${description} logs to ${outPath}...`);
   for (let i = 0; i < count; i++) {
     let line: string;
-    if (mixed) {
+    if (stress) {
+      line = generateStressLog(stress[randomInt(stress.length)]);
+    } else if (mixed) {
       const r = Math.random();
       if (r < 0.5) line = generateErc20Transfer();
       else if (r < 0.8) line = generateErc20Approval();
@@ -0,0 +1,8 @@
+// The JS runtime a harness runs under, as `node/22.11.0`, `bun/1.2.4` or `deno/2.1.9`: the addon and the JS
+// libraries perform differently on each, so the harnesses print it with their results.
+export function runtime(): string {
+	const g = globalThis as any;
+	if (g.Bun) return `bun/${g.Bun.version}`;
+	if (g.Deno) return `deno/${g.Deno.version.deno}`;
+	return `node/${process.versions.node}`;
+}
@@ -0,0 +1,20 @@
+import { resolve } from 'node:path';
+import { createRequire } from 'node:module';
+
+const require = createRequire(import.meta.url);
+// Types come from the index.d.ts that `npm run build:napi` generates
+// eslint-disable-next-line @typescript-eslint/no-var-requires
+const addon = require('../rust-napi') as typeof import('../rust-napi/index.js');
+
+function run() {
+	const fixturesDir = resolve(process.env.FIXTURES || 'fixtures');
+	try {
+		const res = addon.selfTest(fixturesDir);
+		console.log(`napi self-test cases=${res.cases} passed=${res.passed} failed=0`);
+	} catch (err) {
+		console.error(`napi self-test FAILED\n${(err as Error).message}`);
+		process.exit(1);
+	}
+}
+
+run();
@@ -6,24 +6,21 @@ import { performance } from 'node:perf_hooks';
 import { decodeEventLog, type Abi } from 'viem';
 
 const require = createRequire(import.meta.url);
+// Types come from the index.d.ts that `npm run build:napi` generates
 // eslint-disable-next-line @typescript-eslint/no-var-requires
-const addon = require('../rust-napi') as {
-	decodeFile(abiPath: string, eventName: string, inputPath: string): {
-		decoded: number;
-		elapsedMs: number;
-	};
-};
+const addon = require('../rust-napi') as typeof import('../rust-napi/index.js');
 
 const mixed = process.env.MIXED === '1' || process.env.MIXED === 'true';
+const preload = process.env.PRELOAD === '1' || process.env.PRELOAD === 'true';
 const inputPath = resolve(process.env.IN || 'data/logs.jsonl');
 const abiPath = resolve(mixed ? 'abi/mixed.json' : 'abi/erc20.json');
 const batchSize = Number(process.env.BATCH || '50000');
 const iterations = Number(process.env.ITERS || '1');
 const samplePath = resolve('data/logs_sample.jsonl');
 const warmPath = resolve('data/logs_sample_warm.jsonl');
-const cliBin = resolve('rust-cli/target/release/evm_rust_decoder');
+const cliBin = resolve(`rust-cli/target/release/evm_rust_decoder${process.platform === 'win32' ? '.exe' : ''}`);
 
-const lines = readFileSync(inputPath, 'utf8').split('\n').filter(Boolean);
+const lines = readFileSync(inputPath, 'utf8').split(/\r?\n/).filter(Boolean);
 const small = lines.slice(0, batchSize);
 writeFileSync(samplePath, small.join('\n'));
 
@@ -33,6 +30,8 @@ if (warmCount > 0) writeFileSync(warmPath, small.slice(0, warmCount).join('\n'))
 const abi = JSON.parse(readFileSync(abiPath, 'utf8')) as Abi;
 const events = abi.filter((i: any) => i.type === 'event');
 const transfer = events.find((i: any) => i.name === 'Transfer') as any;
+// Parsed once, so repeated iterations only time decoding
+const napiDecoder = new addon.Decoder(readFileSync(abiPath, 'utf8'), mixed ? undefined : 'Transfer');
 
 function runViemOver(linesArr: string[], isMixed: boolean): void {
 	for (const line of linesArr) {
@@ -49,7 +48,7 @@ function runViemMs(): number {
 }
 
 function runNapiMs(): number {
-	const res = addon.decodeFile(abiPath, mixed ? '' : 'Transfer', samplePath);
+	const res = napiDecoder.decodeFile(samplePath, { preload });
 	return res.elapsedMs;
 }
 
@@ -58,15 +57,20 @@ function runCliMs(): number {
 	const args = mixed
 		? ['--abi', abiPath, '--input', samplePath]
 		: ['--abi', abiPath, '--event', 'Transfer', '--input', samplePath];
+	if (preload) args.push('--preload');
 	const out = spawnSync(cliBin, args, { encoding: 'utf8' });
 	if (out.error) throw out.error;
-	return performance.now() - t0;
+	const wallMs = performance.now() - t0;
+	if (!preload) return wallMs;
+	// With preload, use the decoder's own timer so process start-up and file reading are excluded, like viem's in-memory loop.
+	const m = /elapsed_ms=([\d.]+)/.exec(out.stderr);
+	return m ? Number(m[1]) : wallMs;
 }
 
 function warmup(): void {
 	if (warmCount === 0) return;
 	runViemOver(small.slice(0, warmCount), mixed);
-	addon.decodeFile(abiPath, mixed ? '' : 'Transfer', warmPath);
+	napiDecoder.decodeFile(warmPath);
 	const args = mixed
 		? ['--abi', abiPath, '--input', warmPath]
 		: ['--abi', abiPath, '--event', 'Transfer', '--input', warmPath];
@@ -100,6 +104,7 @@ async function main() {
 		batch: batchSize,
 		iters: iterations,
 		warmup: true,
+		preload,
 		results: {
 			viem: { overall_ms: Number(viemMs.toFixed(3)), lps: Number(toLps(viemMs)), runs_ms: viemRuns.map(v => Number(v.toFixed(3))) },
 			napi: { overall_ms: Number(napiMs.toFixed(3)), lps: Number(toLps(napiMs)), runs_ms: napiRuns.map(v => Number(v.toFixed(3))) },